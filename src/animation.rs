@@ -0,0 +1,384 @@
+//! Animated GIFs and PNGs, played inline: a texture loader in front of the
+//! default one that shows each image's frame for the time, paused for the
+//! images the user paused and only the first frame with playing turned off
+//! in Preferences. Frames are decoded on worker threads, and only the first
+//! one until there's a need to play the rest.
+
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+    },
+    thread,
+    time::Duration,
+};
+
+use eframe::egui::{
+    self, ColorImage, TextureHandle, TextureOptions,
+    load::{
+        Bytes, BytesPoll, LoadError, SizeHint, SizedTexture, TextureLoadResult, TextureLoader,
+        TexturePoll,
+    },
+};
+use image::{AnimationDecoder, codecs::gif::GifDecoder, codecs::png::PngDecoder};
+
+/// How long frames without a delay of their own are shown, as browsers do.
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// Shorter delays than this count as none.
+const MIN_DELAY: Duration = Duration::from_millis(20);
+
+/// A frame of an animation, and how long it's shown.
+type Frame = (Arc<ColorImage>, Duration);
+
+/// What a worker thread sends back: the frames, if the image is animated.
+type Decoded = Result<Option<Vec<Frame>>, String>;
+
+/// How an animated image is playing, for the button pausing it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Playback {
+    pub frames: usize,
+    pub shown: usize,
+    pub paused: bool,
+}
+
+/// Pauses the animated image at `uri` where it is, or plays it on from there.
+pub fn toggle_pause(ctx: &egui::Context, uri: &str) {
+    ctx.data_mut(|d| {
+        let paused = d.get_temp_mut_or_default::<bool>(paused_id(uri));
+        *paused = !*paused;
+    });
+}
+
+/// How the image at `uri` is playing, if it's an animation that is.
+pub fn playback(ctx: &egui::Context, uri: &str) -> Option<Playback> {
+    ctx.data(|d| d.get_temp(playback_id(uri)))
+}
+
+fn paused_id(uri: &str) -> egui::Id {
+    egui::Id::new(("animation_paused", uri))
+}
+
+fn playback_id(uri: &str) -> egui::Id {
+    egui::Id::new(("animation_playback", uri))
+}
+
+/// Each image asked for, as far as it's been decoded.
+enum Image {
+    /// On a worker thread, for every frame or just the first.
+    Decoding {
+        all: bool,
+        decoded: Receiver<Decoded>,
+    },
+    /// Left to the other loaders.
+    Still,
+    Animated(Animation),
+    Failed(String),
+}
+
+struct Animation {
+    frames: Vec<Frame>,
+    /// Whether `frames` are all of them, not just the first.
+    complete: bool,
+    /// The rest being decoded on a worker thread.
+    rest: Option<Receiver<Decoded>>,
+    texture: TextureHandle,
+    options: TextureOptions,
+    shown: usize,
+    /// When the loop that's playing started, unless paused.
+    started: Option<f64>,
+}
+
+impl Animation {
+    /// Decodes the frames after the first once it's to be played, and
+    /// takes them in when they're done.
+    fn complete(&mut self, ctx: &egui::Context, uri: &str) {
+        if self.complete {
+            return;
+        }
+        let Some(rest) = &self.rest else {
+            if let Ok(BytesPoll::Ready { bytes, .. }) = ctx.try_load_bytes(uri) {
+                self.rest = Some(decode_on_worker(ctx, bytes, true));
+            }
+            return;
+        };
+        match rest.try_recv() {
+            Err(TryRecvError::Empty) => return,
+            Ok(Ok(Some(frames))) => {
+                self.frames = frames;
+                self.shown = 0;
+                self.started = None;
+            }
+            // The first frame is all it shows, then
+            Ok(_) | Err(TryRecvError::Disconnected) => {}
+        }
+        self.complete = true;
+        self.rest = None;
+    }
+
+    fn size(&self) -> egui::Vec2 {
+        let [width, height] = self.frames[0].0.size;
+        egui::vec2(width as f32, height as f32)
+    }
+
+    /// Goes to the frame for `now`, or stays on the one shown if not
+    /// `playing`. Returns how long until the next frame is due.
+    fn advance(&mut self, now: f64, playing: bool) -> Option<Duration> {
+        if !playing {
+            self.started = None;
+            return None;
+        }
+        let total: f64 = self.frames.iter().map(|(_, d)| d.as_secs_f64()).sum();
+        // Playing on from the frame it was paused at
+        let started = *self.started.get_or_insert_with(|| {
+            now - self.frames[..self.shown]
+                .iter()
+                .map(|(_, d)| d.as_secs_f64())
+                .sum::<f64>()
+        });
+        let mut into_loop = (now - started).rem_euclid(total);
+        for (frame, (_, delay)) in self.frames.iter().enumerate() {
+            let delay = delay.as_secs_f64();
+            if into_loop < delay {
+                self.show(frame);
+                return Some(Duration::from_secs_f64(delay - into_loop));
+            }
+            into_loop -= delay;
+        }
+        None
+    }
+
+    fn show(&mut self, frame: usize) {
+        if frame != self.shown {
+            self.shown = frame;
+            let image = self.frames[frame].0.clone();
+            self.texture.set(image, self.options);
+        }
+    }
+}
+
+pub struct Animations {
+    playing: AtomicBool,
+    images: Mutex<HashMap<String, Image>>,
+}
+
+impl Animations {
+    /// Installs the loader; it must come after the other texture loaders to
+    /// be asked first.
+    pub fn install(ctx: &egui::Context, playing: bool) -> Arc<Self> {
+        let animations = Arc::new(Self {
+            playing: AtomicBool::new(playing),
+            images: Mutex::new(HashMap::new()),
+        });
+        ctx.add_texture_loader(animations.clone());
+        animations
+    }
+
+    /// Off, every animation stays on its first frame.
+    pub fn set_playing(&self, ctx: &egui::Context, playing: bool) {
+        if self.playing.swap(playing, Ordering::Relaxed) != playing {
+            ctx.request_repaint();
+        }
+    }
+}
+
+impl TextureLoader for Animations {
+    fn id(&self) -> &str {
+        egui::generate_loader_id!(Animations)
+    }
+
+    fn load(
+        &self,
+        ctx: &egui::Context,
+        uri: &str,
+        options: TextureOptions,
+        _: SizeHint,
+    ) -> TextureLoadResult {
+        // egui asks for GIFs by frame, as `image.gif#3`, and keeps the
+        // frame at 0 without durations from a loader of its own
+        let uri = match uri.rsplit_once('#') {
+            Some((image, frame)) if frame.parse::<usize>().is_ok() => image,
+            _ => uri,
+        };
+        let playing = self.playing.load(Ordering::Relaxed);
+        let mut images = self.images.lock().unwrap_or_else(PoisonError::into_inner);
+        if !images.contains_key(uri) {
+            let bytes = match ctx.try_load_bytes(uri)? {
+                BytesPoll::Pending { size } => return Ok(TexturePoll::Pending { size }),
+                BytesPoll::Ready { bytes, .. } => bytes,
+            };
+            let image = match may_be_animated(&bytes) {
+                true => Image::Decoding {
+                    all: playing,
+                    decoded: decode_on_worker(ctx, bytes, playing),
+                },
+                false => Image::Still,
+            };
+            images.insert(uri.to_owned(), image);
+        }
+        let Some(image) = images.get_mut(uri) else {
+            return Err(LoadError::NotSupported);
+        };
+        if let Image::Decoding { all, decoded } = image {
+            *image = match decoded.try_recv() {
+                Err(TryRecvError::Empty) => return Ok(TexturePoll::Pending { size: None }),
+                Err(TryRecvError::Disconnected) => Image::Failed("the decoder stopped".into()),
+                Ok(Err(error)) => Image::Failed(error),
+                Ok(Ok(None)) => Image::Still,
+                Ok(Ok(Some(frames))) => Image::Animated(Animation {
+                    texture: ctx.load_texture(uri, frames[0].0.clone(), options),
+                    complete: *all,
+                    rest: None,
+                    options,
+                    frames,
+                    shown: 0,
+                    started: None,
+                }),
+            };
+        }
+        let animation = match image {
+            Image::Animated(animation) => animation,
+            Image::Failed(error) => return Err(LoadError::Loading(error.clone())),
+            Image::Decoding { .. } | Image::Still => return Err(LoadError::NotSupported),
+        };
+
+        if playing {
+            animation.complete(ctx, uri);
+        } else {
+            animation.show(0);
+        }
+        let paused = ctx.data(|d| d.get_temp(paused_id(uri)).unwrap_or(false));
+        let now = ctx.input(|i| i.time);
+        if let Some(next) = animation.advance(now, playing && !paused) {
+            ctx.request_repaint_after(next);
+        }
+        ctx.data_mut(|d| match playing {
+            true => d.insert_temp(
+                playback_id(uri),
+                Playback {
+                    frames: animation.frames.len(),
+                    shown: animation.shown,
+                    paused,
+                },
+            ),
+            false => d.remove::<Playback>(playback_id(uri)),
+        });
+        Ok(TexturePoll::Ready {
+            texture: SizedTexture::new(animation.texture.id(), animation.size()),
+        })
+    }
+
+    fn forget(&self, uri: &str) {
+        let mut images = self.images.lock().unwrap_or_else(PoisonError::into_inner);
+        images.remove(uri);
+    }
+
+    fn forget_all(&self) {
+        let mut images = self.images.lock().unwrap_or_else(PoisonError::into_inner);
+        images.clear();
+    }
+
+    fn byte_size(&self) -> usize {
+        let images = self.images.lock().unwrap_or_else(PoisonError::into_inner);
+        images
+            .values()
+            .filter_map(|image| match image {
+                Image::Animated(animation) => Some(animation),
+                _ => None,
+            })
+            .flat_map(|animation| &animation.frames)
+            .map(|(image, _)| image.pixels.len() * size_of::<egui::Color32>())
+            .sum()
+    }
+}
+
+/// Whether `bytes` are a GIF, or a PNG with an animation control chunk
+/// ahead of its image data; found without decoding anything.
+fn may_be_animated(bytes: &[u8]) -> bool {
+    if bytes.starts_with(b"GIF8") {
+        return true;
+    }
+    let Some(mut chunks) = bytes.strip_prefix(b"\x89PNG\r\n\x1a\n") else {
+        return false;
+    };
+    while let [a, b, c, d, kind @ ..] = chunks
+        && let Some(kind) = kind.get(..4)
+    {
+        match kind {
+            b"acTL" => return true,
+            b"IDAT" => return false,
+            _ => {}
+        }
+        // Length, type, data and checksum
+        let len = u32::from_be_bytes([*a, *b, *c, *d]) as usize;
+        let Some(next) = chunks.get(len.saturating_add(12)..) else {
+            return false;
+        };
+        chunks = next;
+    }
+    false
+}
+
+/// [`decode`] on a worker thread, with a repaint requested once it's done.
+fn decode_on_worker(ctx: &egui::Context, bytes: Bytes, all: bool) -> Receiver<Decoded> {
+    let (sender, decoded) = mpsc::channel();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        // Nobody waits for it once the image is forgotten
+        let _ = sender.send(decode(&bytes, all));
+        ctx.request_repaint();
+    });
+    decoded
+}
+
+/// The frames of a GIF, or of a PNG with more than one, and how long each
+/// is shown; just the first unless `all`. Still PNGs and other images are
+/// left to the other loaders.
+fn decode(bytes: &[u8], all: bool) -> Decoded {
+    let wanted = if all { usize::MAX } else { 1 };
+    let frames: Result<Vec<_>, _> = if bytes.starts_with(b"GIF8") {
+        let decoder = GifDecoder::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+        decoder.into_frames().take(wanted).collect()
+    } else if bytes.starts_with(b"\x89PNG") {
+        let decoder = PngDecoder::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+        if !decoder.is_apng().map_err(|e| e.to_string())? {
+            return Ok(None);
+        }
+        let frames: Result<Vec<_>, _> = decoder
+            .apng()
+            .map_err(|e| e.to_string())?
+            .into_frames()
+            .take(wanted)
+            .collect();
+        match frames {
+            Ok(frames) if all && frames.len() < 2 => return Ok(None),
+            frames => frames,
+        }
+    } else {
+        return Ok(None);
+    };
+    let frames = frames.map_err(|e| e.to_string())?;
+    if frames.is_empty() {
+        return Err("the image has no frames".into());
+    }
+    Ok(Some(
+        frames
+            .into_iter()
+            .map(|frame| {
+                let delay = Duration::from(frame.delay());
+                let delay = if delay < MIN_DELAY {
+                    DEFAULT_DELAY
+                } else {
+                    delay
+                };
+                let buffer = frame.into_buffer();
+                let size = [buffer.width() as usize, buffer.height() as usize];
+                let image = ColorImage::from_rgba_unmultiplied(size, buffer.as_raw());
+                (Arc::new(image), delay)
+            })
+            .collect(),
+    ))
+}
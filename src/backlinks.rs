@@ -0,0 +1,202 @@
+//! The documents linking to the one being read, found among the open tabs
+//! and the files of the opened folder.
+
+use std::{
+    borrow::Cow,
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use eframe::egui;
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag};
+
+use crate::blocks;
+use crate::encoding;
+use crate::formats::Format;
+use crate::i18n::{tr, tr_args};
+use crate::link_preview;
+use crate::tab::DocTab;
+use crate::wikilink;
+use crate::workspace::Workspace;
+
+/// How often to check on the search for links.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A document linking to the active one, and how many times it does.
+struct Backlink {
+    path: PathBuf,
+    count: usize,
+}
+
+#[derive(Default)]
+pub struct Backlinks {
+    /// What the list was worked out from: the target, the workspace and the
+    /// open documents' revisions.
+    key: Option<u64>,
+    links: Vec<Backlink>,
+    loading: Option<Receiver<Vec<Backlink>>>,
+}
+
+impl Backlinks {
+    /// Draws the list of documents linking to `tabs[active]`; returns one
+    /// the user clicked.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        tabs: &[DocTab],
+        active: usize,
+        workspace: Option<&Workspace>,
+    ) -> Option<PathBuf> {
+        ui.heading(tr("Backlinks"));
+        let Some(target) = tabs.get(active).filter(|tab| !tab.is_scratch()) else {
+            ui.weak(tr("Only saved documents can be linked to"));
+            return None;
+        };
+
+        let key = key(target, tabs, workspace);
+        if self.key != Some(key) {
+            self.key = Some(key);
+            self.links.clear();
+            self.loading = Some(search(target, tabs, workspace));
+        }
+        if let Some(loading) = &self.loading {
+            match loading.try_recv() {
+                Ok(links) => {
+                    self.links = links;
+                    self.loading = None;
+                }
+                Err(TryRecvError::Empty) => ui.ctx().request_repaint_after(POLL_INTERVAL),
+                Err(TryRecvError::Disconnected) => self.loading = None,
+            }
+        }
+
+        if self.loading.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.weak(tr("Looking for links…"));
+            });
+            return None;
+        }
+        if self.links.is_empty() {
+            ui.weak(tr("No documents link here"));
+            return None;
+        }
+        let mut clicked = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for link in &self.links {
+                ui.horizontal(|ui| {
+                    let name = match workspace {
+                        Some(ws) => ws.relative(&link.path).display().to_string(),
+                        None => link.path.file_name().map_or_else(
+                            || link.path.display().to_string(),
+                            |name| name.to_string_lossy().into_owned(),
+                        ),
+                    };
+                    if ui
+                        .link(name)
+                        .on_hover_text(link.path.display().to_string())
+                        .clicked()
+                    {
+                        clicked = Some(link.path.clone());
+                    }
+                    match link.count {
+                        1 => ui.weak(tr("1 link")),
+                        n => ui.weak(tr_args("{n} links", &[("n", &n)])),
+                    };
+                });
+            }
+        });
+        clicked
+    }
+}
+
+fn key(target: &DocTab, tabs: &[DocTab], workspace: Option<&Workspace>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    target.path.hash(&mut hasher);
+    workspace
+        .map(|ws| (&ws.root, ws.files.len()))
+        .hash(&mut hasher);
+    for tab in tabs {
+        tab.revision.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Starts looking for links to `target` in the background: in the open
+/// documents as shown, and in the rest of the workspace as saved.
+fn search(
+    target: &DocTab,
+    tabs: &[DocTab],
+    workspace: Option<&Workspace>,
+) -> Receiver<Vec<Backlink>> {
+    let mut sources: Vec<(PathBuf, Option<String>)> = tabs
+        .iter()
+        .filter(|tab| !tab.is_scratch() && tab.path != target.path)
+        // Read from the file below for tabs that let go of it and can't now
+        .map(|tab| (tab.path.clone(), tab.markdown().ok().map(Cow::into_owned)))
+        .collect();
+    if let Some(ws) = workspace {
+        for path in &ws.files {
+            if *path != target.path && !sources.iter().any(|(open, _)| open == path) {
+                sources.push((path.clone(), None));
+            }
+        }
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let target = target.path.clone();
+    let workspace = workspace.cloned();
+    thread::spawn(move || {
+        let target = fs::canonicalize(&target).unwrap_or(target);
+        let mut links = Vec::new();
+        for (path, content) in sources {
+            let markdown = match content {
+                Some(content) => content,
+                None => match encoding::read(&path) {
+                    Ok(decoded) => Format::from_path(&path)
+                        .unwrap_or_default()
+                        .to_markdown(decoded.text),
+                    Err(_) => continue,
+                },
+            };
+            let count = count_links(&markdown, &path, &target, workspace.as_ref());
+            if count > 0 {
+                links.push(Backlink { path, count });
+            }
+        }
+        links.sort_by(|a, b| a.path.cmp(&b.path));
+        let _ = sender.send(links);
+    });
+    receiver
+}
+
+/// How many links in `markdown`, the contents of `path`, lead to `target`.
+fn count_links(markdown: &str, path: &Path, target: &Path, workspace: Option<&Workspace>) -> usize {
+    let dir = path.parent();
+    let parser = Parser::new_ext(
+        markdown,
+        blocks::parser_options() | Options::ENABLE_WIKILINKS,
+    );
+    parser
+        .filter_map(|event| match event {
+            Event::Start(Tag::Link {
+                link_type: LinkType::WikiLink { .. },
+                dest_url,
+                ..
+            }) => wikilink::resolve(wikilink::note(&dest_url), dir, workspace),
+            Event::Start(Tag::Link { dest_url, .. })
+                if link_preview::is_internal(&dest_url) && !dest_url.starts_with('#') =>
+            {
+                let file = dest_url.split('#').next().unwrap_or(&dest_url);
+                Some(dir?.join(file.replace("%20", " ")))
+            }
+            _ => None,
+        })
+        .filter(|linked| fs::canonicalize(linked).is_ok_and(|linked| linked == target))
+        .count()
+}
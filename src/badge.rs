@@ -0,0 +1,74 @@
+//! Document status badges driven by a `status:` key in the frontmatter.
+
+use eframe::egui;
+
+use crate::i18n::tr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DocStatus {
+    Draft,
+    Deprecated,
+    Approved,
+    /// Any other value, shown as-is without a color of its own.
+    Other(String),
+}
+
+impl DocStatus {
+    /// Reads the `status` key out of a YAML frontmatter block.
+    pub fn from_frontmatter(frontmatter: &str) -> Option<Self> {
+        let value = frontmatter.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "status").then_some(value)
+        })?;
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'').trim();
+        Some(match value.to_lowercase().as_str() {
+            "" => return None,
+            "draft" => DocStatus::Draft,
+            "deprecated" => DocStatus::Deprecated,
+            "approved" => DocStatus::Approved,
+            _ => DocStatus::Other(value.to_string()),
+        })
+    }
+
+    fn label(&self) -> &str {
+        match self {
+            DocStatus::Draft => "Draft",
+            DocStatus::Deprecated => "Deprecated",
+            DocStatus::Approved => "Approved",
+            DocStatus::Other(value) => value,
+        }
+    }
+
+    fn color(&self, ui: &egui::Ui) -> egui::Color32 {
+        match self {
+            DocStatus::Draft => egui::Color32::from_rgb(0xc8, 0x8a, 0x12),
+            DocStatus::Deprecated => egui::Color32::from_rgb(0xc0, 0x39, 0x2b),
+            DocStatus::Approved => egui::Color32::from_rgb(0x2e, 0x8b, 0x57),
+            DocStatus::Other(_) => ui.visuals().widgets.inactive.bg_fill,
+        }
+    }
+
+    /// A rounded, colored status label. `small` is for tight spots like the tab strip.
+    pub fn show(&self, ui: &mut egui::Ui, small: bool) -> egui::Response {
+        let fill = self.color(ui);
+        let text_color = match self {
+            DocStatus::Other(_) => ui.visuals().text_color(),
+            _ => egui::Color32::WHITE,
+        };
+        let mut text = egui::RichText::new(self.label()).color(text_color).strong();
+        if small {
+            text = text.small();
+        }
+        egui::Frame::new()
+            .fill(fill)
+            .corner_radius(6.0)
+            .inner_margin(if small {
+                egui::Margin::symmetric(4, 0)
+            } else {
+                egui::Margin::symmetric(8, 2)
+            })
+            .show(ui, |ui| ui.label(text))
+            .response
+            .on_hover_text(tr("Document status from its frontmatter"))
+    }
+}
@@ -0,0 +1,1026 @@
+//! Splits a document into its top-level blocks so each one can be scrolled to,
+//! highlighted, or get extra UI around the regular `egui_commonmark` rendering.
+
+use std::{collections::HashMap, ops::Range};
+
+use pulldown_cmark::{CodeBlockKind, Event, MetadataBlockKind, Parser, Tag, TagEnd};
+
+use crate::badge::DocStatus;
+use crate::callout::{self, Callout};
+use crate::config::Dialect;
+use crate::dialect;
+use crate::frontmatter;
+use crate::hints::Hints;
+
+use crate::i18n::tr_args;
+use crate::lang_detect::{self, Guess};
+
+#[derive(Clone, Default)]
+pub struct Document {
+    pub blocks: Vec<Block>,
+    /// Byte ranges of code block contents and inline code spans, in source order.
+    pub code_spans: Vec<Range<usize>>,
+    /// Deepest nesting of block and inline elements, e.g. quotes within lists.
+    pub max_depth: usize,
+    /// Cell count of the largest table.
+    pub max_table_cells: usize,
+    /// The `status` key of the frontmatter.
+    pub status: Option<DocStatus>,
+    /// How the frontmatter asks for the document to be shown.
+    pub hints: Hints,
+    /// Images with a caption, in order.
+    pub figures: Vec<Figure>,
+    /// Footnotes with a definition, in the order they're defined.
+    pub footnotes: Vec<Footnote>,
+}
+
+/// An image followed by an emphasized paragraph, which [`split`] numbers
+/// as its caption.
+#[derive(Clone)]
+pub struct Figure {
+    pub number: usize,
+    /// The caption's text, without formatting.
+    pub caption: String,
+    /// The block with the image.
+    pub block: usize,
+}
+
+/// A `[^label]: …` definition, with the blocks referring to it. As on
+/// GitHub, footnotes are numbered in the order they're first referred to,
+/// and [`split`] writes the number in place of the label.
+#[derive(Clone)]
+pub struct Footnote {
+    pub label: String,
+    pub number: usize,
+    /// The definition's text, without formatting.
+    pub text: String,
+    /// The block with the definition.
+    pub block: usize,
+    /// The blocks with a `[^label]` reference, in order.
+    pub references: Vec<usize>,
+}
+
+#[derive(Clone)]
+pub struct Block {
+    /// Byte range of the block in the document source.
+    pub span: Range<usize>,
+    /// Markdown handed to the renderer for this block.
+    pub markdown: String,
+    /// `markdown` with its wikilinks rewritten by [`crate::tab::DocTab::resolve_wikilinks`].
+    pub wiki_markdown: Option<String>,
+    pub kind: BlockKind,
+    /// URIs of the images in the block, as the renderer loads them.
+    pub images: Vec<String>,
+    /// Language and contents, if the block is a code block.
+    pub code: Option<Code>,
+    /// How many words there are to read in it.
+    pub words: usize,
+}
+
+#[derive(Clone)]
+pub struct Code {
+    /// First word of the fence's info string.
+    pub lang: Option<String>,
+    pub text: String,
+}
+
+#[derive(Clone)]
+pub enum BlockKind {
+    /// Regular markdown, rendered as-is.
+    Prose,
+    /// A top-level heading.
+    Heading { level: u8, title: String },
+    /// YAML frontmatter between `---` fences at the very start; not rendered.
+    Frontmatter,
+    /// A fenced code block without an info string.
+    UnlabeledCode {
+        guess: Option<Guess>,
+        /// Same fence as `markdown`, with the guessed language filled in.
+        guessed_markdown: String,
+    },
+}
+
+/// Words read in a minute, by an adult reading for understanding.
+pub const WORDS_PER_MINUTE: usize = 230;
+
+/// Beyond these the renderer recurses too deeply or takes too long per frame.
+const MAX_RENDER_DEPTH: usize = 64;
+const MAX_RENDER_TABLE_CELLS: usize = 20_000;
+
+impl Document {
+    /// Why this document is too much for the markdown renderer, if it is.
+    pub fn render_risk(&self) -> Option<String> {
+        if self.max_depth > MAX_RENDER_DEPTH {
+            Some(tr_args(
+                "elements are nested {depth} levels deep",
+                &[("depth", &self.max_depth)],
+            ))
+        } else if self.max_table_cells > MAX_RENDER_TABLE_CELLS {
+            Some(tr_args(
+                "a table has {cells} cells",
+                &[("cells", &self.max_table_cells)],
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// How many words there are to read in the whole document.
+    pub fn words(&self) -> usize {
+        words_in(&self.blocks)
+    }
+
+    /// How many words there are to read in the section started by the
+    /// heading at `idx`, its subsections included.
+    pub fn section_words(&self, idx: usize) -> usize {
+        words_in(&self.blocks[idx..self.section_end(idx)])
+    }
+
+    /// The number of each block's heading by its place in the outline:
+    /// `1.` for the first at the top level, `1.1` for the first below it,
+    /// `1.1.1` below that. `None` for blocks that aren't headings.
+    pub fn heading_numbers(&self) -> Vec<Option<String>> {
+        let top = (0..self.blocks.len())
+            .filter_map(|idx| self.heading_level(idx))
+            .min()
+            .unwrap_or(1);
+        let mut counts = [0usize; 6];
+        (0..self.blocks.len())
+            .map(|idx| {
+                let depth = usize::from(self.heading_level(idx)? - top);
+                counts[depth] += 1;
+                counts[depth + 1..].fill(0);
+                let parts: Vec<String> = counts[..=depth].iter().map(usize::to_string).collect();
+                Some(match depth {
+                    0 => format!("{}.", parts[0]),
+                    _ => parts.join("."),
+                })
+            })
+            .collect()
+    }
+
+    /// Heading level of the block at `idx`, if it is a heading.
+    pub fn heading_level(&self, idx: usize) -> Option<u8> {
+        match self.blocks.get(idx)?.kind {
+            BlockKind::Heading { level, .. } => Some(level),
+            _ => None,
+        }
+    }
+
+    /// Index one past the last block belonging to the section started by the
+    /// heading at `idx`, i.e. the next heading of the same or a higher level.
+    pub fn section_end(&self, idx: usize) -> usize {
+        let Some(level) = self.heading_level(idx) else {
+            return idx + 1;
+        };
+        (idx + 1..self.blocks.len())
+            .find(|&i| self.heading_level(i).is_some_and(|l| l <= level))
+            .unwrap_or(self.blocks.len())
+    }
+
+    /// Index of the block closing the `<details>` element the block at `idx`
+    /// leaves open, or one past the last block if none does.
+    pub fn details_end(&self, idx: usize) -> usize {
+        let depth = |i: usize| {
+            let block = &self.blocks[i];
+            if block.code.is_some() {
+                return 0;
+            }
+            let lower = block.markdown.to_ascii_lowercase();
+            lower.matches("<details").count() as isize - lower.matches("</details").count() as isize
+        };
+        let mut open = depth(idx);
+        (idx + 1..self.blocks.len())
+            .find(|&i| {
+                open += depth(i);
+                open <= 0
+            })
+            .unwrap_or(self.blocks.len())
+    }
+
+    /// Slug of the heading at `idx` and how many earlier headings share it,
+    /// which identifies the heading across edits that move it around.
+    pub fn anchor(&self, idx: usize) -> Option<(String, usize)> {
+        let slug = self.blocks.get(idx)?.slug()?;
+        let occurrence = self.blocks[..idx]
+            .iter()
+            .filter(|b| b.slug().as_ref() == Some(&slug))
+            .count();
+        Some((slug, occurrence))
+    }
+
+    /// The `#` fragment GitHub links the heading at `idx` with: its slug,
+    /// with `-1`, `-2`… on the ones repeating an earlier heading's.
+    pub fn fragment(&self, idx: usize) -> Option<String> {
+        let (slug, occurrence) = self.anchor(idx)?;
+        Some(match occurrence {
+            0 => slug,
+            n => format!("{slug}-{n}"),
+        })
+    }
+
+    /// [`Self::fragment`] of every heading, with its index, found at once.
+    pub fn fragments(&self) -> Vec<(usize, String)> {
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, block)| {
+                let slug = block.slug()?;
+                let occurrence = seen.entry(slug.clone()).or_default();
+                let fragment = match *occurrence {
+                    0 => slug,
+                    n => format!("{slug}-{n}"),
+                };
+                *occurrence += 1;
+                Some((idx, fragment))
+            })
+            .collect()
+    }
+
+    /// Index of the heading linked to as `#fragment`, by its
+    /// [`Self::fragment`] or else by the slug of the text given.
+    pub fn find_fragment(&self, fragment: &str) -> Option<usize> {
+        (0..self.blocks.len())
+            .find(|&idx| self.fragment(idx).as_deref() == Some(fragment))
+            .or_else(|| self.find_anchor(&slug(fragment), 0))
+    }
+
+    /// Index of the heading with the given [`Self::anchor`].
+    pub fn find_anchor(&self, slug: &str, occurrence: usize) -> Option<usize> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.slug().as_deref() == Some(slug))
+            .nth(occurrence)
+            .map(|(idx, _)| idx)
+    }
+
+    /// Indices of the headings whose sections contain the block at `idx`,
+    /// innermost first.
+    pub fn enclosing_headings(&self, idx: usize) -> Vec<usize> {
+        let mut headings = Vec::new();
+        let mut max_level = u8::MAX;
+        for i in (0..idx).rev() {
+            if let Some(level) = self.heading_level(i)
+                && level < max_level
+            {
+                headings.push(i);
+                max_level = level;
+            }
+        }
+        headings
+    }
+
+    /// The footnote defined in the block at `idx`, if it's a definition.
+    pub fn footnote_defined_in(&self, idx: usize) -> Option<&Footnote> {
+        self.footnotes.iter().find(|note| note.block == idx)
+    }
+
+    /// Adds `next`, split from the source that follows this document's at
+    /// `offset`, to its end.
+    pub fn append(&mut self, next: Document, offset: usize) {
+        let first = self.blocks.len();
+        let shift = |span: Range<usize>| span.start + offset..span.end + offset;
+        self.blocks.extend(next.blocks.into_iter().map(|mut block| {
+            block.span = shift(block.span);
+            block
+        }));
+        self.code_spans
+            .extend(next.code_spans.into_iter().map(shift));
+        self.max_depth = self.max_depth.max(next.max_depth);
+        self.max_table_cells = self.max_table_cells.max(next.max_table_cells);
+        let numbered = self.figures.len();
+        self.figures
+            .extend(next.figures.into_iter().map(|figure| Figure {
+                number: figure.number + numbered,
+                block: figure.block + first,
+                ..figure
+            }));
+        self.footnotes
+            .extend(next.footnotes.into_iter().map(|note| Footnote {
+                block: note.block + first,
+                references: note.references.iter().map(|idx| idx + first).collect(),
+                ..note
+            }));
+    }
+}
+
+impl Footnote {
+    /// The number in superscript digits, as references show it.
+    pub fn mark(&self) -> String {
+        const DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+        self.number
+            .to_string()
+            .chars()
+            .filter_map(|digit| Some(DIGITS[digit.to_digit(10)? as usize]))
+            .collect()
+    }
+}
+
+impl Block {
+    /// [`slug`] of the title, if this is a heading.
+    pub fn slug(&self) -> Option<String> {
+        match &self.kind {
+            BlockKind::Heading { title, .. } => Some(slug(title)),
+            _ => None,
+        }
+    }
+}
+
+/// How many words there are to read in `blocks`.
+fn words_in(blocks: &[Block]) -> usize {
+    blocks
+        .iter()
+        .filter(|block| !matches!(block.kind, BlockKind::Frontmatter))
+        .map(|block| block.words)
+        .sum()
+}
+
+/// Where to cut a source into pieces of about `size` bytes that can be split
+/// on their own, as it comes in: at blank lines outside of code fences and
+/// `:::` containers, before a line that isn't indented into what came before.
+pub struct Cuts {
+    size: usize,
+    /// Where the last cut is, and how far the source came.
+    start: usize,
+    at: usize,
+    fence: Option<(char, usize)>,
+    containers: usize,
+    after_blank: bool,
+}
+
+impl Cuts {
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            start: 0,
+            at: 0,
+            fence: None,
+            containers: 0,
+            after_blank: false,
+        }
+    }
+
+    /// The cuts in `lines`, the whole lines following those before, as
+    /// offsets into the whole source.
+    pub fn find(&mut self, lines: &str) -> Vec<usize> {
+        let mut ends = Vec::new();
+        for line in lines.split_inclusive('\n') {
+            let blank = line.trim().is_empty();
+            if self.fence.is_none()
+                && self.containers == 0
+                && self.after_blank
+                && !blank
+                && !line.starts_with([' ', '\t'])
+                && self.at - self.start >= self.size
+            {
+                ends.push(self.at);
+                self.start = self.at;
+            }
+            let trimmed = line.trim_start_matches(' ');
+            let marker = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'));
+            let run = marker.map_or(0, |c| trimmed.chars().take_while(|&d| d == c).count());
+            match (self.fence, marker) {
+                (None, Some(c)) if run >= 3 => self.fence = Some((c, run)),
+                (Some((open, len)), Some(c))
+                    if c == open && run >= len && trimmed[run..].trim().is_empty() =>
+                {
+                    self.fence = None;
+                }
+                _ if self.fence.is_some() => {}
+                _ if self.containers > 0 && callout::is_container_close(line) => {
+                    self.containers -= 1;
+                }
+                _ if callout::is_container_open(line) => self.containers += 1,
+                _ => {}
+            }
+            self.after_blank = blank && self.fence.is_none();
+            self.at += line.len();
+        }
+        ends
+    }
+}
+
+/// GitHub-style anchor for a heading: lowercase words joined by hyphens.
+pub fn slug(title: &str) -> String {
+    title
+        .chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                Some(c.to_lowercase().next().unwrap_or(c))
+            } else if c.is_whitespace() {
+                Some('-')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Same options `egui_commonmark` parses with, so block boundaries line up,
+/// plus frontmatter, which gets split off before rendering.
+pub fn parser_options() -> pulldown_cmark::Options {
+    use pulldown_cmark::Options;
+    Options::ENABLE_TABLES
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_DEFINITION_LIST
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+}
+
+pub fn split(source: &str) -> Document {
+    split_as(source, Dialect::default())
+}
+
+/// The blocks of `source` read as `dialect`, each with the markdown that
+/// shows it that way.
+pub fn split_as(source: &str, dialect: Dialect) -> Document {
+    parse_as(source, dialect).finish_as(source, dialect)
+}
+
+/// The blocks of a piece of a document as [`split`] finds them, before the
+/// footnotes, reference links and figures, which depend on the rest of the
+/// document, are filled in.
+#[derive(Clone)]
+pub struct Parts {
+    doc: Document,
+    /// Footnote definitions and references, by where they are
+    definitions: Vec<(String, usize, String)>,
+    references: Vec<(String, Range<usize>)>,
+    /// [`reference_definitions`] in the piece.
+    ref_defs: String,
+}
+
+impl Parts {
+    /// The document, for parts parsed from all of `source`.
+    pub fn finish(self, source: &str) -> Document {
+        self.finish_as(source, Dialect::default())
+    }
+
+    fn finish_as(self, source: &str, dialect: Dialect) -> Document {
+        let mut doc = self.doc;
+        // Reference definitions usually live at the bottom of the file, so
+        // blocks using `[text][label]` links get a copy to keep them working.
+        if !self.ref_defs.is_empty() {
+            for block in &mut doc.blocks {
+                if has_broken_links(&block.markdown) {
+                    block.markdown.push('\n');
+                    block.markdown.push_str(&self.ref_defs);
+                }
+            }
+        }
+        link_footnotes(&mut doc, self.definitions, self.references);
+        for block in &mut doc.blocks {
+            if let Some(adapted) = dialect::adapt(&block.markdown, dialect) {
+                block.markdown = adapted;
+            }
+        }
+        number_figures(&mut doc);
+        if let Some(frontmatter) = frontmatter::extract(source) {
+            doc.hints = Hints::from_frontmatter(frontmatter);
+        }
+        doc
+    }
+
+    /// Adds `next`, parsed from the source that follows this piece's at
+    /// `offset`, to its end.
+    fn append(&mut self, next: Parts, offset: usize) {
+        self.doc.append(next.doc, offset);
+        self.definitions.extend(
+            next.definitions
+                .into_iter()
+                .map(|(label, start, text)| (label, start + offset, text)),
+        );
+        self.references.extend(
+            next.references
+                .into_iter()
+                .map(|(label, span)| (label, span.start + offset..span.end + offset)),
+        );
+        self.ref_defs.push_str(&next.ref_defs);
+    }
+
+    /// Finds the footnote references in pieces of `source` parsed without
+    /// the definition they refer to, which were taken for text.
+    fn refer_across(&mut self, source: &str) {
+        if self.definitions.is_empty() {
+            return;
+        }
+        let stubs: String = self
+            .definitions
+            .iter()
+            .map(|(label, _, _)| format!("\n\n[^{label}]: _"))
+            .collect();
+        for block in &self.doc.blocks {
+            let text = &source[block.span.clone()];
+            if !text.contains("[^") {
+                continue;
+            }
+            let defined = format!("{text}{stubs}");
+            let parser = Parser::new_ext(&defined, dialect::options(Dialect::default()));
+            for (event, span) in parser.into_offset_iter() {
+                if let Event::FootnoteReference(label) = event
+                    && span.end <= text.len()
+                {
+                    let span = span.start + block.span.start..span.end + block.span.start;
+                    if !self.references.iter().any(|(_, known)| *known == span) {
+                        self.references.push((label.to_string(), span));
+                    }
+                }
+            }
+        }
+        self.references.sort_by_key(|(_, span)| span.start);
+    }
+}
+
+/// [`split`] up to what depends on the rest of the document, for a chunk of
+/// it to be [`join`]ed with the others.
+pub fn parse(source: &str) -> Parts {
+    parse_as(source, Dialect::default())
+}
+
+/// The document `source` was cut into, as [`split`] would find it, from the
+/// [`parse`]d `pieces` by where each starts. Two pieces are parsed again as
+/// one where a block runs over the cut between them, as an unclosed HTML
+/// comment does; the rest only get what depends on the whole filled in.
+pub fn join(source: &str, pieces: Vec<(usize, Parts)>) -> Document {
+    let ends: Vec<usize> = pieces
+        .iter()
+        .skip(1)
+        .map(|(start, _)| *start)
+        .chain([source.len()])
+        .collect();
+    let mut joined: Vec<(Range<usize>, Parts)> = Vec::new();
+    for ((start, parts), end) in pieces.into_iter().zip(ends) {
+        match joined.last_mut() {
+            Some((range, last)) if !cut_holds(source, range, last, &parts) => {
+                range.end = end;
+                *last = parse(&source[range.clone()]);
+            }
+            _ => joined.push((start..end, parts)),
+        }
+    }
+    let mut joined = joined.into_iter();
+    let Some((_, mut whole)) = joined.next() else {
+        return split(source);
+    };
+    for (range, parts) in joined {
+        whole.append(parts, range.start);
+    }
+    whole.refer_across(source);
+    whole.finish(source)
+}
+
+/// Whether the last block of `before`, parsed from `source[range]`, and the
+/// first of `after`, parsed from what follows, are the ones parsing across
+/// the cut finds.
+fn cut_holds(source: &str, range: &Range<usize>, before: &Parts, after: &Parts) -> bool {
+    let (Some(last), Some(first)) = (before.doc.blocks.last(), after.doc.blocks.first()) else {
+        return true;
+    };
+    let start = range.start + last.span.start;
+    let cut = range.end - start;
+    let across = parse(&source[start..range.end + first.span.end]);
+    let spans: Vec<Range<usize>> = across.doc.blocks.into_iter().map(|b| b.span).collect();
+    spans
+        == [
+            0..last.span.len(),
+            cut + first.span.start..cut + first.span.end,
+        ]
+}
+
+fn parse_as(source: &str, dialect: Dialect) -> Parts {
+    let parser = Parser::new_ext(source, dialect::options(dialect));
+    let ref_defs = reference_definitions(&parser, source);
+
+    let mut doc = Document::default();
+    let mut depth = 0usize;
+    let mut in_code_block = false;
+    let mut table_cells = 0usize;
+    let mut images = Vec::new();
+    let mut code = None;
+    // Start offset and kind of the top-level block being parsed.
+    let mut current: Option<(usize, BlockKind)> = None;
+    // Footnote definitions and references, by where they are
+    let mut definitions: Vec<(String, usize, String)> = Vec::new();
+    let mut references: Vec<(String, Range<usize>)> = Vec::new();
+
+    for (event, span) in parser.into_offset_iter() {
+        match event {
+            Event::Start(tag) => {
+                if depth == 0 {
+                    current = Some((span.start, kind_for(&tag, source, &span)));
+                    if let Tag::CodeBlock(fence) = &tag {
+                        code = Some(Code {
+                            lang: match fence {
+                                CodeBlockKind::Fenced(info) => {
+                                    info.split_whitespace().next().map(String::from)
+                                }
+                                CodeBlockKind::Indented => None,
+                            },
+                            text: String::new(),
+                        });
+                    }
+                }
+                match tag {
+                    Tag::CodeBlock(_) => in_code_block = true,
+                    Tag::FootnoteDefinition(label) => {
+                        definitions.push((label.to_string(), span.start, String::new()));
+                    }
+                    Tag::Image { dest_url, .. } => images.push(image_uri(&dest_url)),
+                    Tag::Table(_) => table_cells = 0,
+                    Tag::TableCell => {
+                        table_cells += 1;
+                        doc.max_table_cells = doc.max_table_cells.max(table_cells);
+                    }
+                    _ => {}
+                }
+                depth += 1;
+                doc.max_depth = doc.max_depth.max(depth);
+            }
+            Event::End(_) => {
+                depth = depth.saturating_sub(1);
+                in_code_block = false;
+                if depth == 0
+                    && let Some((start, kind)) = current.take()
+                {
+                    let mut finished = block(source, start..span.end, kind);
+                    finished.images = std::mem::take(&mut images);
+                    finished.code = code.take();
+                    doc.blocks.push(finished);
+                }
+            }
+            Event::FootnoteReference(label) => references.push((label.to_string(), span)),
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some((_, start, text)) = definitions.last_mut()
+                    && current.as_ref().is_some_and(|(at, _)| at == start)
+                {
+                    text.push(' ');
+                }
+            }
+            Event::Text(text) => {
+                if let Some((_, start, note)) = definitions.last_mut()
+                    && current.as_ref().is_some_and(|(at, _)| at == start)
+                {
+                    note.push_str(&text);
+                }
+                if let Some((_, BlockKind::Frontmatter)) = &current {
+                    // The frontmatter may arrive in several pieces
+                    if let Some(status) = DocStatus::from_frontmatter(&text) {
+                        doc.status = Some(status);
+                    }
+                } else if in_code_block {
+                    if let Some(code) = &mut code {
+                        code.text.push_str(&text);
+                    }
+                    doc.code_spans.push(span);
+                } else if let Some((_, BlockKind::Heading { title, .. })) = &mut current {
+                    title.push_str(&text);
+                }
+            }
+            Event::Code(text) => {
+                if let Some((_, start, note)) = definitions.last_mut()
+                    && current.as_ref().is_some_and(|(at, _)| at == start)
+                {
+                    note.push_str(&text);
+                }
+                if let Some((_, BlockKind::Heading { title, .. })) = &mut current {
+                    title.push_str(&text);
+                }
+                doc.code_spans.push(span);
+            }
+            // Rules are the only block-level element without start and end events.
+            Event::Rule if depth == 0 => {
+                doc.blocks.push(block(source, span, BlockKind::Prose));
+            }
+            _ => {}
+        }
+    }
+
+    for block in &mut doc.blocks {
+        if let BlockKind::Heading { title, .. } = &mut block.kind {
+            *title = title.trim().to_string();
+        }
+    }
+    merge_containers(&mut doc, source, dialect);
+    Parts {
+        doc,
+        definitions,
+        references,
+        ref_defs,
+    }
+}
+
+/// Makes each `:::kind` callout one block. Markdown doesn't know them: blank
+/// lines in one split it up, and a closing `:::` can end up in a table or a
+/// definition list along with what follows.
+fn merge_containers(doc: &mut Document, source: &str, dialect: Dialect) {
+    for region in containers(source, &doc.code_spans).into_iter().rev() {
+        let Some(first) = doc.blocks.iter().position(|b| b.span.end > region.start) else {
+            continue;
+        };
+        let Some(last) = doc.blocks.iter().rposition(|b| b.span.start < region.end) else {
+            continue;
+        };
+        if last < first || Callout::parse(&source[region.clone()]).is_none() {
+            continue;
+        }
+        let (start, end) = (doc.blocks[first].span.start, doc.blocks[last].span.end);
+        let mut container = block(source, region.clone(), BlockKind::Prose);
+        container.images = doc.blocks[first..=last]
+            .iter_mut()
+            .flat_map(|block| std::mem::take(&mut block.images))
+            .collect();
+        let mut merged = Vec::new();
+        if start < region.start {
+            merged.extend(split_part(source, start..region.start, dialect));
+        }
+        merged.push(container);
+        if region.end < end {
+            merged.extend(split_part(source, region.end..end, dialect));
+        }
+        doc.blocks.splice(first..=last, merged);
+    }
+}
+
+/// Where the outermost `:::kind` containers of `source` are, from the start
+/// of the opening line to the end of the closing one.
+fn containers(source: &str, code_spans: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut found = Vec::new();
+    let mut open = 0;
+    let mut depth = 0;
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        if code_spans.iter().any(|span| span.contains(&start)) {
+            continue;
+        }
+        if depth > 0 && callout::is_container_close(line) {
+            depth -= 1;
+            if depth == 0 {
+                found.push(open..offset);
+            }
+        } else if callout::is_container_open(line) {
+            if depth == 0 {
+                open = start;
+            }
+            depth += 1;
+        }
+    }
+    found
+}
+
+/// The blocks of `source[range]` split on its own.
+fn split_part(source: &str, range: Range<usize>, dialect: Dialect) -> Vec<Block> {
+    parse_as(&source[range.clone()], dialect)
+        .doc
+        .blocks
+        .into_iter()
+        .map(|part| {
+            let span = part.span.start + range.start..part.span.end + range.start;
+            let mut shifted = block(source, span, part.kind);
+            shifted.images = part.images;
+            shifted.code = part.code;
+            shifted
+        })
+        .collect()
+}
+
+/// What a paragraph holds, as far as figures go.
+enum FigurePart {
+    Image,
+    /// Nothing but emphasis starting at the offset, with its text.
+    Caption(usize, String),
+    /// Images, then the caption on a line of its own.
+    Figure(usize, String),
+    Other,
+}
+
+fn figure_part(block: &Block) -> FigurePart {
+    // Neither an image nor emphasis, without parsing it
+    if !matches!(block.kind, BlockKind::Prose)
+        || !block.markdown.trim_start().starts_with(['!', '*', '_'])
+    {
+        return FigurePart::Other;
+    }
+    let mut events = Parser::new_ext(&block.markdown, parser_options()).into_offset_iter();
+    if !matches!(events.next(), Some((Event::Start(Tag::Paragraph), _))) {
+        return FigurePart::Other;
+    }
+    let mut images = 0;
+    let mut caption: Option<(usize, String)> = None;
+    // How deep inside an image or the caption
+    let mut depth = 0;
+    for (event, span) in events {
+        match event {
+            Event::Start(tag) => {
+                if depth == 0 {
+                    match tag {
+                        Tag::Image { .. } if caption.is_none() => images += 1,
+                        Tag::Emphasis if caption.is_none() => {
+                            caption = Some((span.start, String::new()));
+                        }
+                        _ => return FigurePart::Other,
+                    }
+                }
+                depth += 1;
+            }
+            Event::End(TagEnd::Paragraph) if depth == 0 => break,
+            Event::End(_) => depth -= 1,
+            Event::Text(text) | Event::Code(text) => match &mut caption {
+                Some((_, caption)) if depth > 0 => caption.push_str(&text),
+                _ if depth > 0 || text.trim().is_empty() => {}
+                _ => return FigurePart::Other,
+            },
+            Event::SoftBreak | Event::HardBreak if depth == 0 => {}
+            Event::SoftBreak => {
+                if let Some((_, caption)) = &mut caption {
+                    caption.push(' ');
+                }
+            }
+            _ if depth > 0 => {}
+            _ => return FigurePart::Other,
+        }
+    }
+    match (images, caption) {
+        (0, Some((at, text))) => FigurePart::Caption(at, text),
+        (_, Some((at, text))) => FigurePart::Figure(at, text),
+        (0, None) => FigurePart::Other,
+        (_, None) => FigurePart::Image,
+    }
+}
+
+/// Finds the figures of `doc` and puts "Figure N:" in front of their captions.
+fn number_figures(doc: &mut Document) {
+    let parts: Vec<FigurePart> = doc.blocks.iter().map(figure_part).collect();
+    for (idx, part) in parts.iter().enumerate() {
+        let (caption_block, at, text, split_off) = match (part, parts.get(idx + 1)) {
+            (FigurePart::Figure(at, text), _) => (idx, *at, text, true),
+            (FigurePart::Image, Some(FigurePart::Caption(at, text))) => (idx + 1, *at, text, false),
+            _ => continue,
+        };
+        let number = doc.figures.len() + 1;
+        // A caption under the images in their paragraph gets one of its own
+        let label = match split_off {
+            true => format!("\n\n**Figure {number}:** "),
+            false => format!("**Figure {number}:** "),
+        };
+        doc.blocks[caption_block].markdown.insert_str(at, &label);
+        doc.figures.push(Figure {
+            number,
+            caption: text.trim().to_string(),
+            block: idx,
+        });
+    }
+}
+
+/// Puts the footnotes in `doc` with the blocks they're defined and
+/// referred to in, now that containers are merged, and numbers them.
+///
+/// Blocks are rendered on their own, without the definitions, so
+/// references would show as written: they become their number in bold
+/// superscript instead, a label of its own to hover.
+fn link_footnotes(
+    doc: &mut Document,
+    definitions: Vec<(String, usize, String)>,
+    references: Vec<(String, Range<usize>)>,
+) {
+    let block_at = |offset: usize| doc.blocks.iter().position(|b| b.span.contains(&offset));
+    let mut footnotes: Vec<Footnote> = definitions
+        .into_iter()
+        .filter_map(|(label, start, text)| {
+            Some(Footnote {
+                block: block_at(start)?,
+                label,
+                number: 0,
+                text: text.trim().to_string(),
+                references: Vec::new(),
+            })
+        })
+        .collect();
+    let mut rewrites = Vec::new();
+    for (label, span) in references {
+        let Some(note) = footnotes.iter_mut().find(|note| note.label == label) else {
+            continue;
+        };
+        let Some(idx) = block_at(span.start) else {
+            continue;
+        };
+        if !note.references.contains(&idx) {
+            note.references.push(idx);
+        }
+        rewrites.push((idx, span, label));
+    }
+    // By first reference, then the ones never referred to
+    let mut order: Vec<usize> = (0..footnotes.len()).collect();
+    order.sort_by_key(|&n| {
+        let label = &footnotes[n].label;
+        rewrites
+            .iter()
+            .position(|(_, _, referred)| referred == label)
+            .unwrap_or(usize::MAX)
+    });
+    for (number, n) in order.into_iter().enumerate() {
+        footnotes[n].number = number + 1;
+    }
+
+    for (idx, span, label) in rewrites.into_iter().rev() {
+        let Some(note) = footnotes.iter().find(|note| note.label == label) else {
+            continue;
+        };
+        let block = &mut doc.blocks[idx];
+        let local = span.start - block.span.start..span.end - block.span.start;
+        block
+            .markdown
+            .replace_range(local, &format!("**{}**", note.mark()));
+    }
+    for note in &footnotes {
+        let markdown = &mut doc.blocks[note.block].markdown;
+        let written = format!("[^{}]", note.label);
+        if let Some(at) = markdown.find(&written)
+            && markdown[..at].trim().is_empty()
+        {
+            markdown.replace_range(at..at + written.len(), &format!("[^{}]", note.number));
+        }
+    }
+    doc.footnotes = footnotes;
+}
+
+/// The source text of every link reference definition, for appending to
+/// fragments of the document that are rendered on their own.
+pub fn reference_definitions(parser: &Parser, source: &str) -> String {
+    parser
+        .reference_definitions()
+        .iter()
+        .map(|(_, def)| format!("\n{}", &source[def.span.clone()]))
+        .collect()
+}
+
+fn kind_for(tag: &Tag, source: &str, span: &Range<usize>) -> BlockKind {
+    match tag {
+        Tag::Heading { level, .. } => BlockKind::Heading {
+            level: *level as u8,
+            title: String::new(),
+        },
+        Tag::MetadataBlock(MetadataBlockKind::YamlStyle) => BlockKind::Frontmatter,
+        Tag::CodeBlock(CodeBlockKind::Fenced(info)) if info.trim().is_empty() => {
+            unlabeled_code(&source[span.clone()])
+        }
+        _ => BlockKind::Prose,
+    }
+}
+
+fn block(source: &str, span: Range<usize>, kind: BlockKind) -> Block {
+    let text = &source[span.clone()];
+    Block {
+        words: text
+            .split_whitespace()
+            .filter(|word| word.chars().any(char::is_alphanumeric))
+            .count(),
+        span,
+        markdown: text.to_string(),
+        wiki_markdown: None,
+        kind,
+        images: Vec::new(),
+        code: None,
+    }
+}
+
+/// The URI `egui_commonmark` loads an image link from: as written if it
+/// has a scheme, otherwise a `file://` path.
+pub fn image_uri(dest: &str) -> String {
+    if dest.contains("://") || dest.starts_with("data:") {
+        dest.to_string()
+    } else {
+        format!("file://{dest}")
+    }
+}
+
+/// Whether `text` on its own contains reference links that can't be resolved.
+fn has_broken_links(text: &str) -> bool {
+    let mut broken = false;
+    let callback = |_: pulldown_cmark::BrokenLink<'_>| {
+        broken = true;
+        None
+    };
+    Parser::new_with_broken_link_callback(text, parser_options(), Some(callback)).for_each(drop);
+    broken
+}
+
+fn unlabeled_code(fence: &str) -> BlockKind {
+    let (first_line, rest) = fence.split_once('\n').unwrap_or((fence, ""));
+    let body_end = rest.trim_end().rfind('\n').unwrap_or(0);
+    let guess = lang_detect::detect(&rest[..body_end]);
+
+    let guessed_markdown = match guess {
+        Some(g) => format!("{}{}\n{rest}", first_line.trim_end(), g.token),
+        None => fence.to_string(),
+    };
+
+    BlockKind::UnlabeledCode {
+        guess,
+        guessed_markdown,
+    }
+}
@@ -0,0 +1,205 @@
+//! Callouts: GitHub's `> [!NOTE]` quotes and `:::info` containers, drawn as
+//! colored boxes with an icon and a title over their contents.
+
+use eframe::egui;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Note,
+    Info,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+    Danger,
+}
+
+impl Kind {
+    /// The kind named in `[!NAME]` or after `:::`, in any case.
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "note" => Self::Note,
+            "info" => Self::Info,
+            "tip" | "hint" => Self::Tip,
+            "important" => Self::Important,
+            "warning" => Self::Warning,
+            "caution" => Self::Caution,
+            "danger" | "error" => Self::Danger,
+            _ => return None,
+        })
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Note => "Note",
+            Self::Info => "Info",
+            Self::Tip => "Tip",
+            Self::Important => "Important",
+            Self::Warning => "Warning",
+            Self::Caution => "Caution",
+            Self::Danger => "Danger",
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        match self {
+            Self::Note => "❕",
+            Self::Info => "ℹ",
+            Self::Tip => "💡",
+            Self::Important => "💬",
+            Self::Warning => "⚠",
+            Self::Caution | Self::Danger => "🔴",
+        }
+    }
+
+    /// The colors GitHub uses, lighter on a dark background.
+    fn color(self, dark_mode: bool) -> egui::Color32 {
+        let (light, dark) = match self {
+            Self::Note => ((9, 105, 218), (68, 147, 248)),
+            Self::Info => ((8, 126, 164), (56, 178, 218)),
+            Self::Tip => ((26, 127, 55), (63, 185, 80)),
+            Self::Important => ((130, 80, 223), (171, 125, 248)),
+            Self::Warning => ((154, 103, 0), (210, 153, 34)),
+            Self::Caution | Self::Danger => ((209, 36, 47), (248, 81, 73)),
+        };
+        let (r, g, b) = if dark_mode { dark } else { light };
+        egui::Color32::from_rgb(r, g, b)
+    }
+}
+
+pub struct Callout {
+    pub kind: Kind,
+    pub title: String,
+    /// The Markdown inside it.
+    pub body: String,
+}
+
+impl Callout {
+    /// The callout `markdown` is, if it is one: a quote starting with
+    /// `[!KIND]`, or a `:::kind` container up to its closing `:::`. Text
+    /// after the marker is the title.
+    pub fn parse(markdown: &str) -> Option<Self> {
+        let first = markdown.lines().next()?.trim_end();
+        if first.trim_start().starts_with('>') {
+            Self::parse_quote(markdown)
+        } else if first.starts_with(":::") {
+            Self::parse_container(markdown)
+        } else {
+            None
+        }
+    }
+
+    fn parse_quote(markdown: &str) -> Option<Self> {
+        let mut lines = markdown.lines();
+        let first = unquote(lines.next()?)?.trim();
+        let (name, title) = first.strip_prefix("[!")?.split_once(']')?;
+        let kind = Kind::from_name(name)?;
+
+        let mut body = String::new();
+        let mut quoted = true;
+        for line in lines {
+            // Past the quote come only the reference definitions, as they are
+            match unquote(line) {
+                Some(line) if quoted => body.push_str(line),
+                _ if quoted && !line.trim().is_empty() => body.push_str(line),
+                _ => {
+                    quoted = false;
+                    body.push_str(line);
+                }
+            }
+            body.push('\n');
+        }
+        Some(Self::new(kind, title, body))
+    }
+
+    fn parse_container(markdown: &str) -> Option<Self> {
+        let mut lines = markdown.lines();
+        let first = lines.next()?.trim_start_matches(':').trim();
+        let name_end = first
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(first.len());
+        let kind = Kind::from_name(&first[..name_end])?;
+        // `:::tip[Title]`, `:::tip{title="Title"}` and `:::tip Title` all name it
+        let title = first[name_end..]
+            .trim()
+            .trim_start_matches(['[', '{'])
+            .trim_end_matches([']', '}'])
+            .trim_start_matches("title=")
+            .trim_matches('"');
+
+        let mut body = String::new();
+        let mut depth = 1;
+        let mut closed = false;
+        for line in lines {
+            if !closed {
+                if is_container_close(line) {
+                    depth -= 1;
+                    if depth == 0 {
+                        closed = true;
+                        continue;
+                    }
+                } else if is_container_open(line) {
+                    depth += 1;
+                }
+            }
+            body.push_str(line);
+            body.push('\n');
+        }
+        closed.then(|| Self::new(kind, title, body))
+    }
+
+    fn new(kind: Kind, title: &str, body: String) -> Self {
+        let title = title.trim();
+        Self {
+            kind,
+            title: match title.is_empty() {
+                true => kind.title().to_string(),
+                false => title.to_string(),
+            },
+            body,
+        }
+    }
+
+    /// Draws the box, with `body` drawing the contents.
+    pub fn show(
+        &self,
+        ui: &mut egui::Ui,
+        body: impl FnOnce(&mut egui::Ui, &str),
+    ) -> egui::Response {
+        let color = self.kind.color(ui.visuals().dark_mode);
+        egui::Frame::new()
+            .fill(color.gamma_multiply(0.1))
+            .stroke(egui::Stroke::new(1.0, color.gamma_multiply(0.6)))
+            .corner_radius(4.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.horizontal(|ui| {
+                    ui.colored_label(color, self.kind.icon());
+                    ui.label(egui::RichText::new(&self.title).strong().color(color));
+                });
+                if !self.body.trim().is_empty() {
+                    body(ui, &self.body);
+                }
+            })
+            .response
+    }
+}
+
+/// `line` without its `>` marker, if it has one.
+fn unquote(line: &str) -> Option<&str> {
+    let rest = line.trim_start().strip_prefix('>')?;
+    Some(rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+/// Whether `line` opens a container, like `:::note`.
+pub fn is_container_open(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with(":::") && !line.trim_start_matches(':').trim().is_empty()
+}
+
+/// Whether `line` closes a container: colons alone.
+pub fn is_container_close(line: &str) -> bool {
+    let line = line.trim();
+    line.len() >= 3 && line.chars().all(|c| c == ':')
+}
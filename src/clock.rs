@@ -0,0 +1,33 @@
+//! The local time of day, which std doesn't know for lack of time zones.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The hour of the day it is here, from 0 to 23; the hour in UTC if the
+/// time zone can't be told.
+pub fn local_hour() -> u32 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    local(seconds as libc::time_t).unwrap_or((seconds / 3600 % 24) as u32)
+}
+
+#[cfg(unix)]
+fn local(seconds: libc::time_t) -> Option<u32> {
+    // SAFETY: `tm` is plain data, and both pointers are valid for the call
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    let done = unsafe { libc::localtime_r(&seconds, &mut tm) };
+    (!done.is_null()).then_some(tm.tm_hour as u32)
+}
+
+#[cfg(windows)]
+fn local(seconds: libc::time_t) -> Option<u32> {
+    // SAFETY: as for localtime_r
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    let error = unsafe { libc::localtime_s(&mut tm, &seconds) };
+    (error == 0).then_some(tm.tm_hour as u32)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn local(_seconds: libc::time_t) -> Option<u32> {
+    None
+}
@@ -0,0 +1,75 @@
+//! Tabs closed lately, to bring back with Ctrl+Shift+T or from File →
+//! Reopen Closed Tab where they were read up to.
+
+use std::path::PathBuf;
+
+use eframe::egui;
+
+use crate::reading_state::Position;
+use crate::tab::DocTab;
+
+pub const SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+    egui::Key::T,
+);
+
+/// How many closed tabs are remembered.
+const LIMIT: usize = 20;
+
+pub struct Closed {
+    pub title: String,
+    pub path: PathBuf,
+    pub plain_text: bool,
+    /// Place in the tab strip it was at.
+    pub index: usize,
+    pub position: Option<Position>,
+}
+
+#[derive(Default)]
+pub struct ClosedTabs {
+    /// The last one closed at the end.
+    closed: Vec<Closed>,
+}
+
+impl ClosedTabs {
+    /// Remembers `tab`, closed from place `index`. Scratch tabs have no
+    /// file to come back from.
+    pub fn push(&mut self, tab: &DocTab, index: usize) {
+        if tab.is_scratch() {
+            return;
+        }
+        self.closed.retain(|closed| closed.path != tab.path);
+        if self.closed.len() == LIMIT {
+            self.closed.remove(0);
+        }
+        self.closed.push(Closed {
+            title: tab.title.clone(),
+            path: tab.path.clone(),
+            plain_text: tab.plain_text,
+            index,
+            position: tab.position(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.closed.is_empty()
+    }
+
+    /// Titles of the closed tabs, the last one closed first, with what
+    /// [`Self::take`] takes for them.
+    pub fn titles(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.closed
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(n, closed)| (n, closed.title.as_str()))
+    }
+
+    pub fn take(&mut self, n: usize) -> Option<Closed> {
+        (n < self.closed.len()).then(|| self.closed.remove(n))
+    }
+
+    pub fn take_last(&mut self) -> Option<Closed> {
+        self.closed.pop()
+    }
+}
@@ -0,0 +1,244 @@
+//! Code blocks with numbered lines, or scrolling sideways instead of
+//! wrapping long ones, as set under Preferences or toggled for each block
+//! from buttons shown while it's hovered, next to one saving it to a file.
+
+use std::{fs, path::PathBuf, sync::OnceLock};
+
+use anyhow::{Context, Result};
+use eframe::egui::{self, text::LayoutJob};
+use rfd::FileDialog;
+use syntect::{easy::HighlightLines, highlighting::ThemeSet, util::LinesWithEndings};
+
+use crate::i18n::tr;
+use crate::monochrome;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+    pub line_numbers: bool,
+    pub wrap: bool,
+}
+
+impl Layout {
+    /// How `egui_commonmark` draws code blocks itself.
+    pub const COMMONMARK: Layout = Layout {
+        line_numbers: false,
+        wrap: true,
+    };
+
+    /// The layout of the block `id`: `default` unless toggled from its buttons.
+    pub fn of(ctx: &egui::Context, id: egui::Id, default: Layout) -> Layout {
+        ctx.data(|d| d.get_temp(id)).unwrap_or(default)
+    }
+}
+
+/// Draws `code` highlighted as `lang` with the syntect theme named `theme`.
+pub fn show(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    code: &str,
+    lang: Option<&str>,
+    theme: &str,
+    layout: Layout,
+) -> egui::Response {
+    let code = code.strip_suffix('\n').unwrap_or(code);
+    let themes = themes();
+    let theme = themes
+        .themes
+        .get(theme)
+        .unwrap_or_else(|| &themes.themes["base16-ocean.dark"]);
+    let color =
+        |color: syntect::highlighting::Color| egui::Color32::from_rgb(color.r, color.g, color.b);
+    let background = theme
+        .settings
+        .background
+        .map_or(ui.visuals().extreme_bg_color, color);
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+
+    let syntaxes = monochrome::syntaxes();
+    let syntax = lang
+        .and_then(|lang| syntaxes.find_syntax_by_extension(lang))
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+    let mut job = LayoutJob::default();
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    for line in LinesWithEndings::from(code) {
+        let ranges = highlighter
+            .highlight_line(line, syntaxes)
+            .unwrap_or_default();
+        for (style, piece) in ranges {
+            let format = egui::TextFormat::simple(font_id.clone(), color(style.foreground));
+            job.append(piece, 0.0, format);
+        }
+    }
+
+    let visuals = ui.visuals().widgets.noninteractive;
+    egui::Frame::new()
+        .fill(background)
+        .stroke(visuals.bg_stroke)
+        .corner_radius(visuals.corner_radius)
+        .inner_margin(egui::Margin::symmetric(4, 2))
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.horizontal_top(|ui| {
+                let weak = ui.visuals().weak_text_color();
+                let lines = code.lines().count().max(1);
+                let ch = ui.fonts(|f| f.glyph_width(&font_id, '0'));
+                let gutter = lines.to_string().len() as f32 * ch;
+                job.wrap.max_width = match (layout.wrap, layout.line_numbers) {
+                    (false, _) => f32::INFINITY,
+                    (true, false) => ui.available_width(),
+                    (true, true) => ui.available_width() - gutter - ui.spacing().item_spacing.x,
+                };
+                let galley = ui.fonts(|f| f.layout_job(job));
+
+                if layout.line_numbers {
+                    let (rect, _) = ui.allocate_exact_size(
+                        egui::vec2(gutter, galley.size().y),
+                        egui::Sense::hover(),
+                    );
+                    // Only on the first row of each line, so wrapped ones
+                    // show as continuing
+                    let mut number = 1;
+                    let mut starts_line = true;
+                    for row in &galley.rows {
+                        if starts_line {
+                            ui.painter().text(
+                                egui::pos2(rect.right(), rect.top() + row.pos.y),
+                                egui::Align2::RIGHT_TOP,
+                                number,
+                                font_id.clone(),
+                                weak,
+                            );
+                            number += 1;
+                        }
+                        starts_line = row.ends_with_newline;
+                    }
+                }
+                let text = egui::Label::new(galley).selectable(true);
+                match layout.wrap {
+                    true => ui.add(text),
+                    false => {
+                        egui::ScrollArea::horizontal()
+                            .id_salt(id)
+                            .show(ui, |ui| ui.add(text))
+                            .inner
+                    }
+                };
+            });
+        })
+        .response
+}
+
+/// Buttons over the top right of the code block at `rect` to number its
+/// lines or scroll them sideways, while the block is hovered, and to copy
+/// `code` if `egui_commonmark` didn't draw the block with its own.
+pub fn toggles(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    rect: egui::Rect,
+    layout: Layout,
+    code: Option<&str>,
+) {
+    if !ui.rect_contains_pointer(rect) {
+        return;
+    }
+    if let Some(code) = code
+        && ui
+            .put(
+                corner_button(ui, rect, 0.0),
+                egui::Button::new("🗐").small().frame(false),
+            )
+            .on_hover_text(tr("Copy"))
+            .clicked()
+    {
+        ui.ctx().copy_text(code.to_string());
+    }
+    let mut toggled = layout;
+    if ui
+        .put(
+            corner_button(ui, rect, 2.0),
+            egui::Button::new("#").small().selected(layout.line_numbers),
+        )
+        .on_hover_text(tr("Number lines"))
+        .clicked()
+    {
+        toggled.line_numbers ^= true;
+    }
+    if ui
+        .put(
+            corner_button(ui, rect, 1.0),
+            egui::Button::new("↔").small().selected(!layout.wrap),
+        )
+        .on_hover_text(tr("Scroll long lines instead of wrapping them"))
+        .clicked()
+    {
+        toggled.wrap ^= true;
+    }
+    if toggled != layout {
+        ui.data_mut(|d| d.insert_temp(id, toggled));
+    }
+}
+
+/// A button over the top right of the code block at `rect` to save its
+/// code, while the block is hovered. Returns whether it was clicked.
+pub fn save_button(ui: &mut egui::Ui, rect: egui::Rect) -> bool {
+    if !ui.rect_contains_pointer(rect) {
+        return false;
+    }
+    ui.put(
+        corner_button(ui, rect, 3.0),
+        egui::Button::new("💾").small().frame(false),
+    )
+    .on_hover_text(tr("Save to a file"))
+    .clicked()
+}
+
+/// Where the `n`th button from the right goes over the top right of the
+/// code block at `rect`, left of the copy button in the corner.
+fn corner_button(ui: &egui::Ui, rect: egui::Rect, n: f32) -> egui::Rect {
+    let size = ui.spacing().interact_size.y;
+    let padding = ui.spacing().button_padding;
+    let right = rect.right() - ui.spacing().icon_width - 2.0 * padding.x;
+    egui::Rect::from_min_size(
+        egui::pos2(right - n * size, rect.top() + padding.y),
+        egui::vec2(size, size),
+    )
+}
+
+/// A name for a file of code in `lang`, with the extension files of it
+/// usually have.
+pub fn file_name(lang: Option<&str>) -> String {
+    let extension = lang.and_then(|lang| {
+        let syntaxes = monochrome::syntaxes();
+        let known = syntaxes
+            .find_syntax_by_token(lang)
+            .and_then(|syntax| syntax.file_extensions.first());
+        match known {
+            Some(extension) => Some(extension.clone()),
+            // Most fences that syntect doesn't know are named like files are
+            None => lang
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric())
+                .then(|| lang.to_lowercase()),
+        }
+    });
+    format!("code.{}", extension.as_deref().unwrap_or("txt"))
+}
+
+/// Asks where to save `code` in `lang` and writes it there. Returns `None`
+/// if the user cancelled.
+pub fn save(code: &str, lang: Option<&str>) -> Result<Option<PathBuf>> {
+    let Some(path) = FileDialog::new()
+        .set_file_name(file_name(lang))
+        .set_title(tr("Save code"))
+        .save_file()
+    else {
+        return Ok(None);
+    };
+    fs::write(&path, code).with_context(|| format!("writing {}", path.display()))?;
+    Ok(Some(path))
+}
+
+fn themes() -> &'static ThemeSet {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
@@ -0,0 +1,243 @@
+//! Compare mode: what changed between two versions of a document, first as
+//! an outline of added, removed and renamed sections, then as a text diff.
+
+use eframe::egui;
+use similar::{ChangeTag, DiffOp, TextDiff};
+
+use crate::blocks::{BlockKind, Document};
+use crate::i18n::{tr, tr_args};
+
+pub struct Comparison {
+    /// Labels for the old and new versions, e.g. two file names.
+    old_label: String,
+    new_label: String,
+    outline: Vec<OutlineChange>,
+    lines: Vec<(ChangeTag, String)>,
+    /// Show the text diff without having to open it first.
+    text_first: bool,
+}
+
+enum OutlineChange {
+    Added { level: u8, title: String },
+    Removed { level: u8, title: String },
+    Renamed { level: u8, from: String, to: String },
+}
+
+/// What the compare view asks the window to do.
+#[derive(PartialEq, Eq)]
+pub enum CompareAction {
+    None,
+    Close,
+}
+
+impl Comparison {
+    pub fn new(
+        old_label: &str,
+        old: (&str, &Document),
+        new_label: &str,
+        new: (&str, &Document),
+    ) -> Self {
+        let lines = TextDiff::from_lines(old.0, new.0)
+            .iter_all_changes()
+            .map(|change| {
+                (
+                    change.tag(),
+                    change.value().trim_end_matches('\n').to_string(),
+                )
+            })
+            .collect();
+        Self {
+            old_label: old_label.to_string(),
+            new_label: new_label.to_string(),
+            outline: outline_changes(old.1, new.1),
+            lines,
+            text_first: false,
+        }
+    }
+
+    /// Opens the text diff from the start, for versions of the same file.
+    pub fn text_first(mut self) -> Self {
+        self.text_first = true;
+        self
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui) -> CompareAction {
+        let mut action = CompareAction::None;
+
+        ui.horizontal(|ui| {
+            ui.strong(tr("Comparing"));
+            ui.label(&self.old_label);
+            ui.weak("→");
+            ui.label(&self.new_label);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button(tr("Close Comparison")).clicked() {
+                    action = CompareAction::Close;
+                }
+            });
+        });
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .id_salt("comparison")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                ui.heading(tr("Outline changes"));
+                if self.outline.is_empty() {
+                    ui.weak(tr("Same sections in both versions"));
+                }
+                for change in &self.outline {
+                    show_outline_change(ui, change);
+                }
+
+                ui.add_space(12.0);
+                let changed = self
+                    .lines
+                    .iter()
+                    .filter(|(tag, _)| *tag != ChangeTag::Equal)
+                    .count();
+                egui::CollapsingHeader::new(tr_args(
+                    "Full text diff ({changed} lines changed)",
+                    &[("changed", &changed)],
+                ))
+                .id_salt(("comparison_text", self.text_first))
+                .default_open(self.text_first)
+                .show(ui, |ui| show_lines(ui, &self.lines));
+            });
+
+        action
+    }
+}
+
+fn show_outline_change(ui: &mut egui::Ui, change: &OutlineChange) {
+    let (added, removed) = diff_colors(ui);
+    ui.horizontal(|ui| match change {
+        OutlineChange::Added { level, title } => {
+            ui.add_space(indent(*level));
+            ui.colored_label(added, format!("+ {title}"));
+            ui.weak(tr("added"));
+        }
+        OutlineChange::Removed { level, title } => {
+            ui.add_space(indent(*level));
+            ui.colored_label(removed, format!("− {title}"));
+            ui.weak(tr("removed"));
+        }
+        OutlineChange::Renamed { level, from, to } => {
+            ui.add_space(indent(*level));
+            ui.label(format!("✎ {from}"));
+            ui.weak("→");
+            ui.label(to);
+            ui.weak(tr("renamed"));
+        }
+    });
+}
+
+/// Line diff colors: added, removed.
+pub fn diff_colors(ui: &egui::Ui) -> (egui::Color32, egui::Color32) {
+    if ui.visuals().dark_mode {
+        (
+            egui::Color32::from_rgb(0x7e, 0xc8, 0x7e),
+            egui::Color32::from_rgb(0xe0, 0x7a, 0x7a),
+        )
+    } else {
+        (
+            egui::Color32::from_rgb(0x1a, 0x7f, 0x37),
+            egui::Color32::from_rgb(0xb3, 0x26, 0x1e),
+        )
+    }
+}
+
+/// Colored `+`/`-` lines of a unified diff.
+fn show_lines(ui: &mut egui::Ui, lines: &[(ChangeTag, String)]) {
+    let (added, removed) = diff_colors(ui);
+    for (tag, line) in lines {
+        let (sign, color) = match tag {
+            ChangeTag::Insert => ('+', Some(added)),
+            ChangeTag::Delete => ('-', Some(removed)),
+            ChangeTag::Equal => (' ', None),
+        };
+        let mut text = egui::RichText::new(format!("{sign} {line}")).monospace();
+        if let Some(color) = color {
+            text = text.color(color);
+        }
+        ui.label(text);
+    }
+}
+
+fn indent(level: u8) -> f32 {
+    f32::from(level.saturating_sub(1)) * 12.0
+}
+
+/// Diffs the heading sequences of two documents. A run of headings replaced
+/// by another run is taken as renames, pairwise, with any excess added or removed.
+fn outline_changes(old: &Document, new: &Document) -> Vec<OutlineChange> {
+    let old_headings = headings(old);
+    let new_headings = headings(new);
+    let old_titles: Vec<&str> = old_headings.iter().map(|(_, t)| t.as_str()).collect();
+    let new_titles: Vec<&str> = new_headings.iter().map(|(_, t)| t.as_str()).collect();
+
+    let mut changes = Vec::new();
+    for op in similar::capture_diff_slices(similar::Algorithm::Myers, &old_titles, &new_titles) {
+        match op {
+            DiffOp::Equal { .. } => {}
+            DiffOp::Delete {
+                old_index, old_len, ..
+            } => {
+                for (level, title) in &old_headings[old_index..old_index + old_len] {
+                    changes.push(OutlineChange::Removed {
+                        level: *level,
+                        title: title.clone(),
+                    });
+                }
+            }
+            DiffOp::Insert {
+                new_index, new_len, ..
+            } => {
+                for (level, title) in &new_headings[new_index..new_index + new_len] {
+                    changes.push(OutlineChange::Added {
+                        level: *level,
+                        title: title.clone(),
+                    });
+                }
+            }
+            DiffOp::Replace {
+                old_index,
+                old_len,
+                new_index,
+                new_len,
+            } => {
+                let old_run = &old_headings[old_index..old_index + old_len];
+                let new_run = &new_headings[new_index..new_index + new_len];
+                for (from, to) in old_run.iter().zip(new_run) {
+                    changes.push(OutlineChange::Renamed {
+                        level: to.0,
+                        from: from.1.clone(),
+                        to: to.1.clone(),
+                    });
+                }
+                for (level, title) in old_run.iter().skip(new_len) {
+                    changes.push(OutlineChange::Removed {
+                        level: *level,
+                        title: title.clone(),
+                    });
+                }
+                for (level, title) in new_run.iter().skip(old_len) {
+                    changes.push(OutlineChange::Added {
+                        level: *level,
+                        title: title.clone(),
+                    });
+                }
+            }
+        }
+    }
+    changes
+}
+
+fn headings(doc: &Document) -> Vec<(u8, String)> {
+    doc.blocks
+        .iter()
+        .filter_map(|block| match &block.kind {
+            BlockKind::Heading { level, title } => Some((*level, title.clone())),
+            _ => None,
+        })
+        .collect()
+}
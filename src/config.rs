@@ -0,0 +1,810 @@
+//! Viewer preferences and the TOML file in the platform config directory
+//! they are kept in, e.g. `~/.config/md_viewer/config.toml`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::i18n;
+
+/// Syntax highlighting themes bundled with egui_commonmark, dark ones first.
+pub const CODE_THEMES: [&str; 7] = [
+    "base16-ocean.dark",
+    "base16-eighties.dark",
+    "base16-mocha.dark",
+    "Solarized (dark)",
+    "base16-ocean.light",
+    "InspiredGitHub",
+    "Solarized (light)",
+];
+
+/// How many documents opened lately are remembered.
+const RECENT_FILES: usize = 30;
+
+/// Choices for the UI scale, as factors of the OS scale.
+pub const UI_SCALES: [f32; 8] = [0.75, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0];
+
+/// Narrowest and widest the text column can be set to, in characters.
+pub const MIN_TEXT_WIDTH: u32 = 30;
+pub const MAX_TEXT_WIDTH: u32 = 300;
+
+/// Smallest and largest document text scale; the largest is for low vision.
+pub const MIN_TEXT_SCALE: f32 = 0.5;
+pub const MAX_TEXT_SCALE: f32 = 8.0;
+
+/// Viewer preferences shared by all windows.
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Language of the menus, dialogs and messages.
+    pub language: Language,
+    pub theme: Theme,
+    /// Hours of the day the scheduled theme turns light and dark.
+    pub light_from: u32,
+    pub dark_from: u32,
+    /// Colors of the document, over those of the theme.
+    pub paper: Paper,
+    /// Size of the whole interface relative to the OS scale factor.
+    pub ui_scale: f32,
+    /// Markdown text scale, also changed with A–/A+.
+    pub md_text_scale: f32,
+    /// Ignore Ctrl+wheel, Ctrl+Plus/Minus and A–/A+, keeping the sizes above.
+    pub lock_zoom: bool,
+    pub font: BodyFont,
+    /// Font files used on top of, or instead of, the built-in fonts.
+    pub fonts: Vec<CustomFont>,
+    /// Highlighting themes for code blocks in dark and light mode.
+    pub code_theme_dark: String,
+    pub code_theme_light: String,
+    pub guess_code_languages: bool,
+    /// The Markdown extensions documents are read with, unless a tab has
+    /// its own.
+    pub dialect: Dialect,
+    /// Number the lines of code blocks.
+    pub code_line_numbers: bool,
+    /// Wrap long lines of code blocks instead of scrolling them sideways.
+    pub wrap_code: bool,
+    /// Keep the document to a centered column of `text_width` characters.
+    pub limit_text_width: bool,
+    pub text_width: u32,
+    /// Space left on either side of the document.
+    pub margin: f32,
+    /// Follow the theme, width and text size a document's frontmatter asks for.
+    pub document_hints: bool,
+    /// Justify plain paragraphs, for long-form reading.
+    pub justify_text: bool,
+    /// Hyphenate words at line ends in justified paragraphs.
+    pub hyphenate: bool,
+    /// Number headings by their place in the outline, as 1., 1.1, 1.1.1.
+    pub number_headings: bool,
+    /// Black-on-white code blocks with bold and italics instead of colors,
+    /// for exports that get printed.
+    pub monochrome_code: bool,
+    /// Show the old and new text of ```` ```diff ```` fences side by side.
+    pub diff_side_by_side: bool,
+    /// Show the start of the target when hovering a link to another document or section.
+    pub link_previews: bool,
+    /// Line length marked in the raw source view.
+    pub column_guide: ColumnGuide,
+    /// Scroll with j/k, d/u and gg/G too.
+    pub vim_keys: bool,
+    /// Fetch images from the web; off, only local images are shown.
+    pub load_remote_images: bool,
+    /// Play animated GIFs and PNGs; off, only their first frame is shown.
+    pub animate_images: bool,
+    pub show_toc: bool,
+    /// Show how far into the document the view is, and how long each
+    /// section of the contents takes to read.
+    pub show_reading_progress: bool,
+    /// List the documents that link to the active one.
+    pub show_backlinks: bool,
+    /// List the captioned images of the active document.
+    pub show_figures: bool,
+    /// List the tags of the opened folder's documents.
+    pub show_tags: bool,
+    /// Show the branch and whether the document has uncommitted changes in
+    /// the status bar, asking `git` every few seconds.
+    pub show_git_status: bool,
+    pub auto_reload: bool,
+    /// Queue auto-reloads while scrolled into a document instead of applying them.
+    pub pause_reload_while_reading: bool,
+    /// Also detect changes by comparing file contents, for mounts with unreliable mtimes.
+    pub hash_change_detection: bool,
+    /// Seconds between content comparisons when `hash_change_detection` is on.
+    pub hash_poll_secs: u32,
+    /// Files larger than this many megabytes are shown a chunk at a time
+    /// while they load.
+    pub load_in_chunks_above_mb: u32,
+    /// Let go of the documents of tabs that weren't looked at for
+    /// `unload_tabs_after_mins`, splitting them again when they're shown.
+    pub unload_idle_tabs: bool,
+    pub unload_tabs_after_mins: u32,
+    /// Let go of their file contents too, reading the file again.
+    pub unload_tab_contents: bool,
+    /// Megabytes images may take in memory before those shown longest ago
+    /// are let go of.
+    pub image_cache_mb: u32,
+    pub always_on_top: bool,
+    /// Show the active document's whole path in the window title, not just its name.
+    pub path_in_title: bool,
+    pub startup: Startup,
+    /// Ask GitHub for newer releases when the app starts.
+    pub check_for_updates: bool,
+    /// Extra globs skipped when opening a folder, on top of its `.gitignore` files.
+    pub exclude_patterns: Vec<String>,
+    /// What links with these URL schemes do, instead of going to the system.
+    pub link_handlers: Vec<LinkHandler>,
+    /// Whether clicked links leaving the viewer open in the browser or app
+    /// their scheme goes to.
+    pub web_links: WebLinks,
+    /// Sites whose links open without asking, subdomains included.
+    pub trusted_sites: Vec<String>,
+    /// Folders whose documents may run git and the diagram programs,
+    /// subfolders included.
+    pub trusted_folders: Vec<PathBuf>,
+    /// Folders the user chose not to trust, not asked about again.
+    pub untrusted_folders: Vec<PathBuf>,
+    /// What to log in to sites with when opening documents from a URL.
+    pub site_logins: Vec<SiteLogin>,
+    /// The `pandoc` program exports to Word, OpenDocument and EPUB go through.
+    pub pandoc_path: String,
+    /// Draw PlantUML and Graphviz fences as diagrams, with these programs.
+    pub render_diagrams: bool,
+    pub plantuml_command: String,
+    pub graphviz_command: String,
+    /// The main window as it was last closed, to open it the same way.
+    pub window: WindowGeometry,
+    /// Documents opened lately, the last one first, for quick open.
+    pub recent_files: Vec<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            language: Language::System,
+            theme: Theme::System,
+            light_from: 7,
+            dark_from: 19,
+            paper: Paper::Theme,
+            ui_scale: 1.0,
+            md_text_scale: 1.0,
+            lock_zoom: false,
+            font: BodyFont::Proportional,
+            fonts: Vec::new(),
+            code_theme_dark: CODE_THEMES[0].into(),
+            code_theme_light: CODE_THEMES[4].into(),
+            guess_code_languages: true,
+            dialect: Dialect::default(),
+            code_line_numbers: false,
+            wrap_code: true,
+            limit_text_width: false,
+            text_width: 80,
+            margin: 0.0,
+            document_hints: true,
+            justify_text: false,
+            hyphenate: true,
+            number_headings: false,
+            monochrome_code: false,
+            diff_side_by_side: false,
+            link_previews: true,
+            column_guide: ColumnGuide::Off,
+            vim_keys: true,
+            load_remote_images: false,
+            animate_images: true,
+            show_toc: true,
+            show_reading_progress: true,
+            show_backlinks: false,
+            show_figures: false,
+            show_tags: false,
+            show_git_status: true,
+            auto_reload: false,
+            pause_reload_while_reading: true,
+            hash_change_detection: false,
+            hash_poll_secs: 10,
+            load_in_chunks_above_mb: 5,
+            unload_idle_tabs: false,
+            unload_tabs_after_mins: 15,
+            unload_tab_contents: false,
+            image_cache_mb: 512,
+            always_on_top: false,
+            path_in_title: false,
+            startup: Startup::Welcome,
+            check_for_updates: false,
+            exclude_patterns: ["node_modules/", "target/", "build/", "dist/"]
+                .map(String::from)
+                .to_vec(),
+            link_handlers: Vec::new(),
+            web_links: WebLinks::Open,
+            trusted_sites: Vec::new(),
+            trusted_folders: Vec::new(),
+            untrusted_folders: Vec::new(),
+            site_logins: Vec::new(),
+            pandoc_path: "pandoc".into(),
+            render_diagrams: true,
+            plantuml_command: "plantuml".into(),
+            graphviz_command: "dot".into(),
+            window: WindowGeometry::default(),
+            recent_files: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    /// The language of the system's locale, if translated, else English.
+    System,
+    English,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::System, Language::English, Language::German];
+
+    /// Languages by their own names, so they can be found whatever the
+    /// interface is in.
+    pub fn label(self) -> &'static str {
+        match self {
+            Language::System => i18n::tr("Follow System"),
+            Language::English => "English",
+            Language::German => "Deutsch",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    /// Follow the operating system's light or dark mode.
+    System,
+    Light,
+    Dark,
+    /// Light from [`Settings::light_from`] and dark from
+    /// [`Settings::dark_from`], by the local time.
+    Scheduled,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [Theme::System, Theme::Light, Theme::Dark, Theme::Scheduled];
+
+    pub fn label(self) -> &'static str {
+        i18n::tr(match self {
+            Theme::System => "Follow System",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+            Theme::Scheduled => "On a Schedule",
+        })
+    }
+}
+
+/// Colors of the reading surface, for reading like on paper or an e-reader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Paper {
+    /// The theme's own.
+    Theme,
+    Sepia,
+    /// Solarized light or dark, with the theme.
+    Solarized,
+    /// Black on white or white on black, with the theme.
+    HighContrast,
+}
+
+/// What a [`Paper`] paints the document with.
+pub struct PaperColors {
+    pub background: egui::Color32,
+    pub text: egui::Color32,
+    pub link: egui::Color32,
+    pub code: egui::Color32,
+    /// Whether it is dark, for the code theme to go with it.
+    pub dark: bool,
+}
+
+impl Paper {
+    pub const ALL: [Paper; 4] = [
+        Paper::Theme,
+        Paper::Sepia,
+        Paper::Solarized,
+        Paper::HighContrast,
+    ];
+
+    pub fn label(self) -> &'static str {
+        i18n::tr(match self {
+            Paper::Theme => "Theme Colors",
+            Paper::Sepia => "Sepia",
+            Paper::Solarized => "Solarized",
+            Paper::HighContrast => "High Contrast",
+        })
+    }
+
+    /// The colors of this paper in the theme's `dark` or light mode, `None`
+    /// for the theme's own.
+    pub fn colors(self, dark: bool) -> Option<PaperColors> {
+        let rgb = egui::Color32::from_rgb;
+        let (background, text, link, code) = match (self, dark) {
+            (Paper::Theme, _) => return None,
+            (Paper::Sepia, _) => (
+                rgb(244, 236, 216),
+                rgb(91, 70, 54),
+                rgb(156, 82, 34),
+                rgb(233, 222, 196),
+            ),
+            (Paper::Solarized, false) => (
+                rgb(253, 246, 227),
+                rgb(101, 123, 131),
+                rgb(38, 139, 210),
+                rgb(238, 232, 213),
+            ),
+            (Paper::Solarized, true) => (
+                rgb(0, 43, 54),
+                rgb(131, 148, 150),
+                rgb(38, 139, 210),
+                rgb(7, 54, 66),
+            ),
+            (Paper::HighContrast, false) => (
+                egui::Color32::WHITE,
+                egui::Color32::BLACK,
+                rgb(0, 0, 204),
+                rgb(232, 232, 232),
+            ),
+            (Paper::HighContrast, true) => (
+                egui::Color32::BLACK,
+                egui::Color32::WHITE,
+                rgb(255, 235, 59),
+                rgb(36, 36, 36),
+            ),
+        };
+        Some(PaperColors {
+            background,
+            text,
+            link,
+            code,
+            dark: dark && self != Paper::Sepia,
+        })
+    }
+}
+
+impl PaperColors {
+    /// Uses these colors for document text and code in `visuals`.
+    pub fn apply(&self, visuals: &mut egui::Visuals) {
+        visuals.dark_mode = self.dark;
+        visuals.override_text_color = Some(self.text);
+        visuals.hyperlink_color = self.link;
+        visuals.code_bg_color = self.code;
+        visuals.extreme_bg_color = self.code;
+        visuals.faint_bg_color = self.code;
+    }
+}
+
+/// Typeface of document text; code is always monospace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyFont {
+    Proportional,
+    Monospace,
+}
+
+impl BodyFont {
+    pub const ALL: [BodyFont; 2] = [BodyFont::Proportional, BodyFont::Monospace];
+
+    pub fn label(self) -> &'static str {
+        i18n::tr(match self {
+            BodyFont::Proportional => "Proportional",
+            BodyFont::Monospace => "Monospace",
+        })
+    }
+
+    pub fn family(self) -> egui::FontFamily {
+        match self {
+            BodyFont::Proportional => egui::FontFamily::Proportional,
+            BodyFont::Monospace => egui::FontFamily::Monospace,
+        }
+    }
+}
+
+/// Size and place of a window, kept while it isn't maximized so it comes
+/// back to them when it is restored.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WindowGeometry {
+    /// Size of the contents, in points.
+    pub size: [f32; 2],
+    /// Top left corner of the frame, where the platform tells it.
+    pub position: Option<[f32; 2]>,
+    pub maximized: bool,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self {
+            size: [1080.0, 720.0],
+            position: None,
+            maximized: false,
+        }
+    }
+}
+
+impl WindowGeometry {
+    /// `builder` opening the window with this size, place and state.
+    pub fn apply(self, builder: egui::ViewportBuilder) -> egui::ViewportBuilder {
+        let builder = builder
+            .with_inner_size(self.size)
+            .with_maximized(self.maximized);
+        match self.position {
+            Some(position) => builder.with_position(position),
+            None => builder,
+        }
+    }
+
+    /// Follows the window `viewport` describes. Full screen and minimized
+    /// aren't kept, and the size and place only while it's neither those
+    /// nor maximized.
+    pub fn track(&mut self, viewport: &egui::ViewportInfo) {
+        if viewport.fullscreen == Some(true) || viewport.minimized == Some(true) {
+            return;
+        }
+        self.maximized = viewport.maximized == Some(true);
+        if self.maximized {
+            return;
+        }
+        if let Some(inner) = viewport.inner_rect {
+            self.size = inner.size().into();
+        }
+        if let Some(outer) = viewport.outer_rect {
+            self.position = Some(outer.min.into());
+        }
+    }
+}
+
+/// A font file added in Preferences.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CustomFont {
+    pub path: PathBuf,
+    pub role: FontRole,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FontRole {
+    /// Replaces the built-in proportional font.
+    Text,
+    /// Replaces the built-in monospace font.
+    Code,
+    /// Used in all text for characters the other fonts lack, like CJK or emoji.
+    Fallback,
+}
+
+impl FontRole {
+    pub const ALL: [FontRole; 3] = [FontRole::Text, FontRole::Code, FontRole::Fallback];
+
+    pub fn label(self) -> &'static str {
+        i18n::tr(match self {
+            FontRole::Text => "Text",
+            FontRole::Code => "Code",
+            FontRole::Fallback => "Fallback",
+        })
+    }
+}
+
+/// What clicking a link with a URL scheme does, added in Preferences.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LinkHandler {
+    /// Like `jira`, without the colon.
+    pub scheme: String,
+    pub action: LinkAction,
+    /// The URL to go to or the command to run, with `{url}` standing for
+    /// the link and `{target}` for what follows the scheme.
+    pub template: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkAction {
+    /// Hand the link to the system as it is.
+    OpenExternally,
+    /// Open the template's URL instead.
+    Rewrite,
+    /// Run the template as a command.
+    RunCommand,
+}
+
+impl LinkAction {
+    pub const ALL: [LinkAction; 3] = [
+        LinkAction::OpenExternally,
+        LinkAction::Rewrite,
+        LinkAction::RunCommand,
+    ];
+
+    pub fn label(self) -> &'static str {
+        i18n::tr(match self {
+            LinkAction::OpenExternally => "Open externally",
+            LinkAction::Rewrite => "Rewrite to URL",
+            LinkAction::RunCommand => "Run command",
+        })
+    }
+}
+
+/// Credentials sent along when fetching documents from a site, added in
+/// Preferences.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SiteLogin {
+    /// Like `gitlab.example.com`, subdomains included.
+    pub site: String,
+    pub kind: LoginKind,
+    /// Only for [`LoginKind::Basic`].
+    pub user: String,
+    /// The token, or the password. The config file holding it is only
+    /// readable by the user.
+    pub secret: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginKind {
+    /// `Authorization: Bearer <token>`, as GitHub and GitLab take tokens.
+    Bearer,
+    /// A user name and password.
+    Basic,
+}
+
+impl LoginKind {
+    pub const ALL: [LoginKind; 2] = [LoginKind::Bearer, LoginKind::Basic];
+
+    pub fn label(self) -> &'static str {
+        i18n::tr(match self {
+            LoginKind::Bearer => "Token",
+            LoginKind::Basic => "User and password",
+        })
+    }
+}
+
+/// What clicking a link that leaves the viewer does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebLinks {
+    Open,
+    /// Show the whole URL and ask first, unless its site is trusted.
+    Ask,
+    /// Never hand links to the browser or another app.
+    Never,
+}
+
+impl WebLinks {
+    pub const ALL: [WebLinks; 3] = [WebLinks::Open, WebLinks::Ask, WebLinks::Never];
+
+    pub fn label(self) -> &'static str {
+        i18n::tr(match self {
+            WebLinks::Open => "Open right away",
+            WebLinks::Ask => "Ask before opening",
+            WebLinks::Never => "Don't open",
+        })
+    }
+}
+
+/// Which extensions to CommonMark documents are read with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Dialect {
+    pub tables: bool,
+    pub strikethrough: bool,
+    pub task_lists: bool,
+    pub footnotes: bool,
+    /// Curly quotes, dashes and ellipses from their ASCII spellings.
+    pub smart_punctuation: bool,
+    pub definition_lists: bool,
+}
+
+impl Default for Dialect {
+    /// What the viewer has always read: GitHub's, with definition lists.
+    fn default() -> Self {
+        Self {
+            definition_lists: true,
+            ..Dialect::GITHUB
+        }
+    }
+}
+
+impl Dialect {
+    pub const COMMONMARK: Dialect = Dialect {
+        tables: false,
+        strikethrough: false,
+        task_lists: false,
+        footnotes: false,
+        smart_punctuation: false,
+        definition_lists: false,
+    };
+
+    pub const GITHUB: Dialect = Dialect {
+        tables: true,
+        strikethrough: true,
+        task_lists: true,
+        footnotes: true,
+        ..Dialect::COMMONMARK
+    };
+
+    pub fn presets() -> [Dialect; 3] {
+        [Dialect::default(), Dialect::GITHUB, Dialect::COMMONMARK]
+    }
+
+    pub fn label(self) -> &'static str {
+        i18n::tr(match self {
+            Dialect::COMMONMARK => "CommonMark",
+            Dialect::GITHUB => "GitHub Flavored",
+            _ if self == Dialect::default() => "Extended",
+            _ => "Custom",
+        })
+    }
+
+    /// Each extension with its name, to turn it on and off.
+    pub fn extensions(&mut self) -> [(&mut bool, &'static str); 6] {
+        [
+            (&mut self.tables, i18n::tr("Tables")),
+            (&mut self.strikethrough, i18n::tr("Strikethrough")),
+            (&mut self.task_lists, i18n::tr("Task lists")),
+            (&mut self.footnotes, i18n::tr("Footnotes")),
+            (&mut self.smart_punctuation, i18n::tr("Smart punctuation")),
+            (&mut self.definition_lists, i18n::tr("Definition lists")),
+        ]
+    }
+}
+
+/// Where the raw source view draws a line, to check a line-length limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnGuide {
+    Off,
+    At80,
+    At100,
+    At120,
+}
+
+impl ColumnGuide {
+    pub const ALL: [ColumnGuide; 4] = [
+        ColumnGuide::Off,
+        ColumnGuide::At80,
+        ColumnGuide::At100,
+        ColumnGuide::At120,
+    ];
+
+    pub fn label(self) -> &'static str {
+        i18n::tr(match self {
+            ColumnGuide::Off => "No column guide",
+            ColumnGuide::At80 => "80 columns",
+            ColumnGuide::At100 => "100 columns",
+            ColumnGuide::At120 => "120 columns",
+        })
+    }
+
+    pub fn columns(self) -> Option<usize> {
+        match self {
+            ColumnGuide::Off => None,
+            ColumnGuide::At80 => Some(80),
+            ColumnGuide::At100 => Some(100),
+            ColumnGuide::At120 => Some(120),
+        }
+    }
+}
+
+/// What the main window shows when started without files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Startup {
+    Welcome,
+    /// Reopen the tabs open when the app was last closed.
+    RestoreTabs,
+}
+
+impl Startup {
+    pub const ALL: [Startup; 2] = [Startup::Welcome, Startup::RestoreTabs];
+
+    pub fn label(self) -> &'static str {
+        i18n::tr(match self {
+            Startup::Welcome => "Show the welcome screen",
+            Startup::RestoreTabs => "Reopen last session's tabs",
+        })
+    }
+}
+
+/// Where the config file lives on this platform.
+pub fn path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("md_viewer").join("config.toml"))
+}
+
+/// The saved settings, or `None` if there is no config file yet.
+pub fn load() -> Result<Option<Settings>> {
+    let path = path().ok_or_else(|| anyhow!("no config directory on this platform"))?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    let settings = toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+    Ok(Some(settings))
+}
+
+pub fn save(settings: &Settings) -> Result<()> {
+    let path = path().ok_or_else(|| anyhow!("no config directory on this platform"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    let text = toml::to_string_pretty(settings)?;
+    write_private(&path, &text).with_context(|| format!("writing {}", path.display()))
+}
+
+/// Writes `text` to `path` so only the user can read it, as the site
+/// logins are in it. Files saved before are closed off as well.
+#[cfg(unix)]
+fn write_private(path: &Path, text: &str) -> std::io::Result<()> {
+    use std::{
+        io::Write,
+        os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    };
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(text.as_bytes())
+}
+
+/// Elsewhere the config folder is the user's own already.
+#[cfg(not(unix))]
+fn write_private(path: &Path, text: &str) -> std::io::Result<()> {
+    fs::write(path, text)
+}
+
+impl Settings {
+    /// [`Self::image_cache_mb`] in bytes.
+    pub fn image_cache_limit(&self) -> usize {
+        self.image_cache_mb as usize * 1024 * 1024
+    }
+
+    /// [`Self::load_in_chunks_above_mb`] in bytes.
+    pub fn chunked_above(&self) -> usize {
+        self.load_in_chunks_above_mb as usize * 1024 * 1024
+    }
+
+    /// Puts `path` first among the documents opened lately.
+    pub fn note_recent(&mut self, path: &Path) {
+        self.recent_files.retain(|recent| recent != path);
+        self.recent_files.insert(0, path.to_path_buf());
+        self.recent_files.truncate(RECENT_FILES);
+    }
+
+    /// The theme to use at `hour` of the day.
+    pub fn theme_preference(&self, hour: u32) -> egui::ThemePreference {
+        match self.theme {
+            Theme::System => egui::ThemePreference::System,
+            Theme::Light => egui::ThemePreference::Light,
+            Theme::Dark => egui::ThemePreference::Dark,
+            Theme::Scheduled => {
+                // The light hours may wrap past midnight too
+                let light = match self.light_from <= self.dark_from {
+                    true => (self.light_from..self.dark_from).contains(&hour),
+                    false => hour >= self.light_from || hour < self.dark_from,
+                };
+                match light {
+                    true => egui::ThemePreference::Light,
+                    false => egui::ThemePreference::Dark,
+                }
+            }
+        }
+    }
+}
+
+/// `scale` as a percentage, with 100% marked as the OS default.
+pub fn ui_scale_label(scale: f32) -> String {
+    if scale == 1.0 {
+        i18n::tr("100% (native)").into()
+    } else {
+        format!("{:.0}%", scale * 100.0)
+    }
+}
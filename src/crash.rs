@@ -0,0 +1,226 @@
+//! What happens on a panic: a report with the version, the panic and its
+//! backtrace, the latest events and the open files is written to disk along
+//! with the saved session, and a dialog offers to restart or to show it.
+
+use std::{
+    backtrace::Backtrace,
+    cell::Cell,
+    collections::VecDeque,
+    fmt::Write as _,
+    fs, io,
+    panic::{self, AssertUnwindSafe, PanicHookInfo},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rfd::{MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
+
+use crate::i18n::{tr, tr_args};
+
+/// Events kept for the report.
+const LOG_TAIL: usize = 200;
+
+/// Set for a process started from the crash dialog, which shouldn't hand
+/// its files to the instance that crashed.
+pub const RESTARTED_VAR: &str = "MD_VIEWER_RESTARTED";
+
+const RESTART: &str = "Restart";
+const SHOW_REPORT: &str = "Show Report";
+const CLOSE: &str = "Close";
+
+/// The latest events of every window, newest last.
+static LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Files open in the tabs, as of the last frame.
+static OPEN_FILES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+thread_local! {
+    /// Set while running code whose panics are caught and recovered from.
+    static RECOVERING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Writes a report on panics, in front of the default hook. `app_id` is
+/// the one eframe keeps the session under.
+pub fn install(app_id: &'static str) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if RECOVERING.with(Cell::get) {
+            return;
+        }
+        let report = report(info);
+        let saved = storage_dir(app_id).map(|dir| write_bundle(&dir, &report));
+        // The window is gone only if the UI thread panicked
+        if thread::current().name() != Some("main") {
+            return;
+        }
+        match saved {
+            Some(Ok(bundle)) => {
+                eprintln!("A crash report was saved to {}", bundle.display());
+                offer_restart(Some(&bundle));
+            }
+            Some(Err(e)) => {
+                eprintln!("The crash report couldn't be saved: {e}");
+                offer_restart(None);
+            }
+            None => offer_restart(None),
+        }
+    }));
+}
+
+/// Runs `f`, catching a panic in it to recover from instead of reporting it.
+pub fn recoverable<R>(f: impl FnOnce() -> R) -> thread::Result<R> {
+    let outer = RECOVERING.with(|recovering| recovering.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    RECOVERING.with(|recovering| recovering.set(outer));
+    result
+}
+
+/// Keeps `line` for the report, dropping the oldest past [`LOG_TAIL`].
+pub fn note(line: String) {
+    let Ok(mut log) = LOG.lock() else {
+        return;
+    };
+    log.push_back(line);
+    if log.len() > LOG_TAIL {
+        log.pop_front();
+    }
+}
+
+/// Notes the files open in the tabs, copying them only if they changed
+/// since the last frame, which in most frames they haven't.
+pub fn set_open_files<'a>(paths: impl Iterator<Item = &'a Path> + Clone) {
+    if let Ok(mut open) = OPEN_FILES.lock()
+        && !open.iter().map(PathBuf::as_path).eq(paths.clone())
+    {
+        *open = paths.map(Path::to_path_buf).collect();
+    }
+}
+
+/// Where crash reports go: next to the session eframe saves.
+fn storage_dir(app_id: &str) -> Option<PathBuf> {
+    Some(eframe::storage_dir(app_id)?.join("crashes"))
+}
+
+fn report(info: &PanicHookInfo<'_>) -> String {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "(no message)".into());
+    let location = info
+        .location()
+        .map_or_else(|| "unknown".into(), ToString::to_string);
+
+    let mut report = String::new();
+    let _ = writeln!(
+        report,
+        "Markdown Viewer {} on {} ({})",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let _ = writeln!(
+        report,
+        "Panicked in thread {} at {location}:\n{message}\n",
+        thread::current().name().unwrap_or("unnamed")
+    );
+    let _ = writeln!(report, "Backtrace:\n{}\n", Backtrace::force_capture());
+    // A panic while one of these was held leaves it poisoned; still readable
+    let open = OPEN_FILES.lock().unwrap_or_else(|e| e.into_inner());
+    let _ = writeln!(report, "Open files:");
+    for path in open.iter() {
+        let _ = writeln!(report, "  {}", path.display());
+    }
+    let log = LOG.lock().unwrap_or_else(|e| e.into_inner());
+    let _ = writeln!(report, "\nLatest events:");
+    for line in log.iter() {
+        let _ = writeln!(report, "  {line}");
+    }
+    report
+}
+
+/// Writes `report` and a copy of the saved session into a new folder
+/// under `dir`, and returns the folder.
+fn write_bundle(dir: &Path, report: &str) -> io::Result<PathBuf> {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let bundle = dir.join(format!("crash-{stamp}"));
+    fs::create_dir_all(&bundle)?;
+    fs::write(bundle.join("report.txt"), report)?;
+    if let Some(session) = dir.parent().map(|dir| dir.join("app.ron"))
+        && session.is_file()
+    {
+        fs::copy(session, bundle.join("session.ron"))?;
+    }
+    Ok(bundle)
+}
+
+/// Tells the user the app has to close, with buttons to start it again or
+/// to show the report.
+fn offer_restart(bundle: Option<&Path>) {
+    let mut description = tr("Something went wrong and Markdown Viewer has to close.").to_string();
+    if let Some(bundle) = bundle {
+        description.push(' ');
+        description.push_str(&tr_args(
+            "A report of what happened was saved to {path}; attaching it to a bug report helps get it fixed.",
+            &[("path", &bundle.display())],
+        ));
+    }
+    let buttons = match bundle {
+        Some(_) => MessageButtons::YesNoCancelCustom(
+            tr(RESTART).into(),
+            tr(SHOW_REPORT).into(),
+            tr(CLOSE).into(),
+        ),
+        None => MessageButtons::OkCancelCustom(tr(RESTART).into(), tr(CLOSE).into()),
+    };
+    let choice = MessageDialog::new()
+        .set_level(MessageLevel::Error)
+        .set_title(tr("Markdown Viewer stopped"))
+        .set_description(description)
+        .set_buttons(buttons)
+        .show();
+    let choice = match choice {
+        // The buttons' labels, in the interface language
+        MessageDialogResult::Custom(label) => [RESTART, SHOW_REPORT, CLOSE]
+            .into_iter()
+            .find(|choice| tr(choice) == label)
+            .unwrap_or(CLOSE),
+        MessageDialogResult::Yes | MessageDialogResult::Ok => RESTART,
+        MessageDialogResult::No => SHOW_REPORT,
+        MessageDialogResult::Cancel => CLOSE,
+    };
+    let started = match (choice, bundle) {
+        (RESTART, _) => std::env::current_exe().and_then(|exe| {
+            // Back to the files that were open, which piped input can't be
+            let open = OPEN_FILES.lock().unwrap_or_else(|e| e.into_inner());
+            Command::new(exe)
+                .args(open.iter())
+                .env(RESTARTED_VAR, "1")
+                .spawn()
+        }),
+        (SHOW_REPORT, Some(bundle)) => show_folder(bundle),
+        _ => return,
+    };
+    if let Err(e) = started {
+        eprintln!("Couldn't {}: {e}", choice.to_lowercase());
+    }
+}
+
+/// Opens `folder` in the platform's file manager.
+fn show_folder(folder: &Path) -> io::Result<std::process::Child> {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    Command::new(program).arg(folder).spawn()
+}
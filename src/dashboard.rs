@@ -0,0 +1,329 @@
+//! A health overview of an opened folder: how much is in it, what is going
+//! stale, and which documents lack titles or frontmatter or have broken links.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use eframe::egui;
+use pulldown_cmark::{Event, HeadingLevel, LinkType, Options, Parser, Tag};
+
+use crate::blocks;
+use crate::encoding;
+use crate::formats::Format;
+use crate::frontmatter;
+use crate::i18n::{tr, tr_args};
+use crate::wikilink;
+use crate::workspace::Workspace;
+
+/// How many of the least recently changed documents are listed.
+const STALEST_LISTED: usize = 10;
+
+/// How often to check on the report being put together.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Findings for a whole folder.
+#[derive(Default)]
+struct Report {
+    documents: usize,
+    words: usize,
+    /// Least recently modified first.
+    stalest: Vec<(PathBuf, SystemTime)>,
+    untitled: Vec<PathBuf>,
+    without_frontmatter: Vec<PathBuf>,
+    /// Documents with links to files or notes that don't exist, and how many.
+    broken_links: Vec<(PathBuf, usize)>,
+    /// Documents that couldn't be read.
+    unreadable: Vec<PathBuf>,
+}
+
+/// Findings for one document.
+struct DocReport {
+    words: usize,
+    titled: bool,
+    has_frontmatter: bool,
+    broken_links: usize,
+}
+
+pub struct Dashboard {
+    pub open: bool,
+    /// Folder the report is for.
+    root: PathBuf,
+    report: Option<Report>,
+    loading: Option<Receiver<Report>>,
+}
+
+impl Dashboard {
+    /// Starts putting together the report for `workspace` in the background.
+    pub fn new(workspace: &Workspace) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let root = workspace.root.clone();
+        let workspace = workspace.clone();
+        thread::spawn(move || {
+            let _ = sender.send(analyze(&workspace));
+        });
+        Self {
+            open: true,
+            root,
+            report: None,
+            loading: Some(receiver),
+        }
+    }
+
+    pub fn is_for(&self, workspace: &Workspace) -> bool {
+        self.root == workspace.root
+    }
+
+    /// Draws the dashboard window; returns a document the user clicked.
+    pub fn show(&mut self, ctx: &egui::Context, workspace: &Workspace) -> Option<PathBuf> {
+        if let Some(loading) = &self.loading {
+            match loading.try_recv() {
+                Ok(report) => {
+                    self.report = Some(report);
+                    self.loading = None;
+                }
+                Err(TryRecvError::Empty) => ctx.request_repaint_after(POLL_INTERVAL),
+                Err(TryRecvError::Disconnected) => self.loading = None,
+            }
+        }
+
+        let mut clicked = None;
+        egui::Window::new(tr_args(
+            "{folder} — Dashboard",
+            &[("folder", &workspace.name())],
+        ))
+        .id(egui::Id::new("folder_dashboard"))
+        .open(&mut self.open)
+        .default_width(460.0)
+        .show(ctx, |ui| {
+            let Some(report) = &self.report else {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.weak(tr_args(
+                        "Reading {n} documents…",
+                        &[("n", &workspace.files.len())],
+                    ));
+                });
+                return;
+            };
+            clicked = show_report(ui, report, workspace);
+        });
+        clicked
+    }
+}
+
+fn show_report(ui: &mut egui::Ui, report: &Report, workspace: &Workspace) -> Option<PathBuf> {
+    let broken_total: usize = report.broken_links.iter().map(|(_, count)| count).sum();
+    egui::Grid::new("dashboard_counts")
+        .num_columns(2)
+        .spacing([24.0, 4.0])
+        .show(ui, |ui| {
+            for (label, count) in [
+                ("Documents", report.documents),
+                ("Words", report.words),
+                ("Without a title", report.untitled.len()),
+                ("Without frontmatter", report.without_frontmatter.len()),
+                ("Broken links", broken_total),
+            ] {
+                ui.label(tr(label));
+                ui.strong(count.to_string());
+                ui.end_row();
+            }
+        });
+    ui.separator();
+
+    let mut clicked = None;
+    let mut list = |ui: &mut egui::Ui, title: &'static str, entries: Vec<(&PathBuf, String)>| {
+        egui::CollapsingHeader::new(format!("{} ({})", tr(title), entries.len()))
+            .id_salt(title)
+            .show(ui, |ui| {
+                if entries.is_empty() {
+                    ui.weak(tr("None"));
+                }
+                for (path, note) in entries {
+                    ui.horizontal(|ui| {
+                        let name = workspace.relative(path).display().to_string();
+                        if ui.link(name).clicked() {
+                            clicked = Some(path.clone());
+                        }
+                        ui.weak(note);
+                    });
+                }
+            });
+    };
+
+    egui::ScrollArea::vertical()
+        .max_height(360.0)
+        .show(ui, |ui| {
+            let now = SystemTime::now();
+            list(
+                ui,
+                "Stalest",
+                report
+                    .stalest
+                    .iter()
+                    .map(|(path, modified)| (path, age(now, *modified)))
+                    .collect(),
+            );
+            list(
+                ui,
+                "Broken links",
+                report
+                    .broken_links
+                    .iter()
+                    .map(|(path, count)| (path, tr_args("{count} broken", &[("count", count)])))
+                    .collect(),
+            );
+            list(
+                ui,
+                "Without a title",
+                report
+                    .untitled
+                    .iter()
+                    .map(|path| (path, String::new()))
+                    .collect(),
+            );
+            list(
+                ui,
+                "Without frontmatter",
+                report
+                    .without_frontmatter
+                    .iter()
+                    .map(|path| (path, String::new()))
+                    .collect(),
+            );
+            if !report.unreadable.is_empty() {
+                list(
+                    ui,
+                    "Unreadable",
+                    report
+                        .unreadable
+                        .iter()
+                        .map(|path| (path, String::new()))
+                        .collect(),
+                );
+            }
+        });
+    clicked
+}
+
+fn analyze(workspace: &Workspace) -> Report {
+    let mut report = Report::default();
+    let mut modified = Vec::new();
+    for path in &workspace.files {
+        let Ok(decoded) = encoding::read(path) else {
+            report.unreadable.push(path.clone());
+            continue;
+        };
+        let format = Format::from_path(path).unwrap_or_default();
+        let doc = analyze_doc(&format.to_markdown(decoded.text), path, workspace);
+
+        report.documents += 1;
+        report.words += doc.words;
+        if !doc.titled {
+            report.untitled.push(path.clone());
+        }
+        if !doc.has_frontmatter {
+            report.without_frontmatter.push(path.clone());
+        }
+        if doc.broken_links > 0 {
+            report.broken_links.push((path.clone(), doc.broken_links));
+        }
+        if let Ok(time) = path.metadata().and_then(|meta| meta.modified()) {
+            modified.push((path.clone(), time));
+        }
+    }
+
+    modified.sort_by_key(|(_, time)| *time);
+    modified.truncate(STALEST_LISTED);
+    report.stalest = modified;
+    report
+        .broken_links
+        .sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    report
+}
+
+fn analyze_doc(markdown: &str, path: &Path, workspace: &Workspace) -> DocReport {
+    let dir = path.parent();
+    let mut doc = DocReport {
+        words: 0,
+        titled: false,
+        has_frontmatter: false,
+        broken_links: 0,
+    };
+    let mut in_code = false;
+    let mut in_frontmatter = false;
+
+    let parser = Parser::new_ext(
+        markdown,
+        blocks::parser_options() | Options::ENABLE_WIKILINKS,
+    );
+    for event in parser {
+        match event {
+            Event::Start(Tag::Heading {
+                level: HeadingLevel::H1,
+                ..
+            }) => doc.titled = true,
+            Event::Start(Tag::MetadataBlock(_)) => {
+                doc.has_frontmatter = true;
+                in_frontmatter = true;
+            }
+            Event::Start(Tag::CodeBlock(_)) => in_code = true,
+            Event::End(_) => {
+                in_code = false;
+                in_frontmatter = false;
+            }
+            Event::Start(Tag::Link {
+                link_type: LinkType::WikiLink { .. },
+                dest_url,
+                ..
+            }) => {
+                let note = wikilink::note(&dest_url);
+                if !note.is_empty() && wikilink::resolve(note, dir, Some(workspace)).is_none() {
+                    doc.broken_links += 1;
+                }
+            }
+            Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. })
+                if is_missing_file(&dest_url, dir) =>
+            {
+                doc.broken_links += 1;
+            }
+            Event::Text(text) if in_frontmatter => {
+                doc.titled |= frontmatter::value(&text, "title").is_some();
+            }
+            Event::Text(text) if !in_code => doc.words += text.split_whitespace().count(),
+            _ => {}
+        }
+    }
+    doc
+}
+
+/// Whether `dest` points to a local file that isn't there. Web links and
+/// anchors within the document aren't checked.
+fn is_missing_file(dest: &str, dir: Option<&Path>) -> bool {
+    if dest.is_empty() || dest.starts_with('#') || dest.contains(':') {
+        return false;
+    }
+    let file = dest.split(['#', '?']).next().unwrap_or(dest);
+    let file = file.replace("%20", " ");
+    let path = match dir {
+        Some(dir) => dir.join(&file),
+        None => PathBuf::from(&file),
+    };
+    !path.exists()
+}
+
+/// How long ago `time` was, roughly.
+fn age(now: SystemTime, time: SystemTime) -> String {
+    let days = now.duration_since(time).unwrap_or_default().as_secs() / 86_400;
+    match days {
+        0 => tr("today").into(),
+        1 => tr("yesterday").into(),
+        2..60 => tr_args("{days} days ago", &[("days", &days)]),
+        60..730 => tr_args("{months} months ago", &[("months", &(days / 30))]),
+        _ => tr_args("{years} years ago", &[("years", &(days / 365))]),
+    }
+}
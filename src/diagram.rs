@@ -0,0 +1,197 @@
+//! ```` ```plantuml ```` and ```` ```dot ```` fences drawn as diagrams, by
+//! the programs set in Preferences. Each is rendered to SVG once in the
+//! background and kept in the cache folder by a hash of its source, so
+//! reopening a document shows its diagrams straight away.
+
+use std::{
+    collections::{HashMap, hash_map::DefaultHasher},
+    fs,
+    hash::{Hash, Hasher},
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Mutex, OnceLock, PoisonError},
+    thread,
+};
+
+use anyhow::{Context, Result, bail};
+use eframe::egui;
+
+use crate::i18n::{tr, tr_args};
+use crate::link_handlers;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Engine {
+    PlantUml,
+    Graphviz,
+}
+
+impl Engine {
+    /// The engine for a fence's language, if it's a diagram.
+    pub fn of(lang: Option<&str>) -> Option<Engine> {
+        match lang?.to_ascii_lowercase().as_str() {
+            "plantuml" | "puml" => Some(Engine::PlantUml),
+            "dot" | "graphviz" => Some(Engine::Graphviz),
+            _ => None,
+        }
+    }
+
+    /// What the program is asked for SVG with, its source coming on stdin.
+    fn svg_args(self) -> &'static [&'static str] {
+        match self {
+            Engine::PlantUml => &["-tsvg", "-pipe"],
+            Engine::Graphviz => &["-Tsvg"],
+        }
+    }
+
+    /// The environment the program runs in. Documents can come from
+    /// anywhere, so PlantUML's preprocessor is kept from reading files,
+    /// the environment or URLs for them.
+    fn env(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Engine::PlantUml => &[("PLANTUML_SECURITY_PROFILE", "SANDBOX")],
+            Engine::Graphviz => &[],
+        }
+    }
+}
+
+/// The programs diagrams are rendered with, as set in Preferences.
+#[derive(Clone, Copy)]
+pub struct Commands<'a> {
+    pub plantuml: &'a str,
+    pub graphviz: &'a str,
+}
+
+impl Commands<'_> {
+    fn of(&self, engine: Engine) -> &str {
+        match engine {
+            Engine::PlantUml => self.plantuml,
+            Engine::Graphviz => self.graphviz,
+        }
+    }
+}
+
+enum Render {
+    Running,
+    Done(Result<PathBuf, String>),
+}
+
+/// Diagrams rendered or being rendered, by the hash of what they're
+/// rendered from.
+fn renders() -> &'static Mutex<HashMap<u64, Render>> {
+    static RENDERS: OnceLock<Mutex<HashMap<u64, Render>>> = OnceLock::new();
+    RENDERS.get_or_init(Default::default)
+}
+
+/// Draws the diagram in `source`, rendering it first if needed. `None`
+/// if it couldn't be, after saying why, for the source to be shown instead.
+pub fn show(
+    ui: &mut egui::Ui,
+    engine: Engine,
+    commands: Commands<'_>,
+    source: &str,
+) -> Option<egui::Response> {
+    let command = commands.of(engine);
+    let mut hasher = DefaultHasher::new();
+    (engine, command, source).hash(&mut hasher);
+    let key = hasher.finish();
+    let path = cache_dir().join(format!("{key:016x}.svg"));
+
+    let mut known = renders().lock().unwrap_or_else(PoisonError::into_inner);
+    let render = known.entry(key).or_insert_with(|| {
+        if path.is_file() {
+            return Render::Done(Ok(path.clone()));
+        }
+        let ctx = ui.ctx().clone();
+        let (command, source, path) = (command.to_string(), source.to_string(), path.clone());
+        thread::spawn(move || {
+            let done = render(engine, &command, &source, &path)
+                .map(|()| path)
+                .map_err(|e| format!("{e:#}"));
+            renders()
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(key, Render::Done(done));
+            ctx.request_repaint();
+        });
+        Render::Running
+    });
+    match render {
+        Render::Running => Some(
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.weak(tr("Rendering diagram…"));
+            })
+            .response,
+        ),
+        Render::Done(Ok(path)) => {
+            let uri = format!("file://{}", path.display());
+            // Drawn for white paper, like the diagrams on the web
+            Some(
+                egui::Frame::new()
+                    .fill(egui::Color32::WHITE)
+                    .corner_radius(ui.visuals().widgets.noninteractive.corner_radius)
+                    .inner_margin(8.0)
+                    .show(ui, |ui| {
+                        let width = ui.available_width();
+                        ui.add(
+                            egui::Image::new(uri)
+                                .fit_to_original_size(1.0)
+                                .max_width(width),
+                        );
+                    })
+                    .response,
+            )
+        }
+        Render::Done(Err(error)) => {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                tr_args("Couldn't render the diagram: {error}", &[("error", error)]),
+            );
+            None
+        }
+    }
+}
+
+/// Has `command` write the SVG for `source` to `path`.
+fn render(engine: Engine, command: &str, source: &str, path: &Path) -> Result<()> {
+    let words = link_handlers::words(command);
+    let Some((program, args)) = words.split_first() else {
+        bail!("no program is set for it in Preferences");
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .args(engine.svg_args())
+        .envs(engine.env().iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("starting {program}; set where it is in Preferences"))?;
+    let mut stdin = child.stdin.take().context("no input to the program")?;
+    let source = source.to_string();
+    // Written alongside reading the output, which may fill the pipe first
+    let writer = thread::spawn(move || stdin.write_all(source.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("waiting for {program}"))?;
+    let _ = writer.join();
+    if !output.status.success() || output.stdout.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "{program} {}: {}",
+            output.status,
+            stderr.lines().next().unwrap_or("no message").trim()
+        );
+    }
+    fs::create_dir_all(cache_dir()).context("creating the diagram cache")?;
+    fs::write(path, output.stdout).with_context(|| format!("writing {}", path.display()))?;
+    Ok(())
+}
+
+fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("md_viewer")
+        .join("diagrams")
+}
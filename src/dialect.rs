@@ -0,0 +1,97 @@
+//! Reading documents with only some of the extensions to CommonMark.
+//! `egui_commonmark` parses every block with all of them, so the syntax of
+//! those left out is escaped in what it's given, to show as written, and
+//! smart punctuation is written out for it.
+
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Options, Parser, Tag};
+
+use crate::blocks;
+use crate::config::Dialect;
+
+/// What the renderer shows in place of each ASCII spelling.
+const SMART_PUNCTUATION: [&str; 7] = ["‘", "’", "“", "”", "–", "—", "…"];
+
+/// The parser options for reading documents as `dialect`.
+pub fn options(dialect: Dialect) -> Options {
+    let mut options = Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    options.set(Options::ENABLE_TABLES, dialect.tables);
+    options.set(Options::ENABLE_STRIKETHROUGH, dialect.strikethrough);
+    options.set(Options::ENABLE_TASKLISTS, dialect.task_lists);
+    options.set(Options::ENABLE_FOOTNOTES, dialect.footnotes);
+    options.set(Options::ENABLE_SMART_PUNCTUATION, dialect.smart_punctuation);
+    options.set(Options::ENABLE_DEFINITION_LIST, dialect.definition_lists);
+    options
+}
+
+/// `markdown` as the renderer has to be given it to show it the way
+/// `dialect` reads it, if that's any different.
+pub fn adapt(markdown: &str, dialect: Dialect) -> Option<String> {
+    // The renderer's own
+    if dialect == Dialect::default() {
+        return None;
+    }
+
+    // Where to put a backslash, and what to write over
+    let mut escapes = Vec::new();
+    let mut replacements: Vec<(Range<usize>, &str)> = Vec::new();
+    let mut code = Vec::new();
+    let mut parsing = blocks::parser_options();
+    parsing.set(Options::ENABLE_SMART_PUNCTUATION, dialect.smart_punctuation);
+    let escape_all = |escapes: &mut Vec<usize>, span: Range<usize>, c: char| {
+        let text = &markdown[span.clone()];
+        escapes.extend(
+            text.match_indices(c)
+                .map(|(at, _)| span.start + at)
+                .filter(|&at| !markdown[..at].ends_with('\\')),
+        );
+    };
+    for (event, span) in Parser::new_ext(markdown, parsing).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Table(_)) if !dialect.tables => escape_all(&mut escapes, span, '|'),
+            Event::Start(Tag::Strikethrough) if !dialect.strikethrough => {
+                escape_all(&mut escapes, span, '~');
+            }
+            Event::TaskListMarker(_) if !dialect.task_lists => escapes.push(span.start),
+            Event::FootnoteReference(_) | Event::Start(Tag::FootnoteDefinition(_))
+                if !dialect.footnotes =>
+            {
+                escapes.push(span.start);
+            }
+            Event::Start(Tag::DefinitionListDefinition) if !dialect.definition_lists => {
+                if let Some(at) = markdown[span.clone()].find(':') {
+                    escapes.push(span.start + at);
+                }
+            }
+            Event::Code(_) => code.push(span),
+            Event::Text(text) if dialect.smart_punctuation && markdown[span.clone()] != *text => {
+                if let Some(&smart) = SMART_PUNCTUATION.iter().find(|&&smart| *text == *smart) {
+                    replacements.push((span, smart));
+                }
+            }
+            _ => {}
+        }
+    }
+    escapes.retain(|at| !code.iter().any(|span: &Range<usize>| span.contains(at)));
+    if escapes.is_empty() && replacements.is_empty() {
+        return None;
+    }
+
+    let mut edits: Vec<(Range<usize>, String)> = escapes
+        .into_iter()
+        .map(|at| (at..at, "\\".to_string()))
+        .chain(
+            replacements
+                .into_iter()
+                .map(|(span, smart)| (span, smart.to_string())),
+        )
+        .collect();
+    edits.sort_by_key(|(span, _)| std::cmp::Reverse(span.start));
+    edits.dedup_by_key(|(span, _)| span.start);
+    let mut adapted = markdown.to_string();
+    for (span, text) in edits {
+        adapted.replace_range(span, &text);
+    }
+    Some(adapted)
+}
@@ -0,0 +1,186 @@
+//! ```` ```diff ```` fences drawn as diffs: added and removed lines in color,
+//! and optionally the old and new text side by side.
+
+use std::sync::Arc;
+
+use eframe::egui::{self, Galley, text::LayoutJob};
+
+use crate::compare;
+
+/// Space between a line's background and its text.
+const PADDING: f32 = 4.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// `diff`, `index`, `---` and `+++` lines naming the files.
+    Header,
+    /// `@@ -1,3 +1,4 @@` lines.
+    Hunk,
+    Added,
+    Removed,
+    Context,
+}
+
+/// A line of the side-by-side layout: across both columns, or the old and
+/// new text next to each other with either missing.
+enum Row<'a> {
+    Across(&'a str, Kind),
+    Pair(Option<(&'a str, Kind)>, Option<(&'a str, Kind)>),
+}
+
+/// Whether a fence's language marks it as a diff.
+pub fn is_diff(lang: Option<&str>) -> bool {
+    matches!(lang, Some("diff" | "patch" | "udiff"))
+}
+
+pub fn show(ui: &mut egui::Ui, code: &str, side_by_side: bool) -> egui::Response {
+    egui::Frame::new()
+        .fill(ui.visuals().code_bg_color)
+        .corner_radius(3.0)
+        .inner_margin(8.0)
+        .show(ui, |ui| {
+            // Line backgrounds meet without gaps
+            ui.spacing_mut().item_spacing.y = 0.0;
+            let width = ui.available_width();
+            let lines = code.strip_suffix('\n').unwrap_or(code).lines();
+            if side_by_side {
+                for row in rows(lines) {
+                    match row {
+                        Row::Across(line, kind) => draw_row(ui, &[Some((line, kind))], width),
+                        Row::Pair(old, new) => draw_row(ui, &[old, new], width),
+                    }
+                }
+            } else {
+                for line in lines {
+                    draw_row(ui, &[Some((line, kind(line)))], width);
+                }
+            }
+        })
+        .response
+}
+
+fn kind(line: &str) -> Kind {
+    if ["diff ", "index ", "--- ", "+++ "]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+    {
+        Kind::Header
+    } else if line.starts_with("@@") {
+        Kind::Hunk
+    } else if line.starts_with('+') {
+        Kind::Added
+    } else if line.starts_with('-') {
+        Kind::Removed
+    } else {
+        Kind::Context
+    }
+}
+
+/// Lays a unified diff out in two columns. Runs of removed lines are put
+/// next to the added lines that follow them.
+fn rows<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<Row<'a>> {
+    let mut rows = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let flush = |rows: &mut Vec<Row<'a>>, removed: &mut Vec<&'a str>, added: &mut Vec<&'a str>| {
+        for n in 0..removed.len().max(added.len()) {
+            rows.push(Row::Pair(
+                removed.get(n).map(|line| (*line, Kind::Removed)),
+                added.get(n).map(|line| (*line, Kind::Added)),
+            ));
+        }
+        removed.clear();
+        added.clear();
+    };
+
+    for line in lines {
+        match kind(line) {
+            Kind::Removed if added.is_empty() => removed.push(&line[1..]),
+            Kind::Removed => {
+                flush(&mut rows, &mut removed, &mut added);
+                removed.push(&line[1..]);
+            }
+            Kind::Added => added.push(&line[1..]),
+            kind => {
+                flush(&mut rows, &mut removed, &mut added);
+                match kind {
+                    Kind::Context => {
+                        let text = line.strip_prefix(' ').unwrap_or(line);
+                        rows.push(Row::Pair(
+                            Some((text, Kind::Context)),
+                            Some((text, Kind::Context)),
+                        ));
+                    }
+                    kind => rows.push(Row::Across(line, kind)),
+                }
+            }
+        }
+    }
+    flush(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+/// Draws one line, split evenly between `cells`; a missing cell is left blank.
+fn draw_row(ui: &mut egui::Ui, cells: &[Option<(&str, Kind)>], width: f32) {
+    let column = width / cells.len() as f32;
+    let galleys: Vec<Option<(Arc<Galley>, Kind, &str)>> = cells
+        .iter()
+        .map(|cell| cell.map(|(text, kind)| (layout(ui, text, kind, column), kind, text)))
+        .collect();
+    let line_height = ui.text_style_height(&egui::TextStyle::Monospace);
+    let height = galleys
+        .iter()
+        .flatten()
+        .map(|(galley, ..)| galley.size().y)
+        .fold(line_height, f32::max);
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(width, height), egui::Sense::hover());
+
+    for (n, cell) in galleys.into_iter().enumerate() {
+        let cell_rect = egui::Rect::from_min_size(
+            rect.min + egui::vec2(n as f32 * column, 0.0),
+            egui::vec2(column, height),
+        );
+        let Some((galley, kind, text)) = cell else {
+            ui.painter()
+                .rect_filled(cell_rect, 0.0, ui.visuals().faint_bg_color);
+            continue;
+        };
+        if let Some(fill) = fill(ui, kind) {
+            ui.painter().rect_filled(cell_rect, 0.0, fill);
+        }
+        ui.painter().galley(
+            cell_rect.min + egui::vec2(PADDING, 0.0),
+            galley,
+            ui.visuals().text_color(),
+        );
+        // Painted text is invisible to screen readers otherwise
+        ui.interact(cell_rect, ui.auto_id_with(n), egui::Sense::hover())
+            .widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Label, true, text));
+    }
+}
+
+fn layout(ui: &egui::Ui, text: &str, kind: Kind, width: f32) -> Arc<Galley> {
+    let (added, removed) = compare::diff_colors(ui);
+    let visuals = ui.visuals();
+    let color = match kind {
+        Kind::Header => visuals.strong_text_color(),
+        Kind::Hunk => visuals.weak_text_color(),
+        Kind::Added => added,
+        Kind::Removed => removed,
+        Kind::Context => visuals.text_color(),
+    };
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let job = LayoutJob::simple(text.to_string(), font_id, color, width - 2.0 * PADDING);
+    ui.fonts(|fonts| fonts.layout_job(job))
+}
+
+/// Background of a line, tinted for changes.
+fn fill(ui: &egui::Ui, kind: Kind) -> Option<egui::Color32> {
+    let (added, removed) = compare::diff_colors(ui);
+    match kind {
+        Kind::Added => Some(added.gamma_multiply(0.15)),
+        Kind::Removed => Some(removed.gamma_multiply(0.15)),
+        Kind::Hunk => Some(ui.visuals().faint_bg_color),
+        Kind::Header | Kind::Context => None,
+    }
+}
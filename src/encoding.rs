@@ -0,0 +1,246 @@
+//! Reading text files that aren't necessarily UTF-8.
+
+use std::{fs, io, path::Path};
+
+use encoding_rs::{Decoder, Encoding, UTF_16BE, UTF_16LE, WINDOWS_1252};
+
+/// File contents converted to UTF-8.
+pub struct Decoded {
+    pub text: String,
+    /// Name of the encoding the file was in, for display.
+    pub encoding: &'static str,
+}
+
+pub fn read(path: &Path) -> io::Result<Decoded> {
+    fs::read(path).map(decode)
+}
+
+/// Converts `bytes` to UTF-8, going by a byte order mark if there is one.
+/// Without one, files that aren't valid UTF-8 are taken as UTF-16 when they
+/// look like it and Windows-1252 (a superset of Latin-1) otherwise.
+pub fn decode(bytes: Vec<u8>) -> Decoded {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(&bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return Decoded {
+            text: text.into_owned(),
+            encoding: if bom_len == 3 {
+                "UTF-8 with BOM"
+            } else {
+                encoding.name()
+            },
+        };
+    }
+
+    let bytes = match String::from_utf8(bytes) {
+        Ok(text) => {
+            return Decoded {
+                text,
+                encoding: "UTF-8",
+            };
+        }
+        Err(e) => e.into_bytes(),
+    };
+
+    let encoding = utf16_without_bom(&bytes).unwrap_or(WINDOWS_1252);
+    let (text, _) = encoding.decode_without_bom_handling(&bytes);
+    Decoded {
+        text: text.into_owned(),
+        encoding: encoding.name(),
+    }
+}
+
+/// [`decode`] a piece at a time, for text to be used while the rest of the
+/// file is still being read.
+#[derive(Default)]
+pub struct Stream {
+    text: String,
+    /// `None` while the text is taken as UTF-8 without a byte order mark.
+    decoder: Option<Decoder>,
+    encoding: &'static str,
+    /// Bytes held back: the first few until a byte order mark can be told,
+    /// then the start of a UTF-8 character cut off by the end of a read.
+    held: Vec<u8>,
+    started: bool,
+}
+
+impl Stream {
+    /// The text decoded so far.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Decodes `bytes`, the next ones read, with `last` at the end of the
+    /// file. Returns whether the text decoded before changed, as it does
+    /// when what looked like UTF-8 turns out not to be.
+    pub fn push(&mut self, bytes: &[u8], last: bool) -> bool {
+        self.held.extend_from_slice(bytes);
+        if !self.started {
+            if self.held.len() < 3 && !last {
+                return false;
+            }
+            self.started = true;
+            self.encoding = "UTF-8";
+            if let Some((encoding, bom_len)) = Encoding::for_bom(&self.held) {
+                self.decoder = Some(encoding.new_decoder_without_bom_handling());
+                self.encoding = if bom_len == 3 {
+                    "UTF-8 with BOM"
+                } else {
+                    encoding.name()
+                };
+                self.held.drain(..bom_len);
+            }
+        }
+
+        let held = std::mem::take(&mut self.held);
+        if let Some(decoder) = &mut self.decoder {
+            decode_into(decoder, &held, &mut self.text, last);
+            return false;
+        }
+        match std::str::from_utf8(&held) {
+            Ok(text) => {
+                self.text.push_str(text);
+                false
+            }
+            Err(e) if e.error_len().is_none() && !last => {
+                let (valid, rest) = held.split_at(e.valid_up_to());
+                self.text
+                    .push_str(std::str::from_utf8(valid).expect("checked above"));
+                self.held = rest.to_vec();
+                false
+            }
+            Err(_) => {
+                // What came before was UTF-8, so its bytes are the file's
+                let mut bytes = std::mem::take(&mut self.text).into_bytes();
+                let before_ascii = bytes.is_ascii();
+                bytes.extend_from_slice(&held);
+                let encoding = utf16_without_bom(&bytes).unwrap_or(WINDOWS_1252);
+                let mut decoder = encoding.new_decoder_without_bom_handling();
+                decode_into(&mut decoder, &bytes, &mut self.text, last);
+                self.decoder = Some(decoder);
+                self.encoding = encoding.name();
+                // ASCII reads the same in Windows-1252
+                encoding != WINDOWS_1252 || !before_ascii
+            }
+        }
+    }
+
+    /// Everything decoded, once the last bytes are pushed.
+    pub fn finish(self) -> Decoded {
+        Decoded {
+            text: self.text,
+            encoding: self.encoding,
+        }
+    }
+}
+
+fn decode_into(decoder: &mut Decoder, bytes: &[u8], text: &mut String, last: bool) {
+    // With room for all of it, it's decoded in one go
+    if let Some(needed) = decoder.max_utf8_buffer_length(bytes.len()) {
+        text.reserve(needed);
+    }
+    let _ = decoder.decode_to_string(bytes, text, last);
+}
+
+/// Mostly-ASCII text in UTF-16 has a zero in every other byte, which makes it
+/// easy to spot even without a byte order mark.
+fn utf16_without_bom(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(4096) & !1];
+    let pairs = sample.len() / 2;
+    if pairs == 0 {
+        return None;
+    }
+    let zeros_at = |parity: usize| {
+        sample
+            .iter()
+            .skip(parity)
+            .step_by(2)
+            .filter(|&&b| b == 0)
+            .count()
+    };
+    if zeros_at(1) * 10 > pairs * 4 {
+        Some(UTF_16LE)
+    } else if zeros_at(0) * 10 > pairs * 4 {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn utf16be(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    fn samples() -> Vec<(Vec<u8>, &'static str, &'static str)> {
+        let bom = |bom: &[u8], mut bytes: Vec<u8>| {
+            bytes.splice(0..0, bom.iter().copied());
+            bytes
+        };
+        vec![
+            (b"# Notes\n".to_vec(), "# Notes\n", "UTF-8"),
+            ("# Café\n".into(), "# Café\n", "UTF-8"),
+            (
+                bom(b"\xEF\xBB\xBF", "# Café".into()),
+                "# Café",
+                "UTF-8 with BOM",
+            ),
+            (bom(b"\xFF\xFE", utf16le("# Café")), "# Café", "UTF-16LE"),
+            (bom(b"\xFE\xFF", utf16be("# Café")), "# Café", "UTF-16BE"),
+            (utf16le("# Café"), "# Café", "UTF-16LE"),
+            (utf16be("# Café"), "# Café", "UTF-16BE"),
+            (b"# Caf\xE9 \x80\n".to_vec(), "# Café €\n", "windows-1252"),
+            (b"".to_vec(), "", "UTF-8"),
+        ]
+    }
+
+    #[test]
+    fn files_are_read_in_the_encoding_they_look_like() {
+        for (bytes, text, encoding) in samples() {
+            let decoded = decode(bytes);
+            assert_eq!((decoded.text.as_str(), decoded.encoding), (text, encoding));
+        }
+    }
+
+    #[test]
+    fn streams_decode_as_the_whole_does() {
+        for (bytes, text, encoding) in samples() {
+            for size in [1, 2, 3, 5, 4096] {
+                let mut stream = Stream::default();
+                let mut pieces = bytes.chunks(size).peekable();
+                if pieces.peek().is_none() {
+                    stream.push(&[], true);
+                }
+                while let Some(piece) = pieces.next() {
+                    stream.push(piece, pieces.peek().is_none());
+                }
+                let decoded = stream.finish();
+                assert_eq!(
+                    (decoded.text.as_str(), decoded.encoding),
+                    (text, encoding),
+                    "{size} bytes at a time"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn streams_tell_when_what_came_before_changes() {
+        let mut stream = Stream::default();
+        assert!(!stream.push(b"# Cafe", false));
+        // ASCII reads the same in Windows-1252
+        assert!(!stream.push(b" \xE9\n", true));
+        assert_eq!(stream.text(), "# Cafe é\n");
+
+        let mut stream = Stream::default();
+        assert!(!stream.push("# Café".as_bytes(), false));
+        assert!(stream.push(b" \xFF\n", true));
+        assert_eq!(stream.finish().text, "# CafÃ© ÿ\n");
+    }
+}
@@ -0,0 +1,409 @@
+//! Things that happened in a window, and where they are shown: the latest
+//! in the status bar, errors as toasts, and all of them in the log panel.
+
+use std::{
+    fmt,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use eframe::egui;
+
+use crate::i18n::{tr, tr_args};
+
+/// How long an error toast stays up.
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+/// Entries kept in the log before the oldest are dropped.
+const MAX_LOG_ENTRIES: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AppEvent {
+    Opened { title: String },
+    OpenFailed { title: String, error: String },
+    SkippedUnrecognized { name: String },
+    OpenedFolder { name: String, documents: usize },
+    FolderTrusted { path: PathBuf },
+    FolderFailed { error: String },
+    Reloaded { title: String },
+    ReloadPaused { title: String },
+    ReloadFailed { title: String, error: String },
+    RenderFailed { title: String, error: String },
+    LinkUnresolved { note: String },
+    LinkHandlerFailed { url: String, error: String },
+    WebLinkBlocked { url: String },
+    PlaceNotFound { place: String },
+    NoSectionToBookmark,
+    SnippetSaved { path: PathBuf },
+    SnippetCopied,
+    TocCopied { title: String },
+    SnippetCancelled,
+    SnippetFailed { error: String },
+    Exported { path: PathBuf },
+    ImageSaved { path: PathBuf },
+    ImageSaveFailed { error: String },
+    CodeSaved { path: PathBuf },
+    CodeSaveFailed { error: String },
+    LinksRegistered,
+    LinkRegistrationFailed { error: String },
+    FetchedOffline { url: String, error: String },
+    FetchFailed { url: String, error: String },
+    ExportFailed { error: String },
+    ClipboardEmpty,
+    RichCopyFailed { error: String },
+    ConfigFailed { error: String },
+    FontFailed { error: String },
+    ReadingStateFailed { error: String },
+    UpdateAvailable { version: String },
+    UpdateCheckFailed { error: String },
+    GitFailed { error: String },
+}
+
+impl AppEvent {
+    pub fn severity(&self) -> Severity {
+        match self {
+            AppEvent::OpenFailed { .. }
+            | AppEvent::FolderFailed { .. }
+            | AppEvent::ReloadFailed { .. }
+            | AppEvent::RenderFailed { .. }
+            | AppEvent::SnippetFailed { .. }
+            | AppEvent::LinkHandlerFailed { .. }
+            | AppEvent::ExportFailed { .. }
+            | AppEvent::ImageSaveFailed { .. }
+            | AppEvent::CodeSaveFailed { .. }
+            | AppEvent::LinkRegistrationFailed { .. }
+            | AppEvent::FetchFailed { .. }
+            | AppEvent::ConfigFailed { .. }
+            | AppEvent::FontFailed { .. }
+            | AppEvent::ReadingStateFailed { .. } => Severity::Error,
+            AppEvent::SkippedUnrecognized { .. }
+            | AppEvent::ReloadPaused { .. }
+            | AppEvent::LinkUnresolved { .. }
+            | AppEvent::WebLinkBlocked { .. }
+            | AppEvent::FetchedOffline { .. }
+            | AppEvent::PlaceNotFound { .. }
+            | AppEvent::NoSectionToBookmark
+            | AppEvent::ClipboardEmpty
+            | AppEvent::RichCopyFailed { .. }
+            | AppEvent::UpdateCheckFailed { .. }
+            | AppEvent::GitFailed { .. } => Severity::Warning,
+            _ => Severity::Info,
+        }
+    }
+}
+
+impl fmt::Display for AppEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            AppEvent::Opened { title } => tr_args("Opened {title}", &[("title", title)]),
+            AppEvent::OpenFailed { title, error } => tr_args(
+                "Failed to open {title}: {error}",
+                &[("title", title), ("error", error)],
+            ),
+            AppEvent::SkippedUnrecognized { name } => {
+                tr_args("Skipped non-markdown file: {name}", &[("name", name)])
+            }
+            AppEvent::OpenedFolder { name, documents } => tr_args(
+                "Opened folder {name} ({documents} documents)",
+                &[("name", name), ("documents", documents)],
+            ),
+            AppEvent::FolderTrusted { path } => {
+                tr_args("Trusted the folder {path}", &[("path", &path.display())])
+            }
+            AppEvent::FolderFailed { error } => {
+                tr_args("Failed to open folder: {error}", &[("error", error)])
+            }
+            AppEvent::Reloaded { title } => tr_args("Reloaded {title}", &[("title", title)]),
+            AppEvent::ReloadPaused { title } => tr_args(
+                "{title} changed on disk; update paused",
+                &[("title", title)],
+            ),
+            AppEvent::ReloadFailed { title, error } => tr_args(
+                "Reloading {title} failed: {error}",
+                &[("title", title), ("error", error)],
+            ),
+            AppEvent::RenderFailed { title, error } => tr_args(
+                "Couldn't render {title}: {error}",
+                &[("title", title), ("error", error)],
+            ),
+            AppEvent::LinkUnresolved { note } => tr_args("No note named {note}", &[("note", note)]),
+            AppEvent::LinkHandlerFailed { url, error } => tr_args(
+                "Couldn't handle {url}: {error}",
+                &[("url", url), ("error", error)],
+            ),
+            AppEvent::WebLinkBlocked { url } => tr_args(
+                "Didn't open {url}; links leaving the viewer are turned off in Preferences",
+                &[("url", url)],
+            ),
+            AppEvent::PlaceNotFound { place } => tr_args(
+                "Couldn't find {place} in this document",
+                &[("place", place)],
+            ),
+            AppEvent::NoSectionToBookmark => tr("There's no section here to bookmark").into(),
+            AppEvent::SnippetSaved { path } => {
+                tr_args("Saved snippet to {path}", &[("path", &path.display())])
+            }
+            AppEvent::SnippetCopied => tr("Copied snippet to the clipboard").into(),
+            AppEvent::TocCopied { title } => tr_args(
+                "Copied the table of contents of {title}",
+                &[("title", title)],
+            ),
+            AppEvent::SnippetCancelled => tr("Snippet export cancelled").into(),
+            AppEvent::SnippetFailed { error } => {
+                tr_args("Snippet export failed: {error}", &[("error", error)])
+            }
+            AppEvent::Exported { path } => {
+                tr_args("Exported to {path}", &[("path", &path.display())])
+            }
+            AppEvent::ExportFailed { error } => {
+                tr_args("Export failed: {error}", &[("error", error)])
+            }
+            AppEvent::ImageSaved { path } => {
+                tr_args("Saved image to {path}", &[("path", &path.display())])
+            }
+            AppEvent::ImageSaveFailed { error } => {
+                tr_args("Couldn't save the image: {error}", &[("error", error)])
+            }
+            AppEvent::CodeSaved { path } => {
+                tr_args("Saved code to {path}", &[("path", &path.display())])
+            }
+            AppEvent::CodeSaveFailed { error } => {
+                tr_args("Couldn't save the code: {error}", &[("error", error)])
+            }
+            AppEvent::LinksRegistered => tr("mdviewer:// links now open here").into(),
+            AppEvent::LinkRegistrationFailed { error } => tr_args(
+                "Couldn't register mdviewer:// links: {error}",
+                &[("error", error)],
+            ),
+            AppEvent::FetchedOffline { url, error } => tr_args(
+                "Showing the copy of {url} kept from before: {error}",
+                &[("url", url), ("error", error)],
+            ),
+            AppEvent::FetchFailed { url, error } => tr_args(
+                "Couldn't fetch {url}: {error}",
+                &[("url", url), ("error", error)],
+            ),
+            AppEvent::ClipboardEmpty => tr("The clipboard has no text").into(),
+            AppEvent::RichCopyFailed { error } => {
+                tr_args("Copied as plain text only: {error}", &[("error", error)])
+            }
+            AppEvent::ConfigFailed { error } => tr_args(
+                "Preferences couldn't be saved or loaded: {error}",
+                &[("error", error)],
+            ),
+            AppEvent::FontFailed { error } => {
+                tr_args("Couldn't load a font: {error}", &[("error", error)])
+            }
+            AppEvent::ReadingStateFailed { error } => tr_args(
+                "Bookmarks and highlights couldn't be saved or loaded: {error}",
+                &[("error", error)],
+            ),
+            AppEvent::UpdateAvailable { version } => tr_args(
+                "Version {version} is out; see Help → Update Available",
+                &[("version", version)],
+            ),
+            AppEvent::UpdateCheckFailed { error } => {
+                tr_args("Couldn't check for updates: {error}", &[("error", error)])
+            }
+            AppEvent::GitFailed { error } => tr_args(
+                "Couldn't get the committed version: {error}",
+                &[("error", error)],
+            ),
+        };
+        f.write_str(&text)
+    }
+}
+
+struct Entry {
+    event: AppEvent,
+    at: Instant,
+}
+
+/// The events of one window.
+pub struct EventLog {
+    entries: Vec<Entry>,
+    /// Whether the log panel is shown.
+    pub open: bool,
+    /// Least severe events the log panel lists.
+    min_severity: Severity,
+    /// Errors not yet seen in the log panel, newest last.
+    toasts: Vec<usize>,
+    /// Index of the first entry, counting dropped ones, so toasts stay valid.
+    dropped: usize,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            open: false,
+            min_severity: Severity::Info,
+            toasts: Vec::new(),
+            dropped: 0,
+        }
+    }
+}
+
+impl EventLog {
+    pub fn push(&mut self, event: AppEvent) {
+        crate::crash::note(format!("{:?}: {event}", event.severity()));
+        if event.severity() == Severity::Error && !self.open {
+            self.toasts.push(self.dropped + self.entries.len());
+        }
+        self.entries.push(Entry {
+            event,
+            at: Instant::now(),
+        });
+        if self.entries.len() > MAX_LOG_ENTRIES {
+            self.entries.remove(0);
+            self.dropped += 1;
+        }
+    }
+
+    pub fn latest(&self) -> Option<&AppEvent> {
+        self.entries.last().map(|entry| &entry.event)
+    }
+
+    /// The latest event in the status bar, colored by severity. Clicking it
+    /// opens the log.
+    pub fn show_status(&mut self, ui: &mut egui::Ui) {
+        let Some(event) = self.latest() else {
+            ui.weak(tr("Ready"));
+            return;
+        };
+        let text = egui::RichText::new(event.to_string()).color(color(ui, event.severity()));
+        let response = ui
+            .add(egui::Label::new(text).sense(egui::Sense::click()))
+            .on_hover_text(tr("Show the log"));
+        if response.clicked() {
+            self.open = true;
+        }
+    }
+
+    /// Lists events, newest first, with a filter for how severe they must be.
+    pub fn show_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.strong(tr("Log"));
+            ui.separator();
+            ui.label(tr("Show:"));
+            for (severity, label) in [
+                (Severity::Info, tr("All")),
+                (Severity::Warning, tr("Warnings")),
+                (Severity::Error, tr("Errors")),
+            ] {
+                ui.selectable_value(&mut self.min_severity, severity, label);
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui
+                    .small_button("×")
+                    .on_hover_text(tr("Close log"))
+                    .clicked()
+                {
+                    self.open = false;
+                }
+                if ui.small_button(tr("Clear")).clicked() {
+                    self.dropped += self.entries.len();
+                    self.entries.clear();
+                }
+            });
+        });
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                let now = Instant::now();
+                let shown = self
+                    .entries
+                    .iter()
+                    .rev()
+                    .filter(|entry| entry.event.severity() >= self.min_severity);
+                for entry in shown {
+                    ui.horizontal(|ui| {
+                        ui.weak(ago(now - entry.at));
+                        let severity = entry.event.severity();
+                        ui.colored_label(color(ui, severity), entry.event.to_string());
+                    });
+                }
+            });
+        // Whatever the log shows has been seen
+        self.toasts.clear();
+    }
+
+    /// Recent errors in the bottom-right corner, until they time out or are dismissed.
+    pub fn show_toasts(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        let dropped = self.dropped;
+        let entries = &self.entries;
+        self.toasts.retain(|&idx| {
+            idx >= dropped
+                && entries
+                    .get(idx - dropped)
+                    .is_some_and(|entry| now - entry.at < TOAST_DURATION)
+        });
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let mut dismissed = None;
+        let mut open_log = false;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -36.0))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for &idx in &self.toasts {
+                    let event = &self.entries[idx - self.dropped].event;
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(360.0);
+                        ui.horizontal(|ui| {
+                            ui.colored_label(color(ui, Severity::Error), event.to_string());
+                            if ui.small_button("×").clicked() {
+                                dismissed = Some(idx);
+                            }
+                        });
+                        if ui.link(tr("Show log")).clicked() {
+                            open_log = true;
+                        }
+                    });
+                }
+            });
+
+        if let Some(idx) = dismissed {
+            self.toasts.retain(|&toast| toast != idx);
+        }
+        if open_log {
+            self.open = true;
+        }
+        // Check back when the oldest toast times out
+        if let Some(&idx) = self.toasts.first() {
+            let shown_for = now - self.entries[idx - self.dropped].at;
+            ctx.request_repaint_after(TOAST_DURATION.saturating_sub(shown_for));
+        }
+    }
+}
+
+fn color(ui: &egui::Ui, severity: Severity) -> egui::Color32 {
+    let visuals = ui.visuals();
+    match severity {
+        Severity::Info => visuals.text_color(),
+        Severity::Warning => visuals.warn_fg_color,
+        Severity::Error => visuals.error_fg_color,
+    }
+}
+
+/// A short "how long ago" for the log panel.
+fn ago(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match secs {
+        0..60 => tr_args("{secs}s ago", &[("secs", &secs)]),
+        60..3600 => tr_args("{mins}m ago", &[("mins", &(secs / 60))]),
+        _ => tr_args("{hours}h ago", &[("hours", &(secs / 3600))]),
+    }
+}
@@ -0,0 +1,603 @@
+//! The open documents bundled up as static HTML: either one page with a
+//! table of contents, or a folder of pages laid out and linked like the
+//! originals. Local images they use are copied along.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, LinkType, Options, Parser, Tag, TagEnd};
+use rfd::FileDialog;
+
+use crate::blocks;
+use crate::tab::DocTab;
+use crate::wikilink;
+use crate::workspace::Workspace;
+
+const STYLE: &str = "\
+body { max-width: 48em; margin: 2em auto; padding: 0 1em; font-family: sans-serif; line-height: 1.5; }
+pre { background: #f4f4f4; padding: 0.6em; overflow-x: auto; }
+code { font-family: monospace; }
+kbd { font-family: monospace; border: 1px solid #bbb; border-bottom-width: 2px; border-radius: 3px; padding: 0 0.3em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; }
+blockquote { margin-left: 0; padding-left: 1em; border-left: 3px solid #ccc; color: #555; }
+img { max-width: 100%; }
+nav.toc .toc-h3 { margin-left: 1.5em; }
+section + section { border-top: 1px solid #ccc; margin-top: 3em; }
+@media print {
+  a { color: inherit; }
+  nav.toc { break-after: page; }
+  section + section { break-before: page; border-top: none; margin-top: 0; }
+  h1, h2, h3, h4, h5, h6 { break-after: avoid; }
+}
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bundle {
+    /// Everything in one HTML file, with a table of contents on top.
+    Page,
+    /// One HTML file per document, in the same folders as the originals.
+    Folder,
+}
+
+/// Asks where to put the bundle and writes the saved documents among `tabs`
+/// there. Returns `None` if the user cancelled.
+pub fn export(
+    tabs: &[DocTab],
+    bundle: Bundle,
+    workspace: Option<&Workspace>,
+) -> Result<Option<PathBuf>> {
+    let read: Vec<(&Path, Cow<'_, str>)> = tabs
+        .iter()
+        .filter(|tab| !tab.is_scratch())
+        .map(|tab| {
+            let markdown = tab
+                .markdown()
+                .with_context(|| format!("reading {}", tab.path.display()))?;
+            Ok((tab.path.as_path(), markdown))
+        })
+        .collect::<Result<_>>()?;
+    let docs: Vec<(&Path, &str)> = read.iter().map(|(path, md)| (*path, md.as_ref())).collect();
+    if docs.is_empty() {
+        bail!("only scratch tabs are open");
+    }
+    let out = match bundle {
+        Bundle::Page => FileDialog::new()
+            .add_filter("HTML page", &["html"])
+            .set_file_name("documents.html")
+            .set_title("Export open tabs")
+            .save_file(),
+        Bundle::Folder => FileDialog::new()
+            .set_title("Export open tabs to a folder")
+            .pick_folder(),
+    };
+    let Some(out) = out else {
+        return Ok(None);
+    };
+    write(&docs, bundle, &out, workspace)?;
+    Ok(Some(out))
+}
+
+/// Writes `docs` as a page at `out`, or as a folder of pages in `out`.
+pub fn write(
+    docs: &[(&Path, &str)],
+    bundle: Bundle,
+    out: &Path,
+    workspace: Option<&Workspace>,
+) -> Result<()> {
+    let sources: Vec<PathBuf> = docs
+        .iter()
+        .map(|(path, _)| fs::canonicalize(path).unwrap_or(path.to_path_buf()))
+        .collect();
+    let base = common_dir(&sources);
+    let out_dir = match bundle {
+        Bundle::Page => out.parent().unwrap_or(Path::new("")),
+        Bundle::Folder => out,
+    };
+    let mut exporter = Exporter {
+        pages: sources
+            .iter()
+            .map(|source| {
+                source
+                    .strip_prefix(&base)
+                    .unwrap_or(source)
+                    .with_extension("html")
+            })
+            .collect(),
+        sources,
+        base,
+        bundle,
+        workspace,
+        assets: Vec::new(),
+    };
+
+    fs::create_dir_all(out_dir).with_context(|| format!("creating {}", out_dir.display()))?;
+    let mut rendered = Vec::new();
+    for (n, (_, markdown)) in docs.iter().enumerate() {
+        rendered.push(exporter.render(n, markdown));
+    }
+    let title = |n: usize, headings: &[Heading]| {
+        headings
+            .iter()
+            .find(|heading| heading.level == 1)
+            .map(|heading| heading.text.clone())
+            .unwrap_or_else(|| {
+                let stem = exporter.sources[n].file_stem().unwrap_or_default();
+                stem.to_string_lossy().into_owned()
+            })
+    };
+
+    match bundle {
+        Bundle::Page => {
+            let mut toc = String::from("<nav class=\"toc\">\n<h2>Contents</h2>\n<ul>\n");
+            let mut body = String::new();
+            for (n, (html, headings)) in rendered.iter().enumerate() {
+                toc.push_str(&format!(
+                    "<li><a href=\"#doc-{n}\">{}</a>",
+                    escape(&title(n, headings))
+                ));
+                if let Some(sections) = section_list(headings) {
+                    toc.push('\n');
+                    toc.push_str(&sections);
+                }
+                toc.push_str("</li>\n");
+                body.push_str(&format!("<section id=\"doc-{n}\">\n{html}</section>\n"));
+            }
+            toc.push_str("</ul>\n</nav>\n");
+            let name = exporter.base.file_name().unwrap_or_default();
+            let page = document(&name.to_string_lossy(), &(toc + &body));
+            fs::write(out, page).with_context(|| format!("writing {}", out.display()))?;
+        }
+        Bundle::Folder => {
+            for (n, (html, headings)) in rendered.iter().enumerate() {
+                let path = out_dir.join(&exporter.pages[n]);
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)
+                        .with_context(|| format!("creating {}", dir.display()))?;
+                }
+                // The page's own contents, as the single page has on top
+                let body = match section_list(headings) {
+                    Some(sections) => {
+                        format!("<nav class=\"toc\">\n<h2>Contents</h2>\n{sections}</nav>\n{html}")
+                    }
+                    None => html.clone(),
+                };
+                fs::write(&path, document(&title(n, headings), &body))
+                    .with_context(|| format!("writing {}", path.display()))?;
+            }
+            // A way in, unless one of the documents already is
+            let index = Path::new("index.html");
+            if !exporter.pages.iter().any(|page| page == index) {
+                let mut list = String::from("<h1>Contents</h1>\n<ul>\n");
+                for (n, (_, headings)) in rendered.iter().enumerate() {
+                    list.push_str(&format!(
+                        "<li><a href=\"{}\">{}</a></li>\n",
+                        relative_url(Path::new(""), &exporter.pages[n]),
+                        escape(&title(n, headings))
+                    ));
+                }
+                list.push_str("</ul>\n");
+                let path = out_dir.join(index);
+                fs::write(&path, document("Contents", &list))
+                    .with_context(|| format!("writing {}", path.display()))?;
+            }
+        }
+    }
+
+    for (source, rel) in &exporter.assets {
+        let path = out_dir.join(rel);
+        // Exported next to the originals, the file is already in place
+        if fs::canonicalize(&path).is_ok_and(|path| path == *source) {
+            continue;
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        fs::copy(source, &path).with_context(|| format!("copying {}", source.display()))?;
+    }
+    Ok(())
+}
+
+struct Heading {
+    level: usize,
+    text: String,
+    id: String,
+}
+
+/// Links to the H2 and H3 headings among `headings`, if there are any.
+fn section_list(headings: &[Heading]) -> Option<String> {
+    let sections: Vec<&Heading> = headings
+        .iter()
+        .filter(|heading| matches!(heading.level, 2 | 3))
+        .collect();
+    if sections.is_empty() {
+        return None;
+    }
+    let mut list = String::from("<ul>\n");
+    for heading in sections {
+        list.push_str(&format!(
+            "<li class=\"toc-h{}\"><a href=\"#{}\">{}</a></li>\n",
+            heading.level,
+            escape(&heading.id),
+            escape(&heading.text)
+        ));
+    }
+    list.push_str("</ul>\n");
+    Some(list)
+}
+
+struct Exporter<'a> {
+    /// Canonical paths of the documents.
+    sources: Vec<PathBuf>,
+    /// Where each document's HTML goes, relative to the bundle's folder.
+    pages: Vec<PathBuf>,
+    /// Folder holding all the documents, which the bundle mirrors.
+    base: PathBuf,
+    bundle: Bundle,
+    workspace: Option<&'a Workspace>,
+    /// Local files the documents use, and where they go in the bundle.
+    assets: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Exporter<'_> {
+    /// Anchors of the `n`th document, which share one page with the others
+    /// in a single-page bundle.
+    fn prefix(&self, n: usize) -> String {
+        match self.bundle {
+            Bundle::Page => format!("doc-{n}-"),
+            Bundle::Folder => String::new(),
+        }
+    }
+
+    /// Where a link in the `n`th document should lead in the bundle, or
+    /// `None` for wikilinks to notes that aren't part of it.
+    fn href(&mut self, n: usize, dest: &str, wiki: bool) -> Option<String> {
+        let dir = self.sources[n].parent();
+        let (path, anchor) = if wiki {
+            let anchor = wikilink::heading(dest).map(blocks::slug);
+            match wikilink::note(dest) {
+                "" => (None, anchor),
+                note => (Some(wikilink::resolve(note, dir, self.workspace)?), anchor),
+            }
+        } else {
+            if dest.contains(':') {
+                return Some(dest.to_string());
+            }
+            let (file, anchor) = dest.split_once('#').unwrap_or((dest, ""));
+            let anchor = Some(anchor.to_string()).filter(|anchor| !anchor.is_empty());
+            match file {
+                "" => (None, anchor),
+                file => (Some(dir?.join(file.replace("%20", " "))), anchor),
+            }
+        };
+
+        let target = match path {
+            None => Some(n),
+            Some(path) => {
+                let path = fs::canonicalize(&path).unwrap_or(path);
+                match self.sources.iter().position(|source| *source == path) {
+                    Some(target) => Some(target),
+                    None if wiki => return None,
+                    None => return Some(self.asset(n, &path).unwrap_or(dest.to_string())),
+                }
+            }
+        };
+        let target = target?;
+        let fragment = anchor.map(|anchor| format!("#{}{anchor}", self.prefix(target)));
+        Some(match self.bundle {
+            Bundle::Page => fragment.unwrap_or(format!("#doc-{target}")),
+            Bundle::Folder => {
+                let from = self.pages[n].parent().unwrap_or(Path::new(""));
+                let page = if target == n {
+                    String::new()
+                } else {
+                    relative_url(from, &self.pages[target])
+                };
+                page + &fragment.unwrap_or_default()
+            }
+        })
+    }
+
+    /// URL of a local file used by the `n`th document once copied into the
+    /// bundle; `None` if it isn't there or lies outside the documents' folder.
+    fn asset(&mut self, n: usize, path: &Path) -> Option<String> {
+        let rel = path.strip_prefix(&self.base).ok()?.to_path_buf();
+        if !path.is_file() {
+            return None;
+        }
+        if !self.assets.iter().any(|(source, _)| source == path) {
+            self.assets.push((path.to_path_buf(), rel.clone()));
+        }
+        let from = match self.bundle {
+            Bundle::Page => Path::new(""),
+            Bundle::Folder => self.pages[n].parent().unwrap_or(Path::new("")),
+        };
+        Some(relative_url(from, &rel))
+    }
+
+    fn render(&mut self, n: usize, markdown: &str) -> (String, Vec<Heading>) {
+        let prefix = self.prefix(n);
+        push_html(parse(markdown), &prefix, |dest, wiki| {
+            self.href(n, dest, wiki)
+        })
+    }
+}
+
+/// Parses `markdown` the way exports see it, wikilinks included.
+pub fn parse(markdown: &str) -> Parser<'_> {
+    Parser::new_ext(
+        markdown,
+        blocks::parser_options() | Options::ENABLE_WIKILINKS,
+    )
+}
+
+/// `events` as HTML, with links and images leading where `href` says;
+/// `None` drops a link, or shows an image as its alt text.
+pub fn to_html<'a>(
+    events: impl IntoIterator<Item = Event<'a>>,
+    href: impl FnMut(&str, bool) -> Option<String>,
+) -> String {
+    push_html(events, "", href).0
+}
+
+/// [`to_html`], with `prefix` in front of the anchors it makes, also
+/// returning the headings.
+fn push_html<'a>(
+    events: impl IntoIterator<Item = Event<'a>>,
+    prefix: &str,
+    mut href: impl FnMut(&str, bool) -> Option<String>,
+) -> (String, Vec<Heading>) {
+    let mut html = String::new();
+    let mut headings = Vec::new();
+    // Level, where its tag goes and its text, while inside a heading
+    let mut heading: Option<(usize, usize, String)> = None;
+    // Headings so far with each slug, numbered on as GitHub does
+    let mut slugs: HashMap<String, usize> = HashMap::new();
+    // Whether each link being written got an `<a>` tag
+    let mut links = Vec::new();
+    let mut alignments: Vec<Alignment> = Vec::new();
+    let mut cell = 0;
+    let mut in_table_head = false;
+    let mut in_metadata = false;
+    let mut image_depth = 0;
+    // Whether the image being written is only its alt text
+    let mut alt_only = false;
+
+    for event in events {
+        if in_metadata {
+            in_metadata = !matches!(event, Event::End(TagEnd::MetadataBlock(_)));
+            continue;
+        }
+        // Everything inside an image is its alt text
+        if image_depth > 0 {
+            match event {
+                Event::Text(text) | Event::Code(text) => html.push_str(&escape(&text)),
+                Event::Start(Tag::Image { .. }) => image_depth += 1,
+                Event::End(TagEnd::Image) => {
+                    image_depth -= 1;
+                    if image_depth == 0 && !alt_only {
+                        html.push_str("\" />");
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if let (Event::Text(text) | Event::Code(text), Some((_, _, title))) = (&event, &mut heading)
+        {
+            title.push_str(text);
+        }
+
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph => html.push_str("<p>"),
+                Tag::Heading { level, .. } => {
+                    heading = Some((level as usize, html.len(), String::new()));
+                }
+                Tag::BlockQuote(_) => html.push_str("<blockquote>\n"),
+                Tag::CodeBlock(kind) => match kind {
+                    CodeBlockKind::Fenced(info) if !info.is_empty() => {
+                        let lang = info.split([' ', ',']).next().unwrap_or_default();
+                        html.push_str(&format!("<pre><code class=\"language-{}\">", escape(lang)));
+                    }
+                    _ => html.push_str("<pre><code>"),
+                },
+                Tag::List(Some(1)) => html.push_str("<ol>\n"),
+                Tag::List(Some(start)) => html.push_str(&format!("<ol start=\"{start}\">\n")),
+                Tag::List(None) => html.push_str("<ul>\n"),
+                Tag::Item => html.push_str("<li>"),
+                Tag::FootnoteDefinition(name) => html.push_str(&format!(
+                    "<div class=\"footnote\" id=\"{prefix}fn-{0}\"><sup>{0}</sup> ",
+                    escape(&name)
+                )),
+                Tag::DefinitionList => html.push_str("<dl>\n"),
+                Tag::DefinitionListTitle => html.push_str("<dt>"),
+                Tag::DefinitionListDefinition => html.push_str("<dd>"),
+                Tag::Table(table) => {
+                    alignments = table;
+                    html.push_str("<table>\n");
+                }
+                Tag::TableHead => {
+                    in_table_head = true;
+                    cell = 0;
+                    html.push_str("<thead><tr>");
+                }
+                Tag::TableRow => {
+                    cell = 0;
+                    html.push_str("<tr>");
+                }
+                Tag::TableCell => {
+                    let tag = if in_table_head { "th" } else { "td" };
+                    match alignments.get(cell) {
+                        Some(Alignment::Left) => {
+                            html.push_str(&format!("<{tag} style=\"text-align: left\">"))
+                        }
+                        Some(Alignment::Center) => {
+                            html.push_str(&format!("<{tag} style=\"text-align: center\">"))
+                        }
+                        Some(Alignment::Right) => {
+                            html.push_str(&format!("<{tag} style=\"text-align: right\">"))
+                        }
+                        _ => html.push_str(&format!("<{tag}>")),
+                    }
+                }
+                Tag::Emphasis => html.push_str("<em>"),
+                Tag::Strong => html.push_str("<strong>"),
+                Tag::Strikethrough => html.push_str("<del>"),
+                Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    ..
+                } => {
+                    let wiki = matches!(link_type, LinkType::WikiLink { .. });
+                    let href = href(&dest_url, wiki);
+                    if let Some(href) = &href {
+                        html.push_str(&format!("<a href=\"{}\"", escape(href)));
+                        if !title.is_empty() {
+                            html.push_str(&format!(" title=\"{}\"", escape(&title)));
+                        }
+                        html.push('>');
+                    }
+                    links.push(href.is_some());
+                }
+                Tag::Image {
+                    dest_url, title, ..
+                } => {
+                    image_depth = 1;
+                    alt_only = true;
+                    if let Some(src) = href(&dest_url, false) {
+                        html.push_str(&format!("<img src=\"{}\"", escape(&src)));
+                        if !title.is_empty() {
+                            html.push_str(&format!(" title=\"{}\"", escape(&title)));
+                        }
+                        html.push_str(" alt=\"");
+                        alt_only = false;
+                    }
+                }
+                Tag::MetadataBlock(_) => in_metadata = true,
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Paragraph => html.push_str("</p>\n"),
+                TagEnd::Heading(_) => {
+                    if let Some((level, at, text)) = heading.take() {
+                        let slug = blocks::slug(&text);
+                        let seen = slugs.entry(slug.clone()).or_default();
+                        let id = match *seen {
+                            0 => format!("{prefix}{slug}"),
+                            n => format!("{prefix}{slug}-{n}"),
+                        };
+                        *seen += 1;
+                        html.insert_str(at, &format!("<h{level} id=\"{}\">", escape(&id)));
+                        html.push_str(&format!("</h{level}>\n"));
+                        headings.push(Heading { level, text, id });
+                    }
+                }
+                TagEnd::BlockQuote(_) => html.push_str("</blockquote>\n"),
+                TagEnd::CodeBlock => html.push_str("</code></pre>\n"),
+                TagEnd::List(true) => html.push_str("</ol>\n"),
+                TagEnd::List(false) => html.push_str("</ul>\n"),
+                TagEnd::Item => html.push_str("</li>\n"),
+                TagEnd::FootnoteDefinition => html.push_str("</div>\n"),
+                TagEnd::DefinitionList => html.push_str("</dl>\n"),
+                TagEnd::DefinitionListTitle => html.push_str("</dt>\n"),
+                TagEnd::DefinitionListDefinition => html.push_str("</dd>\n"),
+                TagEnd::Table => html.push_str("</tbody></table>\n"),
+                TagEnd::TableHead => {
+                    in_table_head = false;
+                    html.push_str("</tr></thead>\n<tbody>\n");
+                }
+                TagEnd::TableRow => html.push_str("</tr>\n"),
+                TagEnd::TableCell => {
+                    html.push_str(if in_table_head { "</th>" } else { "</td>" });
+                    cell += 1;
+                }
+                TagEnd::Emphasis => html.push_str("</em>"),
+                TagEnd::Strong => html.push_str("</strong>"),
+                TagEnd::Strikethrough => html.push_str("</del>"),
+                TagEnd::Link => {
+                    let opened = links.pop() == Some(true);
+                    html.push_str(if opened { "</a>" } else { "" });
+                }
+                _ => {}
+            },
+            Event::Text(text) => html.push_str(&escape(&text)),
+            Event::Code(code) => html.push_str(&format!("<code>{}</code>", escape(&code))),
+            Event::Html(raw) | Event::InlineHtml(raw) => html.push_str(&raw),
+            Event::FootnoteReference(name) => html.push_str(&format!(
+                "<sup><a href=\"#{prefix}fn-{0}\">{0}</a></sup>",
+                escape(&name)
+            )),
+            Event::SoftBreak => html.push('\n'),
+            Event::HardBreak => html.push_str("<br />\n"),
+            Event::Rule => html.push_str("<hr />\n"),
+            Event::TaskListMarker(checked) => html.push_str(if checked {
+                "<input type=\"checkbox\" disabled checked /> "
+            } else {
+                "<input type=\"checkbox\" disabled /> "
+            }),
+            _ => {}
+        }
+    }
+    (html, headings)
+}
+
+fn document(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>\n{STYLE}</style>\n</head>\n<body>\n{body}</body>\n</html>\n",
+        escape(title)
+    )
+}
+
+/// The deepest folder all of `paths` are in.
+fn common_dir(paths: &[PathBuf]) -> PathBuf {
+    let mut dirs = paths
+        .iter()
+        .map(|path| path.parent().unwrap_or(Path::new("")));
+    let Some(first) = dirs.next() else {
+        return PathBuf::new();
+    };
+    let mut common = first.to_path_buf();
+    for dir in dirs {
+        while !dir.starts_with(&common) && common.pop() {}
+    }
+    common
+}
+
+/// URL of `to` from a page in `from_dir`, both relative to the bundle.
+fn relative_url(from_dir: &Path, to: &Path) -> String {
+    let from: Vec<_> = from_dir.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts = vec!["..".to_string(); from.len() - common];
+    parts.extend(to[common..].iter().map(|part| {
+        part.as_os_str()
+            .to_string_lossy()
+            .replace('%', "%25")
+            .replace(' ', "%20")
+            .replace('#', "%23")
+    }));
+    parts.join("/")
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,131 @@
+//! Font files added in Preferences, and the installed fonts offered there.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+use anyhow::{Context, Result};
+use eframe::egui::{self, FontData, FontDefinitions, FontFamily};
+
+use crate::config::{CustomFont, FontRole};
+
+const FONT_EXTENSIONS: [&str; 4] = ["ttf", "otf", "ttc", "otc"];
+
+/// How deep font directories are searched; some keep a folder per family.
+const MAX_SCAN_DEPTH: usize = 4;
+
+/// Replaces egui's fonts with the built-in ones plus `fonts`. Files that
+/// can't be read or aren't fonts are left out and reported.
+pub fn install(ctx: &egui::Context, fonts: &[CustomFont]) -> Vec<anyhow::Error> {
+    let mut definitions = FontDefinitions::default();
+    let mut errors = Vec::new();
+    // Replacements go ahead of the built-in font, in the order they were added
+    let (mut text_at, mut code_at) = (0, 0);
+
+    for font in fonts {
+        let name = font.path.display().to_string();
+        let data = match load(&font.path) {
+            Ok(data) => data,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        definitions.font_data.insert(name.clone(), Arc::new(data));
+
+        let families = &mut definitions.families;
+        match font.role {
+            FontRole::Text => {
+                families
+                    .entry(FontFamily::Proportional)
+                    .or_default()
+                    .insert(text_at, name);
+                text_at += 1;
+            }
+            FontRole::Code => {
+                families
+                    .entry(FontFamily::Monospace)
+                    .or_default()
+                    .insert(code_at, name);
+                code_at += 1;
+            }
+            FontRole::Fallback => {
+                families
+                    .entry(FontFamily::Proportional)
+                    .or_default()
+                    .push(name.clone());
+                families
+                    .entry(FontFamily::Monospace)
+                    .or_default()
+                    .push(name);
+            }
+        }
+    }
+
+    ctx.set_fonts(definitions);
+    errors
+}
+
+fn load(path: &Path) -> Result<FontData> {
+    let bytes = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    ab_glyph::FontRef::try_from_slice(&bytes)
+        .with_context(|| format!("{} isn't a TrueType or OpenType font", path.display()))?;
+    Ok(FontData::from_owned(bytes))
+}
+
+/// File name of a font, for lists.
+pub fn name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Font files installed on this system, by file name. Looked up once.
+pub fn system_fonts() -> &'static [PathBuf] {
+    static FONTS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        let mut fonts = Vec::new();
+        for dir in font_dirs() {
+            scan(&dir, 0, &mut fonts);
+        }
+        fonts.sort_by_key(|path| name(path).to_lowercase());
+        fonts.dedup_by_key(|path| name(path));
+        fonts
+    })
+}
+
+fn font_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = dirs::font_dir().into_iter().collect();
+    if cfg!(target_os = "windows") {
+        let windir = std::env::var_os("WINDIR").unwrap_or_else(|| "C:\\Windows".into());
+        dirs.push(PathBuf::from(windir).join("Fonts"));
+    } else if cfg!(target_os = "macos") {
+        dirs.extend(["/Library/Fonts", "/System/Library/Fonts"].map(PathBuf::from));
+    } else {
+        dirs.extend(["/usr/share/fonts", "/usr/local/share/fonts"].map(PathBuf::from));
+        dirs.extend(dirs::home_dir().map(|home| home.join(".fonts")));
+    }
+    dirs
+}
+
+fn scan(dir: &Path, depth: usize, fonts: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.is_dir() {
+            if depth < MAX_SCAN_DEPTH {
+                scan(&path, depth + 1, fonts);
+            }
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| FONT_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        {
+            fonts.push(path);
+        }
+    }
+}
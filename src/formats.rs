@@ -0,0 +1,150 @@
+//! Document formats other than Markdown, converted to Markdown for display.
+
+mod asciidoc;
+mod html;
+mod rst;
+
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Markdown,
+    AsciiDoc,
+    ReStructuredText,
+    /// Web pages, shown as a text preview.
+    Html,
+    /// CSV and TSV data, shown as a table.
+    Csv,
+}
+
+/// Extensions of web pages, which are previewed when opened but aren't
+/// documents to list or filter for.
+const HTML_EXTENSIONS: [&str; 2] = ["html", "htm"];
+
+/// Extensions of data files, which are shown as tables when opened.
+const CSV_EXTENSIONS: [&str; 2] = ["csv", "tsv"];
+
+impl Format {
+    /// Every format with its file extensions, for dialog filters.
+    pub const ALL: [(Format, &'static [&'static str]); 3] = [
+        (Format::Markdown, &["md", "markdown"]),
+        (Format::AsciiDoc, &["adoc", "asciidoc", "asc"]),
+        (Format::ReStructuredText, &["rst", "rest"]),
+    ];
+
+    /// The format of `path` going by its extension, if it is one we can show.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|(_, exts)| exts.contains(&ext.as_str()))
+            .map(|(format, _)| format)
+    }
+
+    /// The format to show `path` in, taking anything unknown for Markdown.
+    pub fn for_tab(path: &Path) -> Self {
+        match Self::from_path(path) {
+            Some(format) => format,
+            None if is_html(path) => Format::Html,
+            None if is_csv(path) => Format::Csv,
+            None => Format::Markdown,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Markdown => "Markdown",
+            Format::AsciiDoc => "AsciiDoc",
+            Format::ReStructuredText => "reStructuredText",
+            Format::Html => "HTML",
+            Format::Csv => "CSV",
+        }
+    }
+
+    /// Converts `source` in this format to Markdown.
+    pub fn to_markdown(self, source: String) -> String {
+        match self {
+            Format::Markdown => source,
+            Format::AsciiDoc => asciidoc::to_markdown(&source),
+            Format::ReStructuredText => rst::to_markdown(&source),
+            Format::Html => html::to_markdown(&source),
+            Format::Csv => csv_fence(&source),
+        }
+    }
+}
+
+/// Extensions of every supported format, e.g. for an "All documents" filter.
+pub fn all_extensions() -> Vec<&'static str> {
+    Format::ALL
+        .iter()
+        .flat_map(|(_, exts)| exts.iter().copied())
+        .collect()
+}
+
+/// Whether `path` is a web page going by its extension.
+pub fn is_html(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| HTML_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+}
+
+/// Whether `path` is a CSV or TSV file going by its extension.
+pub fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| CSV_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+}
+
+/// `source` in a ```` ```csv ```` fence, or ```` ```tsv ```` if its first
+/// line is split by tabs, long enough for any backticks in it.
+fn csv_fence(source: &str) -> String {
+    let first = source.lines().next().unwrap_or("");
+    let lang = if first.matches('\t').count() > first.matches(',').count() {
+        "tsv"
+    } else {
+        "csv"
+    };
+    let longest = source.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!(
+        "{fence}{lang}\n{}\n{fence}\n",
+        source.trim_end_matches(['\n', '\r'])
+    )
+}
+
+/// Collects the indented lines following `lines[start]`, dedented, skipping
+/// leading blank lines. Returns them with the index of the first line after.
+fn indented_block<'a>(lines: &[&'a str], start: usize) -> (Vec<&'a str>, usize) {
+    let mut end = start;
+    while end < lines.len() && lines[end].trim().is_empty() {
+        end += 1;
+    }
+    let body_start = end;
+    while end < lines.len() && (lines[end].trim().is_empty() || lines[end].starts_with([' ', '\t']))
+    {
+        end += 1;
+    }
+    // Trailing blank lines belong to whatever comes next
+    while end > body_start && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+
+    let body = &lines[body_start..end];
+    let indent = body
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let dedented = body
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect();
+    (dedented, end)
+}
+
+/// Wraps `lines` in a fenced code block.
+fn push_fence(out: &mut Vec<String>, lang: &str, lines: &[&str]) {
+    out.push(format!("```{lang}"));
+    out.extend(lines.iter().map(|line| line.to_string()));
+    out.push("```".into());
+}
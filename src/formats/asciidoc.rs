@@ -0,0 +1,271 @@
+//! A converter for the commonly used subset of AsciiDoc.
+
+use super::push_fence;
+
+const ADMONITIONS: [(&str, &str); 5] = [
+    ("NOTE", "Note"),
+    ("TIP", "Tip"),
+    ("IMPORTANT", "Important"),
+    ("WARNING", "Warning"),
+    ("CAUTION", "Caution"),
+];
+
+pub fn to_markdown(source: &str) -> String {
+    let lines: Vec<&str> = source.lines().map(str::trim_end).collect();
+    let mut out = Vec::new();
+    // Language from a `[source,lang]` line, for the listing block after it
+    let mut source_lang: Option<String> = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+
+        // Delimited blocks
+        if let Some(fence) = delimiter(line) {
+            // Example blocks, sidebars and the like: just show the content
+            if matches!(fence, '=' | '*') {
+                continue;
+            }
+            let body_end = (i..lines.len())
+                .find(|&j| lines[j] == line)
+                .unwrap_or(lines.len());
+            let body = &lines[i..body_end];
+            i = (body_end + 1).min(lines.len());
+            match fence {
+                '-' | '.' => push_fence(&mut out, source_lang.as_deref().unwrap_or(""), body),
+                '_' => out.extend(body.iter().map(|l| format!("> {}", inline(l)))),
+                // Comment block
+                _ => {}
+            }
+            source_lang = None;
+            continue;
+        }
+
+        if line.starts_with("//") {
+            continue;
+        }
+
+        // Block attributes and anchors
+        if let Some(attrs) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let mut parts = attrs.split(',').map(str::trim);
+            if parts.next() == Some("source") {
+                source_lang = Some(parts.next().unwrap_or("").to_string());
+            }
+            continue;
+        }
+
+        // Document attributes like `:toc:` or `:author: Jane`
+        if let Some(rest) = line.strip_prefix(':')
+            && let Some((name, _)) = rest.split_once(':')
+            && !name.is_empty()
+            && !name.contains(' ')
+        {
+            continue;
+        }
+
+        if let Some((level, title)) = heading(line) {
+            out.push(format!("{} {}", "#".repeat(level), inline(title)));
+            continue;
+        }
+
+        if line == "'''" {
+            out.push("---".into());
+            continue;
+        }
+        if line == "<<<" {
+            continue;
+        }
+        // List continuation; the attached block follows as-is
+        if line == "+" {
+            out.push(String::new());
+            continue;
+        }
+
+        if let Some(image) = line.strip_prefix("image::") {
+            let (target, alt) = image.split_once('[').unwrap_or((image, ""));
+            let alt = alt.trim_end_matches(']').split(',').next().unwrap_or("");
+            out.push(format!("![{alt}]({target})"));
+            continue;
+        }
+
+        if let Some((label, text)) = ADMONITIONS.iter().find_map(|(marker, label)| {
+            line.strip_prefix(marker)?
+                .strip_prefix(": ")
+                .map(|text| (label, text))
+        }) {
+            out.push(format!("> **{label}:** {}", inline(text)));
+            continue;
+        }
+
+        if let Some((depth, ordered, text)) = list_item(line) {
+            let marker = if ordered { "1." } else { "-" };
+            out.push(format!(
+                "{}{marker} {}",
+                "    ".repeat(depth - 1),
+                inline(text)
+            ));
+            continue;
+        }
+
+        out.push(inline(line));
+    }
+
+    out.join("\n")
+}
+
+/// The character of a delimited block fence like `----` or `____`.
+fn delimiter(line: &str) -> Option<char> {
+    let first = line.chars().next()?;
+    (line.len() >= 4 && "-._=*/".contains(first) && line.chars().all(|c| c == first))
+        .then_some(first)
+}
+
+/// `= Title`, `== Section` and so on, or Markdown-style `#` headings.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let marker = line.chars().next().filter(|c| *c == '=' || *c == '#')?;
+    let level = line.chars().take_while(|c| *c == marker).count();
+    let title = line[level..].strip_prefix(' ')?;
+    (level <= 6).then_some((level, title.trim()))
+}
+
+/// `* item`, `** nested`, `- item` or `. ordered`: nesting depth, whether
+/// it's ordered, and the item text.
+fn list_item(line: &str) -> Option<(usize, bool, &str)> {
+    let line = line.trim_start();
+    let marker = line
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '*' | '-' | '.'))?;
+    let depth = line.chars().take_while(|c| *c == marker).count();
+    let text = line[depth..].strip_prefix(' ')?;
+    // `-` doesn't nest by repetition: `--` is not a marker
+    if marker == '-' && depth > 1 {
+        return None;
+    }
+    Some((depth, marker == '.', text))
+}
+
+/// Converts inline markup: constrained and unconstrained emphasis, link and
+/// image macros, and cross references. Code spans are copied as-is.
+fn inline(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    let mut prev: Option<char> = None;
+
+    while let Some(c) = rest.chars().next() {
+        let at_boundary = prev.is_none_or(|p| !p.is_alphanumeric());
+
+        if c == '`'
+            && let Some(end) = rest[1..].find('`')
+        {
+            out.push_str(&rest[..end + 2]);
+            rest = &rest[end + 2..];
+            prev = Some('`');
+            continue;
+        }
+
+        if at_boundary && let Some((markdown, len)) = link_macro(rest) {
+            out.push_str(&markdown);
+            rest = &rest[len..];
+            prev = Some(']');
+            continue;
+        }
+
+        if let Some(inner) = rest.strip_prefix("<<")
+            && let Some(end) = inner.find(">>")
+        {
+            let xref = &inner[..end];
+            // `<<id,text>>` shows the text; `<<id>>` the target
+            out.push_str(xref.split_once(',').map_or(xref, |(_, text)| text.trim()));
+            rest = &inner[end + 2..];
+            prev = Some('>');
+            continue;
+        }
+
+        if (c == '*' || c == '_')
+            && let Some((inner, len)) = emphasis(rest, c, at_boundary)
+        {
+            let marker = if c == '*' { "**" } else { "*" };
+            out.push_str(marker);
+            out.push_str(&inline(inner));
+            out.push_str(marker);
+            rest = &rest[len..];
+            prev = Some(c);
+            continue;
+        }
+
+        out.push(c);
+        prev = Some(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// `**text**` anywhere, or `*text*` at word boundaries (likewise with `_`).
+/// Returns the text and the length of the whole span.
+fn emphasis(rest: &str, marker: char, at_boundary: bool) -> Option<(&str, usize)> {
+    let double: String = [marker, marker].iter().collect();
+    if let Some(inner) = rest.strip_prefix(double.as_str()) {
+        let end = inner.find(double.as_str())?;
+        return (end > 0).then(|| (&inner[..end], end + 4));
+    }
+
+    if !at_boundary {
+        return None;
+    }
+    let inner = &rest[1..];
+    if inner.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let end = inner.char_indices().find_map(|(idx, ch)| {
+        let closes = ch == marker
+            && idx > 0
+            && !inner[..idx].ends_with(char::is_whitespace)
+            && !inner[idx + 1..].starts_with(char::is_alphanumeric);
+        closes.then_some(idx)
+    })?;
+    Some((&inner[..end], end + 2))
+}
+
+/// `https://url[text]`, `link:url[text]` and `image:path[alt]`. Returns the
+/// Markdown and how much of `rest` it replaces.
+fn link_macro(rest: &str) -> Option<(String, usize)> {
+    let (prefix, is_image) = [
+        ("https://", false),
+        ("http://", false),
+        ("mailto:", false),
+        ("link:", false),
+        ("image:", true),
+    ]
+    .into_iter()
+    .find(|(prefix, _)| rest.starts_with(prefix))?;
+
+    // `link:` and `image:` aren't part of the target; URL schemes are
+    let target_start = if prefix.ends_with("//") || prefix == "mailto:" {
+        0
+    } else {
+        prefix.len()
+    };
+    let target_end = rest[target_start..]
+        .find(|c: char| c.is_whitespace() || c == '[')
+        .map_or(rest.len(), |idx| idx + target_start);
+    let target = &rest[target_start..target_end];
+    if target.is_empty() {
+        return None;
+    }
+
+    let Some(attrs) = rest[target_end..].strip_prefix('[') else {
+        // A bare URL
+        return (target_start == 0).then(|| (format!("<{target}>"), target_end));
+    };
+    let close = attrs.find(']')?;
+    let text = attrs[..close].split(',').next().unwrap_or("").trim();
+    let len = target_end + close + 2;
+    let markdown = match (is_image, text.is_empty()) {
+        (true, _) => format!("![{text}]({target})"),
+        (false, true) => format!("<{target}>"),
+        (false, false) => format!("[{text}]({target})"),
+    };
+    Some((markdown, len))
+}
@@ -0,0 +1,428 @@
+//! A text rendering of HTML pages: tags dropped, with headings, paragraphs,
+//! lists, tables, code and links kept as Markdown. Scripts, styles and
+//! everything else that isn't text are left out.
+
+/// Elements whose contents aren't shown at all.
+const HIDDEN: [&str; 8] = [
+    "head", "script", "style", "noscript", "template", "svg", "iframe", "object",
+];
+
+/// Elements that start a block of their own.
+const BLOCKS: [&str; 17] = [
+    "p",
+    "div",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "main",
+    "nav",
+    "aside",
+    "blockquote",
+    "figure",
+    "figcaption",
+    "form",
+    "dl",
+    "dt",
+    "dd",
+    "address",
+];
+
+pub fn to_markdown(source: &str) -> String {
+    let mut writer = Writer::default();
+    let mut rest = source;
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            // Doctype and processing instructions
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if rest.starts_with('<')
+            && let Some(end) = rest.find('>')
+            && let Some(tag) = Tag::parse(&rest[1..end])
+        {
+            rest = &rest[end + 1..];
+            if !tag.closing && HIDDEN.contains(&tag.name.as_str()) {
+                rest = skip_element(rest, &tag.name);
+            } else {
+                writer.tag(&tag);
+            }
+        } else {
+            let end = match rest.strip_prefix('<') {
+                Some(after) => after.find('<').map_or(rest.len(), |at| at + 1),
+                None => rest.find('<').unwrap_or(rest.len()),
+            };
+            writer.text(&decode_entities(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+    writer.finish()
+}
+
+struct Tag {
+    name: String,
+    closing: bool,
+    /// Raw attributes, e.g. `href="a.html" class="x"`.
+    attributes: String,
+}
+
+impl Tag {
+    fn parse(inner: &str) -> Option<Self> {
+        let (closing, inner) = match inner.strip_prefix('/') {
+            Some(inner) => (true, inner),
+            None => (false, inner),
+        };
+        let inner = inner.strip_suffix('/').unwrap_or(inner);
+        let name_end = inner
+            .find(|c: char| c.is_whitespace())
+            .unwrap_or(inner.len());
+        let name = &inner[..name_end];
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        Some(Self {
+            name: name.to_ascii_lowercase(),
+            closing,
+            attributes: inner[name_end..].to_string(),
+        })
+    }
+
+    /// The value of attribute `name`, quoted or not.
+    fn attribute(&self, name: &str) -> Option<String> {
+        let mut rest = self.attributes.as_str();
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                return None;
+            }
+            let key_end = rest
+                .find(|c: char| c == '=' || c.is_whitespace())
+                .unwrap_or(rest.len());
+            let key = &rest[..key_end];
+            rest = rest[key_end..].trim_start();
+            let mut value = None;
+            if let Some(after) = rest.strip_prefix('=') {
+                let after = after.trim_start();
+                let (found, next) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let quoted = &after[1..];
+                        let end = quoted.find(quote).unwrap_or(quoted.len());
+                        (&quoted[..end], quoted.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => after.split_at(after.find(char::is_whitespace).unwrap_or(after.len())),
+                };
+                value = Some(found);
+                rest = next;
+            }
+            if key.eq_ignore_ascii_case(name) {
+                return value.map(decode_entities);
+            }
+        }
+    }
+}
+
+/// What comes after the element `name` that `html` is the inside of.
+fn skip_element<'a>(html: &'a str, name: &str) -> &'a str {
+    let closing = format!("</{name}");
+    let lower = html.to_ascii_lowercase();
+    match lower.find(&closing) {
+        Some(at) => html[at..].find('>').map_or("", |end| &html[at + end + 1..]),
+        None => "",
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    out: String,
+    /// Text of the paragraph, heading, item or cell being read.
+    inline: String,
+    /// Whether the current block is a heading of this level.
+    heading: Option<usize>,
+    /// Bullet or number of the list item about to start.
+    marker: Option<String>,
+    /// The current block is a list item.
+    item: bool,
+    /// Whitespace came after the last word written.
+    space: bool,
+    /// Inside `<pre>`, where whitespace is kept and nothing is escaped.
+    pre: bool,
+    /// Inside `<code>`, where nothing is escaped.
+    code: bool,
+    /// Next number of each `<ol>` entered, `None` for `<ul>`.
+    lists: Vec<Option<u64>>,
+    /// Destinations of the links entered.
+    links: Vec<Option<String>>,
+    /// Rows of cells of the table being read.
+    table: Option<Vec<Vec<String>>>,
+}
+
+impl Writer {
+    fn tag(&mut self, tag: &Tag) {
+        let name = tag.name.as_str();
+        match (name, tag.closing) {
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                self.end_block();
+                self.heading = name[1..].parse().ok();
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => self.end_block(),
+            (name, _) if BLOCKS.contains(&name) => self.end_block(),
+            ("br", _) if self.pre => self.inline.push('\n'),
+            ("br", _) if self.table.is_some() => self.space = true,
+            ("br", _) => {
+                self.inline.push_str("\\\n");
+                self.space = false;
+            }
+            ("hr", _) => {
+                self.end_block();
+                self.out.push_str("---\n\n");
+            }
+            ("pre", false) => {
+                self.end_block();
+                self.pre = true;
+            }
+            ("pre", true) => {
+                let code = std::mem::take(&mut self.inline);
+                let code = code.trim_matches('\n');
+                let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+                self.out.push_str(&format!("{fence}\n{code}\n{fence}\n\n"));
+                self.pre = false;
+            }
+            ("ul" | "ol", false) => {
+                self.end_block();
+                let start = tag.attribute("start").and_then(|n| n.parse().ok());
+                self.lists.push((name == "ol").then(|| start.unwrap_or(1)));
+            }
+            ("ul" | "ol", true) => {
+                self.end_block();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.out.push('\n');
+                }
+            }
+            ("li", false) => {
+                self.end_block();
+                let indent = "    ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}.", *n - 1)
+                    }
+                    _ => "-".to_string(),
+                };
+                self.marker = Some(format!("{indent}{marker} "));
+            }
+            ("li", true) => self.end_block(),
+            ("table", false) => {
+                self.end_block();
+                self.table = Some(Vec::new());
+            }
+            ("table", true) => {
+                if let Some(rows) = self.table.take() {
+                    self.write_table(rows);
+                }
+                self.inline.clear();
+            }
+            ("tr", false) => {
+                if let Some(rows) = &mut self.table {
+                    rows.push(Vec::new());
+                }
+            }
+            ("td" | "th", false) => {
+                self.inline.clear();
+                self.space = false;
+            }
+            ("td" | "th", true) => {
+                let cell = std::mem::take(&mut self.inline);
+                if let Some(rows) = &mut self.table {
+                    if rows.is_empty() {
+                        rows.push(Vec::new());
+                    }
+                    let row = rows.last_mut().expect("just made sure");
+                    row.push(cell.trim().to_string());
+                }
+            }
+            _ if self.pre => {}
+            ("strong" | "b", _) => self.mark("**"),
+            ("em" | "i", _) => self.mark("*"),
+            ("code" | "kbd" | "samp", closing) => {
+                self.code = !closing;
+                self.mark("`");
+            }
+            ("a", false) => {
+                let href = tag.attribute("href").filter(|href| !href.is_empty());
+                if href.is_some() {
+                    self.mark("[");
+                }
+                self.links.push(href);
+            }
+            ("a", true) => {
+                if let Some(Some(href)) = self.links.pop() {
+                    let href = href.replace(' ', "%20").replace(['(', ')'], "");
+                    self.inline.push_str(&format!("]({href})"));
+                }
+            }
+            ("img", _) => {
+                if let Some(alt) = tag.attribute("alt").filter(|alt| !alt.trim().is_empty()) {
+                    self.text(&format!("[{}]", alt.trim()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.pre {
+            self.inline.push_str(text);
+            return;
+        }
+        for (n, word) in text.split(char::is_whitespace).enumerate() {
+            if n > 0 {
+                self.space = true;
+            }
+            if !word.is_empty() {
+                let word = if self.code {
+                    word.to_string()
+                } else {
+                    escape(word, self.table.is_some())
+                };
+                self.mark(&word);
+            }
+        }
+    }
+
+    /// Adds a word or markup, after a space if one was pending.
+    fn mark(&mut self, text: &str) {
+        if self.inline.is_empty()
+            && let Some(marker) = self.marker.take()
+        {
+            self.inline.push_str(&marker);
+            self.item = true;
+        }
+        let at_start = self.inline.is_empty() || self.inline.ends_with([' ', '[', '\n']);
+        if self.space && !at_start {
+            self.inline.push(' ');
+        }
+        self.space = false;
+        self.inline.push_str(text);
+    }
+
+    /// Ends the paragraph, heading or list item being read.
+    fn end_block(&mut self) {
+        if self.table.is_some() {
+            // Paragraphs in cells run together
+            self.space = true;
+            return;
+        }
+        let line = std::mem::take(&mut self.inline);
+        self.space = false;
+        let level = self.heading.take();
+        let item = std::mem::take(&mut self.item);
+        if line.trim().is_empty() {
+            return;
+        }
+        if let Some(level) = level {
+            self.out
+                .push_str(&format!("{} {}\n\n", "#".repeat(level), line.trim()));
+        } else if item {
+            self.out.push_str(line.trim_end());
+            self.out.push('\n');
+        } else if !self.lists.is_empty() {
+            // More of the item, after a paragraph or a list of its own
+            let indent = "    ".repeat(self.lists.len());
+            self.out.push_str(&format!("{indent}{}\n", line.trim()));
+        } else {
+            self.out.push_str(line.trim());
+            self.out.push_str("\n\n");
+        }
+    }
+
+    fn write_table(&mut self, rows: Vec<Vec<String>>) {
+        let rows: Vec<Vec<String>> = rows.into_iter().filter(|row| !row.is_empty()).collect();
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        for (n, row) in rows.iter().enumerate() {
+            let mut cells = row.clone();
+            cells.resize(columns, String::new());
+            self.out.push_str(&format!("| {} |\n", cells.join(" | ")));
+            if n == 0 {
+                self.out
+                    .push_str(&format!("|{}\n", " --- |".repeat(columns)));
+            }
+        }
+        self.out.push('\n');
+    }
+
+    fn finish(mut self) -> String {
+        self.pre = false;
+        self.end_block();
+        self.out.trim_end().to_string() + "\n"
+    }
+}
+
+/// Backslash-escapes what would otherwise be read as Markdown.
+fn escape(text: &str, in_table: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#') || in_table && c == '|'
+        {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|other| other != c)
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Replaces character references like `&amp;` and `&#8212;` with their characters.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        decoded.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let c = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            "mdash" => Some('—'),
+            "ndash" => Some('–'),
+            "hellip" => Some('…'),
+            "copy" => Some('©'),
+            _ => {
+                let number = entity.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (entity, c) {
+            (Some(entity), Some(c)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
@@ -0,0 +1,232 @@
+//! A converter for the commonly used subset of reStructuredText.
+
+use super::{indented_block, push_fence};
+
+const ADMONITIONS: [&str; 10] = [
+    "note",
+    "tip",
+    "hint",
+    "important",
+    "attention",
+    "warning",
+    "caution",
+    "danger",
+    "error",
+    "seealso",
+];
+
+pub fn to_markdown(source: &str) -> String {
+    let lines: Vec<&str> = source.lines().map(str::trim_end).collect();
+    let mut out = Vec::new();
+    convert(&lines, &mut Vec::new(), &mut out);
+    out.join("\n")
+}
+
+/// Converts `lines` into `out`. `title_styles` collects section adornments
+/// in order of appearance, which is what decides their levels in rST.
+fn convert(lines: &[&str], title_styles: &mut Vec<(char, bool)>, out: &mut Vec<String>) {
+    // Whether the last non-blank line was a list item or part of one
+    let mut in_list = false;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let next = lines.get(i + 1).copied().unwrap_or("");
+
+        if line.is_empty() {
+            out.push(String::new());
+            i += 1;
+            continue;
+        }
+
+        // Title with an overline and an underline
+        if let Some(ch) = adornment(line)
+            && !next.trim().is_empty()
+            && lines.get(i + 2).copied() == Some(line)
+        {
+            let level = level_for(title_styles, (ch, true));
+            out.push(format!("{} {}", "#".repeat(level), inline(next.trim())));
+            i += 3;
+            in_list = false;
+            continue;
+        }
+
+        // Title with just an underline
+        if !line.starts_with(char::is_whitespace)
+            && let Some(ch) = adornment(next)
+        {
+            let level = level_for(title_styles, (ch, false));
+            out.push(format!("{} {}", "#".repeat(level), inline(line)));
+            i += 2;
+            in_list = false;
+            continue;
+        }
+
+        // Transition
+        if adornment(line).is_some() {
+            out.push("---".into());
+            i += 1;
+            continue;
+        }
+
+        // Directives, comments and hyperlink targets
+        if let Some(rest) = line.strip_prefix(".. ") {
+            let (mut body, end) = indented_block(lines, i + 1);
+            i = end;
+            in_list = false;
+            let Some((name, arg)) = rest.split_once("::") else {
+                // A comment or a `.. _target:` definition
+                continue;
+            };
+            let (name, arg) = (name.trim().to_lowercase(), arg.trim());
+            // Directive options come first
+            let options = body.iter().take_while(|line| line.starts_with(':')).count();
+            body.drain(..options);
+
+            match name.as_str() {
+                "code-block" | "code" | "sourcecode" => push_fence(out, arg, &body),
+                "image" | "figure" => out.push(format!("![]({arg})")),
+                name if ADMONITIONS.contains(&name) || name == "admonition" => {
+                    let label = if name == "admonition" {
+                        arg.to_string()
+                    } else {
+                        let mut label = name.to_string();
+                        label[..1].make_ascii_uppercase();
+                        label
+                    };
+                    let mut inner = Vec::new();
+                    if name != "admonition" && !arg.is_empty() {
+                        inner.push(arg.to_string());
+                    }
+                    convert(&body, title_styles, &mut inner);
+                    out.push(format!("> **{label}:**"));
+                    out.extend(inner.iter().map(|line| format!("> {line}")));
+                }
+                // Everything else (toctree, raw, ...) has no useful rendering here
+                _ => {}
+            }
+            continue;
+        }
+
+        // A paragraph ending in `::` introduces an indented literal block
+        if let Some(text) = line.strip_suffix("::") {
+            let text = text.trim_end();
+            if !text.is_empty() {
+                out.push(format!("{}:", inline(text)));
+            }
+            let (body, end) = indented_block(lines, i + 1);
+            out.push(String::new());
+            push_fence(out, "", &body);
+            i = end;
+            continue;
+        }
+
+        // Indented text outside of a list is a block quote
+        if line.starts_with(char::is_whitespace) && !in_list {
+            let (body, end) = indented_block(lines, i);
+            let mut inner = Vec::new();
+            convert(&body, title_styles, &mut inner);
+            out.extend(inner.iter().map(|line| format!("> {line}")));
+            i = end;
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+        if let Some(item) = trimmed.strip_prefix("#. ") {
+            out.push(format!("{indent}1. {}", inline(item)));
+            in_list = true;
+        } else {
+            if !line.starts_with(char::is_whitespace) {
+                in_list = ["* ", "- ", "+ "].iter().any(|m| trimmed.starts_with(m))
+                    || trimmed.split_once(". ").is_some_and(|(n, _)| {
+                        !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())
+                    });
+            }
+            out.push(format!("{indent}{}", inline(trimmed)));
+        }
+        i += 1;
+    }
+}
+
+/// Heading level for a title adorned with `style`, as `(char, overlined)`.
+fn level_for(title_styles: &mut Vec<(char, bool)>, style: (char, bool)) -> usize {
+    let idx = match title_styles.iter().position(|s| *s == style) {
+        Some(idx) => idx,
+        None => {
+            title_styles.push(style);
+            title_styles.len() - 1
+        }
+    };
+    (idx + 1).min(6)
+}
+
+/// The character of a section adornment or transition like `=====`.
+fn adornment(line: &str) -> Option<char> {
+    let first = line.chars().next()?;
+    let valid = line.len() >= 3 && first.is_ascii_punctuation() && line.chars().all(|c| c == first);
+    valid.then_some(first)
+}
+
+/// Converts inline markup: ``` ``literals`` ```, `` `links <url>`_ ``,
+/// roles and interpreted text. `**strong**` and `*emphasis*` are the same in
+/// Markdown and copied as-is.
+fn inline(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(literal) = rest.strip_prefix("``")
+            && let Some(end) = literal.find("``")
+        {
+            out.push('`');
+            out.push_str(&literal[..end]);
+            out.push('`');
+            rest = &literal[end + 2..];
+            continue;
+        }
+
+        // Roles like :code:`x` or :ref:`text <target>`
+        if c == ':'
+            && let Some((role, after)) = rest[1..].split_once(":`")
+            && !role.is_empty()
+            && role.chars().all(|c| c.is_alphanumeric() || c == '-')
+            && let Some(end) = after.find('`')
+        {
+            let text = &after[..end];
+            let text = text.split_once(" <").map_or(text, |(label, _)| label);
+            if matches!(role, "code" | "literal" | "file" | "command") {
+                out.push('`');
+                out.push_str(text);
+                out.push('`');
+            } else {
+                out.push_str(text);
+            }
+            rest = &after[end + 1..];
+            continue;
+        }
+
+        if c == '`'
+            && let Some(end) = rest[1..].find('`')
+        {
+            let text = &rest[1..end + 1];
+            let after = &rest[end + 2..];
+            let underscores = after.chars().take_while(|c| *c == '_').count().min(2);
+            match text.rsplit_once(" <").filter(|_| text.ends_with('>')) {
+                Some((label, url)) if underscores > 0 => {
+                    out.push_str(&format!("[{}]({})", label.trim(), &url[..url.len() - 1]));
+                }
+                // A reference to a target defined elsewhere
+                _ if underscores > 0 => out.push_str(text),
+                // Interpreted text defaults to emphasis
+                _ => out.push_str(&format!("*{text}*")),
+            }
+            rest = &after[underscores..];
+            continue;
+        }
+
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
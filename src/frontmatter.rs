@@ -0,0 +1,75 @@
+//! Just enough YAML frontmatter reading for the keys the viewer uses,
+//! without parsing whole documents.
+
+/// The frontmatter between `---` fences at the very start of `source`.
+pub fn extract(source: &str) -> Option<&str> {
+    let rest = source
+        .strip_prefix("---\n")
+        .or_else(|| source.strip_prefix("---\r\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            return Some(&rest[..offset]);
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// The value of a single-line `key: value` entry.
+pub fn value(frontmatter: &str, key: &str) -> Option<String> {
+    frontmatter.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == key).then(|| scalar(value)).flatten()
+    })
+}
+
+/// Obsidian-style `aliases:` (or `alias:`), written inline as `a`, `[a, b]`,
+/// or as a block list of `- a` lines.
+pub fn aliases(frontmatter: &str) -> Vec<String> {
+    list(frontmatter, &["aliases", "alias"])
+}
+
+/// `tags:` (or `tag:`), written like aliases or as `a b`, without the `#`
+/// they may be written with.
+pub fn tags(frontmatter: &str) -> Vec<String> {
+    list(frontmatter, &["tags", "tag"])
+        .iter()
+        .flat_map(|tags| tags.split([',', ' ']))
+        .map(|tag| tag.trim_start_matches('#'))
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The entries of the first of `keys` there is, inline or as a block list.
+fn list(frontmatter: &str, keys: &[&str]) -> Vec<String> {
+    let mut lines = frontmatter.lines();
+    while let Some(line) = lines.next() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        if !keys.contains(&key.trim()) {
+            continue;
+        }
+
+        let value = value.trim();
+        if value.is_empty() {
+            return lines
+                .map_while(|line| line.trim_start().strip_prefix("- "))
+                .filter_map(scalar)
+                .collect();
+        }
+        if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            return list.split(',').filter_map(scalar).collect();
+        }
+        return scalar(value).into_iter().collect();
+    }
+    Vec::new()
+}
+
+/// A YAML scalar without its quotes, if not empty.
+fn scalar(value: &str) -> Option<String> {
+    let value = value.trim().trim_matches(|c| c == '"' || c == '\'').trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
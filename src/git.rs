@@ -0,0 +1,156 @@
+//! Git awareness for the active document: the branch of the repository it
+//! is in and whether it has uncommitted changes, asked of the `git` command.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use eframe::egui;
+
+use crate::encoding;
+use crate::tab::DocTab;
+
+/// How often the status is asked for again, for commits made meanwhile.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileState {
+    Clean,
+    Modified,
+    /// Not added to the repository yet.
+    Untracked,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GitStatus {
+    /// The branch checked out, or the commit when none is.
+    pub branch: String,
+    pub file: FileState,
+}
+
+/// The git status of the active tab, refreshed in the background.
+#[derive(Default)]
+pub struct GitWatch {
+    /// The file and revision `status` is for, and when it was asked for.
+    checked: Option<(PathBuf, u64, Instant)>,
+    status: Option<GitStatus>,
+    loading: Option<Receiver<Option<GitStatus>>>,
+}
+
+impl GitWatch {
+    /// The status of `tab`'s file, `None` outside a repository or until known.
+    pub fn status(&mut self, ctx: &egui::Context, tab: &DocTab) -> Option<&GitStatus> {
+        if tab.is_scratch() || tab.is_loading() {
+            return None;
+        }
+        let current = self
+            .checked
+            .as_ref()
+            .is_some_and(|(path, revision, _)| *path == tab.path && *revision == tab.revision);
+        let stale = self
+            .checked
+            .as_ref()
+            .is_none_or(|(_, _, at)| at.elapsed() >= REFRESH_INTERVAL);
+        if !current || (stale && self.loading.is_none()) {
+            if !current {
+                self.status = None;
+            }
+            self.checked = Some((tab.path.clone(), tab.revision, Instant::now()));
+            let (sender, receiver) = mpsc::channel();
+            let path = tab.path.clone();
+            let ctx = ctx.clone();
+            thread::spawn(move || {
+                let _ = sender.send(status(&path).ok());
+                ctx.request_repaint();
+            });
+            self.loading = Some(receiver);
+        }
+        if let Some(loading) = &self.loading {
+            match loading.try_recv() {
+                Ok(status) => {
+                    self.status = status;
+                    self.loading = None;
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.loading = None,
+            }
+        }
+        ctx.request_repaint_after(REFRESH_INTERVAL);
+        self.status.as_ref()
+    }
+
+    /// The status as last found, without asking again.
+    pub fn last(&self) -> Option<&GitStatus> {
+        self.status.as_ref()
+    }
+}
+
+/// Runs `git` with `args` in the folder of `path`.
+fn git(path: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let dir = path.parent().context("the file has no folder")?;
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        // A repository's own config could otherwise have `status` run a
+        // program of its choosing
+        .args(["-c", "core.fsmonitor=false"])
+        .args(args)
+        .output()
+        .context("running git")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(output.stdout)
+}
+
+/// The branch of the repository holding `path`, and the state of the file.
+pub fn status(path: &Path) -> Result<GitStatus> {
+    let name = path.file_name().context("not a file")?;
+    let output = git(
+        path,
+        &[
+            "status",
+            "--porcelain=v2",
+            "--branch",
+            "--",
+            &name.to_string_lossy(),
+        ],
+    )?;
+    let output = String::from_utf8_lossy(&output);
+    let mut branch = None;
+    let mut commit = None;
+    let mut file = FileState::Clean;
+    for line in output.lines() {
+        if let Some(head) = line.strip_prefix("# branch.head ") {
+            branch = Some(head.to_string()).filter(|head| head != "(detached)");
+        } else if let Some(oid) = line.strip_prefix("# branch.oid ") {
+            commit = Some(oid.chars().take(7).collect::<String>()).filter(|_| oid != "(initial)");
+        } else if line.starts_with("? ") {
+            file = FileState::Untracked;
+        } else if !line.starts_with('#') && !line.starts_with("! ") {
+            file = FileState::Modified;
+        }
+    }
+    Ok(GitStatus {
+        branch: branch.or(commit).unwrap_or_else(|| "HEAD".into()),
+        file,
+    })
+}
+
+/// The text of `path` as last committed, empty if it never was.
+pub fn committed(path: &Path) -> Result<String> {
+    let name = path.file_name().context("not a file")?;
+    if status(path)?.file == FileState::Untracked {
+        return Ok(String::new());
+    }
+    let bytes = git(
+        path,
+        &["show", &format!("HEAD:./{}", name.to_string_lossy())],
+    )?;
+    Ok(encoding::decode(bytes).text)
+}
@@ -0,0 +1,320 @@
+//! Searching all open tabs, and the documents of the opened folder, at once.
+
+use std::{
+    borrow::Cow,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    ops::Range,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use eframe::egui;
+
+use crate::encoding;
+use crate::formats::Format;
+use crate::i18n::{tr, tr_args};
+use crate::search;
+use crate::tab::DocTab;
+use crate::workspace::Workspace;
+
+pub const SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+    egui::Key::F,
+);
+
+/// How often to check on the search.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Hits listed per document; the rest are only counted.
+const MAX_HITS: usize = 20;
+
+/// Characters of context shown on each side of a hit.
+const CONTEXT: usize = 40;
+
+/// A document searched: an open tab, or a file of the folder that isn't.
+#[derive(Clone, Debug, PartialEq, Hash)]
+pub enum Place {
+    Tab(u64),
+    File(PathBuf),
+}
+
+/// A hit the user picked: where it is, and its byte offset in the document.
+pub struct Picked {
+    pub place: Place,
+    pub offset: usize,
+}
+
+struct Hit {
+    offset: usize,
+    /// The line around the hit, with the hit itself.
+    line: String,
+    matched: Range<usize>,
+}
+
+/// The hits in one document.
+struct Hits {
+    place: Place,
+    name: String,
+    path: PathBuf,
+    hits: Vec<Hit>,
+    total: usize,
+}
+
+#[derive(Default)]
+pub struct GlobalSearch {
+    pub open: bool,
+    query: String,
+    focus_query: bool,
+    /// What the results were worked out from: the query, the workspace and
+    /// the open documents' revisions.
+    key: Option<u64>,
+    results: Vec<Hits>,
+    loading: Option<Receiver<Vec<Hits>>>,
+}
+
+impl GlobalSearch {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.focus_query = true;
+    }
+
+    /// Draws the search field and the hits grouped by document; returns the
+    /// hit the user clicked.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        tabs: &[DocTab],
+        workspace: Option<&Workspace>,
+    ) -> Option<Picked> {
+        ui.horizontal(|ui| {
+            ui.heading(tr("Search All"));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("×").on_hover_text(tr("Close (Esc)")).clicked() {
+                    self.open = false;
+                }
+            });
+        });
+        let hint = match workspace {
+            Some(_) => tr("Search open tabs and the folder"),
+            None => tr("Search open tabs"),
+        };
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.query)
+                .desired_width(f32::INFINITY)
+                .hint_text(hint),
+        );
+        if std::mem::take(&mut self.focus_query) {
+            response.request_focus();
+        }
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.open = false;
+        }
+
+        let key = key(&self.query, tabs, workspace);
+        if self.key != Some(key) {
+            self.key = Some(key);
+            self.results.clear();
+            self.loading = (!self.query.is_empty()).then(|| search(&self.query, tabs, workspace));
+        }
+        if let Some(loading) = &self.loading {
+            match loading.try_recv() {
+                Ok(results) => {
+                    self.results = results;
+                    self.loading = None;
+                }
+                Err(TryRecvError::Empty) => ui.ctx().request_repaint_after(POLL_INTERVAL),
+                Err(TryRecvError::Disconnected) => self.loading = None,
+            }
+        }
+
+        if self.loading.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.weak(tr("Searching…"));
+            });
+            return None;
+        }
+        if self.query.is_empty() {
+            return None;
+        }
+        let total: usize = self.results.iter().map(|hits| hits.total).sum();
+        match (total, self.results.len()) {
+            (0, _) => ui.weak(tr("No matches")),
+            (1, _) => ui.weak(tr("1 match")),
+            (n, 1) => ui.weak(tr_args("{n} matches in 1 document", &[("n", &n)])),
+            (n, docs) => ui.weak(tr_args(
+                "{n} matches in {docs} documents",
+                &[("n", &n), ("docs", &docs)],
+            )),
+        };
+        ui.separator();
+
+        let mut picked = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for hits in &self.results {
+                egui::CollapsingHeader::new(format!("{} ({})", hits.name, hits.total))
+                    .id_salt(("global_search", &hits.place))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        for hit in &hits.hits {
+                            let line = excerpt(ui, hit);
+                            if ui
+                                .add(egui::Button::new(line).frame(false).wrap())
+                                .on_hover_text(hits.path.display().to_string())
+                                .clicked()
+                            {
+                                picked = Some(Picked {
+                                    place: hits.place.clone(),
+                                    offset: hit.offset,
+                                });
+                            }
+                        }
+                        if hits.total > hits.hits.len() {
+                            ui.weak(tr_args(
+                                "and {more} more",
+                                &[("more", &(hits.total - hits.hits.len()))],
+                            ));
+                        }
+                    });
+            }
+        });
+        picked
+    }
+}
+
+/// The line of `hit` with the match in bold.
+fn excerpt(ui: &egui::Ui, hit: &Hit) -> egui::text::LayoutJob {
+    let font = egui::TextStyle::Body.resolve(ui.style());
+    let color = ui.visuals().text_color();
+    let strong = ui.visuals().strong_text_color();
+    let mut job = egui::text::LayoutJob::default();
+    let (before, rest) = hit.line.split_at(hit.matched.start);
+    let (matched, after) = rest.split_at(hit.matched.len());
+    for (text, is_match) in [(before, false), (matched, true), (after, false)] {
+        job.append(
+            text,
+            0.0,
+            egui::TextFormat {
+                font_id: font.clone(),
+                color: if is_match { strong } else { color },
+                background: if is_match {
+                    ui.visuals().selection.bg_fill.gamma_multiply(0.5)
+                } else {
+                    egui::Color32::TRANSPARENT
+                },
+                ..Default::default()
+            },
+        );
+    }
+    job
+}
+
+fn key(query: &str, tabs: &[DocTab], workspace: Option<&Workspace>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    query.hash(&mut hasher);
+    workspace
+        .map(|ws| (&ws.root, ws.files.len()))
+        .hash(&mut hasher);
+    for tab in tabs {
+        (tab.id, tab.revision).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Starts looking for `query` in the background: in the open documents as
+/// shown, and in the rest of the workspace as saved.
+fn search(query: &str, tabs: &[DocTab], workspace: Option<&Workspace>) -> Receiver<Vec<Hits>> {
+    let mut sources: Vec<(Place, String, PathBuf, Option<String>)> = tabs
+        .iter()
+        .filter(|tab| !tab.is_loading())
+        .map(|tab| {
+            (
+                Place::Tab(tab.id),
+                tab.title.clone(),
+                tab.path.clone(),
+                tab.markdown().ok().map(Cow::into_owned),
+            )
+        })
+        .collect();
+    if let Some(ws) = workspace {
+        for path in &ws.files {
+            if !tabs.iter().any(|tab| tab.path == *path) {
+                let name = ws.relative(path).display().to_string();
+                sources.push((Place::File(path.clone()), name, path.clone(), None));
+            }
+        }
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let query = query.to_string();
+    thread::spawn(move || {
+        let mut results = Vec::new();
+        for (place, name, path, content) in sources {
+            let markdown = match content {
+                Some(content) => content,
+                None => match encoding::read(&path) {
+                    Ok(decoded) => Format::from_path(&path)
+                        .unwrap_or_default()
+                        .to_markdown(decoded.text),
+                    Err(_) => continue,
+                },
+            };
+            let found = search::occurrences(&markdown, &query);
+            if found.is_empty() {
+                continue;
+            }
+            results.push(Hits {
+                place,
+                name,
+                path,
+                hits: found
+                    .iter()
+                    .take(MAX_HITS)
+                    .map(|range| hit(&markdown, range.clone()))
+                    .collect(),
+                total: found.len(),
+            });
+        }
+        let _ = sender.send(results);
+    });
+    receiver
+}
+
+/// The hit at `range` of `markdown`, with up to [`CONTEXT`] characters of
+/// its line on either side.
+fn hit(markdown: &str, range: Range<usize>) -> Hit {
+    let line_start = markdown[..range.start].rfind('\n').map_or(0, |at| at + 1);
+    let line_end = markdown[range.end..]
+        .find('\n')
+        .map_or(markdown.len(), |at| range.end + at);
+    let before = &markdown[line_start..range.start];
+    let before = match before.char_indices().rev().nth(CONTEXT) {
+        Some((at, _)) => &before[at..],
+        None => before,
+    };
+    let after = &markdown[range.end..line_end];
+    let after = match after.char_indices().nth(CONTEXT) {
+        Some((at, _)) => &after[..at],
+        None => after,
+    };
+
+    let mut line = before.trim_start().to_string();
+    if before.len() < range.start - line_start {
+        line.insert(0, '…');
+    }
+    let start = line.len();
+    line.push_str(&markdown[range.clone()]);
+    let end = line.len();
+    line.push_str(after.trim_end());
+    if after.len() < line_end - range.end {
+        line.push('…');
+    }
+    Hit {
+        offset: range.start,
+        line,
+        matched: start..end,
+    }
+}
@@ -0,0 +1,46 @@
+//! Reading hints a document gives in its frontmatter, over the settings for
+//! its tab only: `theme: dark`, `width: 70ch` or `font_scale: 1.2`.
+
+use crate::config::{self, Paper};
+use crate::frontmatter;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Hints {
+    /// Dark or light from `theme:`, whatever the app's theme.
+    pub dark: Option<bool>,
+    /// A reading surface from `theme:`, as `sepia`.
+    pub paper: Option<Paper>,
+    /// Width of the text column in characters.
+    pub width: Option<u32>,
+    /// Scale of the text on top of the user's text size.
+    pub font_scale: Option<f32>,
+}
+
+impl Hints {
+    /// Reads the hints out of a YAML frontmatter block, ignoring values
+    /// the viewer can't use.
+    pub fn from_frontmatter(frontmatter: &str) -> Self {
+        let theme = frontmatter::value(frontmatter, "theme").map(|theme| theme.to_lowercase());
+        let (dark, paper) = match theme.as_deref() {
+            Some("dark") => (Some(true), None),
+            Some("light") => (Some(false), None),
+            Some("sepia") => (None, Some(Paper::Sepia)),
+            Some("solarized") => (None, Some(Paper::Solarized)),
+            Some("high-contrast" | "high_contrast") => (None, Some(Paper::HighContrast)),
+            _ => (None, None),
+        };
+        let width = frontmatter::value(frontmatter, "width")
+            .and_then(|width| width.trim_end_matches("ch").trim().parse().ok())
+            .map(|width: u32| width.clamp(config::MIN_TEXT_WIDTH, config::MAX_TEXT_WIDTH));
+        let font_scale = frontmatter::value(frontmatter, "font_scale")
+            .and_then(|scale| scale.parse::<f32>().ok())
+            .filter(|scale| scale.is_finite() && *scale > 0.0)
+            .map(|scale| scale.clamp(config::MIN_TEXT_SCALE, config::MAX_TEXT_SCALE));
+        Self {
+            dark,
+            paper,
+            width,
+            font_scale,
+        }
+    }
+}
@@ -0,0 +1,894 @@
+//! The HTML found in GitHub READMEs, drawn instead of shown as source:
+//! `<img>` with `align`, `width` and `height`, `<br>`, `<sub>`, `<sup>`,
+//! `<kbd>`, `<details>` and the tags for emphasis, links and paragraphs.
+//!
+//! Only these are understood. Other tags are dropped and keep their text,
+//! except scripts, styles and embedded frames, which are dropped whole.
+//! Paragraphs mixing Markdown with inline HTML are laid out here too.
+
+use std::collections::HashSet;
+
+use eframe::egui;
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+use crate::blocks;
+use crate::keycaps;
+
+/// The `<details>` opened or closed from how they start, each by the index
+/// of its block and then its place among the elements it's nested in.
+pub type Toggled = HashSet<Vec<usize>>;
+
+/// Elements dropped along with everything inside them.
+const DROPPED: &[&str] = &[
+    "script", "style", "iframe", "object", "embed", "template", "textarea", "select", "noscript",
+];
+
+/// Elements that start a paragraph of their own.
+const BLOCKS: &[&str] = &[
+    "p",
+    "div",
+    "center",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "ul",
+    "ol",
+    "blockquote",
+    "figure",
+    "figcaption",
+    "picture",
+    "table",
+    "tr",
+    "dl",
+    "dt",
+    "dd",
+    "pre",
+    "section",
+    "header",
+    "footer",
+];
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct Style {
+    strong: bool,
+    emphasis: bool,
+    strikethrough: bool,
+    code: bool,
+    sub: bool,
+    sup: bool,
+}
+
+/// A length in an HTML attribute.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Length {
+    Points(f32),
+    /// Of the width available.
+    Percent(f32),
+}
+
+enum Inline {
+    Text {
+        text: String,
+        style: Style,
+        link: Option<String>,
+    },
+    Image {
+        uri: String,
+        alt: String,
+        width: Option<Length>,
+        height: Option<Length>,
+        link: Option<String>,
+    },
+    Key(String),
+    Break,
+}
+
+#[derive(Default)]
+struct Paragraph {
+    align: Option<egui::Align>,
+    heading: Option<u8>,
+    /// The bullet of a list item.
+    marker: bool,
+    inlines: Vec<Inline>,
+}
+
+enum Node {
+    Paragraph(Paragraph),
+    Rule,
+    Details(Details),
+}
+
+/// A `<details>` element: its summary, and what it shows when open.
+pub struct Details {
+    summary: Vec<Inline>,
+    /// Whether it is open to begin with.
+    pub open: bool,
+    body: Vec<Node>,
+}
+
+pub struct Fragment {
+    nodes: Vec<Node>,
+    /// A `<details>` left open at the end, whose body goes on in the
+    /// blocks up to its `</details>`.
+    pub unclosed: Option<Details>,
+}
+
+impl Fragment {
+    /// The contents of `markdown` if it is an HTML block, or a paragraph
+    /// with inline HTML in it.
+    pub fn parse(markdown: &str) -> Option<Self> {
+        if !markdown.contains('<') {
+            return None;
+        }
+
+        let mut builder = Builder::default();
+        let mut html = String::new();
+        let mut has_html = false;
+        let mut paragraphs = 0;
+        let mut link_text: Option<(String, String)> = None;
+        for event in Parser::new_ext(markdown, blocks::parser_options()) {
+            if let Some((text, _)) = &mut link_text {
+                match event {
+                    Event::Text(piece) | Event::Code(piece) => {
+                        text.push_str(&piece);
+                        continue;
+                    }
+                    Event::End(TagEnd::Image) => {
+                        let (alt, uri) = link_text.take().expect("checked above");
+                        builder.image(uri, alt, None, None);
+                        continue;
+                    }
+                    _ => continue,
+                }
+            }
+            match event {
+                Event::Start(Tag::HtmlBlock) => has_html = true,
+                Event::Html(text) => html.push_str(&text),
+                Event::End(TagEnd::HtmlBlock) => builder.html(&std::mem::take(&mut html)),
+                Event::InlineHtml(text) => {
+                    has_html = true;
+                    builder.html(&text);
+                }
+                Event::Start(Tag::Paragraph) => {
+                    paragraphs += 1;
+                    if paragraphs > 1 {
+                        return None;
+                    }
+                }
+                Event::End(TagEnd::Paragraph) => builder.finish_paragraph(),
+                Event::Text(text) => builder.text(&text),
+                Event::Code(text) => {
+                    let style = builder.style;
+                    builder.style.code = true;
+                    builder.text(&text);
+                    builder.style = style;
+                }
+                Event::SoftBreak => builder.text(" "),
+                Event::HardBreak => builder.paragraph.inlines.push(Inline::Break),
+                Event::FootnoteReference(name) => builder.text(&format!("[{name}]")),
+                Event::Start(Tag::Strong) => builder.style.strong = true,
+                Event::End(TagEnd::Strong) => builder.style.strong = false,
+                Event::Start(Tag::Emphasis) => builder.style.emphasis = true,
+                Event::End(TagEnd::Emphasis) => builder.style.emphasis = false,
+                Event::Start(Tag::Strikethrough) => builder.style.strikethrough = true,
+                Event::End(TagEnd::Strikethrough) => builder.style.strikethrough = false,
+                Event::Start(Tag::Link { dest_url, .. }) => {
+                    builder.link = Some(dest_url.to_string());
+                }
+                Event::End(TagEnd::Link) => builder.link = None,
+                Event::Start(Tag::Image { dest_url, .. }) => {
+                    link_text = Some((String::new(), blocks::image_uri(&dest_url)));
+                }
+                _ => return None,
+            }
+        }
+
+        has_html.then(|| builder.finish())
+    }
+
+    /// Draws the fragment, which is at `place`; `toggled` is as for
+    /// [`Details::show`].
+    pub fn show(
+        &self,
+        ui: &mut egui::Ui,
+        place: &[usize],
+        toggled: &mut Toggled,
+    ) -> egui::Response {
+        ui.vertical(|ui| show_nodes(ui, &self.nodes, place, toggled))
+            .response
+    }
+
+    /// Draws [`Self::unclosed`] as [`Details::show`] does, for the fragment
+    /// at `place`. Returns whether it is open, if there is one.
+    pub fn show_unclosed(
+        &self,
+        ui: &mut egui::Ui,
+        id_salt: impl std::hash::Hash,
+        place: &[usize],
+        toggled: &mut Toggled,
+    ) -> Option<bool> {
+        // After the elements that were closed
+        let place = [place, &[self.nodes.len()]].concat();
+        let details = self.unclosed.as_ref()?;
+        Some(details.show(ui, id_salt, place, toggled))
+    }
+}
+
+impl Details {
+    /// Draws it as a collapsing header with its body, opened or closed from
+    /// how it starts if its `place` is in `toggled`. Clicking the header
+    /// toggles that; returns whether it is open.
+    pub fn show(
+        &self,
+        ui: &mut egui::Ui,
+        id_salt: impl std::hash::Hash,
+        place: Vec<usize>,
+        toggled: &mut Toggled,
+    ) -> bool {
+        let summary = plain_text(&self.summary);
+        let open = self.open != toggled.contains(&place);
+        let response = egui::CollapsingHeader::new(&summary)
+            .id_salt(id_salt)
+            .open(Some(open))
+            .show(ui, |ui| show_nodes(ui, &self.body, &place, toggled));
+        if response.header_response.clicked() {
+            if !toggled.remove(&place) {
+                toggled.insert(place);
+            }
+            return !open;
+        }
+        open
+    }
+}
+
+/// A `<details>` waiting for its `</details>`.
+struct OpenDetails {
+    summary: Vec<Inline>,
+    open: bool,
+    nodes: Vec<Node>,
+}
+
+/// Puts the nodes of a fragment together from Markdown and HTML pieces.
+#[derive(Default)]
+struct Builder {
+    nodes: Vec<Node>,
+    /// Elements within elements, innermost last.
+    details: Vec<OpenDetails>,
+    in_summary: bool,
+    paragraph: Paragraph,
+    style: Style,
+    link: Option<String>,
+    /// Formatting elements open, with the style and link from before them.
+    inline: Vec<(String, Style, Option<String>)>,
+    /// Block elements open, with their alignment and heading level.
+    blocks: Vec<(String, Option<egui::Align>, Option<u8>)>,
+    /// The text of the `<kbd>` being read.
+    key: Option<String>,
+    /// The element whose contents are being dropped.
+    dropping: Option<String>,
+}
+
+impl Builder {
+    fn html(&mut self, html: &str) {
+        for token in tokens(html) {
+            match token {
+                Token::Text(text) => {
+                    if self.dropping.is_none() {
+                        self.text(&collapse_whitespace(&text));
+                    }
+                }
+                Token::Open { name, attrs } => self.open(&name, &attrs),
+                Token::Close(name) => self.close(&name),
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if let Some(key) = &mut self.key {
+            key.push_str(text);
+            return;
+        }
+        // Whitespace between tags at the start of a paragraph isn't content
+        if self.paragraph.inlines.is_empty() && text.trim().is_empty() {
+            return;
+        }
+        match self.paragraph.inlines.last_mut() {
+            Some(Inline::Text {
+                text: last,
+                style,
+                link,
+            }) if *style == self.style && *link == self.link => last.push_str(text),
+            _ => self.paragraph.inlines.push(Inline::Text {
+                text: text.to_string(),
+                style: self.style,
+                link: self.link.clone(),
+            }),
+        }
+    }
+
+    fn image(&mut self, uri: String, alt: String, width: Option<Length>, height: Option<Length>) {
+        self.paragraph.inlines.push(Inline::Image {
+            uri,
+            alt,
+            width,
+            height,
+            link: self.link.clone(),
+        });
+    }
+
+    fn open(&mut self, name: &str, attrs: &[(String, String)]) {
+        if self.dropping.is_some() {
+            return;
+        }
+        let attr = |wanted: &str| {
+            attrs
+                .iter()
+                .find(|(name, _)| name == wanted)
+                .map(|(_, value)| value.as_str())
+        };
+        let mut style = self.style;
+        match name {
+            _ if DROPPED.contains(&name) => {
+                self.dropping = Some(name.to_string());
+                return;
+            }
+            "br" => {
+                self.paragraph.inlines.push(Inline::Break);
+                return;
+            }
+            "img" => {
+                if let Some(src) = attr("src").filter(|src| is_safe_url(src)) {
+                    self.image(
+                        blocks::image_uri(src),
+                        attr("alt").unwrap_or_default().to_string(),
+                        attr("width").and_then(length),
+                        attr("height").and_then(length),
+                    );
+                }
+                return;
+            }
+            "hr" => {
+                self.finish_paragraph();
+                self.push_node(Node::Rule);
+                return;
+            }
+            "kbd" => {
+                self.key = Some(String::new());
+                return;
+            }
+            "details" => {
+                self.finish_paragraph();
+                self.details.push(OpenDetails {
+                    summary: Vec::new(),
+                    open: attr("open").is_some(),
+                    nodes: Vec::new(),
+                });
+                return;
+            }
+            "summary" if !self.details.is_empty() => {
+                self.finish_paragraph();
+                self.in_summary = true;
+                return;
+            }
+            _ if BLOCKS.contains(&name) => {
+                self.finish_paragraph();
+                let align = match attr("align").map(str::to_ascii_lowercase).as_deref() {
+                    Some("center") => Some(egui::Align::Center),
+                    Some("right") => Some(egui::Align::Max),
+                    Some("left") => Some(egui::Align::Min),
+                    _ if name == "center" => Some(egui::Align::Center),
+                    _ => self.blocks.last().and_then(|(_, align, _)| *align),
+                };
+                let heading = name
+                    .strip_prefix('h')
+                    .and_then(|level| level.parse().ok())
+                    .or_else(|| self.blocks.last().and_then(|(_, _, heading)| *heading));
+                self.blocks.push((name.to_string(), align, heading));
+                self.finish_paragraph();
+                self.paragraph.marker = name == "li";
+                return;
+            }
+            "b" | "strong" => style.strong = true,
+            "i" | "em" | "cite" => style.emphasis = true,
+            "s" | "del" | "strike" => style.strikethrough = true,
+            "code" | "tt" | "samp" => style.code = true,
+            "sub" => style.sub = true,
+            "sup" => style.sup = true,
+            "a" => {}
+            _ => return,
+        }
+        self.inline
+            .push((name.to_string(), self.style, self.link.clone()));
+        self.style = style;
+        if name == "a" {
+            self.link = attr("href")
+                .filter(|href| is_safe_url(href))
+                .map(String::from);
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        if let Some(dropping) = &self.dropping {
+            if dropping == name {
+                self.dropping = None;
+            }
+            return;
+        }
+        match name {
+            "kbd" => {
+                if let Some(key) = self.key.take().filter(|key| !key.trim().is_empty()) {
+                    self.paragraph
+                        .inlines
+                        .push(Inline::Key(key.trim().to_string()));
+                }
+            }
+            "summary" if self.in_summary => {
+                let summary = std::mem::take(&mut self.paragraph.inlines);
+                if let Some(details) = self.details.last_mut() {
+                    details.summary = summary;
+                }
+                self.in_summary = false;
+                self.finish_paragraph();
+            }
+            "details" => {
+                // A `</details>` closing one opened in an earlier block
+                if self.details.is_empty() {
+                    return;
+                }
+                self.finish_paragraph();
+                let details = self.details.pop().expect("checked above");
+                self.push_node(Node::Details(Details {
+                    summary: with_default_summary(details.summary),
+                    open: details.open,
+                    body: details.nodes,
+                }));
+            }
+            _ if BLOCKS.contains(&name) => {
+                self.finish_paragraph();
+                if let Some(at) = self.blocks.iter().rposition(|(open, _, _)| open == name) {
+                    self.blocks.truncate(at);
+                }
+                self.finish_paragraph();
+            }
+            "td" | "th" => self.text("  "),
+            _ => {
+                if let Some(at) = self.inline.iter().rposition(|(open, _, _)| open == name) {
+                    let (_, style, link) = self.inline[at].clone();
+                    self.inline.truncate(at);
+                    self.style = style;
+                    self.link = link;
+                }
+            }
+        }
+    }
+
+    fn push_node(&mut self, node: Node) {
+        match self.details.last_mut() {
+            Some(details) => details.nodes.push(node),
+            None => self.nodes.push(node),
+        }
+    }
+
+    /// Ends the paragraph being read and starts the next one.
+    fn finish_paragraph(&mut self) {
+        let paragraph = std::mem::replace(
+            &mut self.paragraph,
+            Paragraph {
+                align: self.blocks.last().and_then(|(_, align, _)| *align),
+                heading: self.blocks.last().and_then(|(_, _, heading)| *heading),
+                ..Default::default()
+            },
+        );
+        if self.in_summary {
+            self.paragraph.inlines = paragraph.inlines;
+            return;
+        }
+        if !paragraph.inlines.is_empty() {
+            self.push_node(Node::Paragraph(paragraph));
+        }
+    }
+
+    fn finish(mut self) -> Fragment {
+        self.in_summary = false;
+        self.finish_paragraph();
+        // Only the outermost open element can go on past this fragment
+        while self.details.len() > 1 {
+            let details = self.details.pop().expect("checked above");
+            self.push_node(Node::Details(Details {
+                summary: with_default_summary(details.summary),
+                open: details.open,
+                body: details.nodes,
+            }));
+        }
+        let unclosed = self.details.pop().map(|details| Details {
+            summary: with_default_summary(details.summary),
+            open: details.open,
+            body: details.nodes,
+        });
+        Fragment {
+            nodes: self.nodes,
+            unclosed,
+        }
+    }
+}
+
+/// `summary`, or "Details" as browsers show for a `<details>` without one.
+fn with_default_summary(summary: Vec<Inline>) -> Vec<Inline> {
+    if summary.is_empty() {
+        vec![Inline::Text {
+            text: "Details".into(),
+            style: Style::default(),
+            link: None,
+        }]
+    } else {
+        summary
+    }
+}
+
+enum Token {
+    Text(String),
+    Open {
+        name: String,
+        attrs: Vec<(String, String)>,
+    },
+    Close(String),
+}
+
+/// The tags and text of `html`, with entities decoded and comments and
+/// declarations dropped. Names are lowercase.
+fn tokens(html: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while let Some(at) = rest.find('<') {
+        if at > 0 {
+            tokens.push(Token::Text(decode(&rest[..at])));
+        }
+        rest = &rest[at..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some(close) = rest.strip_prefix("</") {
+            let end = close.find('>').unwrap_or(close.len());
+            tokens.push(Token::Close(close[..end].trim().to_ascii_lowercase()));
+            rest = close.get(end + 1..).unwrap_or("");
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let end = tag_end(rest);
+            let inner = rest[1..end].trim_end_matches('/');
+            let name_end = inner
+                .find(|c: char| c.is_whitespace() || c == '/')
+                .unwrap_or(inner.len());
+            tokens.push(Token::Open {
+                name: inner[..name_end].to_ascii_lowercase(),
+                attrs: attributes(&inner[name_end..]),
+            });
+            rest = rest.get(end + 1..).unwrap_or("");
+        } else {
+            tokens.push(Token::Text("<".into()));
+            rest = &rest[1..];
+        }
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(decode(rest)));
+    }
+    tokens
+}
+
+/// Where the tag at the start of `html` ends, past `>` in quoted values.
+fn tag_end(html: &str) -> usize {
+    let mut quote = None;
+    for (at, c) in html.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return at,
+            _ => {}
+        }
+    }
+    html.len()
+}
+
+/// The `name="value"` pairs of a tag, names lowercase.
+fn attributes(mut text: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    loop {
+        text = text.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if text.is_empty() {
+            return attrs;
+        }
+        let name_end = text
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(text.len());
+        let name = text[..name_end].to_ascii_lowercase();
+        text = text[name_end..].trim_start();
+        let value = match text.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                let (value, after) = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = value[1..].find(quote).map_or(value.len(), |end| end + 1);
+                        (&value[1..end], value.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                        (&value[..end], &value[end..])
+                    }
+                };
+                text = after;
+                decode(value)
+            }
+            None => String::new(),
+        };
+        attrs.push((name, value));
+    }
+}
+
+/// `text` with its character references replaced.
+fn decode(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        decoded.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let end = rest.find(';').filter(|&end| end <= 10);
+        let entity = end.and_then(|end| {
+            let name = &rest[1..end];
+            let c = match name {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                "copy" => '©',
+                "reg" => '®',
+                "trade" => '™',
+                "mdash" => '—',
+                "ndash" => '–',
+                "hellip" => '…',
+                "middot" => '·',
+                "bull" => '•',
+                _ => {
+                    let number = name.strip_prefix('#')?;
+                    let code = match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Runs of whitespace as single spaces, as browsers show them.
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        // No-break spaces are kept as they are
+        if c.is_whitespace() && c != '\u{a0}' {
+            if !in_space {
+                collapsed.push(' ');
+            }
+            in_space = true;
+        } else {
+            collapsed.push(c);
+            in_space = false;
+        }
+    }
+    collapsed
+}
+
+/// A `width` or `height` like `200`, `200px` or `50%`.
+fn length(value: &str) -> Option<Length> {
+    let value = value.trim();
+    match value.strip_suffix('%') {
+        Some(percent) => percent
+            .trim()
+            .parse()
+            .ok()
+            .map(|p: f32| Length::Percent(p / 100.0)),
+        None => value
+            .trim_end_matches("px")
+            .trim()
+            .parse()
+            .ok()
+            .map(Length::Points),
+    }
+}
+
+/// Whether following or loading `url` can't run script.
+fn is_safe_url(url: &str) -> bool {
+    let scheme = url.trim_start().to_ascii_lowercase();
+    !scheme.starts_with("javascript:") && !scheme.starts_with("vbscript:")
+}
+
+fn plain_text(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text { text: piece, .. } | Inline::Key(piece) => text.push_str(piece),
+            Inline::Image { alt, .. } => text.push_str(alt),
+            Inline::Break => text.push(' '),
+        }
+    }
+    text.trim().to_string()
+}
+
+fn show_nodes(ui: &mut egui::Ui, nodes: &[Node], place: &[usize], toggled: &mut Toggled) {
+    for (n, node) in nodes.iter().enumerate() {
+        match node {
+            Node::Paragraph(paragraph) => show_paragraph(ui, paragraph, ui.id().with(n)),
+            Node::Rule => {
+                ui.separator();
+            }
+            Node::Details(details) => {
+                details.show(ui, ("details", n), [place, &[n]].concat(), toggled);
+            }
+        }
+    }
+}
+
+fn show_paragraph(ui: &mut egui::Ui, paragraph: &Paragraph, id: egui::Id) {
+    let heading = |ui: &mut egui::Ui| {
+        if let Some(level) = paragraph.heading {
+            let style = ui.style_mut();
+            let size = match level {
+                1 => 1.6,
+                2 => 1.35,
+                3 => 1.15,
+                _ => 1.0,
+            };
+            let body = egui::TextStyle::Body.resolve(style);
+            style.override_font_id = Some(egui::FontId::new(body.size * size, body.family));
+        }
+    };
+    let Some(align @ (egui::Align::Center | egui::Align::Max)) = paragraph.align else {
+        ui.horizontal_wrapped(|ui| {
+            heading(ui);
+            if paragraph.marker {
+                ui.label("• ");
+            }
+            show_inlines(ui, &paragraph.inlines);
+        });
+        return;
+    };
+
+    // egui can't center a row it wraps, so each line is moved over by the
+    // room it left the last time it was drawn
+    for (n, line) in paragraph
+        .inlines
+        .split(|inline| matches!(inline, Inline::Break))
+        .enumerate()
+    {
+        let width_id = id.with(n);
+        let available = ui.available_width();
+        let indent = match ui.data(|d| d.get_temp::<f32>(width_id)) {
+            Some(width) => (available - width).max(0.0),
+            None => {
+                ui.ctx().request_discard("measuring centered HTML");
+                0.0
+            }
+        };
+        let indent = if align == egui::Align::Center {
+            indent / 2.0
+        } else {
+            indent
+        };
+        let response = ui.horizontal_wrapped(|ui| {
+            heading(ui);
+            ui.add_space(indent);
+            let start = ui.cursor().left();
+            show_inlines(ui, line);
+            ui.min_rect().right() - start
+        });
+        ui.data_mut(|d| d.insert_temp(width_id, response.inner));
+    }
+}
+
+fn show_inlines(ui: &mut egui::Ui, inlines: &[Inline]) {
+    ui.spacing_mut().item_spacing.x = 0.0;
+    for inline in inlines {
+        match inline {
+            Inline::Text { text, style, link } => {
+                let text = rich_text(ui, text, *style);
+                match link {
+                    Some(url) => {
+                        ui.hyperlink_to(text, url);
+                    }
+                    None => {
+                        ui.label(text);
+                    }
+                }
+            }
+            Inline::Image {
+                uri,
+                alt,
+                width,
+                height,
+                link,
+            } => {
+                let available = ui.available_width().max(1.0);
+                let size = |length: &Option<Length>| match length {
+                    Some(Length::Points(points)) => *points,
+                    Some(Length::Percent(part)) => available * part,
+                    None => f32::INFINITY,
+                };
+                let mut image = egui::Image::new(uri.as_str()).alt_text(alt.as_str());
+                image = if width.is_some() || height.is_some() {
+                    image.fit_to_exact_size(egui::vec2(size(width).min(available), size(height)))
+                } else {
+                    image.fit_to_original_size(1.0).max_width(available)
+                };
+                match link {
+                    Some(url) => {
+                        let response = ui
+                            .add(image.sense(egui::Sense::click()))
+                            .on_hover_cursor(egui::CursorIcon::PointingHand)
+                            .on_hover_text(url);
+                        if response.clicked() {
+                            ui.ctx().open_url(egui::OpenUrl::same_tab(url));
+                        }
+                    }
+                    None => {
+                        ui.add(image);
+                    }
+                }
+            }
+            Inline::Key(name) => {
+                ui.add_space(2.0);
+                keycaps::keycap(ui, name);
+                ui.add_space(2.0);
+            }
+            Inline::Break => ui.end_row(),
+        }
+    }
+}
+
+fn rich_text(ui: &egui::Ui, text: &str, style: Style) -> egui::WidgetText {
+    let mut rich = egui::RichText::new(text);
+    if style.strong {
+        rich = rich.strong();
+    }
+    if style.emphasis {
+        rich = rich.italics();
+    }
+    if style.strikethrough {
+        rich = rich.strikethrough();
+    }
+    if style.code {
+        rich = rich.code();
+    }
+    if style.sup {
+        rich = rich.small_raised();
+    } else if style.sub {
+        // Small text sits low, on the line's baseline
+        let mut job = egui::text::LayoutJob::default();
+        rich.small().append_to(
+            &mut job,
+            ui.style(),
+            egui::FontSelection::Default,
+            egui::Align::BOTTOM,
+        );
+        return job.into();
+    }
+    rich.into()
+}
@@ -0,0 +1,192 @@
+//! Translations of the interface. Text is looked up by its English wording
+//! in the catalog of the language chosen in Preferences, gettext style, and
+//! stays English where the catalog has no entry for it.
+//!
+//! Catalogs are `.po` files in `locales/`, built into the program.
+
+use std::{cell::Cell, collections::HashMap, fmt, sync::OnceLock};
+
+use crate::config::Language;
+
+thread_local! {
+    /// The language the interface is being drawn in.
+    static ACTIVE: Cell<Language> = const { Cell::new(Language::English) };
+}
+
+type Catalog = HashMap<String, String>;
+
+/// Uses `language` for text looked up from now on, the system's for
+/// [`Language::System`].
+pub fn set_language(language: Language) {
+    let language = match language {
+        Language::System => system_language(),
+        language => language,
+    };
+    ACTIVE.with(|active| active.set(language));
+}
+
+/// `english` in the interface language.
+pub fn tr(english: &'static str) -> &'static str {
+    catalog(ACTIVE.with(Cell::get))
+        .and_then(|catalog| catalog.get(english))
+        .map_or(english, String::as_str)
+}
+
+/// [`tr`] for text with `{name}` placeholders, filled in from `args`
+/// after translating, since languages put them in different places.
+pub fn tr_args(english: &'static str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut text = tr(english).to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    text
+}
+
+fn catalog(language: Language) -> Option<&'static Catalog> {
+    static GERMAN: OnceLock<Catalog> = OnceLock::new();
+    match language {
+        Language::German => Some(GERMAN.get_or_init(|| parse_po(include_str!("../locales/de.po")))),
+        Language::System | Language::English => None,
+    }
+}
+
+/// The language of the system's locale settings, if there is a catalog
+/// for it.
+fn system_language() -> Language {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    match locale.get(..2) {
+        Some("de") => Language::German,
+        _ => Language::English,
+    }
+}
+
+/// The `msgid`/`msgstr` pairs of a `.po` file, leaving out untranslated
+/// ones. Strings split over several quoted lines are joined.
+fn parse_po(po: &str) -> Catalog {
+    let mut catalog = Catalog::new();
+    let mut id = String::new();
+    let mut translation = String::new();
+    // Which of the two the quoted lines belong to
+    let mut in_translation = false;
+    let mut finish = |id: &mut String, translation: &mut String| {
+        if !id.is_empty() && !translation.is_empty() {
+            catalog.insert(std::mem::take(id), std::mem::take(translation));
+        }
+        id.clear();
+        translation.clear();
+    };
+    for line in po.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("msgid ") {
+            finish(&mut id, &mut translation);
+            id = unquote(rest);
+            in_translation = false;
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            translation = unquote(rest);
+            in_translation = true;
+        } else if line.starts_with('"') {
+            match in_translation {
+                true => translation.push_str(&unquote(line)),
+                false => id.push_str(&unquote(line)),
+            }
+        }
+    }
+    finish(&mut id, &mut translation);
+    catalog
+}
+
+/// The text of a quoted `.po` string, with its escapes undone.
+fn unquote(quoted: &str) -> String {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|q| q.strip_suffix('"'))
+        .unwrap_or(quoted);
+    let mut text = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some(other) => text.push(other),
+            None => {}
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogs_pair_each_text_with_its_translation() {
+        let catalog = parse_po(
+            r#"# German translations
+msgid ""
+msgstr ""
+"Content-Type: text/plain; charset=UTF-8\n"
+
+#: src/window.rs
+msgid "Open"
+msgstr "Öffnen"
+
+msgid "Didn't open {url}; "
+"links are turned off"
+msgstr "{url} wurde nicht geöffnet; "
+  "Links sind ausgeschaltet"
+
+msgid "Not translated yet"
+msgstr ""
+
+msgid "Say \"hi\"\tnow\\"
+msgstr "Sagen Sie \"hallo\"\n"
+"#,
+        );
+        assert_eq!(catalog.len(), 3);
+        assert_eq!(catalog["Open"], "Öffnen");
+        assert_eq!(
+            catalog["Didn't open {url}; links are turned off"],
+            "{url} wurde nicht geöffnet; Links sind ausgeschaltet"
+        );
+        assert_eq!(catalog["Say \"hi\"\tnow\\"], "Sagen Sie \"hallo\"\n");
+        assert!(!catalog.contains_key(""));
+    }
+
+    #[test]
+    fn the_german_catalog_keeps_every_placeholder() {
+        let catalog = catalog(Language::German).unwrap();
+        assert!(catalog.len() > 100);
+        let placeholders = |text: &str| {
+            let mut names: Vec<String> = text
+                .split('{')
+                .skip(1)
+                .filter_map(|rest| Some(rest.split_once('}')?.0.to_string()))
+                .collect();
+            names.sort();
+            names
+        };
+        for (english, german) in catalog {
+            assert_eq!(placeholders(english), placeholders(german), "{english}");
+        }
+    }
+
+    #[test]
+    fn text_is_looked_up_in_the_language_set() {
+        set_language(Language::German);
+        assert_eq!(tr("Cancel"), "Abbrechen");
+        assert_eq!(tr("Not in any catalog"), "Not in any catalog");
+        set_language(Language::English);
+        assert_eq!(tr("Cancel"), "Cancel");
+        assert_eq!(
+            tr_args("{megabytes} MB", &[("megabytes", &"1.5")]),
+            "1.5 MB"
+        );
+    }
+}
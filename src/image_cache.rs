@@ -0,0 +1,206 @@
+//! A limit on the memory images take, across every tab and window. A
+//! texture loader in front of the others notes when each image was last
+//! asked for, and once the loaders keep more than the limit, the images
+//! asked for longest ago are let go of, to be loaded again if they're shown.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+};
+
+use eframe::egui::{
+    self, TextureOptions,
+    load::{BytesLoader as _, LoadError, SizeHint, TextureLoadResult, TextureLoader},
+};
+
+use crate::i18n::{tr, tr_args};
+
+/// What the loaders keep, for Help → Cache Statistics.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stats {
+    /// Images asked for and not let go of.
+    pub images: usize,
+    /// Bytes of textures on the GPU.
+    pub textures: usize,
+    /// Bytes of decoded images waiting to be uploaded or rasterized again.
+    pub decoded: usize,
+    /// Bytes of files read or fetched.
+    pub files: usize,
+    /// Images let go of so far.
+    pub evicted: usize,
+}
+
+impl Stats {
+    pub fn total(&self) -> usize {
+        self.textures + self.decoded + self.files
+    }
+}
+
+/// The statistics as of the last frame.
+pub fn stats(ctx: &egui::Context) -> Stats {
+    ctx.data(|d| d.get_temp(stats_id()).unwrap_or_default())
+}
+
+fn stats_id() -> egui::Id {
+    egui::Id::new("image_cache_stats")
+}
+
+#[derive(Default)]
+pub struct ImageCache {
+    pass: AtomicU64,
+    /// Each image asked for, with the pass it last was.
+    used: Mutex<HashMap<String, u64>>,
+    evicted: AtomicUsize,
+}
+
+impl ImageCache {
+    /// Installs the loader; it must come after the other texture loaders to
+    /// be asked first.
+    pub fn install(ctx: &egui::Context) -> Arc<Self> {
+        let cache = Arc::new(Self::default());
+        ctx.add_texture_loader(cache.clone());
+        cache
+    }
+
+    /// Lets go of the images asked for longest ago until the loaders keep
+    /// no more than `limit` bytes. Those shown in the last frame stay, even
+    /// over it.
+    pub fn enforce(&self, ctx: &egui::Context, limit: usize) {
+        let mut stats = self.measure(ctx);
+        if stats.total() > limit {
+            let pass = self.pass.load(Ordering::Relaxed);
+            let mut idle: Vec<(String, u64)> = self
+                .used
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .filter(|(_, used)| **used + 1 < pass)
+                .map(|(uri, used)| (uri.clone(), *used))
+                .collect();
+            idle.sort_by_key(|(_, used)| *used);
+            for (uri, _) in idle {
+                ctx.forget_image(&uri);
+                self.evicted.fetch_add(1, Ordering::Relaxed);
+                stats = self.measure(ctx);
+                if stats.total() <= limit {
+                    break;
+                }
+            }
+        }
+        ctx.data_mut(|d| d.insert_temp(stats_id(), stats));
+    }
+
+    fn measure(&self, ctx: &egui::Context) -> Stats {
+        let loaders = ctx.loaders();
+        let files = loaders.include.byte_size()
+            + loaders
+                .bytes
+                .lock()
+                .iter()
+                .map(|loader| loader.byte_size())
+                .sum::<usize>();
+        let decoded = loaders
+            .image
+            .lock()
+            .iter()
+            .map(|loader| loader.byte_size())
+            .sum();
+        let textures = loaders
+            .texture
+            .lock()
+            .iter()
+            .map(|loader| loader.byte_size())
+            .sum();
+        Stats {
+            images: self
+                .used
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .len(),
+            textures,
+            decoded,
+            files,
+            evicted: self.evicted.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl TextureLoader for ImageCache {
+    fn id(&self) -> &str {
+        egui::generate_loader_id!(ImageCache)
+    }
+
+    fn load(
+        &self,
+        _: &egui::Context,
+        uri: &str,
+        _: TextureOptions,
+        _: SizeHint,
+    ) -> TextureLoadResult {
+        // The frames of a GIF are forgotten along with it
+        let uri = match uri.rsplit_once('#') {
+            Some((image, frame)) if frame.parse::<usize>().is_ok() => image,
+            _ => uri,
+        };
+        let pass = self.pass.load(Ordering::Relaxed);
+        let mut used = self.used.lock().unwrap_or_else(PoisonError::into_inner);
+        match used.get_mut(uri) {
+            Some(last) => *last = pass,
+            None => {
+                used.insert(uri.to_owned(), pass);
+            }
+        }
+        // For the loaders behind this one
+        Err(LoadError::NotSupported)
+    }
+
+    fn forget(&self, uri: &str) {
+        let mut used = self.used.lock().unwrap_or_else(PoisonError::into_inner);
+        used.remove(uri);
+    }
+
+    fn forget_all(&self) {
+        let mut used = self.used.lock().unwrap_or_else(PoisonError::into_inner);
+        used.clear();
+    }
+
+    fn end_pass(&self, pass: u64) {
+        self.pass.store(pass, Ordering::Relaxed);
+    }
+
+    fn byte_size(&self) -> usize {
+        0
+    }
+}
+
+/// The Help → Cache Statistics readout, for images kept within `limit` bytes.
+pub fn show_stats(ui: &mut egui::Ui, limit: usize) {
+    let stats = stats(ui.ctx());
+    egui::Grid::new("image_cache_stats")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            let rows = [
+                (tr("Images"), stats.images.to_string()),
+                (tr("Textures"), megabytes(stats.textures)),
+                (tr("Decoded images"), megabytes(stats.decoded)),
+                (tr("Image files"), megabytes(stats.files)),
+                (tr("In all"), megabytes(stats.total())),
+                (tr("Limit"), megabytes(limit)),
+                (tr("Let go of so far"), stats.evicted.to_string()),
+            ];
+            for (name, value) in rows {
+                ui.label(name);
+                ui.label(value);
+                ui.end_row();
+            }
+        });
+}
+
+fn megabytes(bytes: usize) -> String {
+    let megabytes = format!("{:.1}", bytes as f64 / (1024.0 * 1024.0));
+    tr_args("{megabytes} MB", &[("megabytes", &megabytes)])
+}
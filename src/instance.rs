@@ -0,0 +1,204 @@
+//! Single-instance mode: a second launch hands its files to the running
+//! instance over a local socket and exits.
+//!
+//! The protocol is one absolute path per line, with the `#heading` to go to
+//! if one was asked for; the connection closing ends the hand-off. Where
+//! there are no socket files, a handshake over a local port comes first.
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use eframe::egui;
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener as Listener, UnixStream as Stream};
+
+#[cfg(not(unix))]
+use std::net::{TcpListener as Listener, TcpStream as Stream};
+
+/// Where the running instance listens: in the session's runtime folder,
+/// or else a folder of the user's own in the shared temporary one, so no
+/// one else can listen in its place.
+#[cfg(unix)]
+fn address() -> io::Result<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    if let Some(dir) = dirs::runtime_dir() {
+        return Ok(dir.join("md_viewer.sock"));
+    }
+    // SAFETY: getuid has no preconditions and can't fail
+    let uid = unsafe { libc::getuid() };
+    let dir = std::env::temp_dir().join(format!("md_viewer-{uid}"));
+    match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    // Made by someone else first, or opened up since
+    let meta = std::fs::symlink_metadata(&dir)?;
+    if !meta.is_dir() || meta.uid() != uid || meta.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} isn't private", dir.display()),
+        ));
+    }
+    Ok(dir.join("md_viewer.sock"))
+}
+
+/// Where the running instance notes the port it listens on, which is
+/// picked at random, and the secrets of its handshake. The file is in the
+/// user's own profile.
+#[cfg(not(unix))]
+fn address() -> io::Result<PathBuf> {
+    let dir = dirs::data_local_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no local data folder"))?;
+    Ok(dir.join("md_viewer").join("instance"))
+}
+
+/// What each side of a hand-off sends first, to show the other it read
+/// the running instance's file: the later launch knocks, and the running
+/// instance answers.
+#[cfg(not(unix))]
+struct Secrets {
+    knock: String,
+    answer: String,
+}
+
+/// Nothing to show on Unix, where only the user can reach the socket.
+#[cfg(unix)]
+type Secrets = ();
+
+/// Sends `paths` to an already running instance. Returns `false` if there
+/// is none, in which case this process should carry on starting up.
+pub fn hand_off(paths: &[PathBuf]) -> bool {
+    let Some(mut stream) = connect() else {
+        return false;
+    };
+    let sent = paths.iter().try_for_each(|path| {
+        let path = std::path::absolute(path)?;
+        writeln!(stream, "{}", path.display())
+    });
+    sent.is_ok()
+}
+
+#[cfg(unix)]
+fn connect() -> Option<Stream> {
+    Stream::connect(address().ok()?).ok()
+}
+
+/// Connects to the port the running instance noted, if what answers there
+/// knows its secrets; anything else on the port is left alone.
+#[cfg(not(unix))]
+fn connect() -> Option<Stream> {
+    use std::time::Duration;
+
+    let noted = std::fs::read_to_string(address().ok()?).ok()?;
+    let mut lines = noted.lines();
+    let port: u16 = lines.next()?.parse().ok()?;
+    let secrets = Secrets {
+        knock: lines.next()?.to_owned(),
+        answer: lines.next()?.to_owned(),
+    };
+    let mut stream = Stream::connect(("127.0.0.1", port)).ok()?;
+    stream.set_read_timeout(Some(Duration::from_secs(2))).ok()?;
+    writeln!(stream, "{}", secrets.knock).ok()?;
+    let mut answer = String::new();
+    BufReader::new(&stream).read_line(&mut answer).ok()?;
+    (answer.trim_end() == secrets.answer).then_some(stream)
+}
+
+/// Starts accepting hand-offs from later launches. Paths arrive on the
+/// returned channel, with a repaint requested for each.
+pub fn listen(ctx: &egui::Context) -> io::Result<Receiver<PathBuf>> {
+    let (listener, secrets) = bind()?;
+    let (sender, receiver) = mpsc::channel();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(stream);
+            if !knocked(&mut reader, &secrets) {
+                continue;
+            }
+            for line in reader.lines().map_while(Result::ok) {
+                if sender.send(PathBuf::from(line)).is_err() {
+                    // The app has shut down
+                    return;
+                }
+                ctx.request_repaint();
+            }
+        }
+    });
+    Ok(receiver)
+}
+
+#[cfg(unix)]
+fn knocked(_: &mut BufReader<Stream>, _: &Secrets) -> bool {
+    true
+}
+
+/// Whether the launch on the other end knows the knock, answering it if so.
+#[cfg(not(unix))]
+fn knocked(reader: &mut BufReader<Stream>, secrets: &Secrets) -> bool {
+    use std::time::Duration;
+
+    let mut knock = String::new();
+    let stream = reader.get_ref();
+    if stream
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .is_err()
+        || reader.read_line(&mut knock).is_err()
+        || knock.trim_end() != secrets.knock
+    {
+        return false;
+    }
+    let stream = reader.get_mut();
+    stream.set_read_timeout(None).is_ok() && writeln!(stream, "{}", secrets.answer).is_ok()
+}
+
+#[cfg(unix)]
+fn bind() -> io::Result<(Listener, Secrets)> {
+    let address = address()?;
+    let listener = match Listener::bind(&address) {
+        // Left over from a crash, unless another instance answers on it
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse && Stream::connect(&address).is_err() => {
+            std::fs::remove_file(&address)?;
+            Listener::bind(&address)
+        }
+        result => result,
+    }?;
+    Ok((listener, ()))
+}
+
+/// Listens on a port picked by the system, and notes it with new secrets
+/// for later launches.
+#[cfg(not(unix))]
+fn bind() -> io::Result<(Listener, Secrets)> {
+    let listener = Listener::bind(("127.0.0.1", 0))?;
+    let secrets = Secrets {
+        knock: secret(),
+        answer: secret(),
+    };
+    let address = address()?;
+    if let Some(dir) = address.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let port = listener.local_addr()?.port();
+    std::fs::write(
+        &address,
+        format!("{port}\n{}\n{}\n", secrets.knock, secrets.answer),
+    )?;
+    Ok((listener, secrets))
+}
+
+/// 128 bits from the keys the standard library seeds from the system.
+#[cfg(not(unix))]
+fn secret() -> String {
+    use std::hash::{BuildHasher, RandomState};
+
+    (0..2)
+        .map(|_| format!("{:016x}", RandomState::new().hash_one(0u8)))
+        .collect()
+}
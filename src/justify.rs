@@ -0,0 +1,258 @@
+//! Justified, hyphenated layout for plain paragraphs.
+//!
+//! egui only breaks lines at whitespace and justifies whole layout jobs, so
+//! paragraphs are broken into lines here and each word placed by hand.
+//! Only paragraphs of plain text, emphasis and code qualify; anything with
+//! links, images or HTML keeps the regular renderer.
+
+use std::sync::Arc;
+
+use eframe::egui::{self, Galley, TextFormat, text::LayoutJob};
+use hypher::Lang;
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+use crate::blocks;
+
+/// Widest a justified gap may grow, in spaces, before the line is left ragged.
+const MAX_GAP_SPACES: f32 = 4.0;
+
+/// Words shorter than this aren't worth hyphenating.
+const MIN_HYPHENATED_LEN: usize = 6;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct Style {
+    strong: bool,
+    emphasis: bool,
+    strikethrough: bool,
+    code: bool,
+}
+
+/// A word made of differently styled pieces, e.g. `**bold**ly`.
+type Word = Vec<(String, Style)>;
+
+pub struct Paragraph {
+    words: Vec<Word>,
+}
+
+impl Paragraph {
+    /// The words of `markdown` if it is a single paragraph simple enough to lay out here.
+    pub fn parse(markdown: &str) -> Option<Self> {
+        let mut words: Vec<Word> = Vec::new();
+        let mut current: Word = Vec::new();
+        let mut style = Style::default();
+        let mut paragraphs = 0;
+
+        let mut push_text = |text: &str, style: Style, current: &mut Word| {
+            for (idx, part) in text.split(char::is_whitespace).enumerate() {
+                if idx > 0 && !current.is_empty() {
+                    words.push(std::mem::take(current));
+                }
+                if part.is_empty() {
+                    continue;
+                }
+                match current.last_mut() {
+                    Some((piece, piece_style)) if *piece_style == style => piece.push_str(part),
+                    _ => current.push((part.to_string(), style)),
+                }
+            }
+        };
+
+        for event in Parser::new_ext(markdown, blocks::parser_options()) {
+            match event {
+                Event::Start(Tag::Paragraph) => paragraphs += 1,
+                Event::End(TagEnd::Paragraph) => {}
+                Event::Start(Tag::Strong) => style.strong = true,
+                Event::End(TagEnd::Strong) => style.strong = false,
+                Event::Start(Tag::Emphasis) => style.emphasis = true,
+                Event::End(TagEnd::Emphasis) => style.emphasis = false,
+                Event::Start(Tag::Strikethrough) => style.strikethrough = true,
+                Event::End(TagEnd::Strikethrough) => style.strikethrough = false,
+                Event::Text(text) => push_text(&text, style, &mut current),
+                Event::Code(text) => push_text(
+                    &text,
+                    Style {
+                        code: true,
+                        ..style
+                    },
+                    &mut current,
+                ),
+                Event::SoftBreak => push_text(" ", style, &mut current),
+                _ => return None,
+            }
+        }
+        if !current.is_empty() {
+            push_text(" ", style, &mut current);
+        }
+
+        (paragraphs == 1 && !words.is_empty()).then_some(Self { words })
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui, hyphenate: bool) -> egui::Response {
+        let width = ui.available_width();
+        let body = egui::TextStyle::Body.resolve(ui.style());
+        let space = lay_out(ui, " ", Style::default()).size().x;
+        let row_height = ui.fonts(|f| f.row_height(&body));
+
+        let lines = self.break_lines(ui, width, space, hyphenate);
+
+        let size = egui::vec2(width, lines.len() as f32 * row_height);
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+
+        let painter = ui.painter();
+        let fallback_color = ui.visuals().text_color();
+        for (row, line) in lines.iter().enumerate() {
+            let natural: f32 = line.iter().map(|word| word.width).sum();
+            let gaps = line.len().saturating_sub(1);
+            let is_last = row + 1 == lines.len();
+            let mut gap = space;
+            if !is_last && gaps > 0 {
+                let justified = (width - natural) / gaps as f32;
+                if justified <= space * MAX_GAP_SPACES {
+                    gap = justified.max(space);
+                }
+            }
+
+            let mut x = rect.left();
+            let y = rect.top() + row as f32 * row_height;
+            for word in line {
+                for galley in &word.pieces {
+                    painter.galley(egui::pos2(x, y), galley.clone(), fallback_color);
+                    x += galley.size().x;
+                }
+                x += gap;
+            }
+        }
+
+        response
+    }
+
+    /// Greedily fills lines of `width`, hyphenating words that would
+    /// otherwise wrap if `hyphenate` is set.
+    fn break_lines(
+        &self,
+        ui: &egui::Ui,
+        width: f32,
+        space: f32,
+        hyphenate: bool,
+    ) -> Vec<Vec<LaidWord>> {
+        let mut lines: Vec<Vec<LaidWord>> = vec![Vec::new()];
+        let mut line_width = 0.0;
+
+        for word in &self.words {
+            let mut word = LaidWord::new(ui, word);
+            loop {
+                let line = lines.last_mut().expect("always at least one line");
+                let needed = if line.is_empty() {
+                    word.width
+                } else {
+                    line_width + space + word.width
+                };
+                if needed <= width || line.is_empty() && !hyphenate {
+                    line_width = needed;
+                    line.push(word);
+                    break;
+                }
+
+                let budget = if line.is_empty() {
+                    width
+                } else {
+                    width - line_width - space
+                };
+                if hyphenate && let Some((head, tail)) = word.hyphenate(ui, budget) {
+                    line.push(head);
+                    word = tail;
+                } else if line.is_empty() {
+                    // Too long for a line of its own; let it overflow
+                    line_width = word.width;
+                    line.push(word);
+                    break;
+                }
+                lines.push(Vec::new());
+                line_width = 0.0;
+            }
+        }
+        lines
+    }
+}
+
+struct LaidWord {
+    pieces: Vec<Arc<Galley>>,
+    /// Source text and style, if the word is a single piece and can be hyphenated.
+    plain: Option<(String, Style)>,
+    width: f32,
+}
+
+impl LaidWord {
+    fn new(ui: &egui::Ui, word: &Word) -> Self {
+        let pieces: Vec<_> = word
+            .iter()
+            .map(|(text, style)| lay_out(ui, text, *style))
+            .collect();
+        Self {
+            width: pieces.iter().map(|g| g.size().x).sum(),
+            plain: match word.as_slice() {
+                [(text, style)] if !style.code => Some((text.clone(), *style)),
+                _ => None,
+            },
+            pieces,
+        }
+    }
+
+    fn single(ui: &egui::Ui, text: &str, style: Style) -> Self {
+        Self::new(ui, &vec![(text.to_string(), style)])
+    }
+
+    /// Splits off the longest hyphenated start of this word that fits in
+    /// `budget`, returning it with the rest of the word.
+    fn hyphenate(&self, ui: &egui::Ui, budget: f32) -> Option<(LaidWord, LaidWord)> {
+        let (text, style) = self.plain.as_ref()?;
+        // Hyphenate the letters only, keeping punctuation like quotes and commas attached
+        let core_start = text.find(char::is_alphabetic)?;
+        let core_end = text.rfind(char::is_alphabetic)? + 1;
+        let core = &text[core_start..core_end];
+        if core.chars().count() < MIN_HYPHENATED_LEN || !core.chars().all(char::is_alphabetic) {
+            return None;
+        }
+
+        let syllables: Vec<&str> = hypher::hyphenate(core, Lang::English).collect();
+        (1..syllables.len()).rev().find_map(|count| {
+            let split = core_start + syllables[..count].iter().map(|s| s.len()).sum::<usize>();
+            let head = LaidWord::single(ui, &format!("{}-", &text[..split]), *style);
+            (head.width <= budget).then(|| (head, LaidWord::single(ui, &text[split..], *style)))
+        })
+    }
+}
+
+fn lay_out(ui: &egui::Ui, text: &str, style: Style) -> Arc<Galley> {
+    let visuals = ui.visuals();
+    let text_style = if style.code {
+        egui::TextStyle::Monospace
+    } else {
+        egui::TextStyle::Body
+    };
+    let color = if style.strong {
+        visuals.strong_text_color()
+    } else {
+        visuals.text_color()
+    };
+    let format = TextFormat {
+        font_id: text_style.resolve(ui.style()),
+        color,
+        italics: style.emphasis,
+        background: if style.code {
+            visuals.code_bg_color
+        } else {
+            egui::Color32::TRANSPARENT
+        },
+        strikethrough: if style.strikethrough {
+            egui::Stroke::new(1.0, color)
+        } else {
+            egui::Stroke::NONE
+        },
+        ..Default::default()
+    };
+    ui.fonts(|f| f.layout_job(LayoutJob::single_section(text.to_string(), format)))
+}
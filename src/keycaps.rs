@@ -0,0 +1,282 @@
+//! Keyboard shortcuts drawn as keycaps: `<kbd>Ctrl</kbd>+<kbd>C</kbd>` and
+//! key combinations in code spans like `` `Ctrl+Shift+P` ``.
+//!
+//! `egui_commonmark` shows inline HTML as its source, so paragraphs and
+//! simple lists with keys are laid out here instead. Only text, emphasis and
+//! code qualify; anything else keeps the regular renderer.
+
+use eframe::egui;
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+use crate::blocks;
+
+/// Keys that are held down while another one is pressed.
+const MODIFIERS: &[&str] = &[
+    "ctrl", "control", "alt", "option", "opt", "shift", "cmd", "command", "super", "win", "meta",
+    "fn", "⌘", "⌥", "⇧", "⌃",
+];
+
+/// Keys with names, besides single characters and function keys.
+const NAMED_KEYS: &[&str] = &[
+    "enter",
+    "return",
+    "esc",
+    "escape",
+    "tab",
+    "space",
+    "backspace",
+    "delete",
+    "del",
+    "insert",
+    "ins",
+    "home",
+    "end",
+    "pageup",
+    "pagedown",
+    "pgup",
+    "pgdn",
+    "up",
+    "down",
+    "left",
+    "right",
+];
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct Style {
+    strong: bool,
+    emphasis: bool,
+    strikethrough: bool,
+    code: bool,
+}
+
+enum Piece {
+    Text(String, Style),
+    Key(String),
+}
+
+/// A paragraph, or an item of a list, with its bullet or number.
+struct Line {
+    marker: Option<String>,
+    pieces: Vec<Piece>,
+}
+
+pub struct Keycaps {
+    lines: Vec<Line>,
+}
+
+impl Keycaps {
+    /// The text of `markdown` if it is a paragraph or flat list with keys
+    /// in it, simple enough to lay out here.
+    pub fn parse(markdown: &str) -> Option<Self> {
+        if !markdown.contains('`') && !markdown.to_ascii_lowercase().contains("<kbd") {
+            return None;
+        }
+
+        let mut lines: Vec<Line> = Vec::new();
+        let mut style = Style::default();
+        // Next number of each ordered list, `None` for bullets
+        let mut lists: Vec<Option<u64>> = Vec::new();
+        let mut paragraphs_in_line = 0;
+        let mut key: Option<String> = None;
+        let mut has_keys = false;
+
+        for event in Parser::new_ext(markdown, blocks::parser_options()) {
+            match event {
+                Event::Start(Tag::Paragraph) => {
+                    paragraphs_in_line += 1;
+                    if lists.is_empty() {
+                        lines.push(Line {
+                            marker: None,
+                            pieces: Vec::new(),
+                        });
+                    }
+                    if paragraphs_in_line > 1 {
+                        return None;
+                    }
+                }
+                Event::End(TagEnd::Paragraph) if lists.is_empty() => paragraphs_in_line = 0,
+                Event::End(TagEnd::Paragraph) => {}
+                Event::Start(Tag::List(start)) if lists.is_empty() => lists.push(start),
+                Event::End(TagEnd::List(_)) => {
+                    lists.pop();
+                }
+                Event::Start(Tag::Item) => {
+                    let number = lists.last_mut()?;
+                    let marker = match number {
+                        Some(n) => {
+                            *n += 1;
+                            format!("{}.", *n - 1)
+                        }
+                        None => "•".to_string(),
+                    };
+                    lines.push(Line {
+                        marker: Some(marker),
+                        pieces: Vec::new(),
+                    });
+                    paragraphs_in_line = 0;
+                }
+                Event::End(TagEnd::Item) => {}
+                Event::Start(Tag::Strong) => style.strong = true,
+                Event::End(TagEnd::Strong) => style.strong = false,
+                Event::Start(Tag::Emphasis) => style.emphasis = true,
+                Event::End(TagEnd::Emphasis) => style.emphasis = false,
+                Event::Start(Tag::Strikethrough) => style.strikethrough = true,
+                Event::End(TagEnd::Strikethrough) => style.strikethrough = false,
+                Event::InlineHtml(html) if is_tag(&html, "<kbd") => {
+                    // `<kbd>` around a whole combination holds a `<kbd>` per key
+                    if let Some(outer) = key.take().filter(|outer| !outer.is_empty()) {
+                        lines.last_mut()?.pieces.push(Piece::Text(outer, style));
+                    }
+                    key = Some(String::new());
+                }
+                Event::InlineHtml(html) if is_tag(&html, "</kbd") => {
+                    if let Some(name) = key.take().filter(|name| !name.trim().is_empty()) {
+                        lines
+                            .last_mut()?
+                            .pieces
+                            .push(Piece::Key(name.trim().to_string()));
+                        has_keys = true;
+                    }
+                }
+                Event::Text(text) | Event::Code(text) if key.is_some() => {
+                    key.as_mut().expect("checked above").push_str(&text);
+                }
+                Event::Text(text) => push_text(&mut lines.last_mut()?.pieces, &text, style),
+                Event::Code(text) => match combination(&text) {
+                    Some(keys) => {
+                        let pieces = &mut lines.last_mut()?.pieces;
+                        for (n, name) in keys.into_iter().enumerate() {
+                            if n > 0 {
+                                push_text(pieces, "+", style);
+                            }
+                            pieces.push(Piece::Key(name.to_string()));
+                        }
+                        has_keys = true;
+                    }
+                    None => push_text(
+                        &mut lines.last_mut()?.pieces,
+                        &text,
+                        Style {
+                            code: true,
+                            ..style
+                        },
+                    ),
+                },
+                Event::SoftBreak => push_text(&mut lines.last_mut()?.pieces, " ", style),
+                _ => return None,
+            }
+        }
+
+        (has_keys && key.is_none()).then_some(Self { lines })
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui) -> egui::Response {
+        ui.vertical(|ui| {
+            for line in &self.lines {
+                match &line.marker {
+                    Some(marker) => {
+                        ui.horizontal_top(|ui| {
+                            let indent = ui.spacing().indent;
+                            ui.add_sized([indent, 0.0], egui::Label::new(marker.as_str()));
+                            ui.vertical(|ui| show_pieces(ui, &line.pieces));
+                        });
+                    }
+                    None => show_pieces(ui, &line.pieces),
+                }
+            }
+        })
+        .response
+    }
+}
+
+/// Whether `html` is the opening or closing tag starting with `name`.
+fn is_tag(html: &str, name: &str) -> bool {
+    let html = html.to_ascii_lowercase();
+    html.strip_prefix(name)
+        .is_some_and(|rest| rest.starts_with('>') || rest.starts_with(char::is_whitespace))
+}
+
+/// The keys of a code span like `Ctrl+Shift+P`: modifiers joined by `+`
+/// to the key pressed with them.
+fn combination(code: &str) -> Option<Vec<&str>> {
+    let keys: Vec<&str> = code.split('+').map(str::trim).collect();
+    let (last, modifiers) = keys.split_last()?;
+    let is_modifier = |key: &&str| MODIFIERS.contains(&key.to_lowercase().as_str());
+    let is_key = last.chars().count() == 1
+        || NAMED_KEYS.contains(&last.to_lowercase().as_str())
+        || last
+            .strip_prefix(['F', 'f'])
+            .and_then(|n| n.parse::<u8>().ok())
+            .is_some_and(|n| (1..=24).contains(&n));
+    (!modifiers.is_empty() && modifiers.iter().all(is_modifier) && is_key).then_some(keys)
+}
+
+fn push_text(pieces: &mut Vec<Piece>, text: &str, style: Style) {
+    match pieces.last_mut() {
+        Some(Piece::Text(last, last_style)) if *last_style == style => last.push_str(text),
+        _ => pieces.push(Piece::Text(text.to_string(), style)),
+    }
+}
+
+fn show_pieces(ui: &mut egui::Ui, pieces: &[Piece]) {
+    ui.horizontal_wrapped(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        ui.set_row_height(row_height);
+        for piece in pieces {
+            match piece {
+                Piece::Text(text, style) => {
+                    ui.label(rich_text(text, *style));
+                }
+                Piece::Key(name) => {
+                    ui.add_space(2.0);
+                    keycap(ui, name);
+                    ui.add_space(2.0);
+                }
+            }
+        }
+    });
+}
+
+fn rich_text(text: &str, style: Style) -> egui::RichText {
+    let mut rich = egui::RichText::new(text);
+    if style.strong {
+        rich = rich.strong();
+    }
+    if style.emphasis {
+        rich = rich.italics();
+    }
+    if style.strikethrough {
+        rich = rich.strikethrough();
+    }
+    if style.code {
+        rich = rich.code();
+    }
+    rich
+}
+
+/// A key name in a raised, rounded box.
+pub fn keycap(ui: &mut egui::Ui, name: &str) {
+    let visuals = ui.visuals();
+    let edge = visuals.widgets.inactive.bg_stroke.color;
+    let edge = if edge == egui::Color32::TRANSPARENT {
+        visuals.weak_text_color()
+    } else {
+        edge
+    };
+    let frame = egui::Frame::new()
+        .fill(visuals.widgets.inactive.weak_bg_fill)
+        .stroke(egui::Stroke::new(1.0, edge))
+        .corner_radius(3.0)
+        .inner_margin(egui::Margin::symmetric(4, 0));
+    let rect = frame
+        .show(ui, |ui| ui.label(egui::RichText::new(name).monospace()))
+        .response
+        .rect;
+    // A thicker bottom edge, like the side of a key
+    ui.painter().hline(
+        rect.x_range().shrink(2.0),
+        rect.bottom(),
+        egui::Stroke::new(2.0, edge),
+    );
+}
@@ -0,0 +1,176 @@
+//! Heuristic language detection for fenced code blocks that have no info string.
+
+/// A language guessed for an unlabeled code block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Guess {
+    /// Token written into the fence so the highlighter picks the right syntax.
+    pub token: &'static str,
+    /// Human readable name shown in the tooltip.
+    pub name: &'static str,
+}
+
+const fn guess(token: &'static str, name: &'static str) -> Guess {
+    Guess { token, name }
+}
+
+/// Keyword hints per language. Each matching hint adds its weight to the score.
+#[rustfmt::skip]
+const HINTS: &[(Guess, &[(&str, u32)])] = &[
+    (
+        guess("rs", "Rust"),
+        &[("fn ", 2), ("let mut ", 3), ("impl ", 3), ("pub fn ", 3), ("use std::", 4), ("::", 1), ("-> ", 1), ("#[derive", 4), ("println!", 4)],
+    ),
+    (
+        guess("py", "Python"),
+        &[("def ", 3), ("import ", 1), ("from ", 1), ("self.", 2), ("elif ", 4), ("print(", 2), ("__init__", 4), ("None", 1), ("    return", 1)],
+    ),
+    (
+        guess("js", "JavaScript"),
+        &[("function ", 2), ("const ", 2), ("=> ", 2), ("console.log", 4), ("require(", 3), ("document.", 3), ("===", 3), ("let ", 1)],
+    ),
+    (
+        guess("go", "Go"),
+        &[("package ", 3), ("func ", 3), (":= ", 3), ("fmt.", 4), ("import (", 4)],
+    ),
+    (
+        guess("java", "Java"),
+        &[("public class ", 4), ("System.out", 4), ("private ", 2), ("public static void", 4), ("@Override", 4)],
+    ),
+    (
+        guess("c", "C"),
+        &[("#include <", 4), ("int main(", 4), ("printf(", 3), ("->", 1), ("NULL", 2)],
+    ),
+    (
+        guess("cpp", "C++"),
+        &[("std::", 3), ("#include <iostream>", 5), ("cout <<", 4), ("template<", 4), ("namespace ", 3)],
+    ),
+    (
+        guess("rb", "Ruby"),
+        &[("def ", 1), ("end\n", 2), ("puts ", 3), ("require '", 3), ("attr_accessor", 4), ("do |", 4)],
+    ),
+    (
+        guess("sql", "SQL"),
+        &[("SELECT ", 3), (" FROM ", 3), ("WHERE ", 2), ("INSERT INTO", 4), ("CREATE TABLE", 4), ("JOIN ", 2)],
+    ),
+    (
+        guess("sh", "Shell"),
+        &[("$ ", 2), ("echo ", 2), ("sudo ", 3), ("export ", 2), ("cd ", 2), ("apt ", 2), ("fi\n", 3), (" | ", 1), ("&& ", 1)],
+    ),
+    (
+        guess("html", "HTML"),
+        &[("<!DOCTYPE", 5), ("<html", 4), ("<div", 3), ("</", 1), ("<head>", 3)],
+    ),
+    (
+        guess("yaml", "YAML"),
+        &[("---\n", 2), (":\n  ", 3), ("\n- ", 1)],
+    ),
+    (
+        guess("diff", "Diff"),
+        &[("--- a/", 4), ("+++ b/", 4), ("@@ ", 3)],
+    ),
+];
+
+/// Minimum score a language needs before we trust the guess.
+const MIN_SCORE: u32 = 3;
+
+/// Guess the language of `code`, or `None` if nothing looks convincing.
+pub fn detect(code: &str) -> Option<Guess> {
+    let trimmed = code.trim_start();
+
+    if let Some(first_line) = trimmed.strip_prefix("#!").and_then(|s| s.lines().next())
+        && let Some(g) = from_shebang(first_line)
+    {
+        return Some(g);
+    }
+
+    if looks_like_json(trimmed) {
+        return Some(guess("json", "JSON"));
+    }
+
+    // `max_by_key` keeps the last maximum, so walk the table backwards to let
+    // earlier entries win ties.
+    HINTS
+        .iter()
+        .rev()
+        .map(|(g, hints)| {
+            let score: u32 = hints
+                .iter()
+                .filter(|(needle, _)| code.contains(needle))
+                .map(|(_, weight)| weight)
+                .sum();
+            (*g, score)
+        })
+        .filter(|(_, score)| *score >= MIN_SCORE)
+        .max_by_key(|(_, score)| *score)
+        .map(|(g, _)| g)
+}
+
+fn from_shebang(line: &str) -> Option<Guess> {
+    // Past `env` and its options, as in `env -S node --harmony`
+    let interpreter = line
+        .split_whitespace()
+        .find(|w| !w.ends_with("/env") && !w.starts_with('-'))?;
+    let interpreter = interpreter.rsplit('/').next().unwrap_or(interpreter);
+    match interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+        "sh" | "bash" | "zsh" | "dash" | "ksh" => Some(guess("sh", "Shell")),
+        "python" => Some(guess("py", "Python")),
+        "node" | "deno" => Some(guess("js", "JavaScript")),
+        "ruby" => Some(guess("rb", "Ruby")),
+        "perl" => Some(guess("pl", "Perl")),
+        _ => None,
+    }
+}
+
+fn looks_like_json(code: &str) -> bool {
+    let code = code.trim_end();
+    let wrapped = (code.starts_with('{') && code.ends_with('}'))
+        || (code.starts_with('[') && code.ends_with(']'));
+    wrapped && code.contains('"') && (code.contains("\":") || code.starts_with('['))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(code: &str) -> Option<&'static str> {
+        detect(code).map(|guess| guess.token)
+    }
+
+    #[test]
+    fn shebangs_name_the_interpreter() {
+        assert_eq!(token("#!/usr/bin/env python3\nprint(1)"), Some("py"));
+        assert_eq!(token("\n#!/bin/bash\necho hi"), Some("sh"));
+        assert_eq!(token("#!/usr/bin/env -S node --harmony\n"), Some("js"));
+        assert_eq!(token("#!/usr/bin/perl5.36 -w\n"), Some("pl"));
+        // Unknown ones leave it to the keywords
+        assert_eq!(
+            token("#!/opt/tool\nfn main() -> u8 { println!(\"\") }"),
+            Some("rs")
+        );
+    }
+
+    #[test]
+    fn keywords_pick_the_language() {
+        assert_eq!(token("use std::fmt;\n\nfn main() {}"), Some("rs"));
+        assert_eq!(token("SELECT name FROM users WHERE id = 1"), Some("sql"));
+        assert_eq!(token("package main\n\nfunc main() {}"), Some("go"));
+        assert_eq!(token("--- a/x\n+++ b/x\n@@ -1 +1 @@"), Some("diff"));
+        assert_eq!(token("{\"name\": \"md_viewer\"}"), Some("json"));
+        assert_eq!(token("[\"a\", \"b\"]"), Some("json"));
+    }
+
+    #[test]
+    fn earlier_languages_win_ties() {
+        // `def ` scores for both Python and Ruby
+        assert_eq!(token("def x\n  puts 1\nend\n"), Some("rb"));
+        assert_eq!(token("def x():\n    return 1"), Some("py"));
+        assert_eq!(token("def "), Some("py"));
+    }
+
+    #[test]
+    fn prose_is_not_guessed() {
+        assert_eq!(token("Just some words, nothing more."), None);
+        assert_eq!(token("{not json}"), None);
+        assert_eq!(token(""), None);
+    }
+}
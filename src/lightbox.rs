@@ -0,0 +1,277 @@
+//! An image clicked in a document, over the whole window: zoomed with the
+//! wheel, panned by dragging, and saved as a file.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result, bail};
+use eframe::egui;
+use rfd::FileDialog;
+
+use crate::events::AppEvent;
+use crate::i18n::{tr, tr_args};
+
+/// Zoom limits, in screen pixels per image pixel.
+const MIN_ZOOM: f32 = 0.05;
+const MAX_ZOOM: f32 = 32.0;
+
+/// Factor the − and + buttons zoom by.
+const ZOOM_STEP: f32 = 1.25;
+
+/// Space kept around the image when it fits the window.
+const MARGIN: f32 = 24.0;
+
+struct Viewing {
+    uri: String,
+    /// Points per image pixel; `None` fits the image to the window.
+    scale: Option<f32>,
+    /// How far the image was dragged from the middle.
+    offset: egui::Vec2,
+}
+
+#[derive(Default)]
+pub struct Lightbox {
+    viewing: Option<Viewing>,
+}
+
+impl Lightbox {
+    pub fn open(&mut self, uri: String) {
+        self.viewing = Some(Viewing {
+            uri,
+            scale: None,
+            offset: egui::Vec2::ZERO,
+        });
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.viewing.is_some()
+    }
+
+    /// Draws the image over everything else, if one is open. Returns what
+    /// came of saving it.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<AppEvent> {
+        let viewing = self.viewing.as_mut()?;
+        let mut close = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        let mut save = false;
+        let ppp = ctx.pixels_per_point();
+        let screen = ctx.screen_rect();
+        let size = ctx
+            .try_load_texture(
+                &viewing.uri,
+                egui::TextureOptions::default(),
+                egui::SizeHint::default(),
+            )
+            .map(|poll| poll.size());
+
+        egui::Area::new(egui::Id::new("lightbox"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(screen.min)
+            .show(ctx, |ui| {
+                ui.set_min_size(screen.size());
+                // Added first, so the toolbar on top gets its own clicks
+                let backdrop = ui.interact(
+                    screen,
+                    ui.id().with("backdrop"),
+                    egui::Sense::click_and_drag(),
+                );
+                ui.painter()
+                    .rect_filled(screen, 0.0, egui::Color32::from_black_alpha(220));
+
+                let toolbar_height = ui.spacing().interact_size.y + MARGIN;
+                let mut view = screen.shrink(MARGIN);
+                view.min.y += toolbar_height;
+
+                let mut fit = None;
+                match &size {
+                    Ok(Some(size)) => {
+                        let fit_scale = (view.width() / size.x).min(view.height() / size.y);
+                        fit = Some(fit_scale);
+                        let scale = viewing.scale.unwrap_or(fit_scale);
+                        if backdrop.hovered() {
+                            let (scroll, pinch) =
+                                ui.input(|i| (i.smooth_scroll_delta.y, i.zoom_delta()));
+                            let factor = pinch * (scroll / 200.0).exp();
+                            if factor != 1.0 {
+                                let pointer = backdrop.hover_pos().unwrap_or(view.center());
+                                viewing.zoom_about(
+                                    scale * factor,
+                                    scale,
+                                    pointer - view.center(),
+                                    ppp,
+                                );
+                            }
+                        }
+                        if backdrop.dragged() {
+                            viewing.offset += backdrop.drag_delta();
+                        }
+                        if backdrop.double_clicked() {
+                            viewing.scale = match viewing.scale {
+                                None => Some(1.0 / ppp),
+                                Some(_) => None,
+                            };
+                            viewing.offset = egui::Vec2::ZERO;
+                        }
+                        let scale = viewing.scale.unwrap_or(fit_scale);
+                        let rect = egui::Rect::from_center_size(
+                            view.center() + viewing.offset,
+                            *size * scale,
+                        );
+                        egui::Image::from_uri(&viewing.uri).paint_at(ui, rect);
+                        if backdrop.clicked()
+                            && backdrop
+                                .interact_pointer_pos()
+                                .is_some_and(|p| !rect.contains(p))
+                        {
+                            close = true;
+                        }
+                    }
+                    Ok(None) => {
+                        let spinner =
+                            egui::Rect::from_center_size(view.center(), egui::vec2(32.0, 32.0));
+                        egui::Spinner::new().paint_at(ui, spinner);
+                        close |= backdrop.clicked();
+                    }
+                    Err(e) => {
+                        ui.painter().text(
+                            view.center(),
+                            egui::Align2::CENTER_CENTER,
+                            tr_args(
+                                "Couldn't load {image}: {error}",
+                                &[("image", &viewing.uri), ("error", e)],
+                            ),
+                            egui::TextStyle::Body.resolve(ui.style()),
+                            egui::Color32::WHITE,
+                        );
+                        close |= backdrop.clicked();
+                    }
+                }
+
+                let toolbar = egui::Rect::from_min_size(
+                    screen.min + egui::vec2(MARGIN, MARGIN / 2.0),
+                    egui::vec2(screen.width() - 2.0 * MARGIN, toolbar_height),
+                );
+                ui.scope_builder(egui::UiBuilder::new().max_rect(toolbar), |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(name(&viewing.uri));
+                            ui.separator();
+                            let enabled = fit.is_some();
+                            if ui
+                                .add_enabled(
+                                    enabled,
+                                    egui::Button::selectable(
+                                        viewing.scale.is_none(),
+                                        tr("Fit to Window"),
+                                    ),
+                                )
+                                .clicked()
+                            {
+                                viewing.scale = None;
+                                viewing.offset = egui::Vec2::ZERO;
+                            }
+                            let actual = viewing.scale == Some(1.0 / ppp);
+                            if ui
+                                .add_enabled(
+                                    enabled,
+                                    egui::Button::selectable(actual, tr("Actual Pixels")),
+                                )
+                                .on_hover_text(tr("One image pixel to one screen pixel"))
+                                .clicked()
+                            {
+                                viewing.scale = Some(1.0 / ppp);
+                                viewing.offset = egui::Vec2::ZERO;
+                            }
+                            if let Some(fit) = fit {
+                                let scale = viewing.scale.unwrap_or(fit);
+                                if ui.button("−").on_hover_text(tr("Zoom out")).clicked() {
+                                    viewing.zoom_about(
+                                        scale / ZOOM_STEP,
+                                        scale,
+                                        egui::Vec2::ZERO,
+                                        ppp,
+                                    );
+                                }
+                                ui.label(format!("{:.0}%", scale * ppp * 100.0));
+                                if ui.button("+").on_hover_text(tr("Zoom in")).clicked() {
+                                    viewing.zoom_about(
+                                        scale * ZOOM_STEP,
+                                        scale,
+                                        egui::Vec2::ZERO,
+                                        ppp,
+                                    );
+                                }
+                            }
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    if ui.button(tr("Close (Esc)")).clicked() {
+                                        close = true;
+                                    }
+                                    if ui.button(tr("Save Image As…")).clicked() {
+                                        save = true;
+                                    }
+                                },
+                            );
+                        });
+                    });
+                });
+            });
+
+        let event = if save {
+            match save_as(ctx, &viewing.uri) {
+                Ok(Some(path)) => Some(AppEvent::ImageSaved { path }),
+                Ok(None) => None,
+                Err(e) => Some(AppEvent::ImageSaveFailed {
+                    error: format!("{e:#}"),
+                }),
+            }
+        } else {
+            None
+        };
+        if close {
+            self.viewing = None;
+        }
+        event
+    }
+}
+
+impl Viewing {
+    /// Changes the zoom from `scale` to `to`, keeping the point `at` from
+    /// the middle of the view in place.
+    fn zoom_about(&mut self, to: f32, scale: f32, at: egui::Vec2, ppp: f32) {
+        let to = to.clamp(MIN_ZOOM / ppp, MAX_ZOOM / ppp);
+        self.offset = at - (at - self.offset) * (to / scale);
+        self.scale = Some(to);
+    }
+}
+
+/// The file name at the end of `uri`, or a made-up one for inline data.
+fn name(uri: &str) -> String {
+    if uri.starts_with("data:") {
+        return "image".into();
+    }
+    let path = uri.split(['?', '#']).next().unwrap_or(uri);
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    match name.is_empty() {
+        true => "image".into(),
+        false => name.replace("%20", " "),
+    }
+}
+
+/// Asks where to save the image at `uri` and writes its bytes there as they
+/// were loaded. Returns `None` if the user cancelled.
+fn save_as(ctx: &egui::Context, uri: &str) -> Result<Option<PathBuf>> {
+    let bytes = match ctx.try_load_bytes(uri) {
+        Ok(egui::load::BytesPoll::Ready { bytes, .. }) => bytes,
+        Ok(egui::load::BytesPoll::Pending { .. }) => bail!("the image is still loading"),
+        Err(e) => bail!("{e}"),
+    };
+    let Some(path) = FileDialog::new()
+        .set_file_name(name(uri))
+        .set_title(tr("Save image"))
+        .save_file()
+    else {
+        return Ok(None);
+    };
+    fs::write(&path, &*bytes).with_context(|| format!("writing {}", path.display()))?;
+    Ok(Some(path))
+}
@@ -0,0 +1,181 @@
+//! Links with schemes like `jira:` or `tel:`, handled as set up in
+//! Preferences: handed to the system, rewritten to another URL, or given
+//! to a command once the command is confirmed.
+
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use eframe::egui;
+
+use crate::config::LinkHandler;
+use crate::i18n::tr;
+
+/// The handler set up for `url`'s scheme.
+pub fn find<'a>(handlers: &'a [LinkHandler], url: &str) -> Option<&'a LinkHandler> {
+    let (scheme, _) = url.split_once(':')?;
+    handlers.iter().find(|handler| {
+        handler
+            .scheme
+            .trim()
+            .trim_end_matches(':')
+            .eq_ignore_ascii_case(scheme)
+    })
+}
+
+/// `template` with `{url}` and `{target}` filled in for `url`.
+pub fn expand(template: &str, url: &str) -> String {
+    template
+        .replace("{url}", url)
+        .replace("{target}", target(url))
+}
+
+/// What follows the scheme of `url`.
+fn target(url: &str) -> &str {
+    url.split_once(':')
+        .map_or(url, |(_, rest)| rest.trim_start_matches("//"))
+}
+
+/// Starts the command in `template` for `url`. The link goes into the
+/// arguments as it is, never through a shell, and not at all if the
+/// command would take it for an option.
+pub fn run(template: &str, url: &str) -> Result<()> {
+    if target(url).starts_with('-') {
+        bail!("{} would be taken for an option", target(url));
+    }
+    let words = words(template);
+    let Some((program, args)) = words.split_first() else {
+        bail!("no command is set up");
+    };
+    Command::new(expand(program, url))
+        .args(args.iter().map(|arg| expand(arg, url)))
+        .spawn()
+        .with_context(|| format!("running {program}"))?;
+    Ok(())
+}
+
+/// The words of a command line, quotes keeping spaces in them.
+pub fn words(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    for c in command.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (Some(open), _) if c == open => quote = None,
+            (None, _) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                }
+                in_word = false;
+            }
+            _ => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// The question asked before a link is given to a command.
+#[derive(Default)]
+pub struct Confirm {
+    /// The command's template, and the link.
+    pending: Option<(String, String)>,
+}
+
+impl Confirm {
+    pub fn ask(&mut self, template: String, url: String) {
+        self.pending = Some((template, url));
+    }
+
+    /// Asks about the pending link, if any. Returns the template and link
+    /// once the command is to be run, nothing while waiting or if not.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<(String, String)> {
+        let (template, url) = self.pending.as_ref()?;
+        let mut run = false;
+        let mut cancel = false;
+        let modal = egui::Modal::new(egui::Id::new("confirm_link_command")).show(ctx, |ui| {
+            ui.set_max_width(480.0);
+            ui.heading(tr("Run this command for the link?"));
+            ui.add_space(6.0);
+            ui.add(egui::Label::new(egui::RichText::new(url).monospace()).wrap());
+            ui.add_space(4.0);
+            ui.add(
+                egui::Label::new(
+                    egui::RichText::new(expand(template, url))
+                        .monospace()
+                        .weak(),
+                )
+                .wrap(),
+            );
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                run = ui.button(tr("Run")).clicked();
+                cancel = ui.button(tr("Cancel")).clicked();
+            });
+        });
+        if run || cancel || modal.should_close() {
+            return self.pending.take().filter(|_| run);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_keep_spaces_in_words() {
+        assert_eq!(
+            words(r#"open -a "Visual Studio Code" {url}"#),
+            ["open", "-a", "Visual Studio Code", "{url}"]
+        );
+        assert_eq!(
+            words("zotero  --select='{target}'\t"),
+            ["zotero", "--select={target}"]
+        );
+        assert_eq!(
+            words(r#"say 'it "works"' """#),
+            ["say", r#"it "works""#, ""]
+        );
+        // Left open, the quote runs to the end
+        assert_eq!(words("echo 'a b"), ["echo", "a b"]);
+        assert!(words("   ").is_empty());
+    }
+
+    #[test]
+    fn templates_get_the_url_and_what_follows_its_scheme() {
+        assert_eq!(
+            expand("{url}", "zotero://select/items/1"),
+            "zotero://select/items/1"
+        );
+        assert_eq!(
+            expand("--item={target}", "zotero://select/items/1"),
+            "--item=select/items/1"
+        );
+        assert_eq!(expand("call {target}", "tel:+41 44 000"), "call +41 44 000");
+    }
+
+    #[test]
+    fn templates_without_a_program_run_nothing() {
+        assert!(run("", "tel:123").is_err());
+        assert!(run("  ", "tel:123").is_err());
+    }
+
+    #[test]
+    fn links_looking_like_options_are_not_run() {
+        let error = run("echo {target}", "tel:--help").unwrap_err();
+        assert!(error.to_string().contains("would be taken for an option"));
+        let error = run("open {target}", "x:-rf").unwrap_err();
+        assert!(error.to_string().contains("would be taken for an option"));
+    }
+}
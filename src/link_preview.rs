@@ -0,0 +1,157 @@
+//! Peeking at where an internal link goes: hovering it shows the start of
+//! the linked document or section in a popup, rendered with a cache of its
+//! own so previews don't disturb the document's.
+
+use std::path::Path;
+
+use eframe::egui;
+use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+use crate::blocks::{self, BlockKind, Document};
+use crate::encoding;
+use crate::formats::{self, Format};
+use crate::tab::DocTab;
+use crate::wikilink;
+use crate::workspace::Workspace;
+
+/// Most blocks shown in a preview, and about how much text.
+const PREVIEW_BLOCKS: usize = 4;
+const PREVIEW_CHARS: usize = 600;
+
+#[derive(Default)]
+pub struct LinkPreview {
+    cache: CommonMarkCache,
+    /// The link last previewed and its excerpt, if it leads anywhere.
+    current: Option<(String, Option<String>)>,
+}
+
+impl LinkPreview {
+    /// Shows the preview for the link hovered in `tab`, if any.
+    pub fn show(&mut self, ctx: &egui::Context, tab: &DocTab, workspace: Option<&Workspace>) {
+        let Some(dest) = &tab.hovered_link else {
+            return;
+        };
+        if self.current.as_ref().map(|(shown, _)| shown) != Some(dest) {
+            self.current = Some((dest.clone(), excerpt(dest, tab, workspace)));
+        }
+        let (Some((_, Some(markdown))), Some(pointer)) = (&self.current, ctx.pointer_hover_pos())
+        else {
+            return;
+        };
+
+        // Above the pointer, leaving the URL tooltip below it in view
+        egui::Area::new(egui::Id::new("link_preview"))
+            .order(egui::Order::Tooltip)
+            .pivot(egui::Align2::LEFT_BOTTOM)
+            .fixed_pos(pointer - egui::vec2(0.0, 12.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(380.0);
+                    CommonMarkViewer::new().show(ui, &mut self.cache, markdown);
+                });
+            });
+    }
+}
+
+/// Whether `dest` points into this or another document rather than the web.
+pub fn is_internal(dest: &str) -> bool {
+    if dest.starts_with(wikilink::SCHEME) || dest.starts_with('#') {
+        return true;
+    }
+    let file = dest.split('#').next().unwrap_or(dest);
+    !dest.contains(':') && Format::from_path(Path::new(file)).is_some() || is_html_page(dest)
+}
+
+/// Whether `dest` is a web page next to the document, which is opened as
+/// a text preview rather than in the browser.
+pub fn is_html_page(dest: &str) -> bool {
+    let file = dest.split(['#', '?']).next().unwrap_or(dest);
+    !dest.contains(':') && formats::is_html(Path::new(file))
+}
+
+/// Whether `dest` is a CSV or TSV file next to the document, which is
+/// opened as a table rather than by another app.
+pub fn is_csv_file(dest: &str) -> bool {
+    let file = dest.split(['#', '?']).next().unwrap_or(dest);
+    !dest.contains(':') && formats::is_csv(Path::new(file))
+}
+
+/// Text and destination of the internal links in `markdown`.
+pub fn internal_links(markdown: &str) -> Vec<(String, String)> {
+    let mut links = Vec::new();
+    let mut current: Option<(String, String)> = None;
+    for event in Parser::new_ext(markdown, blocks::parser_options()) {
+        match event {
+            Event::Start(Tag::Link { dest_url, .. }) if is_internal(&dest_url) => {
+                current = Some((String::new(), dest_url.to_string()));
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((label, _)) = &mut current {
+                    label.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Link) => links.extend(current.take()),
+            _ => {}
+        }
+    }
+    links
+}
+
+/// The first few blocks of the document or section `dest` points to.
+fn excerpt(dest: &str, tab: &DocTab, workspace: Option<&Workspace>) -> Option<String> {
+    let (path, anchor) = match dest.strip_prefix(wikilink::SCHEME) {
+        Some(target) => {
+            let note = wikilink::note(target);
+            let path = match note {
+                "" => None,
+                note => Some(wikilink::resolve(note, tab.dir(), workspace)?),
+            };
+            (path, wikilink::heading(target).map(blocks::slug))
+        }
+        None => {
+            let (file, anchor) = dest.split_once('#').unwrap_or((dest, ""));
+            let path = match file {
+                "" => None,
+                file => Some(tab.dir()?.join(file.replace("%20", " "))),
+            };
+            (path, Some(anchor.to_string()).filter(|a| !a.is_empty()))
+        }
+    };
+
+    let other;
+    let doc = match path {
+        Some(path) => {
+            let decoded = encoding::read(&path).ok()?;
+            let format = Format::for_tab(&path);
+            other = blocks::split(&format.to_markdown(decoded.text));
+            &other
+        }
+        None => &tab.doc,
+    };
+    let start = match anchor {
+        Some(slug) => doc.find_anchor(&slug, 0)?,
+        None => 0,
+    };
+    Some(section_start(doc, start))
+}
+
+fn section_start(doc: &Document, start: usize) -> String {
+    let mut text = String::new();
+    for block in doc
+        .blocks
+        .iter()
+        .skip(start)
+        .filter(|block| !matches!(block.kind, BlockKind::Frontmatter))
+        .take(PREVIEW_BLOCKS)
+    {
+        if text.len() >= PREVIEW_CHARS {
+            text.push_str("\n\n…");
+            break;
+        }
+        text.push_str(&block.markdown);
+        text.push_str("\n\n");
+    }
+    text
+}
@@ -1,242 +1,594 @@
-use std::{fs, path::PathBuf, time::SystemTime};
+mod animation;
+mod backlinks;
+mod badge;
+mod blocks;
+mod callout;
+mod clock;
+mod closed_tabs;
+mod code_view;
+mod compare;
+mod config;
+mod crash;
+mod dashboard;
+mod diagram;
+mod dialect;
+mod diff_fence;
+mod encoding;
+mod events;
+mod export;
+mod fonts;
+mod formats;
+mod frontmatter;
+mod git;
+mod global_search;
+mod hints;
+mod html_fragment;
+mod i18n;
+mod image_cache;
+mod instance;
+mod justify;
+mod keycaps;
+mod lang_detect;
+mod lightbox;
+mod link_handlers;
+mod link_preview;
+mod mdbook;
+mod monochrome;
+mod nav_macro;
+mod palette;
+mod pandoc;
+mod percent;
+mod preferences;
+mod present;
+mod quick_open;
+mod reading_state;
+mod remote;
+mod remote_docs;
+mod render;
+mod rich_copy;
+mod scroll_keys;
+mod search;
+mod snippet;
+mod startup;
+mod svg;
+mod tab;
+mod tab_order;
+mod table;
+mod tags;
+mod toc;
+mod trust;
+#[cfg(test)]
+mod ui_tests;
+mod updates;
+mod uri_scheme;
+mod web_links;
+mod wikilink;
+mod window;
+mod workspace;
 
+use std::{
+    io::{self, IsTerminal, Read},
+    path::PathBuf,
+    sync::{
+        Arc,
+        mpsc::{self, Receiver},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use animation::Animations;
 use anyhow::Result;
-use eframe::{egui, NativeOptions};
-use egui::{SelectableLabel, Vec2};
-use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
-use rfd::FileDialog;
+use config::Settings;
+use eframe::egui;
+use egui_commonmark::CommonMarkCache;
+use events::AppEvent;
+use image_cache::ImageCache;
+use remote::RemoteGate;
+use svg::SvgLoader;
+use window::{DocWindow, WindowAction};
+use workspace::Workspace;
+
+/// Name of the app, which eframe also keeps the session under.
+const APP_NAME: &str = "Markdown Viewer";
 
 fn main() -> eframe::Result<()> {
+    let timing = startup::Timing::new(std::env::args_os().any(|arg| arg == startup::FLAG));
+    crash::install(APP_NAME);
+
+    let paths: Vec<PathBuf> = std::env::args_os()
+        .skip(1)
+        .filter(|arg| arg != "-" && arg != startup::FLAG)
+        .filter_map(|arg| match arg.to_str() {
+            // As the desktop hands over links to documents
+            Some(uri) if uri_scheme::is_link(uri) => {
+                let path = uri_scheme::parse(uri);
+                if path.is_none() {
+                    eprintln!("Not a link to a document: {uri}");
+                }
+                path
+            }
+            _ => Some(PathBuf::from(arg)),
+        })
+        .collect();
+    let restarted = std::env::var_os(crash::RESTARTED_VAR).is_some();
+    if !paths.is_empty() && !restarted && instance::hand_off(&paths) {
+        return Ok(());
+    }
+
+    let stdin = match read_stdin() {
+        Ok(stdin) => stdin,
+        Err(e) => {
+            eprintln!("Failed to read standard input: {e}");
+            None
+        }
+    };
+
+    // Read before the window is made, to open it as it was last closed
+    let config = config::load();
+    let geometry = match &config {
+        Ok(Some(settings)) => settings.window,
+        _ => config::WindowGeometry::default(),
+    };
     let native_options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([1080.0, 720.0]),
+        viewport: geometry.apply(egui::ViewportBuilder::default()),
+        // Kept in the config file instead, without a maximized window's size
+        persist_window: false,
         ..Default::default()
     };
 
     eframe::run_native(
-        "Markdown Viewer",
+        APP_NAME,
         native_options,
-        Box::new(|cc| {
-            // create and return your App wrapped in Ok(...)
-            Ok(Box::new(App::new(cc)) as Box<dyn eframe::App>)
+        Box::new(move |cc| {
+            timing.mark("window created");
+            // Loads while the first frame is drawn
+            let (send, highlighter) = mpsc::channel();
+            thread::spawn(move || {
+                let cache = timing.measure("highlighter", CommonMarkCache::default);
+                let _ = send.send(cache);
+            });
+            let (settings, config_error) = load_settings(config, cc.storage);
+            cc.egui_ctx
+                .set_theme(settings.theme_preference(clock::local_hour()));
+            let setup = Setup {
+                stdin,
+                paths,
+                settings,
+                config_error,
+                highlighter,
+                timing,
+            };
+            Ok(Box::new(Launch::new(setup)) as Box<dyn eframe::App>)
         }),
     )?;
 
     Ok(())
 }
 
-struct DocTab {
-    title: String,
-    path: PathBuf,
-    content: String,
-    last_read: SystemTime,
+/// The saved settings, or the defaults with the error that kept them from
+/// loading.
+fn load_settings(
+    config: Result<Option<Settings>>,
+    storage: Option<&dyn eframe::Storage>,
+) -> (Settings, Option<anyhow::Error>) {
+    match config {
+        Ok(Some(settings)) => (settings, None),
+        // No config file yet: carry over what eframe had stored
+        Ok(None) => {
+            let stored = storage.and_then(|storage| eframe::get_value(storage, SETTINGS_KEY));
+            (stored.unwrap_or_default(), None)
+        }
+        Err(e) => (Settings::default(), Some(e)),
+    }
 }
 
-impl DocTab {
-    fn from_path(path: PathBuf) -> Result<Self> {
-        let content = fs::read_to_string(&path)?;
-        let title = path
-            .file_name()
-            .map(|s| s.to_string_lossy().to_string())
-            .unwrap_or_else(|| "untitled.md".to_string());
-        Ok(Self {
-            title,
-            path,
-            content,
-            last_read: SystemTime::now(),
-        })
+/// Markdown piped in, read when started as `md_viewer -` or with standard
+/// input redirected.
+fn read_stdin() -> io::Result<Option<encoding::Decoded>> {
+    let requested = std::env::args()
+        .skip(1)
+        .find(|arg| arg != startup::FLAG)
+        .is_some_and(|arg| arg == "-");
+    if !requested && io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    let mut bytes = Vec::new();
+    io::stdin().read_to_end(&mut bytes)?;
+    // Launchers often start us with stdin at /dev/null
+    if bytes.is_empty() && !requested {
+        return Ok(None);
+    }
+    Ok(Some(encoding::decode(bytes)))
+}
+
+/// Where eframe's persistence kept [`Settings`] before the config file; read
+/// once to carry them over.
+const SETTINGS_KEY: &str = "settings";
+
+/// `doc.md#configuration` as the file and the heading to go to in it. Paths
+/// of files that exist are left as they are, `#` and all.
+fn split_heading(path: PathBuf) -> (PathBuf, Option<String>) {
+    if path.exists() {
+        return (path, None);
+    }
+    let text = path.to_string_lossy();
+    match text.rsplit_once('#') {
+        Some((file, heading)) if !file.is_empty() && !heading.is_empty() => {
+            (PathBuf::from(file), Some(heading.to_string()))
+        }
+        _ => (path, None),
     }
 }
 
+/// Storage key for the paths of the tabs open at exit.
+const SESSION_KEY: &str = "session";
+
+/// How often open files are checked for changes when auto-reload is on.
+const AUTO_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// State every window reads and updates.
+pub struct Shared {
+    pub cm_cache: CommonMarkCache,
+    pub settings: Settings,
+    /// The folder opened with File → Open Folder…, if any.
+    pub workspace: Option<Workspace>,
+}
+
 struct App {
-    tabs: Vec<DocTab>,
-    active: usize,
-    cm_cache: CommonMarkCache,
-    status: String,
-    md_text_scale: f32,
+    /// The main window comes first; the rest are tabs detached into their own windows.
+    windows: Vec<DocWindow>,
+    shared: Shared,
+    last_reload_check: Instant,
+    last_hash_check: Instant,
+    /// Files handed over by later launches, if this is the listening instance.
+    handoff: Option<Receiver<PathBuf>>,
+    /// The main window as it is now, put in the settings when saving.
+    geometry: config::WindowGeometry,
+    /// Settings as last written to the config file, to save only on change.
+    saved_settings: Settings,
+    applied_theme: Option<egui::ThemePreference>,
+    applied_ui_scale: Option<f32>,
+    applied_fonts: Option<Vec<config::CustomFont>>,
+    remote: Arc<RemoteGate>,
+    animations: Arc<Animations>,
+    image_cache: Arc<ImageCache>,
+    timing: startup::Timing,
+}
+
+/// What the app is started with, most of it set up after the first frame.
+struct Setup {
+    stdin: Option<encoding::Decoded>,
+    paths: Vec<PathBuf>,
+    settings: Settings,
+    config_error: Option<anyhow::Error>,
+    /// The highlighter's syntaxes and themes, loading in the background.
+    highlighter: Receiver<CommonMarkCache>,
+    timing: startup::Timing,
+}
+
+/// Shows the window before setting up the app in it, so it comes up
+/// without waiting for the slow parts.
+struct Launch {
+    setup: Option<Setup>,
+    app: Option<App>,
+}
+
+impl Launch {
+    fn new(setup: Setup) -> Self {
+        Self {
+            setup: Some(setup),
+            app: None,
+        }
+    }
+}
+
+impl eframe::App for Launch {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        if let Some(app) = &mut self.app {
+            app.save(storage);
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if let Some(app) = &mut self.app {
+            app.update(ctx, frame);
+            return;
+        }
+        let Some(setup) = self.setup.take() else {
+            return;
+        };
+        if ctx.cumulative_frame_nr() == 0 {
+            // An empty window, shown before the rest is set up on the next frame
+            egui::CentralPanel::default().show(ctx, |_| {});
+            ctx.request_repaint();
+            self.setup = Some(setup);
+            return;
+        }
+        let timing = setup.timing;
+        timing.mark("first frame");
+        let app = self.app.insert(App::new(ctx, frame.storage(), setup));
+        app.update(ctx, frame);
+        timing.mark("ready");
+    }
 }
 
 impl App {
-    fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+    fn new(ctx: &egui::Context, storage: Option<&dyn eframe::Storage>, setup: Setup) -> Self {
+        let Setup {
+            stdin,
+            mut paths,
+            settings,
+            config_error,
+            highlighter,
+            timing,
+        } = setup;
+        // Lets egui_commonmark load the images documents refer to
+        egui_extras::install_image_loaders(ctx);
+        SvgLoader::install(ctx);
+        let remote = RemoteGate::install(ctx, settings.load_remote_images);
+        let animations = Animations::install(ctx, settings.animate_images);
+        // Asked first, to see every image
+        let image_cache = ImageCache::install(ctx);
+
+        let mut main_window = DocWindow::main();
+        main_window.chunked_above = settings.chunked_above();
+        if let Some(e) = config_error {
+            main_window.events.push(AppEvent::ConfigFailed {
+                error: format!("{e:#}"),
+            });
+        }
+        timing.measure("session restore", || {
+            if stdin.is_none()
+                && paths.is_empty()
+                && settings.startup == config::Startup::RestoreTabs
+            {
+                paths = storage
+                    .and_then(|storage| eframe::get_value(storage, SESSION_KEY))
+                    .unwrap_or_default();
+            }
+            if let Some(decoded) = stdin {
+                main_window.add_tab(tab::DocTab::stdin(decoded));
+            }
+            for path in paths {
+                let (path, heading) = split_heading(path);
+                main_window.open_at(path, heading);
+            }
+        });
+        let cm_cache = timing.measure("waiting for highlighter", || {
+            highlighter.recv().unwrap_or_default()
+        });
+
         Self {
-            tabs: Vec::new(),
-            active: 0,
-            cm_cache: CommonMarkCache::default(),
-            status: "Ready".into(),
-            md_text_scale: 1.0,
-        }
-    }
-
-    fn open_files(&mut self) {
-        if let Some(files) = FileDialog::new()
-            .add_filter("Markdown", &["md", "markdown"])
-            .set_title("Open Markdown file(s)")
-            .pick_files()
-        {
-            for path in files {
-                let is_md = path
-                    .extension()
-                    .map(|e| matches!(e.to_string_lossy().to_lowercase().as_str(), "md" | "markdown"))
-                    .unwrap_or(false);
-
-                if !is_md {
-                    self.status = format!(
-                        "Skipped non-markdown file: {}",
-                        path.file_name().unwrap_or_default().to_string_lossy()
-                    );
-                    continue;
-                }
+            windows: vec![main_window],
+            shared: Shared {
+                cm_cache,
+                settings: settings.clone(),
+                workspace: None,
+            },
+            last_reload_check: Instant::now(),
+            last_hash_check: Instant::now(),
+            // Fails if another instance is already listening
+            handoff: instance::listen(ctx).ok(),
+            geometry: settings.window,
+            saved_settings: settings,
+            applied_theme: None,
+            applied_ui_scale: None,
+            applied_fonts: None,
+            remote,
+            animations,
+            image_cache,
+            timing,
+        }
+    }
 
-                match DocTab::from_path(path) {
-                    Ok(tab) => {
-                        self.tabs.push(tab);
-                        self.active = self.tabs.len().saturating_sub(1);
-                        self.status = "Opened file".into();
-                    }
-                    Err(e) => {
-                        self.status = format!("Failed to open: {e}");
-                    }
-                }
+    /// Applies settings that live outside the windows, and writes them to
+    /// the config file when they changed.
+    fn sync_settings(&mut self, ctx: &egui::Context) {
+        let settings = &self.shared.settings;
+        let theme = settings.theme_preference(clock::local_hour());
+        if self.applied_theme != Some(theme) {
+            ctx.set_theme(theme);
+            self.applied_theme = Some(theme);
+        }
+        if settings.theme == config::Theme::Scheduled {
+            // To notice the hour changing while nothing else happens
+            ctx.request_repaint_after(Duration::from_secs(60));
+        }
+        // egui's own Ctrl+Plus/Minus zoom
+        if ctx.options(|o| o.zoom_with_keyboard) == settings.lock_zoom {
+            ctx.options_mut(|o| o.zoom_with_keyboard = !settings.lock_zoom);
+        }
+        // On top of the OS scale factor, so it stays sharp on any monitor
+        if self.applied_ui_scale != Some(settings.ui_scale) {
+            ctx.set_zoom_factor(settings.ui_scale);
+            self.applied_ui_scale = Some(settings.ui_scale);
+        }
+        if self.applied_fonts.as_ref() != Some(&settings.fonts) {
+            let errors = self
+                .timing
+                .measure("fonts", || fonts::install(ctx, &settings.fonts));
+            for e in errors {
+                self.windows[0].events.push(AppEvent::FontFailed {
+                    error: format!("{e:#}"),
+                });
             }
+            self.applied_fonts = Some(settings.fonts.clone());
         }
+        if settings.link_previews {
+            // Link previews find hovered links through the accessibility tree
+            ctx.enable_accesskit();
+        }
+        self.remote.set_allowed(ctx, settings.load_remote_images);
+        self.animations.set_playing(ctx, settings.animate_images);
+        self.image_cache.enforce(ctx, settings.image_cache_limit());
+        self.write_settings();
     }
 
-    fn close_tab(&mut self, idx: usize) {
-        if idx < self.tabs.len() {
-            self.tabs.remove(idx);
-            if self.active >= self.tabs.len() {
-                self.active = self.tabs.len().saturating_sub(1);
+    /// Writes the settings to the config file if they changed since.
+    fn write_settings(&mut self) {
+        let settings = &self.shared.settings;
+        if *settings != self.saved_settings {
+            if let Err(e) = config::save(settings) {
+                self.windows[0].events.push(AppEvent::ConfigFailed {
+                    error: format!("{e:#}"),
+                });
             }
+            self.saved_settings = settings.clone();
+        }
+    }
+
+    /// Opens files handed over by another launch and brings the main window forward.
+    fn poll_handoff(&mut self, ctx: &egui::Context) {
+        let Some(handoff) = &self.handoff else {
+            return;
+        };
+        let paths: Vec<PathBuf> = handoff.try_iter().collect();
+        if paths.is_empty() {
+            return;
+        }
+        for path in paths {
+            let (path, heading) = split_heading(path);
+            self.windows[0].open_at(path, heading);
         }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
     }
 
-    fn reload_active(&mut self) {
-        if let Some(tab) = self.tabs.get_mut(self.active) {
-            match fs::read_to_string(&tab.path) {
-                Ok(new_content) => {
-                    tab.content = new_content;
-                    tab.last_read = SystemTime::now();
-                    self.status = "Reloaded from disk".into();
+    /// Reloads tabs whose files changed on disk, or queues the new contents if
+    /// the reader is scrolled into the document. With auto-reload off, the
+    /// tabs only note the change, to offer reloading.
+    fn poll_auto_reload(&mut self, ctx: &egui::Context) {
+        ctx.request_repaint_after(AUTO_RELOAD_INTERVAL);
+        if self.last_reload_check.elapsed() < AUTO_RELOAD_INTERVAL {
+            return;
+        }
+        self.last_reload_check = Instant::now();
+        let settings = &self.shared.settings;
+        let hash_interval = Duration::from_secs(settings.hash_poll_secs.max(1).into());
+        let check_hashes =
+            settings.hash_change_detection && self.last_hash_check.elapsed() >= hash_interval;
+        if check_hashes {
+            self.last_hash_check = Instant::now();
+        }
+
+        for window in &mut self.windows {
+            for tab in window
+                .tabs
+                .iter_mut()
+                .filter(|tab| !tab.is_loading() && !tab.is_scratch())
+            {
+                if !settings.auto_reload {
+                    tab.check_external_change();
+                }
+                // Unloaded tabs catch up when they're shown again
+                if tab.is_unloaded() {
+                    continue;
                 }
-                Err(e) => {
-                    self.status = format!("Reload failed: {e}");
+                // Compared and read on other threads, and taken in once they're done
+                if check_hashes {
+                    tab.check_hash();
                 }
+                let by_hash = tab.changed_by_hash();
+                if !settings.auto_reload {
+                    tab.changed_externally |= by_hash.is_some();
+                    continue;
+                }
+                tab.read_if_changed();
+                let update = if let Some(read) = tab.read_from_disk() {
+                    read
+                } else if let Some(decoded) = by_hash {
+                    Ok(decoded)
+                } else {
+                    continue;
+                };
+                let reading = settings.pause_reload_while_reading && tab.scroll_offset > 0.0;
+                let title = tab.title.clone();
+                window.events.push(match update {
+                    Ok(decoded) => {
+                        tab.update_from_disk(decoded, reading);
+                        if reading {
+                            AppEvent::ReloadPaused { title }
+                        } else {
+                            AppEvent::Reloaded { title }
+                        }
+                    }
+                    Err(e) => AppEvent::ReloadFailed {
+                        title,
+                        error: e.to_string(),
+                    },
+                });
             }
         }
     }
+
+    fn apply(&mut self, action: WindowAction) {
+        match action {
+            WindowAction::Detach(tab) => self.windows.push(DocWindow::detached(tab)),
+            WindowAction::MoveToMain(tab) => self.windows[0].add_tab(tab),
+        }
+    }
 }
 
 impl eframe::App for App {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let session: Vec<&PathBuf> = self
+            .windows
+            .iter()
+            .flat_map(|window| &window.tabs)
+            .filter(|tab| !tab.is_scratch())
+            .map(|tab| &tab.path)
+            .collect();
+        eframe::set_value(storage, SESSION_KEY, &session);
+        for window in &mut self.windows {
+            window.save_reading();
+        }
+        // Only now, rather than on every frame of a drag
+        self.shared.settings.window = self.geometry;
+        self.write_settings();
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Apply Font Scaling
-        ctx.set_pixels_per_point(1.25);
-        
         // Show full URLs on hover (suggested in egui_commonmark docs)
         ctx.style_mut(|s| s.url_in_tooltip = true);
 
-        // Top menu
-        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
-            egui::MenuBar::new().ui(ui, |ui| {
-                ui.menu_button("File", |ui| {
-                    if ui.button("Open…").clicked() {
-                        ui.close();
-                        self.open_files();
-                    }
-                    if ui.button("Reload").clicked() {
-                        ui.close();
-                        self.reload_active();
-                    }
-                    if ui.button("Close Tab").clicked() {
-                        ui.close();
-                        let idx = self.active;
-                        self.close_tab(idx);
-                    }
-                    if ui.button("Quit").clicked() {
-                        ui.close();
-                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                    }
-                });
+        self.poll_auto_reload(ctx);
+        self.poll_handoff(ctx);
+        ctx.input(|i| self.geometry.track(i.viewport()));
 
-                ui.separator();
+        let mut actions = Vec::new();
+        actions.extend(self.windows[0].show(ctx, &mut self.shared));
 
-                // Text size controls
-                if ui.button("A–").clicked() {
-                    self.md_text_scale = (self.md_text_scale * 0.9).max(0.5);
-                }
-                if ui.button("A+").clicked() {
-                    self.md_text_scale = (self.md_text_scale * 1.1).min(3.0);
+        // Detached windows are immediate viewports so they can share the app state.
+        let mut closed = Vec::new();
+        for idx in 1..self.windows.len() {
+            let window = &mut self.windows[idx];
+            let builder = egui::ViewportBuilder::default()
+                .with_title(window.title(self.shared.settings.path_in_title))
+                .with_inner_size([900.0, 650.0]);
+            ctx.show_viewport_immediate(window.viewport_id, builder, |ctx, _class| {
+                if ctx.input(|i| i.viewport().close_requested()) || window.tabs.is_empty() {
+                    closed.push(idx);
+                    return;
                 }
-
-                ui.separator();
-
-                ui.menu_button("Help", |ui| {
-                    ui.label("Markdown Viewer");
-                    ui.label("View-only .md files with tabs and code highlighting.");
-                });
-            });
-        });
-
-        // Status bar
-        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
-            ui.label(&self.status);
-        });
-
-        // Tabs header
-        egui::TopBottomPanel::top("tab_strip").show(ctx, |ui| {
-            ui.horizontal_wrapped(|ui| {
-                for idx in 0..self.tabs.len() {
-                    let selected = idx == self.active;
-                    if ui
-                        .add(SelectableLabel::new(selected, &self.tabs[idx].title))
-                        .clicked()
-                    {
-                        self.active = idx;
-                    }
-                    ui.scope(|ui| {
-                        ui.spacing_mut().item_spacing.x = 4.0;
-                        if ui.button("×").on_hover_text("Close tab").clicked() {
-                            self.close_tab(idx);
-                        }
-                    });
-                }
-
-                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    if ui.button("+ Open").clicked() {
-                        self.open_files();
-                    }
-                });
+                actions.extend(window.show(ctx, &mut self.shared));
             });
-        });
-
-        // Main viewer
-        egui::CentralPanel::default().show(ctx, |ui| {
-            if self.tabs.is_empty() {
-                ui.vertical_centered(|ui| {
-                    ui.add_space(40.0);
-                    ui.heading("Welcome to Markdown Viewer");
-                    ui.label("Use File → Open… or the + Open button to load one or more .md files.");
-                });
-                return;
-            }
-
-            let tab = &self.tabs[self.active];
-
-            egui::ScrollArea::vertical()
-                .auto_shrink([false, false])
-                .show(ui, |ui| {
-                    ui.scope(|ui| {
-                        // Temporarily scale ONLY the markdown area's text styles
-                        let style = ui.style_mut();
-                        for font_id in style.text_styles.values_mut() {
-                            font_id.size *= self.md_text_scale;
-                        }
-
-                        egui_commonmark::CommonMarkViewer::new()
-                            .show(ui, &mut self.cm_cache, &tab.content);
-                    });
-                });
-
-        });
+        }
+        for idx in closed.into_iter().rev() {
+            self.windows.remove(idx);
+        }
 
+        for action in actions {
+            self.apply(action);
+        }
+        crash::set_open_files(
+            self.windows
+                .iter()
+                .flat_map(|window| &window.tabs)
+                .filter(|tab| !tab.is_scratch())
+                .map(|tab| tab.path.as_path()),
+        );
 
+        self.sync_settings(ctx);
     }
 }
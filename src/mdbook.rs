@@ -0,0 +1,201 @@
+//! mdBook projects: a folder with a `book.toml`, whose `SUMMARY.md` lists
+//! the chapters in reading order.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eframe::egui;
+use serde::Deserialize;
+
+use crate::i18n::tr;
+
+#[derive(Clone)]
+pub struct Book {
+    pub title: Option<String>,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Clone)]
+pub enum Entry {
+    /// A `# Part` heading between chapters.
+    Part(String),
+    Chapter {
+        title: String,
+        /// Absolute; `None` for drafts, listed with an empty link.
+        path: Option<PathBuf>,
+        /// Nesting under other chapters.
+        depth: usize,
+    },
+}
+
+/// A chapter's title and file.
+type Page<'a> = (&'a str, &'a Path);
+
+#[derive(Default, Deserialize)]
+struct BookToml {
+    #[serde(default)]
+    book: BookSection,
+}
+
+#[derive(Default, Deserialize)]
+struct BookSection {
+    title: Option<String>,
+    src: Option<PathBuf>,
+}
+
+impl Book {
+    /// The book in `root`, if it is one: a `book.toml` with `SUMMARY.md` in
+    /// its source folder, or a `SUMMARY.md` at the top.
+    pub fn detect(root: &Path) -> Option<Self> {
+        let (src, title) = match fs::read_to_string(root.join("book.toml")) {
+            Ok(text) => {
+                // A book.toml that doesn't parse still has the usual layout
+                let config: BookToml = toml::from_str(&text).unwrap_or_default();
+                let src = config.book.src.unwrap_or_else(|| "src".into());
+                (root.join(src), config.book.title)
+            }
+            Err(_) => (root.to_path_buf(), None),
+        };
+        let summary = fs::read_to_string(src.join("SUMMARY.md")).ok()?;
+        Some(Self {
+            title,
+            entries: parse_summary(&summary, &src),
+        })
+    }
+
+    /// Chapters with a file, in reading order.
+    fn pages(&self) -> impl Iterator<Item = Page<'_>> {
+        self.entries.iter().filter_map(|entry| match entry {
+            Entry::Chapter {
+                title,
+                path: Some(path),
+                ..
+            } => Some((title.as_str(), path.as_path())),
+            _ => None,
+        })
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.pages().any(|(_, page)| page == path)
+    }
+
+    /// The chapters before and after `path`'s.
+    fn neighbors(&self, path: &Path) -> (Option<Page<'_>>, Option<Page<'_>>) {
+        let pages: Vec<_> = self.pages().collect();
+        let Some(idx) = pages.iter().position(|(_, page)| *page == path) else {
+            return (None, None);
+        };
+        let previous = idx.checked_sub(1).map(|idx| pages[idx]);
+        (previous, pages.get(idx + 1).copied())
+    }
+
+    /// The chapter list for the sidebar. Returns the chapter clicked.
+    pub fn show_contents(&self, ui: &mut egui::Ui, active: Option<&Path>) -> Option<PathBuf> {
+        let mut clicked = None;
+        for entry in &self.entries {
+            match entry {
+                Entry::Part(title) => {
+                    ui.add_space(4.0);
+                    ui.strong(title);
+                }
+                Entry::Chapter { title, path, depth } => {
+                    ui.horizontal(|ui| {
+                        ui.add_space(*depth as f32 * 12.0);
+                        match path {
+                            Some(path) => {
+                                let selected = active == Some(path.as_path());
+                                if ui.selectable_label(selected, title).clicked() {
+                                    clicked = Some(path.clone());
+                                }
+                            }
+                            None => {
+                                ui.weak(title).on_hover_text(tr("Not written yet"));
+                            }
+                        }
+                    });
+                }
+            }
+        }
+        clicked
+    }
+
+    /// Previous and next buttons for the bottom of `path`'s chapter.
+    /// Returns the chapter to go to.
+    pub fn show_pager(&self, ui: &mut egui::Ui, path: &Path) -> Option<PathBuf> {
+        let (previous, next) = self.neighbors(path);
+        let mut clicked = None;
+        ui.add_space(12.0);
+        ui.separator();
+        ui.horizontal(|ui| {
+            if let Some((title, page)) = previous
+                && ui.button(format!("← {title}")).clicked()
+            {
+                clicked = Some(page.to_path_buf());
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if let Some((title, page)) = next
+                    && ui.button(format!("{title} →")).clicked()
+                {
+                    clicked = Some(page.to_path_buf());
+                }
+            });
+        });
+        clicked
+    }
+}
+
+/// The entries of a `SUMMARY.md` whose links are relative to `src`. The
+/// title of the summary itself and `---` separators are left out.
+fn parse_summary(summary: &str, src: &Path) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut seen_title = false;
+    // Indentation of the open chapters each entry could be nested under
+    let mut indents: Vec<usize> = Vec::new();
+    for line in summary.lines() {
+        let trimmed = line.trim();
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            let heading = heading.trim_start_matches('#').trim();
+            if seen_title || !entries.is_empty() {
+                entries.push(Entry::Part(heading.to_string()));
+            }
+            seen_title = true;
+            indents.clear();
+            continue;
+        }
+        let item = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+            .unwrap_or(trimmed);
+        let Some((title, target)) = link(item) else {
+            continue;
+        };
+        let indent = line.len() - line.trim_start().len();
+        while indents.last().is_some_and(|&open| open >= indent) {
+            indents.pop();
+        }
+        let depth = indents.len();
+        indents.push(indent);
+        let target = target.split('#').next().unwrap_or_default();
+        let target = target.trim_start_matches("./");
+        entries.push(Entry::Chapter {
+            title: title.to_string(),
+            path: (!target.is_empty()).then(|| src.join(target)),
+            depth,
+        });
+    }
+    entries
+}
+
+/// The text and target of a `[text](target)` link making up `item`.
+fn link(item: &str) -> Option<(&str, &str)> {
+    let (title, rest) = item.strip_prefix('[')?.split_once("](")?;
+    let target = rest.strip_suffix(')')?.trim();
+    // Angle brackets allow spaces in the target
+    let target = target
+        .strip_prefix('<')
+        .and_then(|t| t.strip_suffix('>'))
+        .unwrap_or(target);
+    Some((title, target))
+}
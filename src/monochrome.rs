@@ -0,0 +1,107 @@
+//! Code blocks in black on white, with keywords in bold and comments in
+//! italics instead of colors, so printed handouts stay readable.
+
+use std::sync::OnceLock;
+
+use eframe::egui::{self, text::LayoutJob};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{FontStyle, StyleModifier, Theme, ThemeItem},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+const INK: egui::Color32 = egui::Color32::BLACK;
+const PAPER: egui::Color32 = egui::Color32::WHITE;
+
+/// How far bold text is printed a second time to thicken it.
+const OVERPRINT: f32 = 0.6;
+
+pub fn show(ui: &mut egui::Ui, code: &str, lang: Option<&str>) -> egui::Response {
+    let syntaxes = syntaxes();
+    let syntax = lang
+        .and_then(|lang| {
+            syntaxes
+                .find_syntax_by_extension(lang)
+                .or_else(|| syntaxes.find_syntax_by_token(lang))
+        })
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+
+    // Same text twice: everything, and only the bold parts to overprint
+    let mut text = LayoutJob::default();
+    let mut bold = LayoutJob::default();
+    let mut highlighter = HighlightLines::new(syntax, theme());
+    for line in LinesWithEndings::from(code) {
+        let ranges = highlighter
+            .highlight_line(line, syntaxes)
+            .unwrap_or_default();
+        for (style, piece) in ranges {
+            let format = egui::TextFormat {
+                font_id: font_id.clone(),
+                color: INK,
+                italics: style.font_style.contains(FontStyle::ITALIC),
+                ..Default::default()
+            };
+            let overprint = egui::TextFormat {
+                color: if style.font_style.contains(FontStyle::BOLD) {
+                    INK
+                } else {
+                    egui::Color32::TRANSPARENT
+                },
+                ..format.clone()
+            };
+            text.append(piece, 0.0, format);
+            bold.append(piece, 0.0, overprint);
+        }
+    }
+
+    egui::Frame::new()
+        .fill(PAPER)
+        .stroke(egui::Stroke::new(1.0, INK))
+        .corner_radius(3.0)
+        .inner_margin(8.0)
+        .show(ui, |ui| {
+            let width = ui.available_width();
+            text.wrap.max_width = width;
+            bold.wrap.max_width = width;
+            let text = ui.fonts(|fonts| fonts.layout_job(text));
+            let bold = ui.fonts(|fonts| fonts.layout_job(bold));
+
+            let (rect, _) = ui.allocate_exact_size(text.size(), egui::Sense::hover());
+            // egui has no bold monospace font to switch to
+            ui.painter().galley(rect.min, text, INK);
+            ui.painter()
+                .galley(rect.min + egui::vec2(OVERPRINT, 0.0), bold, INK);
+        })
+        .response
+}
+
+pub fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// A theme that only sets font styles.
+fn theme() -> &'static Theme {
+    static THEME: OnceLock<Theme> = OnceLock::new();
+    THEME.get_or_init(|| {
+        let item = |scope: &str, font_style| ThemeItem {
+            scope: scope.parse().expect("valid scope selector"),
+            style: StyleModifier {
+                font_style: Some(font_style),
+                ..Default::default()
+            },
+        };
+        Theme {
+            scopes: vec![
+                item("comment", FontStyle::ITALIC),
+                item(
+                    "keyword, storage, entity.name.function, entity.name.type",
+                    FontStyle::BOLD,
+                ),
+            ],
+            ..Default::default()
+        }
+    })
+}
@@ -0,0 +1,170 @@
+//! Recorded navigation: the places visited while recording — sections jumped
+//! to, searches, where scrolling stopped — revisited one per key press, for
+//! checking the same parts of a living document again and again.
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use eframe::egui;
+
+use crate::tab::DocTab;
+
+pub const RECORD_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F8);
+
+pub const REPLAY_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F9);
+
+/// How long scrolling has to stop before the spot counts as visited.
+const SCROLL_SETTLE: Duration = Duration::from_millis(600);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    /// Jumped to the section with this heading.
+    Heading(String),
+    /// Went to the `nth` match of a search.
+    Find { query: String, nth: usize },
+    /// Scrolled to `offset` below a heading, or below the top without one.
+    Scroll {
+        heading: Option<String>,
+        offset: f32,
+    },
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Heading(title) => write!(f, "section “{title}”"),
+            Step::Find { query, nth } => write!(f, "match {} of “{query}”", nth + 1),
+            Step::Scroll {
+                heading: Some(title),
+                ..
+            } => write!(f, "a spot in “{title}”"),
+            Step::Scroll { heading: None, .. } => write!(f, "a spot near the top"),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct NavMacro {
+    recording: bool,
+    steps: Vec<Step>,
+    /// Step the next replay goes to.
+    next: usize,
+    /// When the user last scrolled while recording.
+    scrolled: Option<Instant>,
+}
+
+impl NavMacro {
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Starts a new recording, replacing the old one, or stops the current one.
+    pub fn toggle_recording(&mut self) {
+        self.recording = !self.recording;
+        self.scrolled = None;
+        if self.recording {
+            self.steps.clear();
+        }
+        self.next = 0;
+    }
+
+    /// Adds `step` if recording. A search replaces the search just before
+    /// it, so typing and stepping through matches is one step.
+    pub fn record(&mut self, step: Step) {
+        if !self.recording {
+            return;
+        }
+        // A jump ends any scrolling just before it
+        self.scrolled = None;
+        match (self.steps.last_mut(), &step) {
+            (Some(last), _) if *last == step => {}
+            (Some(last @ Step::Find { .. }), Step::Find { .. }) => *last = step,
+            _ => self.steps.push(step),
+        }
+    }
+
+    /// Records where the user stops scrolling in `tab`. Called every frame
+    /// after the document is drawn.
+    pub fn watch_scrolling(&mut self, ctx: &egui::Context, tab: &DocTab) {
+        if !self.recording {
+            return;
+        }
+        let scrolling = ctx.input(|i| {
+            i.raw_scroll_delta != egui::Vec2::ZERO
+                || [
+                    egui::Key::PageUp,
+                    egui::Key::PageDown,
+                    egui::Key::ArrowUp,
+                    egui::Key::ArrowDown,
+                    egui::Key::Home,
+                    egui::Key::End,
+                ]
+                .into_iter()
+                .any(|key| i.key_pressed(key))
+        });
+        if scrolling {
+            self.scrolled = Some(Instant::now());
+        }
+        let Some(scrolled) = self.scrolled else {
+            return;
+        };
+        if scrolled.elapsed() < SCROLL_SETTLE {
+            ctx.request_repaint_after(SCROLL_SETTLE - scrolled.elapsed());
+            return;
+        }
+
+        let heading = tab.current_section().map(str::to_string);
+        let offset = match heading {
+            Some(_) => tab.heading_offset,
+            None => tab.scroll_offset,
+        };
+        self.record(Step::Scroll { heading, offset });
+    }
+
+    /// The step to replay, going round to the first after the last.
+    pub fn next_step(&mut self) -> Option<Step> {
+        let step = self.steps.get(self.next)?.clone();
+        self.next = (self.next + 1) % self.steps.len();
+        Some(step)
+    }
+}
+
+/// Goes to `step` in `tab`. Returns whether the place was found.
+pub fn replay(step: &Step, tab: &mut DocTab) -> bool {
+    match step {
+        Step::Heading(title) => {
+            tab.scroll_to_block = tab.heading_block(title);
+            tab.scroll_to_block.is_some()
+        }
+        Step::Find { query, nth } => {
+            let haystack = tab.markdown().unwrap_or_default().to_ascii_lowercase();
+            let needle = query.to_ascii_lowercase();
+            let found = haystack
+                .match_indices(&needle)
+                .nth(*nth)
+                .map(|(offset, _)| offset);
+            tab.scroll_to_block = found.and_then(|offset| tab.block_at(offset));
+            tab.scroll_to_block.is_some()
+        }
+        Step::Scroll { heading, offset } => {
+            let block = match heading {
+                Some(title) => tab.heading_block(title),
+                None => Some(0),
+            };
+            tab.restore_anchor = block.map(|idx| (idx, *offset));
+            tab.restore_anchor.is_some()
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! The command palette: every action of the menus, and the open tabs, by
+//! typing a few letters of their names.
+
+use eframe::egui;
+
+use crate::config::{Paper, Theme};
+use crate::export::Bundle;
+use crate::i18n::tr;
+
+pub const SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+    egui::Key::P,
+);
+
+/// Most commands listed at once.
+const MAX_MATCHES: usize = 50;
+
+/// What the window does when a command is picked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    OpenFiles,
+    NewScratch,
+    NewScratchFromClipboard,
+    OpenFolder,
+    OpenUrl,
+    QuickOpen,
+    Reload,
+    ReloadAll,
+    ShowReloadChanges,
+    CopyToc,
+    Export(Bundle),
+    ExportWithPandoc,
+    Preferences,
+    CloseTab,
+    ReopenClosedTab,
+    Find,
+    SearchAll,
+    Theme(Theme),
+    Paper(Paper),
+    ToggleToc,
+    ToggleBacklinks,
+    ToggleFigures,
+    ToggleTags,
+    ToggleLog,
+    ToggleAutoReload,
+    CollapseAll,
+    ExpandAll,
+    DiffHead,
+    Present,
+    /// Switch to the tab at this index.
+    GoToTab(usize),
+    CompareWith(usize),
+}
+
+#[derive(Default)]
+pub struct Palette {
+    pub open: bool,
+    query: String,
+    selected: usize,
+    focus_query: bool,
+}
+
+impl Palette {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.focus_query = true;
+    }
+
+    /// Draws the palette over `commands`, each with its name, if open.
+    /// Returns the command picked.
+    pub fn show(&mut self, ctx: &egui::Context, commands: &[(String, Command)]) -> Option<Command> {
+        if !self.open {
+            return None;
+        }
+
+        let mut picked = None;
+        let modal = egui::Modal::new(egui::Id::new("command_palette")).show(ctx, |ui| {
+            ui.set_width(420.0);
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.query)
+                    .desired_width(f32::INFINITY)
+                    .hint_text(tr("Command or tab name")),
+            );
+            if std::mem::take(&mut self.focus_query) {
+                response.request_focus();
+            }
+            if response.changed() {
+                self.selected = 0;
+            }
+
+            let matches = matches(commands, &self.query);
+            let (up, down, enter) = ui.input_mut(|i| {
+                (
+                    i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                    i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                    i.key_pressed(egui::Key::Enter),
+                )
+            });
+            if up {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            if down {
+                self.selected = (self.selected + 1).min(matches.len().saturating_sub(1));
+            }
+
+            ui.separator();
+            if matches.is_empty() {
+                ui.weak(tr("No matching commands"));
+            }
+            egui::ScrollArea::vertical()
+                .max_height(320.0)
+                .show(ui, |ui| {
+                    for (idx, (name, command)) in matches.iter().enumerate() {
+                        let selected = idx == self.selected;
+                        let row = ui.add(egui::Button::selectable(selected, name.as_str()));
+                        if selected && (up || down) {
+                            row.scroll_to_me(None);
+                        }
+                        if row.clicked() {
+                            picked = Some(*command);
+                        }
+                    }
+                });
+
+            if enter && let Some((_, command)) = matches.get(self.selected) {
+                picked = Some(*command);
+            }
+        });
+
+        if picked.is_some() || modal.should_close() {
+            self.open = false;
+        }
+        picked
+    }
+}
+
+/// The commands whose names hold the letters of `query` in order, best
+/// matches first and otherwise in the order given.
+fn matches<'a>(commands: &'a [(String, Command)], query: &str) -> Vec<&'a (String, Command)> {
+    let mut scored: Vec<(i32, &(String, Command))> = commands
+        .iter()
+        .filter_map(|entry| Some((score(query, &entry.0)?, entry)))
+        .collect();
+    scored.sort_by_key(|(score, _)| -score);
+    scored.truncate(MAX_MATCHES);
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// How well `query` matches `name`, ignoring case and spaces: higher for
+/// letters next to each other and at the starts of words, `None` if they
+/// aren't all in it in order.
+pub fn score(query: &str, name: &str) -> Option<i32> {
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut from = 0;
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let at = from + name[from..].iter().position(|&c| c == wanted)?;
+        if at > 0 && at == from {
+            score += 4;
+        }
+        if at == 0 || !name[at - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (at - from).min(4) as i32;
+        from = at + 1;
+    }
+    Some(score)
+}
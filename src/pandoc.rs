@@ -0,0 +1,332 @@
+//! Exporting through `pandoc`, for formats the HTML export doesn't cover.
+//! Offered only once the program set in Preferences answers, so nothing
+//! changes where it isn't installed.
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use eframe::egui;
+use rfd::FileDialog;
+
+use crate::events::AppEvent;
+use crate::i18n::{tr, tr_args};
+use crate::tab::DocTab;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Docx,
+    Odt,
+    Epub,
+}
+
+impl Target {
+    pub const ALL: [Target; 3] = [Target::Docx, Target::Odt, Target::Epub];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Target::Docx => tr("Word (.docx)"),
+            Target::Odt => tr("OpenDocument (.odt)"),
+            Target::Epub => tr("EPUB e-book (.epub)"),
+        }
+    }
+
+    /// Both pandoc's name for the format and the file extension.
+    fn name(self) -> &'static str {
+        match self {
+            Target::Docx => "docx",
+            Target::Odt => "odt",
+            Target::Epub => "epub",
+        }
+    }
+}
+
+enum Detection {
+    Unchecked,
+    /// Asking `program` for its version.
+    Checking {
+        program: String,
+        version: Receiver<Option<String>>,
+    },
+    /// The version `program` reported, `None` if it didn't run.
+    Checked {
+        program: String,
+        version: Option<String>,
+    },
+}
+
+/// The export dialog's choices, while it is open.
+struct Dialog {
+    title: String,
+    markdown: String,
+    /// Where images with relative paths are looked for.
+    dir: Option<PathBuf>,
+    target: Target,
+    out: String,
+}
+
+/// An export pandoc is working on.
+struct Running {
+    title: String,
+    out: PathBuf,
+    outcome: Receiver<Result<()>>,
+}
+
+pub struct Pandoc {
+    detection: Detection,
+    /// The format exported to last, picked again next time.
+    target: Target,
+    dialog: Option<Dialog>,
+    running: Option<Running>,
+}
+
+impl Default for Pandoc {
+    fn default() -> Self {
+        Self {
+            detection: Detection::Unchecked,
+            target: Target::Docx,
+            dialog: None,
+            running: None,
+        }
+    }
+}
+
+impl Pandoc {
+    /// The version of `program`, asking it in the background first. `None`
+    /// while that is going on or if it isn't installed.
+    pub fn version(&mut self, ctx: &egui::Context, program: &str) -> Option<&str> {
+        let current = match &self.detection {
+            Detection::Unchecked => false,
+            Detection::Checking { program: asked, .. }
+            | Detection::Checked { program: asked, .. } => asked == program,
+        };
+        if !current {
+            let (sender, version) = mpsc::channel();
+            let asked = program.to_string();
+            let ctx = ctx.clone();
+            thread::spawn(move || {
+                let _ = sender.send(detect(&asked));
+                ctx.request_repaint();
+            });
+            self.detection = Detection::Checking {
+                program: program.to_string(),
+                version,
+            };
+        }
+        if let Detection::Checking { program, version } = &self.detection {
+            let version = match version.try_recv() {
+                Ok(version) => version,
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => None,
+            };
+            self.detection = Detection::Checked {
+                program: program.clone(),
+                version,
+            };
+        }
+        match &self.detection {
+            Detection::Checked { version, .. } => version.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Opens the dialog for exporting `tab`.
+    pub fn start(&mut self, tab: &DocTab) -> Result<()> {
+        let dir = tab.dir().map(Path::to_path_buf);
+        let target = self.target;
+        let out = match &dir {
+            Some(dir) => dir.join(tab.path.file_stem().unwrap_or_default()),
+            None => PathBuf::from(&tab.title),
+        };
+        self.dialog = Some(Dialog {
+            title: tab.title.clone(),
+            markdown: tab
+                .markdown()
+                .with_context(|| format!("reading {}", tab.path.display()))?
+                .into_owned(),
+            dir,
+            target,
+            out: out.with_extension(target.name()).display().to_string(),
+        });
+        Ok(())
+    }
+
+    /// The export dialog, if open, and the outcome of a finished export.
+    pub fn show(&mut self, ctx: &egui::Context, program: &str) -> Option<AppEvent> {
+        let mut export = false;
+        let mut open = self.dialog.is_some();
+        if let Some(dialog) = &mut self.dialog {
+            egui::Window::new(tr("Export with Pandoc"))
+                .id(egui::Id::new("pandoc_export"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    egui::Grid::new("pandoc_export_grid")
+                        .num_columns(2)
+                        .spacing([12.0, 6.0])
+                        .show(ui, |ui| {
+                            ui.label(tr("Format"));
+                            let before = dialog.target;
+                            egui::ComboBox::from_id_salt("pandoc_target")
+                                .selected_text(dialog.target.label())
+                                .show_ui(ui, |ui| {
+                                    for target in Target::ALL {
+                                        ui.selectable_value(
+                                            &mut dialog.target,
+                                            target,
+                                            target.label(),
+                                        );
+                                    }
+                                });
+                            if dialog.target != before {
+                                let out = PathBuf::from(&dialog.out);
+                                dialog.out = out
+                                    .with_extension(dialog.target.name())
+                                    .display()
+                                    .to_string();
+                            }
+                            ui.end_row();
+
+                            ui.label(tr("Save to"));
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut dialog.out)
+                                        .desired_width(260.0),
+                                );
+                                if ui.button(tr("Choose…")).clicked()
+                                    && let Some(path) = FileDialog::new()
+                                        .add_filter(dialog.target.label(), &[dialog.target.name()])
+                                        .set_file_name(
+                                            Path::new(&dialog.out)
+                                                .file_name()
+                                                .unwrap_or_default()
+                                                .to_string_lossy(),
+                                        )
+                                        .save_file()
+                                {
+                                    dialog.out = path.display().to_string();
+                                }
+                            });
+                            ui.end_row();
+                        });
+                    ui.add_space(6.0);
+                    let ready = !dialog.out.trim().is_empty() && self.running.is_none();
+                    if ui
+                        .add_enabled(ready, egui::Button::new(tr("Export")))
+                        .clicked()
+                    {
+                        export = true;
+                    }
+                });
+        }
+        if export && let Some(dialog) = self.dialog.take() {
+            self.target = dialog.target;
+            self.run(ctx, program, dialog);
+        } else if !open {
+            self.dialog = None;
+        }
+        self.poll()
+    }
+
+    /// What the status bar shows while an export is going on.
+    pub fn show_progress(&self, ui: &mut egui::Ui) {
+        if let Some(running) = &self.running {
+            ui.spinner();
+            ui.weak(tr_args(
+                "Exporting {title} with pandoc…",
+                &[("title", &running.title)],
+            ));
+        }
+    }
+
+    fn run(&mut self, ctx: &egui::Context, program: &str, dialog: Dialog) {
+        let (sender, outcome) = mpsc::channel();
+        let out = PathBuf::from(dialog.out.trim());
+        let program = program.to_string();
+        let ctx = ctx.clone();
+        let (markdown, dir, target) = (dialog.markdown, dialog.dir, dialog.target);
+        let to = out.clone();
+        thread::spawn(move || {
+            let _ = sender.send(convert(&program, &markdown, dir.as_deref(), target, &to));
+            ctx.request_repaint();
+        });
+        self.running = Some(Running {
+            title: dialog.title,
+            out,
+            outcome,
+        });
+    }
+
+    fn poll(&mut self) -> Option<AppEvent> {
+        let running = self.running.as_ref()?;
+        let outcome = match running.outcome.try_recv() {
+            Ok(outcome) => outcome,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => Err(anyhow!("pandoc stopped")),
+        };
+        let running = self.running.take()?;
+        Some(match outcome {
+            Ok(()) => AppEvent::Exported { path: running.out },
+            Err(e) => AppEvent::ExportFailed {
+                error: format!("{e:#}"),
+            },
+        })
+    }
+}
+
+/// The version `program --version` reports, if it runs.
+fn detect(program: &str) -> Option<String> {
+    let output = Command::new(program).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    Some(text.lines().next().unwrap_or("pandoc").trim().to_string())
+}
+
+/// Has pandoc write `markdown` to `out` as `target`.
+fn convert(
+    program: &str,
+    markdown: &str,
+    dir: Option<&Path>,
+    target: Target,
+    out: &Path,
+) -> Result<()> {
+    let mut command = Command::new(program);
+    command
+        .args(["--from", "markdown", "--to", target.name(), "--standalone"])
+        .arg("--output")
+        .arg(out);
+    if let Some(dir) = dir {
+        command.arg("--resource-path").arg(dir);
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("starting {program}"))?;
+    // Dropped once written, so pandoc sees the end of its input
+    child
+        .stdin
+        .take()
+        .context("no input to pandoc")?
+        .write_all(markdown.as_bytes())
+        .context("handing the document to pandoc")?;
+    let output = child.wait_with_output().context("waiting for pandoc")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "pandoc {}: {}",
+            output.status,
+            stderr.lines().next().unwrap_or("no message").trim()
+        );
+    }
+    Ok(())
+}
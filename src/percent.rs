@@ -0,0 +1,109 @@
+//! `%xx` escapes in the paths of links and URLs.
+
+use std::path::Path;
+
+/// `text` without its `%xx` escapes. Anything that isn't one, such as a lone
+/// `%`, is kept as written.
+pub fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        // Two hex digits, not a sign, which `from_str_radix` would take too
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// `path` as a `file://` URL, with what URLs can't have in a path escaped.
+pub fn file_url(path: &Path) -> String {
+    to_url(&path.to_string_lossy(), cfg!(windows))
+}
+
+fn to_url(path: &str, windows: bool) -> String {
+    let path = if windows {
+        // Without the prefix for long paths, and with the slashes of URLs
+        let path = match path.strip_prefix(r"\\?\UNC\") {
+            Some(share) => format!(r"\\{share}"),
+            None => path.strip_prefix(r"\\?\").unwrap_or(path).to_string(),
+        };
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    };
+    // A share on a server names it where the host goes, a drive comes after
+    // the slash for the root
+    let mut url = String::from(if path.starts_with("//") {
+        "file:"
+    } else if path.starts_with('/') {
+        "file://"
+    } else {
+        "file:///"
+    });
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_are_undone() {
+        assert_eq!(decode("My%20Notes+2.md"), "My Notes+2.md");
+        assert_eq!(decode("caf%C3%A9"), "café");
+        assert_eq!(decode("%2Fetc%2fhosts"), "/etc/hosts");
+        // Not escapes, so kept as written
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz%4"), "%zz%4");
+        assert_eq!(decode("%+1"), "%+1");
+        assert_eq!(decode("%FF"), "\u{FFFD}");
+    }
+    #[test]
+    fn paths_become_file_urls() {
+        assert_eq!(
+            to_url("/home/me/My Notes/café#1.html", false),
+            "file:///home/me/My%20Notes/caf%C3%A9%231.html"
+        );
+        assert_eq!(
+            to_url(r"/tmp/a\b%.html", false),
+            "file:///tmp/a%5Cb%25.html"
+        );
+        assert_eq!(
+            to_url(r"C:\Users\me\a b.html", true),
+            "file:///C:/Users/me/a%20b.html"
+        );
+        assert_eq!(
+            to_url(r"\\?\C:\Users\me\a.html", true),
+            "file:///C:/Users/me/a.html"
+        );
+        assert_eq!(
+            to_url(r"\\server\share\a.html", true),
+            "file://server/share/a.html"
+        );
+        assert_eq!(
+            to_url(r"\\?\UNC\server\share\a.html", true),
+            "file://server/share/a.html"
+        );
+    }
+}
@@ -0,0 +1,690 @@
+//! The Preferences window. Changes apply right away and are saved to the
+//! config file by the app.
+
+use std::path::PathBuf;
+
+use eframe::egui;
+
+use rfd::FileDialog;
+
+use crate::config::{
+    self, BodyFont, CODE_THEMES, ColumnGuide, CustomFont, Dialect, FontRole, Language, LinkAction,
+    LinkHandler, LoginKind, Paper, Settings, SiteLogin, Startup, Theme, UI_SCALES, WebLinks,
+};
+use crate::events::AppEvent;
+use crate::fonts;
+use crate::i18n::{tr, tr_args};
+use crate::uri_scheme;
+
+/// Draws the window if `open`. Returns what came of an action taken in it.
+pub fn show(ctx: &egui::Context, open: &mut bool, settings: &mut Settings) -> Option<AppEvent> {
+    let mut event = None;
+    egui::Window::new(tr("Preferences"))
+        .id(egui::Id::new("preferences"))
+        .open(open)
+        .resizable(false)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            ui.heading(tr("Appearance"));
+            egui::Grid::new("prefs_appearance")
+                .num_columns(2)
+                .spacing([12.0, 6.0])
+                .show(ui, |ui| {
+                    ui.label(tr("Language"));
+                    egui::ComboBox::from_id_salt("prefs_language")
+                        .selected_text(settings.language.label())
+                        .show_ui(ui, |ui| {
+                            for language in Language::ALL {
+                                ui.selectable_value(
+                                    &mut settings.language,
+                                    language,
+                                    language.label(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label(tr("Theme"));
+                    egui::ComboBox::from_id_salt("prefs_theme")
+                        .selected_text(settings.theme.label())
+                        .show_ui(ui, |ui| {
+                            for theme in Theme::ALL {
+                                ui.selectable_value(&mut settings.theme, theme, theme.label());
+                            }
+                        });
+                    ui.end_row();
+
+                    if settings.theme == Theme::Scheduled {
+                        ui.label(tr("Light from"));
+                        ui.horizontal(|ui| {
+                            hour_choice(ui, &mut settings.light_from);
+                            ui.label(tr("dark from"));
+                            hour_choice(ui, &mut settings.dark_from);
+                        });
+                        ui.end_row();
+                    }
+
+                    ui.label(tr("Reading surface"));
+                    egui::ComboBox::from_id_salt("prefs_paper")
+                        .selected_text(settings.paper.label())
+                        .show_ui(ui, |ui| {
+                            for paper in Paper::ALL {
+                                ui.selectable_value(&mut settings.paper, paper, paper.label());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label(tr("UI scale"));
+                    ui_scale_choice(ui, &mut settings.ui_scale);
+                    ui.end_row();
+
+                    ui.label(tr("Document text size"));
+                    ui.add(
+                        egui::Slider::new(
+                            &mut settings.md_text_scale,
+                            config::MIN_TEXT_SCALE..=config::MAX_TEXT_SCALE,
+                        )
+                        .logarithmic(true)
+                        .custom_formatter(|scale, _| format!("{:.0}%", scale * 100.0)),
+                    );
+                    ui.end_row();
+
+                    ui.label("");
+                    ui.checkbox(&mut settings.lock_zoom, tr("Lock zoom"))
+                        .on_hover_text(tr("Ignore Ctrl+wheel, Ctrl+Plus/Minus and A–/A+"));
+                    ui.end_row();
+
+                    ui.label(tr("Font"));
+                    egui::ComboBox::from_id_salt("prefs_font")
+                        .selected_text(settings.font.label())
+                        .show_ui(ui, |ui| {
+                            for font in BodyFont::ALL {
+                                ui.selectable_value(&mut settings.font, font, font.label());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label(tr("Code theme (dark)"));
+                    code_theme_choice(ui, "prefs_code_dark", &mut settings.code_theme_dark);
+                    ui.end_row();
+
+                    ui.label(tr("Code theme (light)"));
+                    code_theme_choice(ui, "prefs_code_light", &mut settings.code_theme_light);
+                    ui.end_row();
+
+                    ui.label(tr("Window title"));
+                    ui.checkbox(&mut settings.path_in_title, tr("Show the full path"))
+                        .on_hover_text(tr(
+                            "Name the document by its whole path, not just its file name",
+                        ));
+                    ui.end_row();
+                });
+
+            ui.add_space(8.0);
+            ui.heading(tr("Fonts"));
+            font_list(ui, &mut settings.fonts);
+
+            ui.add_space(8.0);
+            ui.heading(tr("Documents"));
+            ui.checkbox(
+                &mut settings.guess_code_languages,
+                tr("Guess code block languages"),
+            );
+            ui.checkbox(&mut settings.monochrome_code, tr("Monochrome code blocks"))
+                .on_hover_text(tr("Black on white with bold and italics, for printing"));
+            ui.checkbox(
+                &mut settings.code_line_numbers,
+                tr("Number lines of code blocks"),
+            );
+            ui.checkbox(&mut settings.wrap_code, tr("Wrap long lines of code"))
+                .on_hover_text(tr(
+                    "Otherwise they scroll sideways; either can be toggled on a block while hovering it",
+                ));
+            ui.checkbox(
+                &mut settings.diff_side_by_side,
+                tr("Side-by-side diff blocks"),
+            )
+            .on_hover_text(tr(
+                "Old and new text of diff code blocks next to each other",
+            ));
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.limit_text_width, tr("Limit text width to"));
+                ui.add_enabled(
+                    settings.limit_text_width,
+                    egui::DragValue::new(&mut settings.text_width)
+                        .range(config::MIN_TEXT_WIDTH..=config::MAX_TEXT_WIDTH)
+                        .suffix(tr(" characters")),
+                );
+            })
+            .response
+            .on_hover_text(tr(
+                "Center the document in a column instead of filling the window",
+            ));
+            ui.horizontal(|ui| {
+                ui.label(tr("Margins"));
+                ui.add(
+                    egui::Slider::new(&mut settings.margin, 0.0..=200.0)
+                        .suffix(" pt")
+                        .step_by(4.0),
+                );
+            });
+            ui.checkbox(
+                &mut settings.document_hints,
+                tr("Follow reading hints in frontmatter"),
+            )
+            .on_hover_text(tr(
+                "Documents may ask for a theme, text width or text size with theme:, width: and font_scale: keys",
+            ));
+            ui.checkbox(&mut settings.justify_text, tr("Justify paragraphs"));
+            ui.add_enabled(
+                settings.justify_text,
+                egui::Checkbox::new(&mut settings.hyphenate, tr("Hyphenate")),
+            );
+            ui.checkbox(&mut settings.link_previews, tr("Preview links on hover"));
+            ui.checkbox(&mut settings.vim_keys, tr("Scroll with vim keys"))
+                .on_hover_text(tr(
+                    "j/k by lines, d/u by half pages, gg/G to the top or bottom",
+                ));
+            ui.horizontal(|ui| {
+                ui.label(tr("Source view"));
+                egui::ComboBox::from_id_salt("prefs_column_guide")
+                    .selected_text(settings.column_guide.label())
+                    .show_ui(ui, |ui| {
+                        for guide in ColumnGuide::ALL {
+                            ui.selectable_value(&mut settings.column_guide, guide, guide.label());
+                        }
+                    });
+            });
+            ui.checkbox(
+                &mut settings.load_remote_images,
+                tr("Load images from the web"),
+            )
+            .on_hover_text(tr("Off, images with http(s) links aren't fetched"));
+            ui.checkbox(&mut settings.animate_images, tr("Play animated images"))
+                .on_hover_text(tr("Off, only the first frame of GIFs and animated PNGs is shown"));
+            ui.horizontal(|ui| {
+                ui.label(tr("Keep images in memory up to"));
+                ui.add(
+                    egui::DragValue::new(&mut settings.image_cache_mb)
+                        .range(32..=8192)
+                        .suffix(" MB"),
+                );
+            })
+            .response
+            .on_hover_text(tr(
+                "Beyond it, the images shown longest ago are let go of and loaded again when needed",
+            ));
+            ui.horizontal(|ui| {
+                ui.label(tr("Load files in chunks above"));
+                ui.add(
+                    egui::DragValue::new(&mut settings.load_in_chunks_above_mb)
+                        .range(1..=1024)
+                        .suffix(" MB"),
+                );
+            })
+            .response
+            .on_hover_text(tr(
+                "Larger files show their beginning straight away, and the rest as it's read",
+            ));
+            ui.horizontal(|ui| {
+                ui.checkbox(
+                    &mut settings.unload_idle_tabs,
+                    tr("Free the memory of tabs not looked at for"),
+                );
+                ui.add_enabled(
+                    settings.unload_idle_tabs,
+                    egui::DragValue::new(&mut settings.unload_tabs_after_mins)
+                        .range(1..=1440)
+                        .suffix(" min"),
+                );
+            })
+            .response
+            .on_hover_text(tr("They're prepared again when you go back to them"));
+            ui.add_enabled(
+                settings.unload_idle_tabs,
+                egui::Checkbox::new(
+                    &mut settings.unload_tab_contents,
+                    tr("Their file contents too, read again from disk"),
+                ),
+            );
+
+            ui.add_space(8.0);
+            ui.heading(tr("Markdown Dialect"));
+            ui.horizontal(|ui| {
+                ui.label(tr("Read documents as"));
+                egui::ComboBox::from_id_salt("prefs_dialect")
+                    .selected_text(settings.dialect.label())
+                    .show_ui(ui, |ui| {
+                        for dialect in Dialect::presets() {
+                            ui.selectable_value(&mut settings.dialect, dialect, dialect.label());
+                        }
+                    });
+            });
+            extension_choice(ui, &mut settings.dialect);
+
+            ui.add_space(8.0);
+            ui.heading(tr("Link Schemes"));
+            link_handler_list(ui, &mut settings.link_handlers);
+            if ui
+                .button(tr("Open mdviewer:// Links Here"))
+                .on_hover_text(tr(
+                    "For other apps to link to documents and their sections, as mdviewer://open?path=…&anchor=…",
+                ))
+                .clicked()
+            {
+                event = Some(match uri_scheme::register() {
+                    Ok(()) => AppEvent::LinksRegistered,
+                    Err(e) => AppEvent::LinkRegistrationFailed {
+                        error: format!("{e:#}"),
+                    },
+                });
+            }
+
+            ui.add_space(8.0);
+            ui.heading(tr("Web Links"));
+            web_link_choice(ui, settings);
+
+            ui.add_space(8.0);
+            ui.heading(tr("Site Logins"));
+            site_login_list(ui, &mut settings.site_logins);
+
+            ui.add_space(8.0);
+            ui.heading(tr("Diagrams"));
+            ui.checkbox(
+                &mut settings.render_diagrams,
+                tr("Draw PlantUML and Graphviz fences as diagrams"),
+            );
+            ui.add_enabled_ui(settings.render_diagrams, |ui| {
+                egui::Grid::new("prefs_diagrams")
+                    .num_columns(2)
+                    .spacing([12.0, 6.0])
+                    .show(ui, |ui| {
+                        ui.label(tr("PlantUML command"));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut settings.plantuml_command)
+                                .hint_text("java -jar plantuml.jar")
+                                .desired_width(200.0),
+                        );
+                        ui.end_row();
+                        ui.label(tr("Graphviz command"));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut settings.graphviz_command)
+                                .desired_width(200.0),
+                        );
+                        ui.end_row();
+                    });
+            });
+
+            ui.add_space(8.0);
+            ui.heading(tr("Trusted Folders"));
+            trusted_folder_list(ui, &mut settings.trusted_folders);
+
+            ui.add_space(8.0);
+            ui.heading(tr("Export"));
+            ui.horizontal(|ui| {
+                ui.label(tr("Pandoc program"));
+                ui.add(egui::TextEdit::singleline(&mut settings.pandoc_path).desired_width(200.0))
+                    .on_hover_text(tr(
+                        "For exports to Word, OpenDocument and EPUB, if installed",
+                    ));
+            });
+
+            ui.add_space(8.0);
+            ui.heading(tr("Reloading"));
+            ui.checkbox(
+                &mut settings.auto_reload,
+                tr("Reload files when they change"),
+            );
+            ui.add_enabled_ui(settings.auto_reload, |ui| {
+                ui.checkbox(
+                    &mut settings.pause_reload_while_reading,
+                    tr("Pause updates while reading"),
+                );
+            });
+            // With reloading off, changes found are offered instead
+            ui.horizontal(|ui| {
+                ui.checkbox(
+                    &mut settings.hash_change_detection,
+                    tr("Compare file contents"),
+                );
+                ui.add_enabled(
+                    settings.hash_change_detection,
+                    egui::DragValue::new(&mut settings.hash_poll_secs)
+                        .range(1..=600)
+                        .prefix(tr("every "))
+                        .suffix(" s"),
+                );
+            });
+
+            ui.add_space(8.0);
+            ui.heading(tr("Startup"));
+            for startup in Startup::ALL {
+                ui.radio_value(&mut settings.startup, startup, startup.label());
+            }
+
+            ui.checkbox(&mut settings.check_for_updates, tr("Check for updates"))
+                .on_hover_text(tr(
+                    "Ask GitHub for newer releases at startup; nothing else is sent",
+                ));
+
+            ui.add_space(8.0);
+            ui.separator();
+            ui.horizontal(|ui| {
+                if let Some(path) = config::path() {
+                    ui.weak(tr_args("Saved to {file}", &[("file", &path.display())]));
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button(tr("Restore Defaults")).clicked() {
+                        // Folder excludes are edited in the folder panel, and
+                        // the window geometry and recent files aren't
+                        // preferences; keep them
+                        let exclude_patterns = std::mem::take(&mut settings.exclude_patterns);
+                        let recent_files = std::mem::take(&mut settings.recent_files);
+                        *settings = Settings {
+                            exclude_patterns,
+                            window: settings.window,
+                            recent_files,
+                            ..Settings::default()
+                        };
+                    }
+                });
+            });
+        });
+    event
+}
+
+/// The added fonts, and buttons to add more from a file or the system.
+fn font_list(ui: &mut egui::Ui, fonts: &mut Vec<CustomFont>) {
+    ui.weak(tr(
+        "Add fonts for text the built-in ones can't show, like Chinese, Japanese, Korean or emoji.",
+    ));
+    let mut remove = None;
+    egui::Grid::new("prefs_fonts")
+        .num_columns(3)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            for (idx, font) in fonts.iter_mut().enumerate() {
+                ui.label(fonts::name(&font.path))
+                    .on_hover_text(font.path.display().to_string());
+                egui::ComboBox::from_id_salt(("prefs_font_role", idx))
+                    .selected_text(font.role.label())
+                    .show_ui(ui, |ui| {
+                        for role in FontRole::ALL {
+                            ui.selectable_value(&mut font.role, role, role.label());
+                        }
+                    });
+                if ui
+                    .small_button("×")
+                    .on_hover_text(tr("Remove font"))
+                    .clicked()
+                {
+                    remove = Some(idx);
+                }
+                ui.end_row();
+            }
+        });
+    if let Some(idx) = remove {
+        fonts.remove(idx);
+    }
+
+    let mut added = None;
+    ui.horizontal(|ui| {
+        if ui.button(tr("Add Font File…")).clicked() {
+            added = FileDialog::new()
+                .add_filter(tr("Fonts"), &["ttf", "otf", "ttc", "otc"])
+                .set_title(tr("Add font"))
+                .pick_file();
+        }
+        egui::ComboBox::from_id_salt("prefs_system_font")
+            .selected_text(tr("Add Installed Font"))
+            .height(320.0)
+            .show_ui(ui, |ui| {
+                let system = fonts::system_fonts();
+                if system.is_empty() {
+                    ui.weak(tr("No fonts found"));
+                }
+                for path in system {
+                    if ui.selectable_label(false, fonts::name(path)).clicked() {
+                        added = Some(path.clone());
+                    }
+                }
+            });
+    });
+    if let Some(path) = added
+        && !fonts.iter().any(|font| font.path == path)
+    {
+        fonts.push(CustomFont {
+            path,
+            role: FontRole::Fallback,
+        });
+    }
+}
+
+/// The URL schemes with a handler, and a button to add another.
+fn link_handler_list(ui: &mut egui::Ui, handlers: &mut Vec<LinkHandler>) {
+    ui.weak(tr(
+        "For links like jira:ABC-123. {url} is the link, {target} what follows the scheme.",
+    ));
+    let mut remove = None;
+    egui::Grid::new("prefs_link_handlers")
+        .num_columns(4)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            for (idx, handler) in handlers.iter_mut().enumerate() {
+                ui.add(
+                    egui::TextEdit::singleline(&mut handler.scheme)
+                        .hint_text(tr("scheme"))
+                        .desired_width(70.0),
+                );
+                egui::ComboBox::from_id_salt(("prefs_link_action", idx))
+                    .selected_text(handler.action.label())
+                    .show_ui(ui, |ui| {
+                        for action in LinkAction::ALL {
+                            ui.selectable_value(&mut handler.action, action, action.label());
+                        }
+                    });
+                let hint = match handler.action {
+                    LinkAction::OpenExternally => "",
+                    LinkAction::Rewrite => "https://jira.example.com/browse/{target}",
+                    LinkAction::RunCommand => "zoom-client --join {url}",
+                };
+                ui.add_enabled(
+                    handler.action != LinkAction::OpenExternally,
+                    egui::TextEdit::singleline(&mut handler.template)
+                        .hint_text(hint)
+                        .desired_width(240.0),
+                );
+                if ui
+                    .small_button("×")
+                    .on_hover_text(tr("Remove scheme"))
+                    .clicked()
+                {
+                    remove = Some(idx);
+                }
+                ui.end_row();
+            }
+        });
+    if let Some(idx) = remove {
+        handlers.remove(idx);
+    }
+    if ui.button(tr("Add Scheme")).clicked() {
+        handlers.push(LinkHandler {
+            scheme: String::new(),
+            action: LinkAction::Rewrite,
+            template: String::new(),
+        });
+    }
+}
+
+/// What links leaving the viewer do, and the sites opened without asking.
+fn web_link_choice(ui: &mut egui::Ui, settings: &mut Settings) {
+    ui.horizontal(|ui| {
+        ui.label(tr("Clicking a link that leaves the viewer"));
+        egui::ComboBox::from_id_salt("prefs_web_links")
+            .selected_text(settings.web_links.label())
+            .show_ui(ui, |ui| {
+                for choice in WebLinks::ALL {
+                    ui.selectable_value(&mut settings.web_links, choice, choice.label());
+                }
+            });
+    });
+    if settings.web_links != WebLinks::Ask {
+        return;
+    }
+    let mut remove = None;
+    for (idx, site) in settings.trusted_sites.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(site)
+                    .hint_text(tr("example.com"))
+                    .desired_width(240.0),
+            );
+            if ui
+                .small_button("×")
+                .on_hover_text(tr("Remove site"))
+                .clicked()
+            {
+                remove = Some(idx);
+            }
+        });
+    }
+    if let Some(idx) = remove {
+        settings.trusted_sites.remove(idx);
+    }
+    if ui
+        .button(tr("Add Trusted Site"))
+        .on_hover_text(tr("Its links, and its subdomains', open without asking"))
+        .clicked()
+    {
+        settings.trusted_sites.push(String::new());
+    }
+}
+
+/// The folders whose documents may run git and the diagram programs, each
+/// with a button to stop trusting it.
+fn trusted_folder_list(ui: &mut egui::Ui, folders: &mut Vec<PathBuf>) {
+    ui.weak(tr(
+        "Their documents show their git status and draw their diagrams. Folders are asked about when they're opened.",
+    ));
+    let mut remove = None;
+    for (idx, folder) in folders.iter().enumerate() {
+        ui.horizontal(|ui| {
+            ui.monospace(folder.display().to_string());
+            if ui
+                .small_button("×")
+                .on_hover_text(tr("Stop trusting this folder"))
+                .clicked()
+            {
+                remove = Some(idx);
+            }
+        });
+    }
+    if let Some(idx) = remove {
+        folders.remove(idx);
+    }
+}
+
+/// The logins sent to sites when opening documents from a URL, and a
+/// button to add another.
+fn site_login_list(ui: &mut egui::Ui, logins: &mut Vec<SiteLogin>) {
+    ui.weak(tr(
+        "For private documents opened from a URL. Only sent over HTTPS, and kept in the preferences file, which only you can read.",
+    ));
+    let mut remove = None;
+    egui::Grid::new("prefs_site_logins")
+        .num_columns(5)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            for (idx, login) in logins.iter_mut().enumerate() {
+                ui.add(
+                    egui::TextEdit::singleline(&mut login.site)
+                        .hint_text(tr("github.com"))
+                        .desired_width(140.0),
+                );
+                egui::ComboBox::from_id_salt(("prefs_login_kind", idx))
+                    .selected_text(login.kind.label())
+                    .show_ui(ui, |ui| {
+                        for kind in LoginKind::ALL {
+                            ui.selectable_value(&mut login.kind, kind, kind.label());
+                        }
+                    });
+                ui.add_enabled(
+                    login.kind == LoginKind::Basic,
+                    egui::TextEdit::singleline(&mut login.user)
+                        .hint_text(tr("user"))
+                        .desired_width(90.0),
+                );
+                let hint = match login.kind {
+                    LoginKind::Bearer => tr("token"),
+                    LoginKind::Basic => tr("password"),
+                };
+                ui.add(
+                    egui::TextEdit::singleline(&mut login.secret)
+                        .password(true)
+                        .hint_text(hint)
+                        .desired_width(160.0),
+                );
+                if ui
+                    .small_button("×")
+                    .on_hover_text(tr("Remove login"))
+                    .clicked()
+                {
+                    remove = Some(idx);
+                }
+                ui.end_row();
+            }
+        });
+    if let Some(idx) = remove {
+        logins.remove(idx);
+    }
+    if ui.button(tr("Add Login")).clicked() {
+        logins.push(SiteLogin {
+            site: String::new(),
+            kind: LoginKind::Bearer,
+            user: String::new(),
+            secret: String::new(),
+        });
+    }
+}
+
+/// A checkbox for each extension of `dialect`. Returns whether one was
+/// clicked.
+pub fn extension_choice(ui: &mut egui::Ui, dialect: &mut Dialect) -> bool {
+    let mut changed = false;
+    for (on, name) in dialect.extensions() {
+        changed |= ui.checkbox(on, name).changed();
+    }
+    changed
+}
+
+fn code_theme_choice(ui: &mut egui::Ui, id: &str, theme: &mut String) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(theme.as_str())
+        .show_ui(ui, |ui| {
+            for name in CODE_THEMES {
+                if ui.selectable_label(theme == name, name).clicked() {
+                    *theme = name.to_string();
+                }
+            }
+        });
+}
+
+fn ui_scale_choice(ui: &mut egui::Ui, scale: &mut f32) {
+    egui::ComboBox::from_id_salt("prefs_ui_scale")
+        .selected_text(config::ui_scale_label(*scale))
+        .show_ui(ui, |ui| {
+            for choice in UI_SCALES {
+                ui.selectable_value(scale, choice, config::ui_scale_label(choice));
+            }
+        });
+}
+
+/// An hour of the day, shown as its time.
+fn hour_choice(ui: &mut egui::Ui, hour: &mut u32) {
+    ui.add(
+        egui::DragValue::new(hour)
+            .range(0..=23)
+            .custom_formatter(|hour, _| format!("{hour:02.0}:00"))
+            .custom_parser(|text| text.split(':').next()?.trim().parse().ok()),
+    );
+}
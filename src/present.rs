@@ -0,0 +1,173 @@
+//! Presentation mode: the active document split into slides on thematic breaks.
+
+use eframe::egui;
+use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
+use pulldown_cmark::{Event, Parser, TagEnd};
+
+use crate::blocks;
+use crate::i18n::{tr, tr_args};
+
+/// Text size multiplier applied on top of the regular markdown scale.
+const SLIDE_TEXT_SCALE: f32 = 1.6;
+
+pub struct Presentation {
+    title: String,
+    slides: Vec<String>,
+    current: usize,
+}
+
+/// What the presentation wants the app to do after a frame.
+#[derive(PartialEq, Eq)]
+pub enum Outcome {
+    Continue,
+    Exit,
+}
+
+impl Presentation {
+    pub fn new(title: &str, source: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            slides: split_slides(source),
+            current: 0,
+        }
+    }
+
+    fn next(&mut self) {
+        self.current = (self.current + 1).min(self.slides.len().saturating_sub(1));
+    }
+
+    fn prev(&mut self) {
+        self.current = self.current.saturating_sub(1);
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        cache: &mut CommonMarkCache,
+        text_scale: f32,
+    ) -> Outcome {
+        let (forward, back, exit) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowRight)
+                    || i.key_pressed(egui::Key::ArrowDown)
+                    || i.key_pressed(egui::Key::Space)
+                    || i.key_pressed(egui::Key::PageDown),
+                i.key_pressed(egui::Key::ArrowLeft)
+                    || i.key_pressed(egui::Key::ArrowUp)
+                    || i.key_pressed(egui::Key::Backspace)
+                    || i.key_pressed(egui::Key::PageUp),
+                i.key_pressed(egui::Key::Escape),
+            )
+        });
+        let mut outcome = if exit {
+            Outcome::Exit
+        } else {
+            Outcome::Continue
+        };
+        if forward {
+            self.next();
+        }
+        if back {
+            self.prev();
+        }
+
+        egui::TopBottomPanel::bottom("slide_counter").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(tr_args(
+                    "{title}  —  slide {slide} / {slides}",
+                    &[
+                        ("title", &self.title),
+                        ("slide", &(self.current + 1)),
+                        ("slides", &self.slides.len()),
+                    ],
+                ));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button(tr("Exit (Esc)")).clicked() {
+                        outcome = Outcome::Exit;
+                    }
+                    if ui.button("▶").clicked() {
+                        self.next();
+                    }
+                    if ui.button("◀").clicked() {
+                        self.prev();
+                    }
+                });
+            });
+        });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // Registered before the slide content so links and code blocks on top
+            // still get their own clicks.
+            let background = ui.interact(
+                ui.max_rect(),
+                ui.id().with("slide_background"),
+                egui::Sense::click(),
+            );
+            if background.clicked() {
+                self.next();
+            } else if background.secondary_clicked() {
+                self.prev();
+            }
+
+            egui::ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .show(ui, |ui| {
+                    let margin = ui.available_width() * 0.08;
+                    ui.add_space(margin * 0.5);
+                    ui.horizontal(|ui| {
+                        ui.add_space(margin);
+                        ui.vertical(|ui| {
+                            ui.set_max_width(ui.available_width() - margin);
+                            let style = ui.style_mut();
+                            for font_id in style.text_styles.values_mut() {
+                                font_id.size *= text_scale * SLIDE_TEXT_SCALE;
+                            }
+                            if let Some(slide) = self.slides.get(self.current) {
+                                CommonMarkViewer::new().show(ui, cache, slide);
+                            }
+                        });
+                    });
+                });
+        });
+
+        outcome
+    }
+}
+
+/// Splits `source` on top-level thematic breaks (`---`, `***`, `___`).
+fn split_slides(source: &str) -> Vec<String> {
+    let parser = Parser::new_ext(source, blocks::parser_options());
+    let ref_defs = blocks::reference_definitions(&parser, source);
+
+    let mut slides = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    for (event, span) in parser.into_offset_iter() {
+        match event {
+            Event::Rule if depth == 0 => {
+                push_slide(&mut slides, &source[start..span.start], &ref_defs);
+                start = span.end;
+            }
+            Event::Start(_) => depth += 1,
+            Event::End(tag) => {
+                depth = depth.saturating_sub(1);
+                // Frontmatter isn't part of any slide
+                if depth == 0 && matches!(tag, TagEnd::MetadataBlock(_)) {
+                    start = span.end;
+                }
+            }
+            _ => {}
+        }
+    }
+    push_slide(&mut slides, &source[start..], &ref_defs);
+
+    if slides.is_empty() {
+        slides.push(String::new());
+    }
+    slides
+}
+
+fn push_slide(slides: &mut Vec<String>, text: &str, ref_defs: &str) {
+    if !text.trim().is_empty() {
+        slides.push(format!("{text}\n{ref_defs}"));
+    }
+}
@@ -0,0 +1,183 @@
+//! The quick-open palette: jump to a document of the open folder, or one
+//! opened lately, by typing some letters of its path or of one of its
+//! frontmatter aliases.
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+
+use crate::i18n::tr;
+use crate::palette;
+use crate::workspace::Workspace;
+
+/// Most matches listed at once.
+const MAX_MATCHES: usize = 50;
+
+#[derive(Default)]
+pub struct QuickOpen {
+    pub open: bool,
+    query: String,
+    selected: usize,
+    focus_query: bool,
+}
+
+/// A document the query matched, by path or by one of its aliases.
+struct Match<'a> {
+    path: &'a Path,
+    label: String,
+    alias: Option<&'a str>,
+    /// Opened lately, rather than found in the folder.
+    recent: bool,
+}
+
+impl QuickOpen {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.focus_query = true;
+    }
+
+    /// Draws the palette if open; returns the document picked.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        workspace: Option<&Workspace>,
+        recent: &[PathBuf],
+    ) -> Option<PathBuf> {
+        if !self.open {
+            return None;
+        }
+
+        let mut picked = None;
+        let modal = egui::Modal::new(egui::Id::new("quick_open")).show(ctx, |ui| {
+            ui.set_width(420.0);
+            if workspace.is_none() && recent.is_empty() {
+                ui.label(tr("Open a folder or some documents to jump between them."));
+                return;
+            }
+
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.query)
+                    .desired_width(f32::INFINITY)
+                    .hint_text(tr("File name or alias")),
+            );
+            if std::mem::take(&mut self.focus_query) {
+                response.request_focus();
+            }
+            if response.changed() {
+                self.selected = 0;
+            }
+
+            let matches = matches(workspace, recent, &self.query);
+            let (up, down, enter) = ui.input_mut(|i| {
+                (
+                    i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                    i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                    i.key_pressed(egui::Key::Enter),
+                )
+            });
+            if up {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            if down {
+                self.selected = (self.selected + 1).min(matches.len().saturating_sub(1));
+            }
+
+            ui.separator();
+            if matches.is_empty() {
+                ui.weak(tr("No matching documents"));
+            }
+            egui::ScrollArea::vertical()
+                .max_height(320.0)
+                .show(ui, |ui| {
+                    for (idx, entry) in matches.iter().enumerate() {
+                        let mut text = match entry.alias {
+                            Some(alias) => format!("{alias} → {}", entry.label),
+                            None => entry.label.clone(),
+                        };
+                        if entry.recent {
+                            text.insert_str(0, "🕘 ");
+                        }
+                        let selected = idx == self.selected;
+                        let row = ui.add(egui::Button::selectable(selected, text));
+                        if selected && (up || down) {
+                            row.scroll_to_me(None);
+                        }
+                        if row.clicked() {
+                            picked = Some(entry.path.to_path_buf());
+                        }
+                    }
+                });
+
+            if enter && let Some(entry) = matches.get(self.selected) {
+                picked = Some(entry.path.to_path_buf());
+            }
+        });
+
+        if picked.is_some() || modal.should_close() {
+            self.open = false;
+        }
+        picked
+    }
+}
+
+/// Documents whose path or alias holds the letters of `query` in order,
+/// best matches first. The one it names exactly comes before all others,
+/// and those opened lately before the rest of the folder.
+fn matches<'a>(
+    workspace: Option<&'a Workspace>,
+    recent: &'a [PathBuf],
+    query: &str,
+) -> Vec<Match<'a>> {
+    let label = |path: &Path| match workspace {
+        Some(workspace) if path.starts_with(&workspace.root) => {
+            workspace.relative(path).to_string_lossy().into_owned()
+        }
+        _ => path.to_string_lossy().into_owned(),
+    };
+
+    let recent_files = recent.iter().map(|path| Match {
+        path,
+        label: label(path),
+        alias: None,
+        recent: true,
+    });
+    let folder_files = workspace
+        .into_iter()
+        .flat_map(|workspace| &workspace.files)
+        .filter(|path| !recent.contains(path))
+        .map(|path| Match {
+            path,
+            label: label(path),
+            alias: None,
+            recent: false,
+        });
+    let by_alias = workspace
+        .into_iter()
+        .flat_map(|workspace| &workspace.aliases)
+        .filter(|_| !query.trim().is_empty())
+        .map(|(alias, path)| Match {
+            path,
+            label: label(path),
+            alias: Some(alias),
+            recent: false,
+        });
+
+    let exact = workspace.and_then(|workspace| workspace.resolve(query));
+    let mut scored: Vec<(i32, Match)> = recent_files
+        .chain(folder_files)
+        .chain(by_alias)
+        .filter_map(|entry| {
+            let score = palette::score(query, entry.alias.unwrap_or(&entry.label))?;
+            Some((score, entry))
+        })
+        .collect();
+    // Recent files stay in the order they were opened, the sort being stable
+    scored.sort_by_key(|(score, entry)| {
+        let length = if entry.recent { 0 } else { entry.label.len() };
+        (Some(entry.path) != exact, -score, !entry.recent, length)
+    });
+    scored.truncate(MAX_MATCHES);
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
@@ -0,0 +1,222 @@
+//! Bookmarks, highlights and reading positions, kept next to the documents
+//! in `.mdviewer/state.toml` so they can be committed or synced along with
+//! them. A folder's file holds the state of every document below it:
+//!
+//! ```toml
+//! # Paths are relative to the folder holding `.mdviewer`.
+//! [documents."guide/setup.md"]
+//! # Sections bookmarked, by heading
+//! bookmarks = ["Installation", "Troubleshooting"]
+//!
+//! # Where reading stopped: below a heading, or the top without one
+//! [documents."guide/setup.md".position]
+//! heading = "Installation"
+//! offset = 120.0
+//!
+//! # Passages marked, as their text reads
+//! [[documents."guide/setup.md".highlights]]
+//! text = "never run the installer as root"
+//! ```
+//!
+//! The file goes in the folder being browsed, or next to the document
+//! otherwise, the first time something is bookmarked or highlighted. Reading
+//! positions are only kept where there is a file already, so just reading a
+//! document leaves nothing behind.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+use crate::blocks;
+use crate::workspace::Workspace;
+
+pub const DIR: &str = ".mdviewer";
+const FILE: &str = "state.toml";
+
+const HEADER: &str = "\
+# Bookmarks, highlights and reading positions kept by md_viewer.
+# Paths are relative to the folder holding `.mdviewer`.
+
+";
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+struct FolderState {
+    #[serde(default)]
+    documents: BTreeMap<String, DocState>,
+}
+
+/// What is kept for one document.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DocState {
+    /// Headings of the bookmarked sections.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<Position>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<Highlight>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Position {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    pub offset: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Highlight {
+    pub text: String,
+}
+
+impl DocState {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Bookmarks or removes the bookmark of the section under `heading`.
+    pub fn toggle_bookmark(&mut self, heading: &str) {
+        match self.bookmarks.iter().position(|b| b == heading) {
+            Some(idx) => {
+                self.bookmarks.remove(idx);
+            }
+            None => self.bookmarks.push(heading.to_string()),
+        }
+    }
+
+    /// Source ranges of the highlighted passages in `markdown`, one per
+    /// highlight, or `None` where the text is no longer there.
+    pub fn highlighted(&self, markdown: &str) -> Vec<Option<Range<usize>>> {
+        if self.highlights.is_empty() {
+            return Vec::new();
+        }
+        // Rendered text without whitespace, and where in the source each
+        // character is
+        let mut text = String::new();
+        let mut offsets = Vec::new();
+        let mut in_metadata = false;
+        for (event, span) in Parser::new_ext(markdown, blocks::parser_options()).into_offset_iter()
+        {
+            match event {
+                Event::Start(Tag::MetadataBlock(_)) => in_metadata = true,
+                Event::End(TagEnd::MetadataBlock(_)) => in_metadata = false,
+                Event::Text(piece) | Event::Code(piece) if !in_metadata => {
+                    for (at, c) in piece.char_indices().filter(|(_, c)| !c.is_whitespace()) {
+                        text.push(c);
+                        offsets.push((span.start + at).min(span.end.saturating_sub(1)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.highlights
+            .iter()
+            .map(|highlight| {
+                let needle: String = highlight
+                    .text
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect();
+                if needle.is_empty() {
+                    return None;
+                }
+                let start = text[..text.find(&needle)?].chars().count();
+                let end = start + needle.chars().count() - 1;
+                Some(offsets[start]..offsets[end] + 1)
+            })
+            .collect()
+    }
+}
+
+/// Where a document's state is kept: the state file of `folder`, under `key`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sidecar {
+    folder: PathBuf,
+    key: String,
+}
+
+impl Sidecar {
+    /// Where the state of the document at `path` goes: the nearest folder
+    /// above with a `.mdviewer` folder, else the open workspace, else the
+    /// document's own folder.
+    pub fn locate(path: &Path, workspace: Option<&Workspace>) -> Option<Self> {
+        let dir = path.parent()?;
+        let folder = dir
+            .ancestors()
+            .find(|folder| folder.join(DIR).is_dir())
+            .or_else(|| {
+                workspace
+                    .map(|ws| ws.root.as_path())
+                    .filter(|root| path.starts_with(root))
+            })
+            .unwrap_or(dir);
+        let key = path
+            .strip_prefix(folder)
+            .ok()?
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        Some(Self {
+            folder: folder.to_path_buf(),
+            key,
+        })
+    }
+
+    pub fn file(&self) -> PathBuf {
+        self.folder.join(DIR).join(FILE)
+    }
+
+    fn read(&self) -> Result<Option<FolderState>> {
+        let path = self.file();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+        };
+        let state = toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+        Ok(Some(state))
+    }
+
+    /// What is kept for the document.
+    pub fn load(&self) -> Result<DocState> {
+        Ok(self
+            .read()?
+            .and_then(|mut state| state.documents.remove(&self.key))
+            .unwrap_or_default())
+    }
+
+    /// Keeps `state` for the document, leaving the other documents' state
+    /// in the file as it is on disk. Without `create`, only writes to a
+    /// file that already exists.
+    pub fn save(&self, state: &DocState, create: bool) -> Result<()> {
+        let mut folder_state = match self.read()? {
+            Some(folder_state) => folder_state,
+            None if create && !state.is_empty() => FolderState::default(),
+            None => return Ok(()),
+        };
+        if state.is_empty() {
+            folder_state.documents.remove(&self.key);
+        } else {
+            folder_state
+                .documents
+                .insert(self.key.clone(), state.clone());
+        }
+
+        let path = self.file();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        let text = format!("{HEADER}{}", toml::to_string_pretty(&folder_state)?);
+        fs::write(&path, text).with_context(|| format!("writing {}", path.display()))
+    }
+}
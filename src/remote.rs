@@ -0,0 +1,60 @@
+//! The remote-content policy: a bytes loader in front of the HTTP one that
+//! turns away web URLs unless the user allowed them.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use eframe::egui::{
+    self,
+    load::{BytesLoadResult, BytesLoader, LoadError},
+};
+
+pub struct RemoteGate {
+    allowed: AtomicBool,
+}
+
+impl RemoteGate {
+    /// Installs the gate; it must come after the other loaders to be asked first.
+    pub fn install(ctx: &egui::Context, allowed: bool) -> Arc<Self> {
+        let gate = Arc::new(Self {
+            allowed: AtomicBool::new(allowed),
+        });
+        ctx.add_bytes_loader(gate.clone());
+        gate
+    }
+
+    pub fn set_allowed(&self, ctx: &egui::Context, allowed: bool) {
+        if self.allowed.swap(allowed, Ordering::Relaxed) != allowed {
+            // Retry images that were turned away, or drop those fetched
+            ctx.forget_all_images();
+        }
+    }
+}
+
+impl BytesLoader for RemoteGate {
+    fn id(&self) -> &str {
+        egui::generate_loader_id!(RemoteGate)
+    }
+
+    fn load(&self, _ctx: &egui::Context, uri: &str) -> BytesLoadResult {
+        let remote = uri.starts_with("http://") || uri.starts_with("https://");
+        if remote && !self.allowed.load(Ordering::Relaxed) {
+            Err(LoadError::Loading(
+                "remote images are turned off in Preferences".into(),
+            ))
+        } else {
+            // Let the regular loaders have it
+            Err(LoadError::NotSupported)
+        }
+    }
+
+    fn forget(&self, _uri: &str) {}
+
+    fn forget_all(&self) {}
+
+    fn byte_size(&self) -> usize {
+        0
+    }
+}
@@ -0,0 +1,419 @@
+//! Documents opened from a URL. They are fetched over HTTP(S) into a cache
+//! folder, and the tab shows the copy there: it can be read offline, and
+//! reloading it fetches it again. Sites with a login in Preferences get it
+//! sent along, and proxies set in the environment are gone through.
+
+use std::{
+    fs, mem,
+    path::{Path, PathBuf},
+    slice,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use eframe::egui;
+
+use crate::config::{LoginKind, SiteLogin};
+use crate::i18n::{tr, tr_args};
+use crate::percent;
+use crate::web_links;
+
+/// How long a fetch may take before it is given up on.
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Next to each cached document, the URL it was fetched from.
+const URL_FILE: &str = "url.txt";
+
+/// What came of fetching a document.
+pub enum Outcome {
+    Fetched(PathBuf),
+    /// Fetching failed, but there is a copy from an earlier time.
+    Offline {
+        path: PathBuf,
+        error: String,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+struct Fetch {
+    url: String,
+    started: Instant,
+    response: Receiver<Result<Vec<u8>>>,
+}
+
+#[derive(Default)]
+pub struct RemoteDocs {
+    /// What is typed into the Open URL dialog, while it is open.
+    asking: Option<String>,
+    /// URLs to fetch on the next frame.
+    queued: Vec<String>,
+    fetches: Vec<Fetch>,
+}
+
+impl RemoteDocs {
+    pub fn ask(&mut self) {
+        self.asking = Some(String::new());
+    }
+
+    /// Fetches `url`, or its raw version for pages showing a file on
+    /// GitHub or GitLab.
+    pub fn fetch(&mut self, url: &str) {
+        if !self.fetches.iter().any(|fetch| fetch.url == url) {
+            self.queued.push(url.to_string());
+        }
+    }
+
+    /// The Open URL dialog, if open. Returns the URL entered.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<String> {
+        let url = self.asking.as_mut()?;
+        let mut entered = None;
+        let mut cancel = false;
+        let modal = egui::Modal::new(egui::Id::new("open_url")).show(ctx, |ui| {
+            ui.set_width(480.0);
+            ui.heading(tr("Open URL"));
+            ui.add_space(6.0);
+            let field = ui.add(
+                egui::TextEdit::singleline(url)
+                    .desired_width(f32::INFINITY)
+                    .hint_text("https://github.com/owner/repo/blob/main/README.md"),
+            );
+            if !field.has_focus() && url.is_empty() {
+                field.request_focus();
+            }
+            let valid = is_web(url.trim());
+            let enter = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(valid, egui::Button::new(tr("Open")))
+                    .clicked()
+                    || (valid && enter)
+                {
+                    entered = Some(url.trim().to_string());
+                }
+                if ui.button(tr("Cancel")).clicked() {
+                    cancel = true;
+                }
+            });
+        });
+        if entered.is_some() || cancel || modal.should_close() {
+            self.asking = None;
+        }
+        entered
+    }
+
+    /// A spinner for the documents being fetched.
+    pub fn show_progress(&self, ui: &mut egui::Ui) {
+        if let Some(fetch) = self.fetches.first() {
+            ui.spinner();
+            ui.weak(tr_args("Fetching {url}…", &[("url", &fetch.url)]));
+        }
+    }
+
+    /// Starts the fetches asked for, logged in with `logins`, and picks up
+    /// those that are done with the URLs they were for.
+    pub fn poll(&mut self, ctx: &egui::Context, logins: &[SiteLogin]) -> Vec<(String, Outcome)> {
+        for url in mem::take(&mut self.queued) {
+            self.fetches.push(start(ctx, url, logins));
+        }
+        if !self.fetches.is_empty() {
+            // To notice fetches that time out
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+
+        let mut done = Vec::new();
+        self.fetches.retain(|fetch| {
+            let response = match fetch.response.try_recv() {
+                Ok(response) => response,
+                Err(TryRecvError::Empty) if fetch.started.elapsed() < TIMEOUT => return true,
+                Err(TryRecvError::Empty) => {
+                    Err(anyhow!("no answer within {} seconds", TIMEOUT.as_secs()))
+                }
+                Err(TryRecvError::Disconnected) => Err(anyhow!("the fetch stopped")),
+            };
+            done.push((fetch.url.clone(), outcome(&fetch.url, response)));
+            false
+        });
+        done
+    }
+}
+
+/// Whether `url` is one these documents can be fetched from.
+pub fn is_web(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+/// The URL of the file itself for pages showing one on GitHub or GitLab,
+/// otherwise `url`.
+pub fn raw_url(url: &str) -> String {
+    let parts: Vec<_> = url
+        .strip_prefix("https://github.com/")
+        .map(|rest| rest.split('/').collect())
+        .unwrap_or_default();
+    if let [owner, repo, "blob", path @ ..] = &parts[..]
+        && !path.is_empty()
+    {
+        return format!(
+            "https://raw.githubusercontent.com/{owner}/{repo}/{}",
+            path.join("/")
+        );
+    }
+    if url.starts_with("https://gitlab.com/") && url.contains("/-/blob/") {
+        return url.replacen("/-/blob/", "/-/raw/", 1);
+    }
+    url.to_string()
+}
+
+/// The URL the document at `path` was fetched from, if it is one.
+pub fn source_url(path: &Path) -> Option<String> {
+    if !path.starts_with(cache_root()?) {
+        return None;
+    }
+    let url = fs::read_to_string(path.parent()?.join(URL_FILE)).ok()?;
+    Some(url.trim().to_string())
+}
+
+/// The `Authorization` header for fetching `url`, from the first of
+/// `logins` for its site or the site of its raw version. Only sent over
+/// HTTPS, where it can't be read on the way.
+pub fn authorization(logins: &[SiteLogin], url: &str) -> Option<String> {
+    let raw = raw_url(url);
+    if !raw.starts_with("https://") {
+        return None;
+    }
+    let hosts = [web_links::host(url), web_links::host(&raw)];
+    let login = logins.iter().find(|login| {
+        !login.secret.trim().is_empty()
+            && hosts
+                .iter()
+                .flatten()
+                .any(|host| web_links::covers(slice::from_ref(&login.site), host))
+    })?;
+    // Pasted with the spaces or line break around them, either kind
+    let secret = login.secret.trim();
+    Some(match login.kind {
+        LoginKind::Bearer => format!("Bearer {secret}"),
+        LoginKind::Basic => format!(
+            "Basic {}",
+            STANDARD.encode(format!("{}:{secret}", login.user.trim()))
+        ),
+    })
+}
+
+fn start(ctx: &egui::Context, url: String, logins: &[SiteLogin]) -> Fetch {
+    let (sender, response) = mpsc::channel();
+    let ctx = ctx.clone();
+    let mut request = ehttp::Request::get(raw_url(&url));
+    request.headers.insert(
+        "User-Agent",
+        format!("md_viewer/{}", env!("CARGO_PKG_VERSION")),
+    );
+    if let Some(authorization) = authorization(logins, &url) {
+        request.headers.insert("Authorization", authorization);
+    }
+    ehttp::fetch(request, move |answer| {
+        let bytes = match answer {
+            Ok(answer) if answer.ok => Ok(answer.bytes),
+            Ok(answer) => Err(anyhow!(
+                "the server answered {} {}",
+                answer.status,
+                answer.status_text
+            )),
+            Err(error) => Err(anyhow!(error)),
+        };
+        let _ = sender.send(bytes);
+        ctx.request_repaint();
+    });
+    Fetch {
+        url,
+        started: Instant::now(),
+        response,
+    }
+}
+
+/// Keeps what `url` answered in the cache, or falls back on the copy kept
+/// there before.
+fn outcome(url: &str, response: Result<Vec<u8>>) -> Outcome {
+    let Some(path) = cache_path(url) else {
+        return Outcome::Failed {
+            error: "no cache folder to keep documents in".into(),
+        };
+    };
+    let saved = response.and_then(|bytes| {
+        let dir = path.parent().context("cache path without a folder")?;
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        fs::write(dir.join(URL_FILE), url)?;
+        fs::write(&path, bytes).with_context(|| format!("writing {}", path.display()))
+    });
+    match saved {
+        Ok(()) => Outcome::Fetched(path),
+        Err(e) if path.is_file() => Outcome::Offline {
+            path,
+            error: format!("{e:#}"),
+        },
+        Err(e) => Outcome::Failed {
+            error: format!("{e:#}"),
+        },
+    }
+}
+
+fn cache_root() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("md_viewer").join("documents"))
+}
+
+/// Where the copy of the document at `url` is kept: in a folder of its
+/// own, named for a hash of the URL that is the same from run to run, under
+/// the file name the URL ends in.
+fn cache_path(url: &str) -> Option<PathBuf> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let page = path.split_once("://").map_or(path, |(_, page)| page);
+    let name = page
+        .split_once('/')
+        .and_then(|(_, path)| path.rsplit('/').next())
+        .map(file_name)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "index.md".to_string());
+    Some(
+        cache_root()?
+            .join(format!("{:016x}", fnv1a(url)))
+            .join(name),
+    )
+}
+
+/// `segment` of a URL as a file name that stays in its folder: with no
+/// separators or characters Windows keeps for itself, and not `.` or `..`.
+fn file_name(segment: &str) -> String {
+    let name: String = percent::decode(segment)
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // Windows drops these from the end, which leaves nothing of `..`
+    name.trim_end_matches(['.', ' ']).to_string()
+}
+
+/// The 64-bit FNV-1a hash of `text`.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn login(site: &str, kind: LoginKind, user: &str, secret: &str) -> SiteLogin {
+        SiteLogin {
+            site: site.to_string(),
+            kind,
+            user: user.to_string(),
+            secret: secret.to_string(),
+        }
+    }
+
+    #[test]
+    fn pages_showing_a_file_fetch_the_file() {
+        assert_eq!(
+            raw_url("https://github.com/rust-lang/rust/blob/master/README.md"),
+            "https://raw.githubusercontent.com/rust-lang/rust/master/README.md"
+        );
+        assert_eq!(
+            raw_url("https://gitlab.com/group/project/-/blob/main/docs/a.md"),
+            "https://gitlab.com/group/project/-/raw/main/docs/a.md"
+        );
+        for url in [
+            "https://github.com/rust-lang/rust",
+            "https://github.com/rust-lang/rust/tree/master/src",
+            "https://example.com/owner/repo/blob/main/a.md",
+        ] {
+            assert_eq!(raw_url(url), url);
+        }
+    }
+
+    #[test]
+    fn logins_go_to_their_sites_over_https_only() {
+        let logins = [
+            login("github.com", LoginKind::Bearer, "", " ghp_1234\n"),
+            login("wiki.example.com", LoginKind::Basic, " me ", "pa55\n"),
+        ];
+        assert_eq!(
+            authorization(&logins, "https://github.com/o/r/blob/main/a.md").as_deref(),
+            Some("Bearer ghp_1234")
+        );
+        assert_eq!(
+            authorization(&logins, "https://docs.wiki.example.com/a.md").as_deref(),
+            Some("Basic bWU6cGE1NQ==")
+        );
+        assert_eq!(authorization(&logins, "http://github.com/a.md"), None);
+        assert_eq!(authorization(&logins, "https://evilgithub.com/a.md"), None);
+        assert_eq!(authorization(&logins, "https://example.com/a.md"), None);
+    }
+
+    #[test]
+    fn logins_without_a_secret_are_passed_over() {
+        let logins = [
+            login("example.com", LoginKind::Bearer, "", " \n"),
+            login("example.com", LoginKind::Bearer, "", "token"),
+        ];
+        assert_eq!(
+            authorization(&logins, "https://example.com/a.md").as_deref(),
+            Some("Bearer token")
+        );
+    }
+
+    #[test]
+    fn copies_are_kept_by_the_file_name_in_a_folder_per_url() {
+        let name = |url| {
+            let path = cache_path(url).unwrap();
+            assert!(path.parent().unwrap().starts_with(cache_root().unwrap()));
+            path.file_name().unwrap().to_string_lossy().into_owned()
+        };
+        assert_eq!(
+            name("https://example.com/docs/My%20Notes.md?v=2#top"),
+            "My Notes.md"
+        );
+        assert_eq!(name("https://example.com/docs/"), "index.md");
+        assert_eq!(name("https://example.com"), "index.md");
+        assert_eq!(name("https://example.com/docs/.."), "index.md");
+        assert_eq!(name("https://example.com/a\\..\\b:c.md"), "a_.._b_c.md");
+        assert_eq!(name("https://example.com/notes.md. . "), "notes.md");
+        assert_ne!(
+            cache_path("https://example.com/a/notes.md")
+                .unwrap()
+                .parent(),
+            cache_path("https://example.com/b/notes.md")
+                .unwrap()
+                .parent()
+        );
+        assert_eq!(
+            cache_path("https://example.com/a/notes.md"),
+            cache_path("https://example.com/a/notes.md")
+        );
+    }
+
+    #[test]
+    fn file_names_stay_in_their_folder() {
+        assert_eq!(file_name(".."), "");
+        assert_eq!(file_name("."), "");
+        assert_eq!(file_name("..\\..\\secret"), ".._.._secret");
+        assert_eq!(file_name("a\u{7}b|c?.md"), "a_b_c_.md");
+        // Escaped, the same
+        assert_eq!(file_name("%2E%2E"), "");
+        assert_eq!(file_name("..%2F..%5Csecret"), ".._.._secret");
+        assert_eq!(file_name("My%20Notes%C3%A9.md"), "My Notesé.md");
+    }
+
+    #[test]
+    fn hashes_are_fnv1a() {
+        assert_eq!(fnv1a(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -0,0 +1,727 @@
+//! Renders a document block by block on top of `egui_commonmark`.
+
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow, bail};
+use eframe::egui;
+use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
+
+use crate::animation;
+use crate::blocks::{self, Block, BlockKind, Document, Footnote};
+use crate::callout::Callout;
+use crate::code_view::{self, Layout};
+use crate::crash;
+use crate::diagram::{self, Engine};
+use crate::diff_fence;
+use crate::html_fragment::{Fragment, Toggled};
+use crate::i18n::{tr, tr_args};
+use crate::justify;
+use crate::keycaps::Keycaps;
+use crate::link_preview;
+use crate::monochrome;
+use crate::tab::DocTab;
+use crate::table::{self, Table};
+
+/// Per-frame viewer settings that affect how blocks are drawn.
+pub struct ViewOptions<'a> {
+    pub guess_code_languages: bool,
+    /// Lay out plain paragraphs justified, optionally hyphenated.
+    pub justify: bool,
+    pub hyphenate: bool,
+    /// Source range of the search match the user is on.
+    pub highlight: Option<Range<usize>>,
+    /// Syntax highlighting themes for code blocks.
+    pub code_theme_dark: &'a str,
+    pub code_theme_light: &'a str,
+    /// Code blocks in black on white with bold and italics, for printing.
+    pub monochrome_code: bool,
+    /// Line numbers and wrapping of code blocks not toggled from their buttons.
+    pub code_layout: Layout,
+    /// Diff fences in two columns instead of one.
+    pub diff_side_by_side: bool,
+    /// Put its number in the outline in front of each heading.
+    pub number_headings: bool,
+    /// What draws PlantUML and Graphviz fences, if they're drawn as diagrams.
+    pub diagrams: Option<diagram::Commands<'a>>,
+    /// Note which internal link is hovered, for previews.
+    pub link_previews: bool,
+    /// Shade every block as selected.
+    pub selected_all: bool,
+    /// Source ranges of the passages the user highlighted.
+    pub marked: &'a [Range<usize>],
+}
+
+/// How far below the viewport, in viewport heights, images get their
+/// textures uploaded ahead of time.
+const PREWARM_DISTANCE: f32 = 2.0;
+
+/// Images to have loading at once, so pre-warming never causes a hitch itself.
+const PREWARM_PER_FRAME: usize = 2;
+
+/// A frame spent rendering longer than this means the document is too heavy
+/// to keep rendering as markdown.
+const RENDER_BUDGET: Duration = Duration::from_secs(2);
+
+/// [`show_document`], but fails instead of crashing or freezing on documents
+/// the renderer can't cope with.
+pub fn show_document_guarded(
+    ui: &mut egui::Ui,
+    cache: &mut CommonMarkCache,
+    tab: &mut DocTab,
+    options: &ViewOptions<'_>,
+) -> Result<()> {
+    if let Some(risk) = tab.doc.render_risk() {
+        bail!("{risk}");
+    }
+
+    let started = Instant::now();
+    crash::recoverable(|| {
+        show_document(ui, cache, tab, options);
+    })
+    .map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown error".into());
+        anyhow!("the renderer crashed: {message}")
+    })?;
+
+    let elapsed = started.elapsed();
+    if elapsed > RENDER_BUDGET {
+        bail!("rendering took {:.1}s", elapsed.as_secs_f32());
+    }
+    Ok(())
+}
+
+/// The raw source, for documents that can't be rendered as markdown. With
+/// `guide`, a line is drawn at that column and text past it is tinted.
+pub fn show_plain_text(ui: &mut egui::Ui, tab: &DocTab, guide: Option<usize>) {
+    let output = egui::TextEdit::multiline(&mut tab.content.as_str())
+        .code_editor()
+        .desired_width(f32::INFINITY)
+        .show(ui);
+    let Some(guide) = guide else {
+        return;
+    };
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let column = ui.fonts(|f| f.glyph_width(&font, ' '));
+    let origin = output.galley_pos;
+    let x = origin.x + guide as f32 * column;
+    let painter = ui.painter_at(output.text_clip_rect);
+    let rect = output.response.rect;
+    let color = ui.visuals().warn_fg_color;
+    painter.vline(
+        x,
+        rect.y_range(),
+        egui::Stroke::new(1.0, color.gamma_multiply(0.5)),
+    );
+
+    // Rows wrapped to the window go on with the column their line was at
+    let mut start = 0;
+    for row in &output.galley.rows {
+        let end = start + row.char_count_excluding_newline();
+        if end > guide {
+            let row_rect = row.rect().translate(origin.to_vec2());
+            let from = row_rect.left() + guide.saturating_sub(start) as f32 * column;
+            let over = egui::Rect::from_x_y_ranges(from..=row_rect.right(), row_rect.y_range());
+            painter.rect_filled(over, 0.0, color.gamma_multiply(0.15));
+        }
+        start = if row.ends_with_newline { 0 } else { end };
+    }
+}
+
+pub fn show_document(
+    ui: &mut egui::Ui,
+    cache: &mut CommonMarkCache,
+    tab: &mut DocTab,
+    options: &ViewOptions<'_>,
+) {
+    let block_gap = ui.text_style_height(&egui::TextStyle::Body);
+    let scroll_to = tab.scroll_to_block.take();
+    let restore = tab.restore_anchor.take();
+
+    // Whatever we jump to has to be visible
+    if let Some(target) = scroll_to {
+        for heading in tab.doc.enclosing_headings(target) {
+            if let Some(fragment) = tab.doc.fragment(heading) {
+                tab.collapsed.remove(&fragment);
+            }
+        }
+    }
+
+    let view_top = ui.clip_rect().top();
+    let view_bottom = ui.clip_rect().bottom();
+    let prewarm_bottom = view_bottom + ui.clip_rect().height() * PREWARM_DISTANCE;
+    // Blocks coming up below the viewport, nearest first
+    let mut upcoming = Vec::new();
+    let numbers = match options.number_headings {
+        true => tab.doc.heading_numbers(),
+        false => Vec::new(),
+    };
+    tab.current_heading = None;
+    tab.hovered_link = None;
+    tab.clicked_image = None;
+    tab.save_code = None;
+
+    if let Some(status) = &tab.doc.status {
+        ui.horizontal(|ui| {
+            ui.add_space(COLLAPSE_GUTTER);
+            status.show(ui, false);
+        });
+    }
+
+    egui::Frame::NONE
+        .inner_margin(egui::Margin {
+            left: COLLAPSE_GUTTER as i8,
+            ..Default::default()
+        })
+        .show(ui, |ui| {
+            let mut skip_until = 0;
+            for (idx, block) in tab.doc.blocks.iter().enumerate() {
+                if idx < skip_until || matches!(block.kind, BlockKind::Frontmatter) {
+                    continue;
+                }
+                // Headings already start with a blank line of their own.
+                if idx > 0 && !matches!(block.kind, BlockKind::Heading { .. }) {
+                    ui.add_space(block_gap);
+                }
+
+                let number = numbers.get(idx).and_then(Option::as_deref);
+                let response = show_highlighted_block(
+                    ui,
+                    cache,
+                    block,
+                    number,
+                    options,
+                    &[idx],
+                    &mut tab.toggled_details,
+                );
+                if options.link_previews && ui.rect_contains_pointer(response.rect) {
+                    tab.hovered_link = hovered_link(ui.ctx(), block, response.rect);
+                }
+                if !block.images.is_empty()
+                    && ui.rect_contains_pointer(response.rect)
+                    && let Some((uri, rect)) = hovered_image(ui.ctx(), block, response.rect)
+                    && !animation_button(ui, uri, rect)
+                {
+                    ui.ctx().set_cursor_icon(egui::CursorIcon::ZoomIn);
+                    if ui.input(|i| i.pointer.primary_clicked()) {
+                        tab.clicked_image = Some(uri.to_string());
+                    }
+                }
+                if ui.rect_contains_pointer(response.rect)
+                    && let Some((note, reference)) =
+                        hovered_footnote(ui.ctx(), &tab.doc, idx, response.rect)
+                {
+                    reference.show_tooltip_ui(|ui| {
+                        ui.set_max_width(FOOTNOTE_POPUP_WIDTH);
+                        ui.label(&note.text);
+                    });
+                    ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                    if ui.input(|i| i.pointer.primary_clicked()) {
+                        tab.scroll_to_block = Some(note.block);
+                        tab.footnote_return = Some(idx);
+                    }
+                }
+                if let Some(note) = tab.doc.footnote_defined_in(idx)
+                    && let Some(&first) = note.references.first()
+                    && back_link(ui, response.rect)
+                {
+                    // Back to the reference it was jumped to from, if one
+                    let from = tab
+                        .footnote_return
+                        .filter(|from| note.references.contains(from));
+                    tab.scroll_to_block = Some(from.unwrap_or(first));
+                }
+                if block.code.is_some() && code_view::save_button(ui, response.rect) {
+                    tab.save_code = Some(idx);
+                }
+                if response.rect.top() > view_bottom && response.rect.top() <= prewarm_bottom {
+                    upcoming.push(idx);
+                }
+
+                if let BlockKind::Heading { .. } = &block.kind {
+                    // Jumps leave a little space above the heading
+                    if response.rect.top() <= view_top + block_gap / 2.0 {
+                        tab.current_heading = Some(idx);
+                        tab.heading_offset = (view_top - response.rect.top()).max(0.0);
+                    }
+                    let fragment = tab.doc.fragment(idx).unwrap_or_default();
+                    let collapsed = tab.collapsed.contains(&fragment);
+                    if collapse_toggle(ui, &response, block_gap, collapsed) {
+                        if collapsed {
+                            tab.collapsed.remove(&fragment);
+                        } else {
+                            tab.collapsed.insert(fragment.clone());
+                        }
+                    }
+                    if tab.collapsed.contains(&fragment) {
+                        skip_until = tab.doc.section_end(idx);
+                    }
+                    if permalink_button(ui, &response, block_gap)
+                        && let Some(fragment) = tab.doc.fragment(idx)
+                    {
+                        let file = match tab.is_scratch() {
+                            true => String::new(),
+                            false => tab.path.file_name().map_or_else(String::new, |name| {
+                                name.to_string_lossy().into_owned()
+                            }),
+                        };
+                        ui.ctx().copy_text(format!("{file}#{fragment}"));
+                    }
+                }
+
+                // A `<details>` going on past its block hides the blocks up to
+                // its end while closed
+                if matches!(block.kind, BlockKind::Prose)
+                    && let Some(fragment) =
+                        Fragment::parse(block.wiki_markdown.as_deref().unwrap_or(&block.markdown))
+                    && fragment.show_unclosed(
+                        ui,
+                        ("details", idx),
+                        &[idx],
+                        &mut tab.toggled_details,
+                    ) == Some(false)
+                {
+                    skip_until = tab.doc.details_end(idx);
+                }
+
+                if scroll_to == Some(idx) {
+                    ui.scroll_to_rect(response.rect, Some(egui::Align::TOP));
+                }
+                if let Some((anchor, offset)) = restore
+                    && anchor == idx
+                {
+                    let rect = response.rect.translate(egui::vec2(0.0, offset));
+                    ui.scroll_to_rect(rect, Some(egui::Align::TOP));
+                }
+            }
+        });
+
+    prewarm_images(ui.ctx(), upcoming.iter().map(|&idx| &tab.doc.blocks[idx]));
+}
+
+/// Destination of the internal link under the pointer in `block`. egui
+/// doesn't say which URL a hovered link has, so it goes by the link's text
+/// in the accessibility tree.
+fn hovered_link(ctx: &egui::Context, block: &Block, rect: egui::Rect) -> Option<String> {
+    let links =
+        link_preview::internal_links(block.wiki_markdown.as_deref().unwrap_or(&block.markdown));
+    if links.is_empty() {
+        return None;
+    }
+    let hovered: Vec<egui::Id> = ctx.interaction_snapshot(|i| i.hovered.iter().copied().collect());
+    let label = hovered
+        .into_iter()
+        .filter(|id| {
+            ctx.read_response(*id)
+                .is_some_and(|r| r.sense.senses_click() && rect.contains_rect(r.rect))
+        })
+        .find_map(|id| ctx.accesskit_node_builder(id, |node| node.label().map(String::from)))
+        .flatten()?;
+    links
+        .into_iter()
+        .find(|(text, _)| *text == label)
+        .map(|(_, dest)| dest)
+}
+
+/// How wide the text of a footnote gets over its reference.
+const FOOTNOTE_POPUP_WIDTH: f32 = 360.0;
+
+/// The footnote whose reference is under the pointer in the block at `idx`,
+/// with the reference. Like links, it goes by the reference's text in the
+/// accessibility tree, where labels have it as their value.
+fn hovered_footnote<'a>(
+    ctx: &egui::Context,
+    doc: &'a Document,
+    idx: usize,
+    rect: egui::Rect,
+) -> Option<(&'a Footnote, egui::Response)> {
+    let notes: Vec<&Footnote> = doc
+        .footnotes
+        .iter()
+        .filter(|note| note.references.contains(&idx))
+        .collect();
+    if notes.is_empty() {
+        return None;
+    }
+    let under: Vec<egui::Id> =
+        ctx.interaction_snapshot(|i| i.contains_pointer.iter().copied().collect());
+    under
+        .into_iter()
+        .filter_map(|id| ctx.read_response(id))
+        .filter(|r| rect.contains_rect(r.rect))
+        .find_map(|r| {
+            let label = ctx
+                .accesskit_node_builder(r.id, |node| node.value().map(String::from))
+                .flatten()?;
+            let note = notes.iter().find(|note| note.mark() == label)?;
+            Some((*note, r))
+        })
+}
+
+/// A ↩ after the footnote definition at `rect`, like the ones on the web.
+/// Returns whether it was clicked.
+fn back_link(ui: &mut egui::Ui, rect: egui::Rect) -> bool {
+    let size = COLLAPSE_GUTTER.max(ui.spacing().interact_size.y);
+    let at = egui::Rect::from_min_size(
+        egui::pos2(rect.right() - size, rect.top()),
+        egui::vec2(size, size),
+    );
+    ui.put(at, egui::Button::new("↩").small().frame(false))
+        .on_hover_text(tr("Back to the reference"))
+        .clicked()
+}
+
+/// URI and place of the image under the pointer in `block`. egui doesn't
+/// say which image a widget shows either, so it goes by the size of the
+/// widgets under the pointer: an image keeps its proportions and is never
+/// drawn larger.
+fn hovered_image<'a>(
+    ctx: &egui::Context,
+    block: &'a Block,
+    rect: egui::Rect,
+) -> Option<(&'a str, egui::Rect)> {
+    let under: Vec<egui::Rect> = ctx
+        .interaction_snapshot(|i| i.contains_pointer.iter().copied().collect::<Vec<_>>())
+        .into_iter()
+        .filter_map(|id| ctx.read_response(id))
+        .filter(|r| !r.sense.senses_click() && !r.sense.senses_drag() && rect.contains_rect(r.rect))
+        .map(|r| r.rect)
+        .collect();
+    block.images.iter().find_map(|uri| {
+        let poll = ctx.try_load_texture(
+            uri,
+            egui::TextureOptions::default(),
+            egui::SizeHint::default(),
+        );
+        let Ok(egui::load::TexturePoll::Ready { texture }) = poll else {
+            return None;
+        };
+        let size = texture.size;
+        let shown = under.iter().find(|r| {
+            r.width() <= size.x + 1.0 && (r.width() * size.y / size.x - r.height()).abs() <= 1.0
+        })?;
+        Some((uri.as_str(), *shown))
+    })
+}
+
+/// A button over the top left of the animated image `uri` at `rect`, to
+/// pause it or play it on. Returns whether the pointer is on the button.
+fn animation_button(ui: &mut egui::Ui, uri: &str, rect: egui::Rect) -> bool {
+    let Some(playback) = animation::playback(ui.ctx(), uri).filter(|p| p.frames > 1) else {
+        return false;
+    };
+    let size = ui.spacing().interact_size.y;
+    let at = egui::Rect::from_min_size(rect.min + egui::vec2(4.0, 4.0), egui::vec2(size, size));
+    let icon = if playback.paused { "▶" } else { "⏸" };
+    let button = ui
+        .put(at, egui::Button::new(icon).small())
+        .on_hover_text(tr(match playback.paused {
+            true => "Play",
+            false => "Pause",
+        }));
+    if button.clicked() {
+        animation::toggle_pause(ui.ctx(), uri);
+    }
+    button.hovered()
+}
+
+/// Uploads textures for the images in `upcoming` while nothing else is going
+/// on, so they don't all arrive at once on the first scroll through.
+fn prewarm_images<'a>(ctx: &egui::Context, upcoming: impl Iterator<Item = &'a Block>) {
+    let idle = ctx.input(|i| {
+        i.events.is_empty() && i.smooth_scroll_delta == egui::Vec2::ZERO && !i.pointer.is_moving()
+    });
+    if !idle {
+        return;
+    }
+
+    let mut in_flight = 0;
+    for uri in upcoming.flat_map(|block| &block.images) {
+        if in_flight == PREWARM_PER_FRAME {
+            break;
+        }
+        // Textures already uploaded are just a cache lookup
+        let poll = ctx.try_load_texture(
+            uri,
+            egui::TextureOptions::default(),
+            egui::SizeHint::default(),
+        );
+        if matches!(poll, Ok(egui::load::TexturePoll::Pending { .. })) {
+            in_flight += 1;
+        }
+    }
+}
+
+/// Width of the left margin holding the section collapse toggles.
+const COLLAPSE_GUTTER: f32 = 20.0;
+
+/// Draws the ▶/▼ toggle for a heading in the left gutter. Returns whether it was clicked.
+fn collapse_toggle(
+    ui: &mut egui::Ui,
+    heading: &egui::Response,
+    leading_gap: f32,
+    collapsed: bool,
+) -> bool {
+    // The heading's rect starts with an empty line; center on the text below it.
+    let rect = heading.rect;
+    let center_y = (rect.top() + leading_gap + rect.bottom()) / 2.0;
+    // As tall as other buttons, which grow with large text
+    let toggle_rect = egui::Rect::from_center_size(
+        egui::pos2(rect.left() - COLLAPSE_GUTTER / 2.0, center_y),
+        egui::vec2(
+            COLLAPSE_GUTTER,
+            COLLAPSE_GUTTER.max(ui.spacing().interact_size.y),
+        ),
+    );
+    let hovered = ui.rect_contains_pointer(heading.rect.union(toggle_rect));
+    if !collapsed && !hovered {
+        return false;
+    }
+
+    let icon = if collapsed { "▶" } else { "▼" };
+    ui.put(toggle_rect, egui::Button::new(icon).small().frame(false))
+        .on_hover_text(if collapsed {
+            tr("Expand section")
+        } else {
+            tr("Collapse section")
+        })
+        .clicked()
+}
+
+/// Draws a button to copy a link to the heading after its text while it's
+/// hovered. Returns whether it was clicked.
+fn permalink_button(ui: &mut egui::Ui, heading: &egui::Response, leading_gap: f32) -> bool {
+    let rect = heading.rect;
+    let size = COLLAPSE_GUTTER.max(ui.spacing().interact_size.y);
+    let button_rect = egui::Rect::from_center_size(
+        egui::pos2(
+            rect.right() + size / 2.0 + 4.0,
+            (rect.top() + leading_gap + rect.bottom()) / 2.0,
+        ),
+        egui::vec2(size, size),
+    );
+    if !ui.rect_contains_pointer(rect.union(button_rect)) {
+        return false;
+    }
+    ui.put(button_rect, egui::Button::new("🔗").small().frame(false))
+        .on_hover_text(tr("Copy a link to this heading"))
+        .clicked()
+}
+
+fn show_highlighted_block(
+    ui: &mut egui::Ui,
+    cache: &mut CommonMarkCache,
+    block: &Block,
+    number: Option<&str>,
+    options: &ViewOptions<'_>,
+    place: &[usize],
+    toggled_details: &mut Toggled,
+) -> egui::Response {
+    // Reserve a spot behind the block in case it needs a highlight.
+    let background = ui.painter().add(egui::Shape::Noop);
+    // Tables keep their sorting and layout under the parent's id, so blocks
+    // need their own or tables in different blocks fight over one state.
+    let response = ui
+        .push_id(block.span.start, |ui| {
+            show_block(ui, cache, block, number, options, place, toggled_details)
+        })
+        .inner;
+
+    let highlighted = options
+        .highlight
+        .as_ref()
+        .is_some_and(|hit| block.span.start <= hit.start && hit.start < block.span.end);
+    if options.selected_all {
+        ui.painter().set(
+            background,
+            egui::epaint::RectShape::filled(
+                response.rect.expand(2.0),
+                0.0,
+                ui.visuals().selection.bg_fill.gamma_multiply(0.5),
+            ),
+        );
+    } else if !highlighted
+        && options
+            .marked
+            .iter()
+            .any(|range| range.start < block.span.end && block.span.start < range.end)
+    {
+        ui.painter().set(
+            background,
+            egui::epaint::RectShape::filled(
+                response.rect.expand(2.0),
+                ui.visuals().widgets.noninteractive.corner_radius,
+                ui.visuals().warn_fg_color.gamma_multiply(0.2),
+            ),
+        );
+    } else if highlighted {
+        let visuals = ui.visuals();
+        ui.painter().set(
+            background,
+            egui::epaint::RectShape::new(
+                response.rect.expand(4.0),
+                visuals.widgets.noninteractive.corner_radius,
+                visuals.selection.bg_fill.gamma_multiply(0.25),
+                visuals.selection.stroke,
+                egui::StrokeKind::Outside,
+            ),
+        );
+    }
+
+    response
+}
+
+/// Draws `block`, numbered `number` if it's a heading; `toggled_details`
+/// are the `<details>` opened or closed from how they start, and `place`
+/// is where the block is among them.
+fn show_block(
+    ui: &mut egui::Ui,
+    cache: &mut CommonMarkCache,
+    block: &Block,
+    number: Option<&str>,
+    options: &ViewOptions<'_>,
+    place: &[usize],
+    toggled_details: &mut Toggled,
+) -> egui::Response {
+    if let Some(code) = &block.code
+        && let Some(separator) = table::separator(code.lang.as_deref())
+    {
+        return Table::from_delimited(&code.text, separator).show(ui);
+    }
+    if let Some(code) = &block.code
+        && let Some(commands) = options.diagrams
+        && let Some(engine) = Engine::of(code.lang.as_deref())
+        && let Some(response) = diagram::show(ui, engine, commands, &code.text)
+    {
+        return response;
+    }
+    if options.monochrome_code
+        && let Some(code) = &block.code
+    {
+        return monochrome::show(
+            ui,
+            &code.text,
+            code_lang(block, options.guess_code_languages),
+        );
+    }
+    if let Some(code) = &block.code
+        && diff_fence::is_diff(code.lang.as_deref())
+    {
+        return diff_fence::show(ui, &code.text, options.diff_side_by_side);
+    }
+    let code_layout = block.code.as_ref().map(|code| {
+        let id = ui.id().with("code_layout");
+        (id, code, Layout::of(ui.ctx(), id, options.code_layout))
+    });
+    if let Some((id, code, layout)) = code_layout
+        && layout != Layout::COMMONMARK
+    {
+        let theme = match ui.visuals().dark_mode {
+            true => options.code_theme_dark,
+            false => options.code_theme_light,
+        };
+        let lang = code_lang(block, options.guess_code_languages);
+        let response = code_view::show(ui, id, &code.text, lang, theme, layout);
+        code_view::toggles(ui, id, response.rect, layout, Some(&code.text));
+        return response;
+    }
+
+    let markdown = block.wiki_markdown.as_deref().unwrap_or(&block.markdown);
+    if matches!(block.kind, BlockKind::Prose)
+        && let Some(callout) = Callout::parse(markdown)
+    {
+        return callout.show(ui, |ui, body| {
+            // Its contents are blocks like any others, tables and all
+            let gap = ui.text_style_height(&egui::TextStyle::Body);
+            for (n, block) in blocks::split(body).blocks.iter().enumerate() {
+                if n > 0 {
+                    ui.add_space(gap);
+                }
+                let place = [place, &[n]].concat();
+                ui.push_id(n, |ui| {
+                    show_block(ui, cache, block, None, options, &place, toggled_details)
+                });
+            }
+        });
+    }
+    if matches!(block.kind, BlockKind::Prose)
+        && let Some(table) = Table::parse(markdown)
+    {
+        return table.show(ui);
+    }
+    if matches!(block.kind, BlockKind::Prose)
+        && let Some(keycaps) = Keycaps::parse(markdown)
+    {
+        return keycaps.show(ui);
+    }
+    if matches!(block.kind, BlockKind::Prose)
+        && let Some(fragment) = Fragment::parse(markdown)
+    {
+        return fragment.show(ui, place, toggled_details);
+    }
+    let numbered = number
+        .filter(|_| matches!(block.kind, BlockKind::Heading { .. }))
+        .map(|number| numbered(markdown, number));
+    let markdown = numbered.as_deref().unwrap_or(markdown);
+    let response = match &block.kind {
+        BlockKind::Prose if options.justify => match justify::Paragraph::parse(markdown) {
+            Some(paragraph) => paragraph.show(ui, options.hyphenate),
+            None => viewer(options).show(ui, cache, markdown).response,
+        },
+        BlockKind::UnlabeledCode {
+            guess: Some(lang),
+            guessed_markdown,
+        } if options.guess_code_languages => {
+            let response = viewer(options).show(ui, cache, guessed_markdown).response;
+            if ui.rect_contains_pointer(response.rect) {
+                response.show_tooltip_text(tr_args(
+                    "Language guessed: {language} (disable under View)",
+                    &[("language", &lang.name)],
+                ));
+            }
+            response
+        }
+        BlockKind::Prose | BlockKind::Heading { .. } | BlockKind::UnlabeledCode { .. } => {
+            viewer(options).show(ui, cache, markdown).response
+        }
+        BlockKind::Frontmatter => ui.allocate_response(egui::Vec2::ZERO, egui::Sense::hover()),
+    };
+    if let Some((id, _, layout)) = code_layout {
+        code_view::toggles(ui, id, response.rect, layout, None);
+    }
+    response
+}
+
+/// The heading in `markdown` with `number` in front of its text, after the
+/// `#`s of an ATX heading. Starting a line, `1.` would begin a list.
+fn numbered(markdown: &str, number: &str) -> String {
+    let text = markdown.trim_start();
+    let marks = text.len() - text.trim_start_matches('#').len();
+    match marks {
+        0 => format!("{} {text}", number.replacen('.', "\\.", 1)),
+        _ => format!("{} {number}{}", &text[..marks], &text[marks..]),
+    }
+}
+
+/// The language of a code block: its fence's, or the one guessed if
+/// `guessing`.
+pub fn code_lang(block: &Block, guessing: bool) -> Option<&str> {
+    match &block.kind {
+        BlockKind::UnlabeledCode {
+            guess: Some(guess), ..
+        } if guessing => Some(guess.token),
+        _ => block.code.as_ref()?.lang.as_deref(),
+    }
+}
+
+fn viewer<'f>(options: &ViewOptions<'_>) -> CommonMarkViewer<'f> {
+    CommonMarkViewer::new()
+        .syntax_theme_dark(options.code_theme_dark)
+        .syntax_theme_light(options.code_theme_light)
+}
@@ -0,0 +1,284 @@
+//! Copying selected document text with its formatting. egui only copies
+//! plain text, so the copy is matched back to the Markdown it came from and
+//! put on the clipboard again as HTML too, for email and word processors.
+//! Code blocks are text fields of their own, so a selection across one
+//! picks it up here.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use eframe::egui;
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+
+use crate::export;
+
+/// Where a copy out of a viewport's labels waits for its window.
+fn copied_id(viewport: egui::ViewportId) -> egui::Id {
+    egui::Id::new(("rich_copy", viewport))
+}
+
+/// Marks a copy as only [`capture`]d and where its text waits.
+fn capturing_id(viewport: egui::ViewportId) -> egui::Id {
+    egui::Id::new(("rich_copy_capturing", viewport))
+}
+
+fn captured_id(viewport: egui::ViewportId) -> egui::Id {
+    egui::Id::new(("rich_copy_captured", viewport))
+}
+
+/// Starts watching for text copied out of document labels. Does nothing
+/// the second time.
+pub fn install(ctx: &egui::Context) {
+    let installed = egui::Id::new("rich_copy_installed");
+    if ctx.data(|d| d.get_temp::<bool>(installed)).is_some() {
+        return;
+    }
+    ctx.data_mut(|d| d.insert_temp(installed, true));
+    // Labels copy at the end of the pass, so look right after them
+    ctx.on_end_pass(
+        "rich_copy",
+        Arc::new(|ctx| {
+            let viewport = ctx.viewport_id();
+            let capturing = ctx.data_mut(|d| d.remove_temp::<bool>(capturing_id(viewport)));
+            let from_labels = egui::text_selection::LabelSelectionState::load(ctx).has_selection()
+                && ctx.memory(|m| m.focused().is_none());
+            if !from_labels {
+                return;
+            }
+            let copied = ctx.output(|o| {
+                o.commands.iter().rev().find_map(|command| match command {
+                    egui::OutputCommand::CopyText(text) => Some(text.clone()),
+                    _ => None,
+                })
+            });
+            if capturing.is_some() {
+                // Wanted for something else than the clipboard
+                ctx.output_mut(|o| {
+                    o.commands
+                        .retain(|command| !matches!(command, egui::OutputCommand::CopyText(_)));
+                });
+            }
+            if let Some(copied) = copied {
+                let id = match capturing {
+                    Some(_) => captured_id(viewport),
+                    None => copied_id(viewport),
+                };
+                ctx.data_mut(|d| d.insert_temp(id, copied));
+                ctx.request_repaint();
+            }
+        }),
+    );
+}
+
+/// Text copied out of labels in this viewport since the last call.
+pub fn take_copied(ctx: &egui::Context) -> Option<String> {
+    let id = copied_id(ctx.viewport_id());
+    ctx.data_mut(|d| d.remove_temp::<String>(id))
+}
+
+/// Has the labels hand over their selected text as if copied, but without
+/// it going on the clipboard. It can be taken with [`take_captured`] on the
+/// next frame. Returns whether there is a selection.
+pub fn capture(ctx: &egui::Context) -> bool {
+    let selected = egui::text_selection::LabelSelectionState::load(ctx).has_selection()
+        && ctx.memory(|m| m.focused().is_none());
+    if selected {
+        let id = capturing_id(ctx.viewport_id());
+        ctx.data_mut(|d| d.insert_temp(id, true));
+        ctx.input_mut(|i| i.events.push(egui::Event::Copy));
+    }
+    selected
+}
+
+/// Text [`capture`]d out of labels in this viewport since the last call.
+pub fn take_captured(ctx: &egui::Context) -> Option<String> {
+    let id = captured_id(ctx.viewport_id());
+    ctx.data_mut(|d| d.remove_temp::<String>(id))
+}
+
+/// Puts `html` on the clipboard with `text` for where HTML can't be pasted.
+pub fn set_clipboard(
+    clipboard: &mut Option<arboard::Clipboard>,
+    html: &str,
+    text: &str,
+) -> Result<()> {
+    // Kept around, since on some systems the clipboard empties with it
+    let clipboard = match clipboard {
+        Some(clipboard) => clipboard,
+        None => clipboard.insert(arboard::Clipboard::new()?),
+    };
+    clipboard.set_html(html, Some(text))?;
+    Ok(())
+}
+
+/// What a copy puts on the clipboard.
+pub struct Copied {
+    pub html: String,
+    pub text: String,
+}
+
+/// The part of `markdown` that reads as `copied` once rendered, or `None`
+/// if it isn't found there.
+pub fn selection(markdown: &str, copied: &str) -> Option<Copied> {
+    let events: Vec<Event> = export::parse(markdown).collect();
+
+    // Rendered text without whitespace, which egui lays out its own way,
+    // and which event and byte each character comes from
+    let mut text = String::new();
+    let mut origins = Vec::new();
+    let mut in_metadata = false;
+    let mut in_code_block = false;
+    let mut image_depth = 0;
+    for (n, event) in events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::MetadataBlock(_)) => in_metadata = true,
+            Event::End(TagEnd::MetadataBlock(_)) => in_metadata = false,
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Start(Tag::Image { .. }) => image_depth += 1,
+            Event::End(TagEnd::Image) => image_depth -= 1,
+            Event::Text(piece) | Event::Code(piece) | Event::FootnoteReference(piece)
+                if !in_metadata && !in_code_block && image_depth == 0 =>
+            {
+                for (at, c) in piece.char_indices().filter(|(_, c)| !c.is_whitespace()) {
+                    text.push(c);
+                    origins.push((n, at, at + c.len_utf8()));
+                }
+            }
+            _ => {}
+        }
+    }
+    let needle: String = copied.chars().filter(|c| !c.is_whitespace()).collect();
+    if needle.is_empty() {
+        return None;
+    }
+    let start = text[..text.find(&needle)?].chars().count();
+    let (first, from, _) = origins[start];
+    let (last, _, to) = origins[start + needle.chars().count() - 1];
+
+    // Only what was selected, inside just the elements it was in
+    let mut selected = Vec::new();
+    let mut open: Vec<(Event, bool)> = Vec::new();
+    for (n, event) in events.into_iter().enumerate() {
+        if n > last && open.is_empty() {
+            break;
+        }
+        match event {
+            Event::Start(_) => open.push((event, false)),
+            Event::End(_) => {
+                if let Some((_, true)) = open.pop() {
+                    selected.push(event);
+                }
+            }
+            Event::Text(ref piece) | Event::Code(ref piece) if (first..=last).contains(&n) => {
+                let end = if n == last { to } else { piece.len() };
+                let begin = if n == first { from } else { 0 };
+                let piece = CowStr::from(piece[begin..end].to_string());
+                let event = match event {
+                    Event::Code(_) => Event::Code(piece),
+                    _ => Event::Text(piece),
+                };
+                emit(&mut selected, &mut open, event);
+            }
+            event if first < n && n < last => emit(&mut selected, &mut open, event),
+            _ => {}
+        }
+    }
+    Some(copy(selected))
+}
+
+/// All of `markdown`, for when the whole document is selected.
+pub fn everything(markdown: &str) -> Copied {
+    copy(export::parse(markdown).collect())
+}
+
+fn copy(events: Vec<Event>) -> Copied {
+    let text = plain_text(&events);
+    let html = export::to_html(events, |dest, wiki| {
+        // Web links still work pasted elsewhere; links within the folder don't
+        (!wiki && dest.contains(':')).then(|| dest.to_string())
+    });
+    Copied { html, text }
+}
+
+/// The text of `events` as read, with a line per paragraph, item or row.
+fn plain_text(events: &[Event]) -> String {
+    let mut text = String::new();
+    // Next number of each ordered list, `None` for bullets
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut depth = 0;
+    let mut hidden = 0;
+    let end_line = |text: &mut String| {
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+    };
+    for event in events {
+        match event {
+            Event::Start(Tag::MetadataBlock(_) | Tag::Image { .. }) => hidden += 1,
+            Event::End(TagEnd::MetadataBlock(_) | TagEnd::Image) => hidden -= 1,
+            _ if hidden > 0 => continue,
+            Event::Start(tag) => {
+                match tag {
+                    Tag::List(start) => lists.push(*start),
+                    Tag::Item => {
+                        end_line(&mut text);
+                        text.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                        match lists.last_mut() {
+                            Some(Some(n)) => {
+                                text.push_str(&format!("{n}. "));
+                                *n += 1;
+                            }
+                            _ => text.push_str("- "),
+                        }
+                    }
+                    Tag::TableCell if !text.ends_with('\n') && !text.is_empty() => {
+                        text.push('\t');
+                    }
+                    Tag::FootnoteDefinition(label) => text.push_str(&format!("[{label}]: ")),
+                    _ => {}
+                }
+                depth += 1;
+            }
+            Event::End(tag) => {
+                depth -= 1;
+                match tag {
+                    TagEnd::List(_) => {
+                        lists.pop();
+                    }
+                    TagEnd::Paragraph
+                    | TagEnd::Heading(_)
+                    | TagEnd::CodeBlock
+                    | TagEnd::Item
+                    | TagEnd::TableHead
+                    | TagEnd::TableRow
+                    | TagEnd::DefinitionListTitle
+                    | TagEnd::DefinitionListDefinition => end_line(&mut text),
+                    _ => {}
+                }
+                // A blank line between top-level blocks
+                if depth == 0 && !text.is_empty() {
+                    text.push('\n');
+                }
+            }
+            Event::Text(piece) | Event::Code(piece) => text.push_str(piece),
+            Event::FootnoteReference(label) => text.push_str(&format!("[{label}]")),
+            Event::SoftBreak => text.push(' '),
+            Event::HardBreak => text.push('\n'),
+            Event::TaskListMarker(done) => text.push_str(if *done { "[x] " } else { "[ ] " }),
+            Event::Rule if depth == 0 => text.push_str("---\n\n"),
+            _ => {}
+        }
+    }
+    text.trim_end().to_string()
+}
+
+/// Adds `event` to `selected`, after the starts of the elements it's in
+/// that aren't there yet.
+fn emit<'a>(selected: &mut Vec<Event<'a>>, open: &mut [(Event<'a>, bool)], event: Event<'a>) {
+    for (start, written) in open.iter_mut().filter(|(_, written)| !*written) {
+        selected.push(start.clone());
+        *written = true;
+    }
+    selected.push(event);
+}
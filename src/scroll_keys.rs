@@ -0,0 +1,90 @@
+//! Reading with the keyboard: Space, PageUp/PageDown and Home/End, and
+//! j/k, d/u and gg/G as in vim and less.
+
+use eframe::egui::{self, Key, Modifiers};
+
+/// Share of the view a page scrolls by, keeping some of it in sight.
+const PAGE: f32 = 0.9;
+
+/// Text lines j and k scroll by.
+const LINES: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scroll {
+    Lines(f32),
+    Pages(f32),
+    Top,
+    Bottom,
+}
+
+#[derive(Default)]
+pub struct ScrollKeys {
+    /// A first `g` was pressed, waiting for the second.
+    pending_g: bool,
+}
+
+impl ScrollKeys {
+    /// The scroll asked for with the keys pressed this frame, reading vim's
+    /// letters too with `vim`. Nothing while a text field has the keyboard.
+    pub fn read(&mut self, ctx: &egui::Context, vim: bool) -> Option<Scroll> {
+        if ctx.memory(|m| m.focused().is_some()) {
+            return None;
+        }
+        ctx.input_mut(|i| {
+            // Shifted keys first, as the unshifted ones would take them too
+            let mut keys = vec![
+                (Modifiers::SHIFT, Key::Space, Scroll::Pages(-1.0)),
+                (Modifiers::NONE, Key::Space, Scroll::Pages(1.0)),
+                (Modifiers::NONE, Key::PageUp, Scroll::Pages(-1.0)),
+                (Modifiers::NONE, Key::PageDown, Scroll::Pages(1.0)),
+                (Modifiers::NONE, Key::Home, Scroll::Top),
+                (Modifiers::NONE, Key::End, Scroll::Bottom),
+            ];
+            if vim {
+                keys.extend([
+                    (Modifiers::SHIFT, Key::G, Scroll::Bottom),
+                    (Modifiers::NONE, Key::J, Scroll::Lines(LINES)),
+                    (Modifiers::NONE, Key::K, Scroll::Lines(-LINES)),
+                    (Modifiers::NONE, Key::D, Scroll::Pages(0.5)),
+                    (Modifiers::NONE, Key::U, Scroll::Pages(-0.5)),
+                ]);
+            }
+            let mut scroll = keys
+                .into_iter()
+                .find(|(modifiers, key, _)| i.consume_key(*modifiers, *key))
+                .map(|(_, _, scroll)| scroll);
+            if vim && scroll.is_none() && i.consume_key(Modifiers::NONE, Key::G) {
+                if std::mem::take(&mut self.pending_g) {
+                    scroll = Some(Scroll::Top);
+                } else {
+                    self.pending_g = true;
+                }
+            } else if scroll.is_some() {
+                self.pending_g = false;
+            }
+            scroll
+        })
+    }
+}
+
+/// Scrolls the scroll area `ui` is the contents of, before they are added.
+/// The bottom is scrolled to with [`finish`], once they are.
+pub fn start(ui: &mut egui::Ui, scroll: Option<Scroll>, line_height: f32) {
+    let page = ui.clip_rect().height() * PAGE;
+    let by = match scroll {
+        Some(Scroll::Lines(lines)) => lines * line_height,
+        Some(Scroll::Pages(pages)) => pages * page,
+        Some(Scroll::Top) => {
+            ui.scroll_to_cursor(Some(egui::Align::TOP));
+            return;
+        }
+        _ => return,
+    };
+    ui.scroll_with_delta(egui::vec2(0.0, -by));
+}
+
+pub fn finish(ui: &mut egui::Ui, scroll: Option<Scroll>) {
+    if scroll == Some(Scroll::Bottom) {
+        ui.scroll_to_cursor(Some(egui::Align::BOTTOM));
+    }
+}
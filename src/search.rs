@@ -0,0 +1,215 @@
+//! Find-in-document bar.
+
+use std::ops::Range;
+
+use eframe::egui;
+
+use crate::blocks::Document;
+use crate::i18n::tr;
+
+/// Which parts of the document a search looks at.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SearchScope {
+    #[default]
+    Everything,
+    Prose,
+    Code,
+}
+
+impl SearchScope {
+    const CHOICES: [SearchScope; 3] = [
+        SearchScope::Everything,
+        SearchScope::Prose,
+        SearchScope::Code,
+    ];
+
+    fn label(self) -> &'static str {
+        tr(match self {
+            SearchScope::Everything => "Whole document",
+            SearchScope::Prose => "Prose only",
+            SearchScope::Code => "Code only",
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct Search {
+    pub open: bool,
+    query: String,
+    scope: SearchScope,
+    matches: Vec<Range<usize>>,
+    current: usize,
+    /// Query, scope and document revision `matches` were computed for.
+    computed_for: Option<(String, SearchScope, u64)>,
+    focus_query: bool,
+}
+
+/// What the search bar asks the viewer to do.
+pub enum SearchAction {
+    None,
+    /// Bring the match starting at this byte offset into view.
+    Reveal(usize),
+}
+
+impl Search {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.focus_query = true;
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Index of the match the user is on, among all of them.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Byte range of the match the user is currently on.
+    pub fn current_match(&self) -> Option<Range<usize>> {
+        self.open
+            .then(|| self.matches.get(self.current).cloned())
+            .flatten()
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        source: &str,
+        doc: &Document,
+        revision: u64,
+    ) -> SearchAction {
+        let mut step: Option<isize> = None;
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Find:"));
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.query)
+                    .desired_width(240.0)
+                    .hint_text(tr("Search this document")),
+            );
+            if std::mem::take(&mut self.focus_query) {
+                response.request_focus();
+            }
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                step = Some(if ui.input(|i| i.modifiers.shift) {
+                    -1
+                } else {
+                    1
+                });
+                response.request_focus();
+            }
+
+            egui::ComboBox::from_id_salt("search_scope")
+                .selected_text(self.scope.label())
+                .show_ui(ui, |ui| {
+                    for choice in SearchScope::CHOICES {
+                        ui.selectable_value(&mut self.scope, choice, choice.label());
+                    }
+                });
+
+            if self.refresh(source, doc, revision) && !self.matches.is_empty() {
+                // Jump to the first hit while typing
+                step = Some(0);
+            }
+
+            if ui
+                .button("▲")
+                .on_hover_text(tr("Previous match (Shift+Enter)"))
+                .clicked()
+            {
+                step = Some(-1);
+            }
+            if ui
+                .button("▼")
+                .on_hover_text(tr("Next match (Enter)"))
+                .clicked()
+            {
+                step = Some(1);
+            }
+
+            if self.matches.is_empty() {
+                if !self.query.is_empty() {
+                    ui.weak(tr("No matches"));
+                }
+            } else {
+                ui.label(format!("{} / {}", self.current + 1, self.matches.len()));
+            }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("×").on_hover_text(tr("Close (Esc)")).clicked() {
+                    self.open = false;
+                }
+            });
+        });
+
+        if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.open = false;
+        }
+
+        match step {
+            Some(delta) if !self.matches.is_empty() => {
+                let len = self.matches.len() as isize;
+                self.current = (self.current as isize + delta).rem_euclid(len) as usize;
+                SearchAction::Reveal(self.matches[self.current].start)
+            }
+            _ => SearchAction::None,
+        }
+    }
+
+    /// Recomputes matches if the query, scope or document changed. Returns whether it did.
+    fn refresh(&mut self, source: &str, doc: &Document, revision: u64) -> bool {
+        let key = (self.query.clone(), self.scope, revision);
+        if self.computed_for.as_ref() == Some(&key) {
+            return false;
+        }
+        self.matches = find(source, doc, &self.query, self.scope);
+        self.current = 0;
+        self.computed_for = Some(key);
+        true
+    }
+}
+
+/// ASCII case-insensitive search for `query` in `source`, limited to `scope`.
+fn find(source: &str, doc: &Document, query: &str, scope: SearchScope) -> Vec<Range<usize>> {
+    // Code spans are sorted and never overlap, so the only candidate is the
+    // first span ending at or after the match.
+    let in_code = |range: &Range<usize>| {
+        let idx = doc.code_spans.partition_point(|code| code.end < range.end);
+        doc.code_spans
+            .get(idx)
+            .is_some_and(|code| code.start <= range.start)
+    };
+
+    let mut matches = occurrences(source, query);
+    matches.retain(|range| match scope {
+        SearchScope::Everything => true,
+        SearchScope::Prose => !in_code(range),
+        SearchScope::Code => in_code(range),
+    });
+    matches
+}
+
+/// Where `query` appears in `source`, ignoring ASCII case, without overlaps.
+pub fn occurrences(source: &str, query: &str) -> Vec<Range<usize>> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let haystack = source.as_bytes();
+    let needle = query.as_bytes();
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start + needle.len() <= haystack.len() {
+        if haystack[start..start + needle.len()].eq_ignore_ascii_case(needle)
+            && source.is_char_boundary(start)
+        {
+            matches.push(start..start + needle.len());
+            start += needle.len();
+        } else {
+            start += 1;
+        }
+    }
+    matches
+}
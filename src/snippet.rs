@@ -0,0 +1,212 @@
+//! Capturing a dragged-out region of the rendered document as an image.
+
+use std::path::PathBuf;
+
+use crate::i18n::tr;
+use anyhow::{Context as _, Result};
+use eframe::egui;
+use rfd::FileDialog;
+
+/// Space around the captured region, filled with the theme background.
+const PADDING: usize = 16;
+
+/// Where a captured snippet ends up.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Destination {
+    File,
+    Clipboard,
+}
+
+#[derive(Default)]
+pub struct SnippetCapture {
+    state: State,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Idle,
+    /// Waiting for the user to drag out a region.
+    Selecting {
+        destination: Destination,
+        start: Option<egui::Pos2>,
+    },
+    /// A screenshot of the window was requested; crop it to `region` once it arrives.
+    Capturing {
+        destination: Destination,
+        region: egui::Rect,
+    },
+}
+
+/// How a capture ended.
+pub enum Captured {
+    Saved(PathBuf),
+    Copied,
+    /// The save dialog was dismissed.
+    Cancelled,
+}
+
+impl SnippetCapture {
+    pub fn start(&mut self, destination: Destination) {
+        self.state = State::Selecting {
+            destination,
+            start: None,
+        };
+    }
+
+    /// Lets the user drag out a region of `area` while selecting, and turns
+    /// the screenshot into an image once it comes back. Returns `None` until
+    /// a capture finishes or fails.
+    pub fn show(&mut self, ctx: &egui::Context, area: egui::Rect) -> Option<Result<Captured>> {
+        match &mut self.state {
+            State::Idle => None,
+            State::Selecting { destination, start } => {
+                let destination = *destination;
+                if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    self.state = State::Idle;
+                    return None;
+                }
+
+                let response = egui::Area::new(egui::Id::new("snippet_selection"))
+                    .order(egui::Order::Foreground)
+                    .fixed_pos(area.min)
+                    .show(ctx, |ui| {
+                        let response = ui.allocate_rect(area, egui::Sense::drag());
+                        response.on_hover_cursor(egui::CursorIcon::Crosshair)
+                    })
+                    .inner;
+
+                if response.drag_started() {
+                    *start = response.interact_pointer_pos();
+                }
+                let current = ctx.input(|i| i.pointer.interact_pos());
+                let selection = start
+                    .zip(current)
+                    .map(|(a, b)| egui::Rect::from_two_pos(a, b).intersect(area));
+
+                if response.drag_stopped() {
+                    match selection.filter(|r| r.width() >= 4.0 && r.height() >= 4.0) {
+                        Some(region) => {
+                            // Nothing of ours is painted this frame, so the screenshot is clean
+                            self.state = State::Capturing {
+                                destination,
+                                region,
+                            };
+                            ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot(
+                                egui::UserData::default(),
+                            ));
+                        }
+                        None => *start = None,
+                    }
+                    return None;
+                }
+
+                let painter = ctx.layer_painter(egui::LayerId::new(
+                    egui::Order::Foreground,
+                    egui::Id::new("snippet_overlay"),
+                ));
+                let dim = egui::Color32::from_black_alpha(90);
+                match selection {
+                    Some(region) => {
+                        // Dim everything but the selection
+                        for outside in [
+                            egui::Rect::from_min_max(
+                                area.min,
+                                egui::pos2(area.max.x, region.min.y),
+                            ),
+                            egui::Rect::from_min_max(
+                                egui::pos2(area.min.x, region.max.y),
+                                area.max,
+                            ),
+                            egui::Rect::from_min_max(
+                                egui::pos2(area.min.x, region.min.y),
+                                egui::pos2(region.min.x, region.max.y),
+                            ),
+                            egui::Rect::from_min_max(
+                                egui::pos2(region.max.x, region.min.y),
+                                egui::pos2(area.max.x, region.max.y),
+                            ),
+                        ] {
+                            painter.rect_filled(outside, 0.0, dim);
+                        }
+                        painter.rect_stroke(
+                            region,
+                            0.0,
+                            ctx.style().visuals.selection.stroke,
+                            egui::StrokeKind::Outside,
+                        );
+                    }
+                    None => {
+                        painter.rect_filled(area, 0.0, dim);
+                        painter.text(
+                            area.center(),
+                            egui::Align2::CENTER_CENTER,
+                            tr("Drag to select a region · Esc to cancel"),
+                            egui::FontId::proportional(18.0),
+                            egui::Color32::WHITE,
+                        );
+                    }
+                }
+                None
+            }
+            State::Capturing {
+                destination,
+                region,
+            } => {
+                let (destination, region) = (*destination, *region);
+                let screenshot = ctx.input(|i| {
+                    i.raw.events.iter().find_map(|event| match event {
+                        egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                        _ => None,
+                    })
+                })?;
+                self.state = State::Idle;
+
+                let background = ctx.style().visuals.panel_fill;
+                let snippet = pad(
+                    &screenshot.region(&region, Some(ctx.pixels_per_point())),
+                    background,
+                );
+                Some(match destination {
+                    Destination::Clipboard => {
+                        ctx.copy_image(snippet);
+                        Ok(Captured::Copied)
+                    }
+                    Destination::File => save_png(&snippet)
+                        .map(|path| path.map_or(Captured::Cancelled, Captured::Saved)),
+                })
+            }
+        }
+    }
+}
+
+/// `image` with [`PADDING`] pixels of `background` on every side.
+fn pad(image: &egui::ColorImage, background: egui::Color32) -> egui::ColorImage {
+    let [width, height] = image.size;
+    let mut padded =
+        egui::ColorImage::filled([width + 2 * PADDING, height + 2 * PADDING], background);
+    for (row, pixels) in image.pixels.chunks(width.max(1)).enumerate() {
+        let start = (row + PADDING) * padded.size[0] + PADDING;
+        padded.pixels[start..start + pixels.len()].copy_from_slice(pixels);
+    }
+    padded
+}
+
+/// Asks where to save `image` and writes it there. Returns `None` if the user cancelled.
+fn save_png(image: &egui::ColorImage) -> Result<Option<PathBuf>> {
+    let Some(path) = FileDialog::new()
+        .add_filter(tr("PNG image"), &["png"])
+        .set_file_name("snippet.png")
+        .set_title(tr("Export snippet"))
+        .save_file()
+    else {
+        return Ok(None);
+    };
+
+    let [width, height] = image.size;
+    let bytes = image.pixels.iter().flat_map(|c| c.to_array()).collect();
+    image::RgbaImage::from_raw(width as u32, height as u32, bytes)
+        .context("snippet has an invalid size")?
+        .save(&path)?;
+    Ok(Some(path))
+}
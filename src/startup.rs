@@ -0,0 +1,51 @@
+//! Cold-start timing, printed to standard error with `--startup-timing` to
+//! see where the time before the window is usable goes.
+
+use std::time::Instant;
+
+pub const FLAG: &str = "--startup-timing";
+
+#[derive(Clone, Copy)]
+pub struct Timing {
+    enabled: bool,
+    /// When the process started, near enough.
+    launched: Instant,
+}
+
+impl Timing {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            launched: Instant::now(),
+        }
+    }
+
+    /// Runs `f`, reporting how long it took as `phase`.
+    pub fn measure<R>(&self, phase: &str, f: impl FnOnce() -> R) -> R {
+        let started = Instant::now();
+        let result = f();
+        if self.enabled {
+            eprintln!(
+                "startup: {phase:<24} {:>8.1} ms  (done at {:.1} ms)",
+                started.elapsed().as_secs_f64() * 1000.0,
+                self.since_launch()
+            );
+        }
+        result
+    }
+
+    /// Reports that `phase` is done, by the time since launch.
+    pub fn mark(&self, phase: &str) {
+        if self.enabled {
+            eprintln!(
+                "startup: {phase:<24} {:>8}     (done at {:.1} ms)",
+                "",
+                self.since_launch()
+            );
+        }
+    }
+
+    fn since_launch(&self) -> f64 {
+        self.launched.elapsed().as_secs_f64() * 1000.0
+    }
+}
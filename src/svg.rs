@@ -0,0 +1,141 @@
+//! SVG images that `egui_extras`' loader doesn't recognize, since it only
+//! goes by a URI ending in `.svg`: ones with a query after the name, like
+//! GitHub's `?raw=true`, an uppercase extension, or none at all but served
+//! as `image/svg+xml`. They're rasterized for the size they're shown at,
+//! again when zooming.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex, OnceLock, PoisonError,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use eframe::egui::{
+    self, ColorImage,
+    load::{BytesPoll, ImageLoadResult, ImageLoader, ImagePoll, LoadError, SizeHint},
+};
+
+type Rasterized = Result<Arc<ColorImage>, String>;
+
+/// Each size an image was asked for, with the pass it last was.
+type Sizes = HashMap<SizeHint, (u64, Rasterized)>;
+
+#[derive(Default)]
+pub struct SvgLoader {
+    pass: AtomicU64,
+    cache: Mutex<HashMap<String, Sizes>>,
+}
+
+impl SvgLoader {
+    /// Installs the loader; it must come after `egui_extras`' loaders, so
+    /// the image one doesn't turn these away first.
+    pub fn install(ctx: &egui::Context) {
+        ctx.add_image_loader(Arc::new(Self::default()));
+    }
+}
+
+/// Whether the file part of `uri` says it's an SVG, when the whole URI
+/// doesn't.
+fn is_svg_name(uri: &str) -> bool {
+    let path = uri.split(['?', '#']).next().unwrap_or(uri);
+    !uri.ends_with(".svg")
+        && path
+            .rsplit_once('.')
+            .is_some_and(|(_, extension)| extension.eq_ignore_ascii_case("svg"))
+}
+
+/// Whether `uri` names no file type, leaving it to how it's served. Other
+/// images, and anything on disk, would be fetched only to be turned away.
+fn leaves_it_to_mime(uri: &str) -> bool {
+    let path = uri.split(['?', '#']).next().unwrap_or(uri);
+    let name = path.rsplit('/').next().unwrap_or(path);
+    (uri.starts_with("http://") || uri.starts_with("https://")) && !name.contains('.')
+}
+
+impl ImageLoader for SvgLoader {
+    fn id(&self) -> &str {
+        egui::generate_loader_id!(SvgLoader)
+    }
+
+    fn load(&self, ctx: &egui::Context, uri: &str, size_hint: SizeHint) -> ImageLoadResult {
+        let by_name = is_svg_name(uri);
+        if !by_name && !leaves_it_to_mime(uri) {
+            return Err(LoadError::NotSupported);
+        }
+        let pass = self.pass.load(Ordering::Relaxed);
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        let sizes = cache.entry(uri.to_owned()).or_default();
+        if let Some((used, rasterized)) = sizes.get_mut(&size_hint) {
+            *used = pass;
+            return match rasterized.clone() {
+                Ok(image) => Ok(ImagePoll::Ready { image }),
+                Err(e) => Err(LoadError::Loading(e)),
+            };
+        }
+
+        match ctx.try_load_bytes(uri)? {
+            BytesPoll::Pending { size } => Ok(ImagePoll::Pending { size }),
+            BytesPoll::Ready { bytes, mime, .. } => {
+                let served_as_svg = mime.is_some_and(|mime| mime.contains("svg"));
+                if !by_name && !served_as_svg {
+                    return Err(LoadError::NotSupported);
+                }
+                let rasterized =
+                    egui_extras::image::load_svg_bytes_with_size(&bytes, size_hint, options())
+                        .map(Arc::new);
+                sizes.insert(size_hint, (pass, rasterized.clone()));
+                match rasterized {
+                    Ok(image) => Ok(ImagePoll::Ready { image }),
+                    Err(e) => Err(LoadError::Loading(e)),
+                }
+            }
+        }
+    }
+
+    fn forget(&self, uri: &str) {
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.remove(uri);
+    }
+
+    fn forget_all(&self) {
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.clear();
+    }
+
+    fn byte_size(&self) -> usize {
+        let cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache
+            .values()
+            .flat_map(HashMap::values)
+            .map(|(_, rasterized)| match rasterized {
+                Ok(image) => image.pixels.len() * size_of::<egui::Color32>(),
+                Err(e) => e.len(),
+            })
+            .sum()
+    }
+
+    fn end_pass(&self, pass: u64) {
+        self.pass.store(pass, Ordering::Relaxed);
+        // Sizes zoomed away from aren't kept around
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.retain(|_, sizes| {
+            if sizes.len() > 1 {
+                sizes.retain(|_, (used, _)| *used + 1 >= pass);
+            }
+            !sizes.is_empty()
+        });
+    }
+}
+
+/// With the system's fonts, for the labels of diagrams. Loaded the first
+/// time an image needs them.
+fn options() -> &'static resvg::usvg::Options<'static> {
+    static OPTIONS: OnceLock<resvg::usvg::Options<'static>> = OnceLock::new();
+    OPTIONS.get_or_init(|| {
+        let mut options = resvg::usvg::Options::default();
+        options.fontdb_mut().load_system_fonts();
+        options
+    })
+}
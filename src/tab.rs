@@ -0,0 +1,888 @@
+//! An open document and its per-tab viewing state.
+
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fs::{self, File},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, Read},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+    },
+    thread,
+    time::{Instant, SystemTime},
+};
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::blocks::{self, BlockKind, Document};
+use crate::config::Dialect;
+use crate::encoding::{self, Decoded};
+use crate::formats::Format;
+use crate::html_fragment::Toggled;
+use crate::reading_state::{DocState, Highlight, Position, Sidecar};
+use crate::toc::TocDepth;
+use crate::wikilink;
+use crate::workspace::Workspace;
+
+/// Where a highlight is in the source, if it still is.
+type Found = Option<Range<usize>>;
+
+/// The hash and contents of a file a comparison found changed.
+type Changed = Option<(u64, Decoded)>;
+
+pub struct DocTab {
+    /// Stable identity for egui state such as the scroll position.
+    pub id: u64,
+    pub title: String,
+    pub path: PathBuf,
+    pub format: Format,
+    /// The document as Markdown, converted from `format` if needed.
+    pub content: String,
+    pub doc: Document,
+    /// Changes whenever `content` does; unique across all tabs.
+    pub revision: u64,
+    pub last_read: SystemTime,
+    /// Encoding the file was stored in before being converted to UTF-8.
+    pub encoding: &'static str,
+    /// Hash of the file contents as of the last read, or of the ones a
+    /// comparison found since, if the change was ignored.
+    pub disk_hash: u64,
+    pub toc_depth: TocDepth,
+    /// Block to bring into view on the next frame, e.g. after a TOC click.
+    pub scroll_to_block: Option<usize>,
+    /// Byte offset to bring into view once the file has loaded.
+    pub reveal_on_load: Option<usize>,
+    /// Heading to go to once the file has loaded, by its `#` fragment.
+    pub heading_on_load: Option<String>,
+    /// Where to go back to once the file has loaded, as a reopened tab.
+    pub return_on_load: Option<Position>,
+    /// Vertical scroll offset of the viewer, as of the last frame.
+    pub scroll_offset: f32,
+    /// How much of the document has been scrolled through, from 0 to 1, as
+    /// of the last frame.
+    pub read_fraction: f32,
+    /// Destination of the internal link under the pointer, as of the last frame.
+    pub hovered_link: Option<String>,
+    /// URI of the image clicked in the document this frame.
+    pub clicked_image: Option<String>,
+    /// Code block whose save button was clicked this frame.
+    pub save_code: Option<usize>,
+    /// Block with the footnote reference last followed to its definition.
+    pub footnote_return: Option<usize>,
+    /// Heading block of the section at the top of the viewport, as of the last frame.
+    pub current_heading: Option<usize>,
+    /// How far the top of the viewport is below `current_heading`.
+    pub heading_offset: f32,
+    /// Heading to scroll back to after a reload, with `heading_offset` to restore.
+    pub restore_anchor: Option<(usize, f32)>,
+    /// Newer file contents held back while the user is reading.
+    pub pending_content: Option<String>,
+    /// What was shown before the last reload that changed the document.
+    pub previous_content: Option<String>,
+    /// Offer to show what reloads changed since `previous_content` was shown.
+    pub reload_notice: bool,
+    /// Reloads with changes since then.
+    pub unseen_reloads: usize,
+    /// The file changed on disk since the last read, with auto-reload off.
+    pub changed_externally: bool,
+    /// Headings whose sections are collapsed, by their [`Document::fragment`],
+    /// which tells apart headings with the same title.
+    pub collapsed: HashSet<String>,
+    /// `<details>` elements opened or closed from how they start.
+    pub toggled_details: Toggled,
+    /// Why the document couldn't be rendered as markdown.
+    pub render_error: Option<String>,
+    /// Show the raw source instead of rendered markdown.
+    pub plain_text: bool,
+    /// The Markdown extensions to read this document with, over the ones
+    /// in Preferences.
+    pub dialect: Option<Dialect>,
+    /// What `doc` was read as.
+    read_as: Dialect,
+    /// The whole document is selected, with Ctrl+A.
+    pub selected_all: bool,
+    /// Show an editor for the source above a scratch tab.
+    pub editing: bool,
+    /// Bookmarks, highlights and where reading stopped.
+    pub reading: DocState,
+    /// Where `reading` is kept, once the document is loaded.
+    pub sidecar: Option<Sidecar>,
+    /// `reading` as last loaded or saved, to only write it when it changed.
+    reading_saved: Option<DocState>,
+    /// Source ranges of the highlights, with the revision and highlights
+    /// they were found for.
+    highlighted: Option<(u64, Vec<Highlight>, Vec<Found>)>,
+    /// Revision and workspace root the wikilinks were last resolved for.
+    wikilinks_resolved: Option<(u64, Option<PathBuf>)>,
+    /// Contents being read on a background thread, until they arrive.
+    loading: Option<Receiver<Load>>,
+    /// Length of the whole document while it comes in chunks.
+    loading_len: Option<usize>,
+    /// When the tab was last shown.
+    pub last_viewed: Instant,
+    /// The document was let go of while the tab wasn't looked at.
+    unloaded: bool,
+    /// And so were its contents, to be read from the file again.
+    content_unloaded: bool,
+    /// Why reading the file again after that failed, until it's tried again.
+    load_again_failed: Option<String>,
+    /// A comparison of the file with `disk_hash` on a background thread,
+    /// with the hash it compares against, until it's done.
+    hash_check: Option<(u64, Receiver<Changed>)>,
+    /// Hash of the changed contents the last comparison found.
+    found_hash: Option<u64>,
+    /// The file being read on a background thread after it was modified,
+    /// until it's done.
+    disk_read: Option<Receiver<io::Result<Decoded>>>,
+}
+
+/// Bytes of a large file that are split at a time, about a few screenfuls.
+const CHUNK_BYTES: usize = 256 * 1024;
+
+/// What the background loader sends.
+enum Load {
+    /// The next piece of a file loaded in chunks, to show until it's all in.
+    Chunk {
+        doc: Document,
+        text: String,
+        len: usize,
+    },
+    /// The chunks so far were read in the wrong encoding, and come again.
+    Restart,
+    Done(io::Result<Loaded>),
+}
+
+/// A file read and prepared for display by the background loader.
+struct Loaded {
+    markdown: String,
+    doc: Document,
+    encoding: &'static str,
+    hash: u64,
+}
+
+impl DocTab {
+    /// A tab for `path` whose contents are read (and split into blocks) on a
+    /// background thread. It stays empty until [`Self::poll_load`] picks them up.
+    /// Documents longer than `chunked_above` bytes are split a chunk at a
+    /// time as they're read, and shown as far as they are.
+    pub fn open(path: PathBuf, chunked_above: usize) -> Self {
+        // Anything that isn't a known format was opened as Markdown on purpose
+        let format = Format::for_tab(&path);
+        let (sender, receiver) = mpsc::channel();
+        let thread_path = path.clone();
+        thread::spawn(move || {
+            let loaded = load(&thread_path, format, chunked_above, &sender);
+            // The tab may have been closed in the meantime
+            let _ = sender.send(Load::Done(loaded));
+        });
+
+        let title = path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "untitled.md".to_string());
+        let mut tab = Self::empty(title, path, format);
+        tab.loading = Some(receiver);
+        tab
+    }
+
+    /// An unnamed tab with no file behind it, for previewing pasted Markdown.
+    pub fn scratch(content: String) -> Self {
+        let mut tab = Self::empty("Scratch".into(), PathBuf::new(), Format::Markdown);
+        tab.set_content(content);
+        tab.editing = true;
+        tab
+    }
+
+    /// A tab showing Markdown piped in on standard input.
+    pub fn stdin(decoded: Decoded) -> Self {
+        let mut tab = Self::empty("stdin".into(), PathBuf::new(), Format::Markdown);
+        tab.disk_hash = content_hash(&decoded.text);
+        tab.encoding = decoded.encoding;
+        tab.set_content(decoded.text);
+        tab
+    }
+
+    fn empty(title: String, path: PathBuf, format: Format) -> Self {
+        Self {
+            id: next_id(),
+            title,
+            path,
+            format,
+            content: String::new(),
+            doc: Document::default(),
+            revision: next_id(),
+            disk_hash: content_hash(""),
+            last_read: SystemTime::now(),
+            encoding: "UTF-8",
+            toc_depth: TocDepth::default(),
+            scroll_to_block: None,
+            reveal_on_load: None,
+            heading_on_load: None,
+            return_on_load: None,
+            scroll_offset: 0.0,
+            read_fraction: 0.0,
+            hovered_link: None,
+            clicked_image: None,
+            save_code: None,
+            footnote_return: None,
+            current_heading: None,
+            heading_offset: 0.0,
+            restore_anchor: None,
+            pending_content: None,
+            previous_content: None,
+            reload_notice: false,
+            unseen_reloads: 0,
+            changed_externally: false,
+            collapsed: HashSet::new(),
+            toggled_details: Toggled::new(),
+            render_error: None,
+            plain_text: false,
+            dialect: None,
+            read_as: Dialect::default(),
+            selected_all: false,
+            editing: false,
+            reading: DocState::default(),
+            sidecar: None,
+            reading_saved: None,
+            highlighted: None,
+            wikilinks_resolved: None,
+            loading: None,
+            loading_len: None,
+            last_viewed: Instant::now(),
+            unloaded: false,
+            content_unloaded: false,
+            load_again_failed: None,
+            hash_check: None,
+            found_hash: None,
+            disk_read: None,
+        }
+    }
+
+    pub fn is_scratch(&self) -> bool {
+        self.path.as_os_str().is_empty()
+    }
+
+    /// Folder the document is in, which its relative links start from.
+    pub fn dir(&self) -> Option<&Path> {
+        self.path.parent().filter(|_| !self.is_scratch())
+    }
+
+    /// Points the document's wikilinks at their notes, marking those that
+    /// don't exist. Only does the work again when the document or the
+    /// workspace changed.
+    pub fn resolve_wikilinks(&mut self, workspace: Option<&Workspace>) {
+        let current = (self.revision, workspace.map(|ws| ws.root.as_path()));
+        if let Some((revision, root)) = &self.wikilinks_resolved
+            && (*revision, root.as_deref()) == current
+        {
+            return;
+        }
+        self.wikilinks_resolved = Some((self.revision, workspace.map(|ws| ws.root.clone())));
+
+        let dir = self.dir().map(Path::to_path_buf);
+        for block in &mut self.doc.blocks {
+            if block.markdown.contains("[[") {
+                block.wiki_markdown = wikilink::rewrite(&block.markdown, |note| {
+                    wikilink::resolve(note, dir.as_deref(), workspace).is_some()
+                });
+            }
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading.is_some()
+    }
+
+    /// How much of a document coming in chunks is in, from 0 to 1.
+    pub fn load_progress(&self) -> Option<f32> {
+        let len = self.loading_len.filter(|_| self.is_loading())?;
+        Some(self.content.len() as f32 / len.max(1) as f32)
+    }
+
+    pub fn is_unloaded(&self) -> bool {
+        self.unloaded
+    }
+
+    /// Lets go of the split document until the tab is shown again, with
+    /// `content` its contents too if they can be read from the file again
+    /// as they were.
+    pub fn unload(&mut self, content: bool) {
+        if self.unloaded || self.is_loading() {
+            return;
+        }
+        self.reading.position = self.position();
+        // The tab strip still shows it
+        let status = self.doc.status.take();
+        self.doc = Document {
+            status,
+            ..Document::default()
+        };
+        self.current_heading = None;
+        self.highlighted = None;
+        self.wikilinks_resolved = None;
+        self.unloaded = true;
+        if content && !self.is_scratch() && self.pending_content.is_none() && !self.reload_notice {
+            self.content = String::new();
+            self.content_unloaded = true;
+        }
+    }
+
+    /// Splits the document again after [`Self::unload`], reading the file
+    /// first if its contents were let go of too. If that fails the tab stays
+    /// unloaded, to be tried again.
+    pub fn load_again(&mut self) -> Result<()> {
+        if !self.unloaded {
+            return Ok(());
+        }
+        if self.content_unloaded {
+            let decoded = match encoding::read(&self.path) {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    self.load_again_failed = Some(e.to_string());
+                    return Err(e.into());
+                }
+            };
+            self.last_read = SystemTime::now();
+            self.changed_externally = false;
+            self.disk_hash = content_hash(&decoded.text);
+            self.encoding = decoded.encoding;
+            self.content = self.format.to_markdown(decoded.text);
+            self.revision = next_id();
+            self.content_unloaded = false;
+        }
+        self.doc = blocks::split_as(&self.content, self.read_as);
+        self.unloaded = false;
+        self.load_again_failed = None;
+        Ok(())
+    }
+
+    /// Why the file couldn't be read again by [`Self::load_again`], if it
+    /// couldn't the last time.
+    pub fn load_again_failed(&self) -> Option<&str> {
+        self.load_again_failed.as_deref()
+    }
+
+    /// The document as Markdown, read from the file again if its contents
+    /// were let go of by [`Self::unload`].
+    pub fn markdown(&self) -> io::Result<Cow<'_, str>> {
+        if !self.content_unloaded {
+            return Ok(Cow::Borrowed(&self.content));
+        }
+        let decoded = encoding::read(&self.path)?;
+        Ok(Cow::Owned(self.format.to_markdown(decoded.text)))
+    }
+
+    /// Takes in the background read started by [`Self::open`] once it is done,
+    /// and the chunks that came in so far of one in chunks. Returns `None`
+    /// while it is still running.
+    pub fn poll_load(&mut self) -> Option<Result<()>> {
+        let loaded = loop {
+            match self.loading.as_ref()?.try_recv() {
+                Ok(Load::Chunk { doc, text, len }) => {
+                    self.doc.append(doc, self.content.len());
+                    self.content.push_str(&text);
+                    self.loading_len = Some(len);
+                    self.revision = next_id();
+                }
+                Ok(Load::Restart) => {
+                    self.doc = Document::default();
+                    self.content.clear();
+                    self.revision = next_id();
+                }
+                Ok(Load::Done(loaded)) => break loaded.map_err(Into::into),
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => break Err(anyhow!("loader thread stopped")),
+            }
+        };
+        self.loading = None;
+        self.loading_len = None;
+        Some(loaded.map(|loaded| {
+            // Which the loader reads them as
+            self.read_as = Dialect::default();
+            let old_doc = std::mem::replace(&mut self.doc, loaded.doc);
+            // Where the reader got to in the chunks shown so far
+            self.restore_anchor = self.reanchor(&old_doc);
+            self.revision = next_id();
+            self.disk_hash = loaded.hash;
+            self.content = loaded.markdown;
+            self.encoding = loaded.encoding;
+            self.last_read = SystemTime::now();
+        }))
+    }
+
+    pub fn reload(&mut self) -> Result<()> {
+        if self.is_scratch() {
+            bail!("scratch tabs have no file to reload");
+        }
+        if self.content_unloaded {
+            // Nothing shown to compare with
+            return self.load_again();
+        }
+        let decoded = encoding::read(&self.path)?;
+        self.update_from_disk(decoded, false);
+        Ok(())
+    }
+
+    /// Takes in freshly read file contents, holding them back in
+    /// `pending_content` instead of showing them if `hold` is set.
+    pub fn update_from_disk(&mut self, decoded: Decoded, hold: bool) {
+        self.last_read = SystemTime::now();
+        self.changed_externally = false;
+        self.disk_hash = content_hash(&decoded.text);
+        self.encoding = decoded.encoding;
+        if hold {
+            self.pending_content = Some(decoded.text);
+        } else {
+            self.show_new_version(decoded.text);
+            self.pending_content = None;
+        }
+    }
+
+    /// Shows newly read file contents, keeping the old ones to compare.
+    /// Until the changes are looked at or dismissed, they are compared with
+    /// what was shown before the first of them, however often it reloads.
+    fn show_new_version(&mut self, text: String) {
+        let previous = self.content.clone();
+        self.set_content(text);
+        if self.content == previous {
+            return;
+        }
+        if !self.reload_notice {
+            self.previous_content = Some(previous);
+            self.unseen_reloads = 0;
+        }
+        self.unseen_reloads += 1;
+        // Changed back to what was being read
+        self.reload_notice = self.previous_content.as_ref() != Some(&self.content);
+    }
+
+    /// Whether the file on disk was modified after we last read it.
+    pub fn changed_on_disk(&self) -> bool {
+        fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified > self.last_read)
+    }
+
+    /// Notes whether the file changed on disk, to offer reloading it.
+    pub fn check_external_change(&mut self) {
+        if !self.changed_externally && !self.is_loading() && !self.is_scratch() {
+            self.changed_externally = self.changed_on_disk();
+        }
+    }
+
+    /// Keeps showing what was read, until the file changes again.
+    pub fn ignore_external_change(&mut self) {
+        // A modification time ahead of the clock shouldn't bring it back
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified());
+        self.last_read = modified.map_or(SystemTime::now(), |t| t.max(SystemTime::now()));
+        // Nor should the contents compared
+        if let Some(hash) = self.found_hash.take() {
+            self.disk_hash = hash;
+        }
+        self.changed_externally = false;
+    }
+
+    /// Starts reading the file on a background thread if it was modified
+    /// since the last read, unless it's being read already.
+    pub fn read_if_changed(&mut self) {
+        if self.disk_read.is_some() || !self.changed_on_disk() {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        let path = self.path.clone();
+        thread::spawn(move || {
+            // The tab may have been closed in the meantime
+            let _ = sender.send(encoding::read(&path));
+        });
+        self.disk_read = Some(receiver);
+    }
+
+    /// The file's contents, or why they couldn't be read, once the read
+    /// [`Self::read_if_changed`] started is done.
+    pub fn read_from_disk(&mut self) -> Option<io::Result<Decoded>> {
+        let read = match self.disk_read.as_ref()?.try_recv() {
+            Ok(read) => read,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => Err(io::Error::other("the read was cut short")),
+        };
+        self.disk_read = None;
+        Some(read)
+    }
+
+    /// Starts reading the file on a background thread to compare it with
+    /// the last read, unless a comparison is under way. Slower than
+    /// [`Self::changed_on_disk`], but doesn't rely on modification times,
+    /// which network mounts don't always keep accurate.
+    pub fn check_hash(&mut self) {
+        if self.hash_check.is_some() {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        let path = self.path.clone();
+        let disk_hash = self.disk_hash;
+        thread::spawn(move || {
+            let changed = encoding::read(&path).ok().and_then(|decoded| {
+                let hash = content_hash(&decoded.text);
+                (hash != disk_hash).then_some((hash, decoded))
+            });
+            // The tab may have been closed in the meantime
+            let _ = sender.send(changed);
+        });
+        self.hash_check = Some((disk_hash, receiver));
+    }
+
+    /// The file's contents, once the comparison [`Self::check_hash`]
+    /// started is done, if they differ from the last read.
+    pub fn changed_by_hash(&mut self) -> Option<Decoded> {
+        let (compared, receiver) = self.hash_check.as_ref()?;
+        let changed = match receiver.try_recv() {
+            Ok(changed) => changed,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => None,
+        };
+        // Read again since it started, which makes it out of date
+        let current = *compared == self.disk_hash;
+        self.hash_check = None;
+        let (hash, decoded) = changed.filter(|_| current)?;
+        self.found_hash = Some(hash);
+        Some(decoded)
+    }
+
+    pub fn set_all_collapsed(&mut self, collapse: bool) {
+        self.collapsed.clear();
+        if collapse {
+            let fragments = self.doc.fragments().into_iter();
+            self.collapsed
+                .extend(fragments.map(|(_, fragment)| fragment));
+        }
+    }
+
+    pub fn apply_pending(&mut self) {
+        if let Some(content) = self.pending_content.take() {
+            self.show_new_version(content);
+        }
+    }
+
+    /// Shows `text`, the file contents in the tab's format, as the document.
+    pub fn set_content(&mut self, text: String) {
+        let content = self.format.to_markdown(text);
+        let old_doc = std::mem::replace(&mut self.doc, blocks::split_as(&content, self.read_as));
+        self.restore_anchor = self.reanchor(&old_doc);
+        self.unloaded = false;
+        self.revision = next_id();
+        // The new contents get another chance
+        self.render_error = None;
+        self.content = content;
+    }
+
+    /// Reads the document again as `dialect` if it was read as another,
+    /// keeping the place the reader is at.
+    pub fn use_dialect(&mut self, dialect: Dialect) {
+        if self.read_as == dialect || self.is_loading() {
+            return;
+        }
+        self.read_as = dialect;
+        let old_doc = std::mem::replace(&mut self.doc, blocks::split_as(&self.content, dialect));
+        self.restore_anchor = self.reanchor(&old_doc);
+        self.revision = next_id();
+        self.render_error = None;
+    }
+
+    /// Finds the heading the reader was at in `old_doc` in the current
+    /// document, falling back to the closest earlier heading that survived.
+    fn reanchor(&self, old_doc: &Document) -> Option<(usize, f32)> {
+        let current = self.current_heading?;
+        if self.scroll_offset <= 0.0 {
+            return None;
+        }
+        let mut offset = self.heading_offset;
+        for idx in (0..=current).rev() {
+            if let Some((slug, occurrence)) = old_doc.anchor(idx) {
+                if let Some(new_idx) = self.doc.find_anchor(&slug, occurrence) {
+                    return Some((new_idx, offset));
+                }
+                // Position within a section we no longer know is meaningless
+                offset = 0.0;
+            }
+        }
+        None
+    }
+
+    /// Titles of the headings leading to the section currently at the top of
+    /// the viewport, outermost first, with their block indices.
+    pub fn breadcrumbs(&self) -> Vec<(usize, &str)> {
+        let Some(current) = self.current_heading else {
+            return Vec::new();
+        };
+        let mut path = self.doc.enclosing_headings(current);
+        path.reverse();
+        path.push(current);
+        path.into_iter()
+            .filter_map(|idx| match &self.doc.blocks.get(idx)?.kind {
+                BlockKind::Heading { title, .. } => Some((idx, title.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Title of the section at the top of the viewport, as of the last frame.
+    pub fn current_section(&self) -> Option<&str> {
+        match &self.doc.blocks.get(self.current_heading?)?.kind {
+            BlockKind::Heading { title, .. } => Some(title),
+            _ => None,
+        }
+    }
+
+    /// The block of the heading titled `title`.
+    pub fn heading_block(&self, title: &str) -> Option<usize> {
+        self.doc.blocks.iter().position(
+            |block| matches!(&block.kind, BlockKind::Heading { title: t, .. } if t == title),
+        )
+    }
+
+    /// Reads the bookmarks and highlights kept for the document, and goes
+    /// back to where reading stopped.
+    pub fn load_reading(&mut self, workspace: Option<&Workspace>) -> Result<()> {
+        if self.is_scratch() {
+            return Ok(());
+        }
+        let Some(sidecar) = Sidecar::locate(&self.path, workspace) else {
+            return Ok(());
+        };
+        let state = sidecar.load();
+        self.sidecar = Some(sidecar);
+        let state = state?;
+        if let Some(position) = &state.position {
+            self.return_to(position);
+        }
+        self.reading_saved = Some(state.clone());
+        self.reading = state;
+        Ok(())
+    }
+
+    /// Where reading is at: the section at the top of the view and how far
+    /// into it, or the offset from the top before the first heading.
+    pub fn position(&self) -> Option<Position> {
+        if self.unloaded {
+            // As it was when the document was let go of
+            return self.reading.position.clone();
+        }
+        let heading = self.current_section().map(str::to_string);
+        match heading {
+            Some(_) => Some(Position {
+                heading,
+                offset: self.heading_offset,
+            }),
+            None if self.scroll_offset >= 1.0 => Some(Position {
+                heading: None,
+                offset: self.scroll_offset,
+            }),
+            None => None,
+        }
+    }
+
+    /// Scrolls back to `position` on the next frame.
+    pub fn return_to(&mut self, Position { heading, offset }: &Position) {
+        let block = match heading {
+            Some(title) => self.heading_block(title),
+            None => Some(0),
+        };
+        self.restore_anchor = block.map(|idx| (idx, *offset));
+    }
+
+    /// Writes the reading state if it changed since it was loaded or saved.
+    pub fn save_reading(&mut self) -> Result<()> {
+        let (Some(sidecar), Some(saved)) = (&self.sidecar, &self.reading_saved) else {
+            return Ok(());
+        };
+        self.reading.position = self.position();
+        if self.reading == *saved {
+            return Ok(());
+        }
+        // Bookmarks and highlights are worth starting a file for; positions aren't
+        let create = self.reading.bookmarks != saved.bookmarks
+            || self.reading.highlights != saved.highlights;
+        sidecar.save(&self.reading, create)?;
+        self.reading_saved = Some(self.reading.clone());
+        Ok(())
+    }
+
+    /// Source ranges of the highlighted passages, `None` for those no
+    /// longer in the document.
+    pub fn highlighted(&mut self) -> &[Found] {
+        let current = self
+            .highlighted
+            .as_ref()
+            .is_some_and(|(revision, highlights, _)| {
+                *revision == self.revision && *highlights == self.reading.highlights
+            });
+        if !current {
+            self.highlighted = Some((
+                self.revision,
+                self.reading.highlights.clone(),
+                self.reading.highlighted(&self.content),
+            ));
+        }
+        &self.highlighted.as_ref().expect("just filled in").2
+    }
+
+    /// Scrolls to the heading linked to as `#fragment`, over where reading
+    /// stopped. Returns `false` if there is none.
+    pub fn go_to_fragment(&mut self, fragment: &str) -> bool {
+        let Some(idx) = self.doc.find_fragment(fragment) else {
+            return false;
+        };
+        self.scroll_to_block = Some(idx);
+        self.restore_anchor = None;
+        true
+    }
+
+    /// Index of the block containing the byte at `offset`.
+    pub fn block_at(&self, offset: usize) -> Option<usize> {
+        self.doc
+            .blocks
+            .iter()
+            .rposition(|block| block.span.start <= offset)
+    }
+}
+
+/// Reads the file at `path` for [`DocTab::open`] and splits it, sending the
+/// chunks of one longer than `chunked_above` bytes as they're split.
+fn load(
+    path: &Path,
+    format: Format,
+    chunked_above: usize,
+    sender: &Sender<Load>,
+) -> io::Result<Loaded> {
+    let mut file = File::open(path)?;
+    let size = usize::try_from(file.metadata()?.len()).unwrap_or(usize::MAX);
+    // Other formats only become Markdown as a whole
+    let streamed = format == Format::Markdown && size > chunked_above;
+    let mut chunks = Chunks::new(sender, size);
+    let mut stream = encoding::Stream::default();
+    let mut read = Vec::with_capacity(CHUNK_BYTES);
+    loop {
+        read.clear();
+        let last = file
+            .by_ref()
+            .take(CHUNK_BYTES as u64)
+            .read_to_end(&mut read)?
+            < CHUNK_BYTES;
+        let changed = stream.push(&read, last);
+        if streamed {
+            if changed {
+                chunks.restart()?;
+            }
+            chunks.split(stream.text(), last)?;
+        }
+        if last {
+            break;
+        }
+    }
+
+    let decoded = stream.finish();
+    let hash = content_hash(&decoded.text);
+    let markdown = format.to_markdown(decoded.text);
+    if !streamed && markdown.len() > chunked_above {
+        chunks.len = markdown.len();
+        chunks.split(&markdown, true)?;
+    }
+    Ok(Loaded {
+        doc: match chunks.pieces.is_empty() {
+            true => blocks::split(&markdown),
+            false => blocks::join(&markdown, chunks.pieces),
+        },
+        markdown,
+        encoding: decoded.encoding,
+        hash,
+    })
+}
+
+/// The chunks of a document split so far by [`load`], as its text grows.
+struct Chunks<'a> {
+    sender: &'a Sender<Load>,
+    /// About how long the whole text will be.
+    len: usize,
+    cuts: blocks::Cuts,
+    /// How far the text is looked at for cuts, and split into chunks.
+    scanned: usize,
+    split_to: usize,
+    /// Each chunk parsed, by where it starts.
+    pieces: Vec<(usize, blocks::Parts)>,
+}
+
+impl<'a> Chunks<'a> {
+    fn new(sender: &'a Sender<Load>, len: usize) -> Self {
+        Self {
+            sender,
+            len,
+            cuts: blocks::Cuts::new(CHUNK_BYTES),
+            scanned: 0,
+            split_to: 0,
+            pieces: Vec::new(),
+        }
+    }
+
+    /// Splits and sends the chunks `text` now completes, through its end if
+    /// it's `last`.
+    fn split(&mut self, text: &str, last: bool) -> io::Result<()> {
+        let lines_end = match last {
+            true => text.len(),
+            false => text.rfind('\n').map_or(0, |at| at + 1),
+        };
+        let mut ends = self
+            .cuts
+            .find(&text[self.scanned.min(lines_end)..lines_end]);
+        self.scanned = self.scanned.max(lines_end);
+        if last {
+            ends.push(text.len());
+        }
+        for end in ends {
+            let start = self.split_to;
+            if end <= start {
+                continue;
+            }
+            self.split_to = end;
+            let chunk = text[start..end].to_string();
+            let parts = blocks::parse(&chunk);
+            let doc = parts.clone().finish(&chunk);
+            self.pieces.push((start, parts));
+            self.send(Load::Chunk {
+                doc,
+                text: chunk,
+                len: self.len,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Starts over, for text that was decoded again.
+    fn restart(&mut self) -> io::Result<()> {
+        self.cuts = blocks::Cuts::new(CHUNK_BYTES);
+        self.scanned = 0;
+        self.split_to = 0;
+        self.pieces.clear();
+        self.send(Load::Restart)
+    }
+
+    fn send(&self, load: Load) -> io::Result<()> {
+        self.sender
+            .send(load)
+            .map_err(|_| io::Error::other("closed before it was all in"))
+    }
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn next_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
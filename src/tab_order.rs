@@ -0,0 +1,113 @@
+//! Switching tabs from the keyboard: Ctrl+1 to Ctrl+9 by position,
+//! Ctrl+PageUp/PageDown to the neighbors, and Ctrl+Tab through the most
+//! recently used, with a list of them shown while Ctrl is held.
+
+use eframe::egui::{self, Key, Modifiers};
+
+use crate::i18n::tr;
+use crate::tab::DocTab;
+
+const NUMBER_KEYS: [Key; 9] = [
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+
+#[derive(Default)]
+pub struct TabOrder {
+    /// Ids of the tabs, the active one first and the one used longest ago last.
+    recent: Vec<u64>,
+    /// Place in `recent` Ctrl+Tab is on, until Ctrl is let go.
+    switching: Option<usize>,
+}
+
+impl TabOrder {
+    /// Puts the active tab first and forgets closed ones.
+    pub fn update(&mut self, tabs: &[DocTab], active: usize) {
+        self.recent
+            .retain(|id| tabs.iter().any(|tab| tab.id == *id));
+        for tab in tabs {
+            if !self.recent.contains(&tab.id) {
+                self.recent.push(tab.id);
+            }
+        }
+        if self.switching.is_none()
+            && let Some(tab) = tabs.get(active)
+            && let Some(at) = self.recent.iter().position(|id| *id == tab.id)
+        {
+            let id = self.recent.remove(at);
+            self.recent.insert(0, id);
+        }
+    }
+
+    /// The tab the keys pressed this frame switch to.
+    pub fn read(&mut self, ctx: &egui::Context, tabs: &[DocTab], active: usize) -> Option<usize> {
+        let count = tabs.len();
+        if count == 0 {
+            self.switching = None;
+            return None;
+        }
+        ctx.input_mut(|i| {
+            if let Some(n) = NUMBER_KEYS
+                .iter()
+                .position(|key| i.consume_key(Modifiers::COMMAND, *key))
+            {
+                return (n < count).then_some(n);
+            }
+            if i.consume_key(Modifiers::COMMAND, Key::PageDown) {
+                return Some((active + 1) % count);
+            }
+            if i.consume_key(Modifiers::COMMAND, Key::PageUp) {
+                return Some((active + count - 1) % count);
+            }
+
+            // Shifted first, as the unshifted one would take it too
+            let back = i.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Tab);
+            let forward = !back && i.consume_key(Modifiers::COMMAND, Key::Tab);
+            let len = self.recent.len();
+            if (back || forward) && len > 1 {
+                let at = self.switching.unwrap_or(0);
+                self.switching = Some(match back {
+                    true => (at + len - 1) % len,
+                    false => (at + 1) % len,
+                });
+            }
+            if self.switching.is_some() && i.consume_key(Modifiers::NONE, Key::Escape) {
+                self.switching = None;
+            }
+            if self.switching.is_some() && !i.modifiers.command {
+                let id = self.recent[self.switching.take()?];
+                return tabs.iter().position(|tab| tab.id == id);
+            }
+            None
+        })
+    }
+
+    /// The recently used tabs, while Ctrl+Tab goes through them.
+    pub fn show(&self, ctx: &egui::Context, tabs: &[DocTab]) {
+        let Some(at) = self.switching else {
+            return;
+        };
+        egui::Area::new(egui::Id::new("tab_switcher"))
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_min_width(260.0);
+                    ui.weak(tr("Recent tabs"));
+                    ui.separator();
+                    for (n, id) in self.recent.iter().enumerate() {
+                        if let Some(tab) = tabs.iter().find(|tab| tab.id == *id) {
+                            let _ = ui.selectable_label(n == at, &tab.title);
+                        }
+                    }
+                });
+            });
+    }
+}
@@ -0,0 +1,558 @@
+//! GFM tables drawn here instead of by `egui_commonmark`, so that a click
+//! on a column's header sorts the rows by it and tables wider than the page
+//! scroll sideways instead of squashing their columns. Cells can be
+//! selected and copied as CSV or TSV, for pasting into spreadsheets.
+//! ```` ```csv ```` and ```` ```tsv ```` fences are drawn the same way.
+
+use std::{cmp::Ordering, ops::RangeInclusive};
+
+use eframe::egui;
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+use crate::blocks;
+use crate::i18n::tr;
+
+/// Widest a column grows before its cells wrap.
+const MAX_COLUMN_WIDTH: f32 = 320.0;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct Style {
+    strong: bool,
+    emphasis: bool,
+    strikethrough: bool,
+    code: bool,
+}
+
+struct Piece {
+    text: String,
+    style: Style,
+    link: Option<String>,
+}
+
+#[derive(Default)]
+struct Cell {
+    pieces: Vec<Piece>,
+}
+
+impl Cell {
+    fn text(&self) -> String {
+        self.pieces
+            .iter()
+            .map(|piece| piece.text.as_str())
+            .collect()
+    }
+}
+
+/// The column rows are sorted by, and which way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sort {
+    pub column: usize,
+    pub descending: bool,
+}
+
+pub struct Table {
+    header: Vec<Cell>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    /// The cells of `markdown` if it is a table.
+    pub fn parse(markdown: &str) -> Option<Self> {
+        if !markdown.contains('|') {
+            return None;
+        }
+
+        let mut events = Parser::new_ext(markdown, blocks::parser_options());
+        if !matches!(events.next(), Some(Event::Start(Tag::Table(_)))) {
+            return None;
+        }
+        let mut header = Vec::new();
+        let mut rows: Vec<Vec<Cell>> = Vec::new();
+        let mut in_head = false;
+        let mut style = Style::default();
+        let mut link: Option<String> = None;
+
+        for event in events {
+            let cell = match (in_head, rows.last_mut()) {
+                (true, _) => header.last_mut(),
+                (false, Some(row)) => row.last_mut(),
+                (false, None) => None,
+            };
+            match event {
+                Event::Start(Tag::TableHead) => in_head = true,
+                Event::End(TagEnd::TableHead) => in_head = false,
+                Event::Start(Tag::TableRow) => rows.push(Vec::new()),
+                Event::Start(Tag::TableCell) if in_head => header.push(Cell::default()),
+                Event::Start(Tag::TableCell) => rows.last_mut()?.push(Cell::default()),
+                Event::Start(Tag::Strong) => style.strong = true,
+                Event::End(TagEnd::Strong) => style.strong = false,
+                Event::Start(Tag::Emphasis) => style.emphasis = true,
+                Event::End(TagEnd::Emphasis) => style.emphasis = false,
+                Event::Start(Tag::Strikethrough) => style.strikethrough = true,
+                Event::End(TagEnd::Strikethrough) => style.strikethrough = false,
+                Event::Start(Tag::Link { dest_url, .. }) => link = Some(dest_url.to_string()),
+                Event::End(TagEnd::Link) => link = None,
+                // Images show their alt text, which arrives as text
+                Event::Text(text) | Event::InlineHtml(text) => {
+                    push_text(cell?, &text, style, &link);
+                }
+                Event::Code(text) => push_text(
+                    cell?,
+                    &text,
+                    Style {
+                        code: true,
+                        ..style
+                    },
+                    &link,
+                ),
+                Event::FootnoteReference(name) => {
+                    push_text(cell?, &format!("[{name}]"), style, &None);
+                }
+                Event::End(TagEnd::Table) => break,
+                _ => {}
+            }
+        }
+
+        Some(Self { header, rows })
+    }
+
+    /// The records of CSV or TSV `text`, the first one as the header.
+    pub fn from_delimited(text: &str, separator: char) -> Self {
+        let cell = |text: String| Cell {
+            pieces: vec![Piece {
+                text,
+                style: Style::default(),
+                link: None,
+            }],
+        };
+        let mut records = records(text, separator)
+            .into_iter()
+            .map(|record| record.into_iter().map(cell).collect::<Vec<_>>());
+        let mut header = records.next().unwrap_or_default();
+        let rows: Vec<Vec<Cell>> = records.collect();
+        // Short headers get blank names for the columns the rows have extra
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        while header.len() < columns {
+            header.push(Cell::default());
+        }
+        Self { header, rows }
+    }
+
+    /// Indices of the rows in the order `sort` puts them, empty cells last.
+    pub fn order(&self, sort: Option<Sort>) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.rows.len()).collect();
+        let Some(sort) = sort else {
+            return order;
+        };
+        let keys: Vec<String> = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.get(sort.column)
+                    .map(|cell| cell.text().trim().to_string())
+                    .unwrap_or_default()
+            })
+            .collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&keys[a], &keys[b]);
+            match (a.is_empty(), b.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) if sort.descending => compare(b, a),
+                (false, false) => compare(a, b),
+            }
+        });
+        order
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui) -> egui::Response {
+        let id = ui.id().with("table");
+        let selection_id = id.with("selection");
+        let layout_id = id.with("layout");
+        let (mut sort, mut selection) = ui.data(|d| {
+            (
+                d.get_temp::<Option<Sort>>(id).flatten(),
+                d.get_temp::<Option<Selection>>(selection_id).flatten(),
+            )
+        });
+        let columns = self.header.len();
+        let order = self.order(sort);
+        let mut sorted = sort;
+
+        let response = egui::Frame::group(ui.style())
+            .show(ui, |ui| {
+                egui::ScrollArea::horizontal()
+                    .id_salt(id.with("scroll"))
+                    .show(ui, |ui| {
+                        let origin = ui.cursor().min.to_vec2();
+                        let shading = ui.painter().add(egui::Shape::Noop);
+                        // Cells are clicked where they were last frame, under
+                        // their contents so links in them keep working
+                        if let Some(layout) = ui.data(|d| d.get_temp::<Layout>(layout_id)) {
+                            let shapes =
+                                self.select_cells(ui, id, &layout, origin, &order, &mut selection);
+                            ui.painter().set(shading, egui::Shape::Vec(shapes));
+                        }
+
+                        let mut layout = Layout {
+                            columns: vec![egui::Rect::NOTHING; columns],
+                            rows: Vec::new(),
+                        };
+                        egui::Grid::new(id.with("grid"))
+                            .striped(true)
+                            .max_col_width(MAX_COLUMN_WIDTH)
+                            .show(ui, |ui| {
+                                for (column, cell) in self.header.iter().enumerate() {
+                                    let response = header_button(ui, cell, sort, column);
+                                    if response.clicked() {
+                                        sorted = next_sort(sort, column);
+                                    }
+                                    layout.columns[column] = layout.columns[column]
+                                        .union(response.rect.translate(-origin));
+                                }
+                                ui.end_row();
+                                for &row in &order {
+                                    let cells = &self.rows[row];
+                                    let mut y = egui::Rect::NOTHING;
+                                    for column in 0..columns {
+                                        let rect = match cells.get(column) {
+                                            Some(cell) => show_cell(ui, cell),
+                                            None => {
+                                                ui.add(egui::Label::new("").selectable(false)).rect
+                                            }
+                                        };
+                                        layout.columns[column] =
+                                            layout.columns[column].union(rect.translate(-origin));
+                                        y = y.union(rect.translate(-origin));
+                                    }
+                                    layout.rows.push(y);
+                                    ui.end_row();
+                                }
+                            });
+                        ui.data_mut(|d| d.insert_temp(layout_id, layout));
+                    });
+            })
+            .response;
+
+        if sorted != sort {
+            // Positions in the old order mean other rows now
+            selection = None;
+            sort = sorted;
+            ui.ctx().request_repaint();
+        }
+        ui.data_mut(|d| {
+            d.insert_temp(id, sort);
+            d.insert_temp(selection_id, selection);
+        });
+        response
+    }
+
+    /// Lets cells be clicked to select them, shift-clicked to select up to
+    /// them and right-clicked for copying; returns the selection's shading.
+    fn select_cells(
+        &self,
+        ui: &mut egui::Ui,
+        id: egui::Id,
+        layout: &Layout,
+        origin: egui::Vec2,
+        order: &[usize],
+        selection: &mut Option<Selection>,
+    ) -> Vec<egui::Shape> {
+        let gap = ui.spacing().item_spacing / 2.0;
+        let fill = ui.visuals().selection.bg_fill.gamma_multiply(0.5);
+        let last = (
+            order.len().saturating_sub(1),
+            self.header.len().saturating_sub(1),
+        );
+        let mut shapes = Vec::new();
+        for (row, &y) in layout.rows.iter().enumerate().take(order.len()) {
+            for (column, &x) in layout.columns.iter().enumerate() {
+                let rect = egui::Rect::from_x_y_ranges(x.x_range(), y.y_range())
+                    .translate(origin)
+                    .expand2(gap);
+                let response =
+                    ui.interact(rect, id.with(("cell", row, column)), egui::Sense::click());
+                let selected = selection.is_some_and(|s| s.contains(row, column));
+                if response.clicked() {
+                    *selection = match *selection {
+                        Some(s) if ui.input(|i| i.modifiers.shift) => Some(Selection {
+                            end: (row, column),
+                            ..s
+                        }),
+                        Some(s) if s == Selection::cell(row, column) => None,
+                        _ => Some(Selection::cell(row, column)),
+                    };
+                } else if response.secondary_clicked() && !selected {
+                    *selection = Some(Selection::cell(row, column));
+                }
+                response.context_menu(|ui| {
+                    if ui.button(tr("Select Row")).clicked() {
+                        *selection = Some(Selection {
+                            anchor: (row, 0),
+                            end: (row, last.1),
+                        });
+                    }
+                    if ui.button(tr("Select Column")).clicked() {
+                        *selection = Some(Selection {
+                            anchor: (0, column),
+                            end: (last.0, column),
+                        });
+                    }
+                    if ui.button(tr("Select All")).clicked() {
+                        *selection = Some(Selection {
+                            anchor: (0, 0),
+                            end: last,
+                        });
+                    }
+                    ui.separator();
+                    let chosen = selection.unwrap_or(Selection::cell(row, column));
+                    for (label, separator) in [("Copy as CSV", ','), ("Copy as TSV", '\t')] {
+                        if ui.button(tr(label)).clicked() {
+                            let text = delimited(&self.selected(order, chosen), separator);
+                            ui.ctx().copy_text(text);
+                        }
+                    }
+                });
+                if selection.is_some_and(|s| s.contains(row, column)) {
+                    shapes.push(egui::Shape::rect_filled(rect, 0.0, fill));
+                }
+            }
+        }
+        shapes
+    }
+
+    /// The text of the cells in `selection`, a row at a time. Whole columns
+    /// come with their header.
+    fn selected(&self, order: &[usize], selection: Selection) -> Vec<Vec<String>> {
+        let rows = selection.rows();
+        let columns = selection.columns();
+        let text = |row: &[Cell]| {
+            columns
+                .clone()
+                .map(|column| {
+                    row.get(column)
+                        .map(|cell| cell.text().trim().to_string())
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut selected = Vec::new();
+        if *rows.start() == 0 && *rows.end() + 1 >= order.len() {
+            selected.push(text(&self.header));
+        }
+        for &row in order.get(rows).unwrap_or_default() {
+            selected.push(text(&self.rows[row]));
+        }
+        selected
+    }
+}
+
+/// Where the cells of a table were drawn, relative to its top left corner.
+#[derive(Clone, Default)]
+struct Layout {
+    columns: Vec<egui::Rect>,
+    /// The rows below the header, in the order shown.
+    rows: Vec<egui::Rect>,
+}
+
+/// A block of cells, by position in the order shown and column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Selection {
+    /// The cell first clicked.
+    anchor: (usize, usize),
+    end: (usize, usize),
+}
+
+impl Selection {
+    fn cell(row: usize, column: usize) -> Self {
+        Self {
+            anchor: (row, column),
+            end: (row, column),
+        }
+    }
+
+    fn rows(&self) -> RangeInclusive<usize> {
+        self.anchor.0.min(self.end.0)..=self.anchor.0.max(self.end.0)
+    }
+
+    fn columns(&self) -> RangeInclusive<usize> {
+        self.anchor.1.min(self.end.1)..=self.anchor.1.max(self.end.1)
+    }
+
+    fn contains(&self, row: usize, column: usize) -> bool {
+        self.rows().contains(&row) && self.columns().contains(&column)
+    }
+}
+
+/// `rows` as CSV or TSV, quoting fields that need it.
+fn delimited(rows: &[Vec<String>], separator: char) -> String {
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .map(|field| {
+                    if field.contains([separator, '"', '\n', '\r']) {
+                        format!("\"{}\"", field.replace('"', "\"\""))
+                    } else {
+                        field.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(&separator.to_string())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The separator of a fence's language, if it holds CSV or TSV.
+pub fn separator(lang: Option<&str>) -> Option<char> {
+    match lang?.to_ascii_lowercase().as_str() {
+        "csv" => Some(','),
+        "tsv" => Some('\t'),
+        _ => None,
+    }
+}
+
+/// The fields of each line of `text`. Fields in double quotes may hold the
+/// separator, line breaks and `""` for a quote; blank lines are skipped.
+fn records(text: &str, separator: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            _ if quoted => field.push(c),
+            '\r' => {}
+            '\n' => {
+                if !record.is_empty() || !field.is_empty() {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+            }
+            _ if c == separator => record.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if !record.is_empty() || !field.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Sorting by `column`: ascending first, then descending, then as written.
+fn next_sort(sort: Option<Sort>, column: usize) -> Option<Sort> {
+    match sort {
+        Some(Sort {
+            column: sorted,
+            descending: false,
+        }) if sorted == column => Some(Sort {
+            column,
+            descending: true,
+        }),
+        Some(Sort {
+            column: sorted,
+            descending: true,
+        }) if sorted == column => None,
+        _ => Some(Sort {
+            column,
+            descending: false,
+        }),
+    }
+}
+
+/// Numbers by value, before text, which goes by letter regardless of case.
+fn compare(a: &str, b: &str) -> Ordering {
+    match (number(a), number(b)) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+/// The number `text` starts with, like `1,204` in `1,204 ms` or `-3.5` in
+/// `-3.5%`.
+fn number(text: &str) -> Option<f64> {
+    let digits: String = text
+        .char_indices()
+        .take_while(|&(at, c)| {
+            c.is_ascii_digit() || c == '.' || c == ',' || (at == 0 && (c == '-' || c == '+'))
+        })
+        .map(|(_, c)| c)
+        .filter(|&c| c != ',')
+        .collect();
+    digits.parse().ok()
+}
+
+fn push_text(cell: &mut Cell, text: &str, style: Style, link: &Option<String>) {
+    match cell.pieces.last_mut() {
+        Some(last) if last.style == style && last.link == *link => last.text.push_str(text),
+        _ => cell.pieces.push(Piece {
+            text: text.to_string(),
+            style,
+            link: link.clone(),
+        }),
+    }
+}
+
+fn header_button(
+    ui: &mut egui::Ui,
+    cell: &Cell,
+    sort: Option<Sort>,
+    column: usize,
+) -> egui::Response {
+    let arrow = match sort {
+        Some(sort) if sort.column == column && sort.descending => " ▼",
+        Some(sort) if sort.column == column => " ▲",
+        _ => "",
+    };
+    let text = egui::RichText::new(format!("{}{arrow}", cell.text().trim())).strong();
+    ui.add(egui::Button::new(text).frame(false))
+        .on_hover_text(tr("Sort by this column"))
+}
+
+fn show_cell(ui: &mut egui::Ui, cell: &Cell) -> egui::Rect {
+    ui.horizontal_wrapped(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        for piece in &cell.pieces {
+            let text = rich_text(&piece.text, piece.style);
+            match &piece.link {
+                Some(url) => {
+                    ui.hyperlink_to(text, url);
+                }
+                // Clicks select the cell instead of its text
+                None => {
+                    ui.add(egui::Label::new(text).selectable(false));
+                }
+            }
+        }
+    })
+    .response
+    .rect
+}
+
+fn rich_text(text: &str, style: Style) -> egui::RichText {
+    let mut rich = egui::RichText::new(text);
+    if style.strong {
+        rich = rich.strong();
+    }
+    if style.emphasis {
+        rich = rich.italics();
+    }
+    if style.strikethrough {
+        rich = rich.strikethrough();
+    }
+    if style.code {
+        rich = rich.code();
+    }
+    rich
+}
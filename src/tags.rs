@@ -0,0 +1,75 @@
+//! The tags of the opened folder's documents, from their frontmatter, and
+//! the documents carrying the one picked.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use eframe::egui;
+
+use crate::i18n::{tr, tr_args};
+use crate::workspace::Workspace;
+
+#[derive(Default)]
+pub struct TagPanel {
+    /// The tag whose documents are listed.
+    selected: Option<String>,
+}
+
+impl TagPanel {
+    /// Draws the tags and the documents of the one picked; returns a
+    /// document the user clicked.
+    pub fn show(&mut self, ui: &mut egui::Ui, workspace: Option<&Workspace>) -> Option<PathBuf> {
+        ui.heading(tr("Tags"));
+        let Some(workspace) = workspace else {
+            ui.weak(tr("Open a folder to browse the tags of its documents"));
+            return None;
+        };
+        // Sorted by name, ignoring case, and counted once per document
+        let mut tags: BTreeMap<String, (&str, Vec<&PathBuf>)> = BTreeMap::new();
+        for (tag, path) in &workspace.tags {
+            let (_, paths) = tags.entry(tag.to_lowercase()).or_insert((tag, Vec::new()));
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        if tags.is_empty() {
+            ui.weak(tr("No document of the folder has tags"));
+            return None;
+        }
+        if self
+            .selected
+            .as_ref()
+            .is_some_and(|selected| !tags.contains_key(selected))
+        {
+            self.selected = None;
+        }
+
+        let mut clicked = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (key, (tag, paths)) in &tags {
+                    let selected = self.selected.as_ref() == Some(key);
+                    let text = format!("#{tag} {}", paths.len());
+                    if ui.selectable_label(selected, text).clicked() {
+                        self.selected = (!selected).then(|| key.clone());
+                    }
+                }
+            });
+            let Some((tag, paths)) = self.selected.as_ref().and_then(|key| tags.get(key)) else {
+                return;
+            };
+            ui.separator();
+            ui.strong(tr_args("Tagged #{tag}", &[("tag", tag)]));
+            for path in paths {
+                let name = workspace.relative(path).display().to_string();
+                if ui
+                    .link(name)
+                    .on_hover_text(path.display().to_string())
+                    .clicked()
+                {
+                    clicked = Some(path.to_path_buf());
+                }
+            }
+        });
+        clicked
+    }
+}
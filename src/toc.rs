@@ -0,0 +1,167 @@
+//! Table of contents sidebar built from a document's top-level headings,
+//! and the list of its figures.
+
+use eframe::egui;
+
+use crate::blocks::{self, BlockKind, Document, Figure};
+use crate::i18n::{tr, tr_args};
+
+/// How deep the table of contents goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TocDepth {
+    H1,
+    H2,
+    #[default]
+    All,
+}
+
+impl TocDepth {
+    const CHOICES: [TocDepth; 3] = [TocDepth::H1, TocDepth::H2, TocDepth::All];
+
+    fn label(self) -> &'static str {
+        match self {
+            TocDepth::H1 => "H1",
+            TocDepth::H2 => "H1–H2",
+            TocDepth::All => tr("All"),
+        }
+    }
+
+    fn max_level(self) -> u8 {
+        match self {
+            TocDepth::H1 => 1,
+            TocDepth::H2 => 2,
+            TocDepth::All => 6,
+        }
+    }
+}
+
+/// Shows the outline of `doc`, with the headings' numbers if `numbered`
+/// and how long their sections take to read if `reading_times`. Returns the
+/// index of the heading block that was clicked.
+pub fn show(
+    ui: &mut egui::Ui,
+    doc: &Document,
+    numbered: bool,
+    reading_times: bool,
+    depth: &mut TocDepth,
+) -> Option<usize> {
+    let blocks = &doc.blocks;
+    let numbers = match numbered {
+        true => doc.heading_numbers(),
+        false => Vec::new(),
+    };
+    ui.horizontal(|ui| {
+        ui.strong(tr("Contents"));
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            for choice in TocDepth::CHOICES.iter().rev() {
+                ui.selectable_value(depth, *choice, choice.label());
+            }
+        });
+    });
+    ui.separator();
+
+    let headings = || {
+        blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, block)| match &block.kind {
+                BlockKind::Heading { level, title } => Some((idx, *level, title)),
+                _ => None,
+            })
+    };
+
+    // Indent relative to the shallowest heading so documents starting at H2 aren't pushed right.
+    let Some(top_level) = headings().map(|(_, level, _)| level).min() else {
+        ui.weak(tr("No headings"));
+        return None;
+    };
+
+    let mut clicked = None;
+    egui::ScrollArea::vertical()
+        .auto_shrink([false, false])
+        .show(ui, |ui| {
+            for (idx, level, title) in
+                headings().filter(|(_, level, _)| *level <= depth.max_level())
+            {
+                ui.horizontal(|ui| {
+                    ui.add_space(f32::from(level - top_level) * 12.0);
+                    let label = match numbers.get(idx) {
+                        Some(Some(number)) => format!("{number} {title}"),
+                        _ => title.clone(),
+                    };
+                    if ui.link(label).clicked() {
+                        clicked = Some(idx);
+                    }
+                    if reading_times {
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            let time = reading_time(doc.section_words(idx));
+                            ui.weak(egui::RichText::new(time).small());
+                        });
+                    }
+                });
+            }
+        });
+
+    clicked
+}
+
+/// About how long `words` take to read, in whole minutes.
+pub fn reading_time(words: usize) -> String {
+    match words.div_ceil(blocks::WORDS_PER_MINUTE) {
+        0 | 1 if words < blocks::WORDS_PER_MINUTE / 2 => tr("< 1 min").to_string(),
+        minutes => tr_args("{minutes} min", &[("minutes", &minutes)]),
+    }
+}
+
+/// The headings of `doc` as a nested Markdown list of links to them.
+pub fn to_markdown(doc: &Document) -> String {
+    let headings: Vec<(usize, u8, &String)> = doc
+        .blocks
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, block)| match &block.kind {
+            BlockKind::Heading { level, title } => Some((idx, *level, title)),
+            _ => None,
+        })
+        .collect();
+    let top_level = headings
+        .iter()
+        .map(|(_, level, _)| *level)
+        .min()
+        .unwrap_or(1);
+    let mut markdown = String::new();
+    for (idx, level, title) in headings {
+        let indent = "  ".repeat(usize::from(level - top_level));
+        let title = title.replace('[', "\\[").replace(']', "\\]");
+        let fragment = doc.fragment(idx).unwrap_or_default();
+        markdown.push_str(&format!("{indent}- [{title}](#{fragment})\n"));
+    }
+    markdown
+}
+
+/// Lists `figures` by number and caption. Returns the block of the one clicked.
+pub fn show_figures(ui: &mut egui::Ui, figures: &[Figure]) -> Option<usize> {
+    ui.strong(tr("Figures"));
+    ui.separator();
+    if figures.is_empty() {
+        ui.weak(tr("No captioned images"));
+        return None;
+    }
+
+    let mut clicked = None;
+    egui::ScrollArea::vertical()
+        .auto_shrink([false, false])
+        .show(ui, |ui| {
+            for figure in figures {
+                let label = tr_args(
+                    "Figure {number}: {caption}",
+                    &[("number", &figure.number), ("caption", &figure.caption)],
+                );
+                if ui.link(label).clicked() {
+                    clicked = Some(figure.block);
+                }
+            }
+        });
+
+    clicked
+}
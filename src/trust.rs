@@ -0,0 +1,111 @@
+//! Folders whose documents may run programs: `git` for the status bar, and
+//! the diagram programs set in Preferences on their fences. A folder is
+//! asked about once when it's opened, and until it's trusted its documents
+//! run neither, so a cloned repository can't have them run anything.
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+
+use crate::i18n::tr;
+
+/// Whether `path` is in one of the `trusted` folders.
+pub fn is_trusted(trusted: &[PathBuf], path: &Path) -> bool {
+    trusted
+        .iter()
+        .any(|folder| !folder.as_os_str().is_empty() && path.starts_with(folder))
+}
+
+/// The folder trusting `path` is asked about for: the opened one if it's in
+/// it, otherwise its own.
+pub fn folder_of(path: &Path, workspace: Option<&Path>) -> Option<PathBuf> {
+    match workspace {
+        Some(root) if path.starts_with(root) => Some(root.to_path_buf()),
+        _ => path.parent().map(Path::to_path_buf),
+    }
+}
+
+pub enum Answer {
+    Trust(PathBuf),
+    /// Not trusted, and not asked about again when opened.
+    Distrust(PathBuf),
+}
+
+/// The question asked before a folder's documents may run programs.
+#[derive(Default)]
+pub struct Confirm {
+    pending: Option<PathBuf>,
+}
+
+impl Confirm {
+    pub fn ask(&mut self, folder: PathBuf) {
+        self.pending = Some(folder);
+    }
+
+    /// Asks about the pending folder, if any. Returns the answer once given,
+    /// nothing while waiting or if the question was dismissed.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<Answer> {
+        let folder = self.pending.as_ref()?;
+        let mut answer = None;
+        let modal = egui::Modal::new(egui::Id::new("confirm_trust")).show(ctx, |ui| {
+            ui.set_max_width(480.0);
+            ui.heading(tr("Trust this folder?"));
+            ui.add_space(6.0);
+            ui.add(
+                egui::Label::new(egui::RichText::new(folder.display().to_string()).monospace())
+                    .wrap(),
+            );
+            ui.add_space(6.0);
+            ui.label(tr(
+                "Documents in a trusted folder show their git status and draw their diagrams \
+                 with the programs set in Preferences, which a folder from someone else \
+                 could make run what it likes. Untrusted, they're shown without either.",
+            ));
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if ui.button(tr("Trust Folder")).clicked() {
+                    answer = Some(Answer::Trust(folder.clone()));
+                }
+                if ui.button(tr("Don't Trust")).clicked() {
+                    answer = Some(Answer::Distrust(folder.clone()));
+                }
+            });
+        });
+        if answer.is_some() || modal.should_close() {
+            self.pending = None;
+        }
+        answer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folders_trust_what_is_in_them() {
+        let trusted = [PathBuf::from("/home/me/notes"), PathBuf::new()];
+        assert!(is_trusted(&trusted, Path::new("/home/me/notes/a.md")));
+        assert!(is_trusted(&trusted, Path::new("/home/me/notes/sub/b.md")));
+        assert!(!is_trusted(&trusted, Path::new("/home/me/notes-old/a.md")));
+        assert!(!is_trusted(&trusted, Path::new("/home/me/a.md")));
+        assert!(!is_trusted(&[], Path::new("/home/me/notes/a.md")));
+    }
+
+    #[test]
+    fn documents_are_asked_about_with_the_opened_folder() {
+        let root = Path::new("/home/me/notes");
+        assert_eq!(
+            folder_of(Path::new("/home/me/notes/sub/a.md"), Some(root)),
+            Some(root.to_path_buf())
+        );
+        assert_eq!(
+            folder_of(Path::new("/tmp/b.md"), Some(root)),
+            Some(PathBuf::from("/tmp"))
+        );
+        assert_eq!(
+            folder_of(Path::new("/tmp/b.md"), None),
+            Some(PathBuf::from("/tmp"))
+        );
+    }
+}
@@ -0,0 +1,2655 @@
+//! Headless UI tests driving a viewer window through egui_kittest, with
+//! tricky documents from `tests/fixtures`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use eframe::egui;
+use egui_commonmark::CommonMarkCache;
+use egui_kittest::{
+    Harness,
+    kittest::{NodeT, Queryable},
+};
+
+use crate::{
+    Shared,
+    blocks::BlockKind,
+    code_view,
+    config::{
+        self, ColumnGuide, Dialect, Language, LinkAction, LinkHandler, LoginKind, Paper, Settings,
+        SiteLogin, WebLinks,
+    },
+    events::AppEvent,
+    export::{self, Bundle},
+    remote_docs, rich_copy, updates, uri_scheme,
+    window::DocWindow,
+    workspace::Workspace,
+};
+
+struct TestApp {
+    window: DocWindow,
+    shared: Shared,
+}
+
+fn harness<'a>() -> Harness<'a, TestApp> {
+    let app = TestApp {
+        window: DocWindow::main(),
+        shared: Shared {
+            cm_cache: CommonMarkCache::default(),
+            // Whatever the locale the tests run in
+            settings: Settings {
+                language: Language::English,
+                ..Settings::default()
+            },
+            workspace: None,
+        },
+    };
+    Harness::builder()
+        .with_size(egui::vec2(1000.0, 700.0))
+        // Tables take a few frames to settle on their column widths
+        .with_max_steps(20)
+        .build_state(
+            |ctx, app: &mut TestApp| {
+                app.window.show(ctx, &mut app.shared);
+            },
+            app,
+        )
+}
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// A copy of `name` in a fresh directory, for tests that change the file.
+fn scratch_copy(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "md_viewer-test-{}-{}",
+        std::process::id(),
+        crate::tab::next_id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::copy(fixture(name), &path).unwrap();
+    path
+}
+
+/// Opens `path` and runs frames until the background load is done.
+fn open(harness: &mut Harness<'_, TestApp>, path: PathBuf) {
+    harness.state_mut().window.open_any(path);
+    wait_for_loads(harness);
+}
+
+/// Runs frames until no tab is loading anymore.
+fn wait_for_loads(harness: &mut Harness<'_, TestApp>) {
+    for _ in 0..200 {
+        harness.step();
+        if !harness
+            .state()
+            .window
+            .tabs
+            .iter()
+            .any(|tab| tab.is_loading())
+        {
+            harness.run();
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("document didn't finish loading");
+}
+
+fn headings(harness: &Harness<'_, TestApp>) -> Vec<String> {
+    let window = &harness.state().window;
+    window.tabs[window.active]
+        .doc
+        .blocks
+        .iter()
+        .filter_map(|block| match &block.kind {
+            BlockKind::Heading { title, .. } => Some(title.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Presses the mouse button at `from`, moves to `to` and lets go.
+fn drag(harness: &mut Harness<'_, TestApp>, from: egui::Pos2, to: egui::Pos2) {
+    let press = |pos, pressed| egui::Event::PointerButton {
+        pos,
+        button: egui::PointerButton::Primary,
+        pressed,
+        modifiers: egui::Modifiers::NONE,
+    };
+    harness
+        .input_mut()
+        .events
+        .push(egui::Event::PointerMoved(from));
+    harness.step();
+    harness.input_mut().events.push(press(from, true));
+    harness.step();
+    for step in 1..=10 {
+        let pos = from + (to - from) * (step as f32 / 10.0);
+        harness
+            .input_mut()
+            .events
+            .push(egui::Event::PointerMoved(pos));
+        harness.step();
+    }
+    harness.input_mut().events.push(press(to, false));
+    harness.step();
+}
+
+/// Queues Ctrl (or Cmd) + `key` for the next frame.
+fn press_shortcut(harness: &mut Harness<'_, TestApp>, key: egui::Key) {
+    harness.input_mut().events.push(egui::Event::Key {
+        key,
+        physical_key: None,
+        pressed: true,
+        repeat: false,
+        modifiers: egui::Modifiers::COMMAND,
+    });
+}
+
+/// Text the last frame put on the clipboard.
+fn copied_text(harness: &Harness<'_, TestApp>) -> Option<String> {
+    harness
+        .output()
+        .platform_output
+        .commands
+        .iter()
+        .find_map(|command| match command {
+            egui::OutputCommand::CopyText(text) => Some(text.clone()),
+            _ => None,
+        })
+}
+
+#[test]
+fn fixtures_render_without_errors() {
+    for name in [
+        "anchors.md",
+        "code_blocks.md",
+        "callouts.md",
+        "csv.md",
+        "details.md",
+        "diff_blocks.md",
+        "figures.md",
+        "frontmatter.md",
+        "html.md",
+        "keyboard.md",
+        "reference_links.md",
+        "schemes.md",
+        "tables.md",
+        "unicode.md",
+        "utf16.md",
+        "web_links.md",
+    ] {
+        let mut harness = harness();
+        open(&mut harness, fixture(name));
+        let window = &harness.state().window;
+        assert_eq!(window.tabs.len(), 1, "{name} should open in a tab");
+        assert_eq!(window.tabs[0].render_error, None, "{name} should render");
+    }
+}
+
+#[test]
+fn code_blocks_render_in_monochrome() {
+    let mut harness = harness();
+    harness.state_mut().shared.settings.monochrome_code = true;
+    open(&mut harness, fixture("code_blocks.md"));
+    let tab = &harness.state().window.tabs[0];
+    assert_eq!(tab.render_error, None);
+    assert!(tab.doc.blocks.iter().any(|block| block.code.is_some()));
+}
+
+#[test]
+fn code_blocks_number_and_scroll_their_lines() {
+    let mut harness = harness();
+    harness.state_mut().shared.settings.code_line_numbers = true;
+    open(&mut harness, fixture("code_blocks.md"));
+    let first_lines = |harness: &Harness<'_, TestApp>| {
+        harness
+            .output()
+            .shapes
+            .iter()
+            .filter(|clipped| {
+                matches!(&clipped.shape, egui::Shape::Text(text) if text.galley.text() == "1")
+            })
+            .count()
+    };
+    assert_eq!(first_lines(&harness), 5);
+
+    // Turned off for one block from the button shown over it
+    harness.get_by_label("let labeled = true;").hover();
+    harness.run();
+    harness.get_by_label("#").click();
+    harness.run();
+    assert_eq!(first_lines(&harness), 4);
+
+    let settings = &mut harness.state_mut().shared.settings;
+    settings.code_line_numbers = false;
+    settings.wrap_code = false;
+    let long = format!("```\n{}\n```", "long_line ".repeat(200));
+    harness.state_mut().window.tabs[0].set_content(long);
+    harness.run();
+    let line = harness.get_by_label_contains("long_line").rect();
+    assert!(line.width() > 1000.0, "{line:?}");
+}
+
+#[test]
+fn code_blocks_are_saved_as_files_of_their_language() {
+    let mut harness = harness();
+    open(&mut harness, fixture("code_blocks.md"));
+    assert!(harness.query_by_label("💾").is_none());
+    let code = harness
+        .query_all_by_value("let labeled = true;")
+        .next()
+        .unwrap();
+    code.hover();
+    harness.run();
+    assert!(harness.query_by_label("💾").is_some());
+
+    assert_eq!(code_view::file_name(Some("rust")), "code.rs");
+    assert_eq!(code_view::file_name(Some("python")), "code.py");
+    assert_eq!(code_view::file_name(Some("bash")), "code.sh");
+    assert_eq!(code_view::file_name(Some("toml")), "code.toml");
+    assert_eq!(code_view::file_name(Some("unknown-language")), "code.txt");
+    assert_eq!(code_view::file_name(None), "code.txt");
+}
+
+#[test]
+fn diff_fences_show_changed_lines() {
+    let mut harness = harness();
+    open(&mut harness, fixture("diff_blocks.md"));
+    assert!(harness.query_by_label("+    let total = 2;").is_some());
+
+    harness.state_mut().shared.settings.diff_side_by_side = true;
+    harness.run();
+    // Without the signs, the old line next to the new one
+    assert!(harness.query_by_label("    let total = 1;").is_some());
+    assert!(harness.query_by_label("    let total = 2;").is_some());
+    assert_eq!(harness.query_all_by_label("fn main() {").count(), 2);
+}
+
+#[test]
+fn table_rows_sort_by_the_clicked_column() {
+    let mut harness = harness();
+    open(&mut harness, fixture("tables.md"));
+    let rows = |harness: &Harness<'_, TestApp>| {
+        let mut rows = ["slow", "fast", "middle"];
+        rows.sort_by(|a, b| {
+            let y = |label| harness.get_by_label(label).rect().top();
+            y(*a).total_cmp(&y(*b))
+        });
+        rows
+    };
+    assert_eq!(rows(&harness), ["slow", "fast", "middle"]);
+
+    // By value, not as text
+    harness.get_by_label("Time (ms)").click();
+    harness.run();
+    assert_eq!(rows(&harness), ["fast", "middle", "slow"]);
+    harness.get_by_label("Time (ms) ▲").click();
+    harness.run();
+    assert_eq!(rows(&harness), ["slow", "middle", "fast"]);
+    harness.get_by_label("Time (ms) ▼").click();
+    harness.run();
+    assert_eq!(rows(&harness), ["slow", "fast", "middle"]);
+    assert!(harness.query_by_label("docs").is_some());
+}
+
+#[test]
+fn selected_table_cells_are_copied_as_csv_and_tsv() {
+    let mut harness = harness();
+    open(&mut harness, fixture("tables.md"));
+    harness.get_by_label("slow").click();
+    harness.run();
+    harness
+        .get_by_label("87")
+        .click_modifiers(egui::Modifiers::SHIFT);
+    harness.run();
+    harness.get_by_label("fast").click_secondary();
+    harness.run();
+    harness.get_by_label("Copy as CSV").click();
+    harness.step();
+    assert_eq!(
+        copied_text(&harness).as_deref(),
+        Some("slow,\"1,204\"\nfast,87")
+    );
+    harness.run();
+
+    // Whole columns come with their header
+    harness.get_by_label("middle").click_secondary();
+    harness.run();
+    harness.get_by_label("Select Column").click();
+    harness.run();
+    harness.get_by_label("fast").click_secondary();
+    harness.run();
+    harness.get_by_label("Copy as TSV").click();
+    harness.step();
+    assert_eq!(
+        copied_text(&harness).as_deref(),
+        Some("Parser\nslow\nfast\nmiddle")
+    );
+}
+
+#[test]
+fn captions_under_images_are_numbered_and_listed() {
+    let mut harness = harness();
+    harness.state_mut().shared.settings.show_figures = true;
+    open(&mut harness, fixture("figures.md"));
+    let figures = &harness.state().window.tabs[0].doc.figures;
+    assert_eq!(figures.len(), 2);
+    assert_eq!(figures[1].caption, "The settings page before and after.");
+    assert!(harness.query_by_label("Figure 1:").is_some());
+    assert!(harness.query_by_label("Figure 3:").is_none());
+    assert!(
+        harness
+            .query_by_label("Emphasis that doesn't follow an image.")
+            .is_some()
+    );
+
+    // Short enough that the second figure starts out of view
+    harness.set_size(egui::vec2(1000.0, 300.0));
+    harness.run();
+    let in_view =
+        |harness: &Harness<'_, TestApp>| harness.get_by_label("Figure 2:").rect().bottom() < 300.0;
+    assert!(!in_view(&harness));
+    harness
+        .get_by_label("Figure 2: The settings page before and after.")
+        .click();
+    harness.run();
+    assert!(in_view(&harness));
+}
+
+#[test]
+fn clicked_images_open_in_the_lightbox() {
+    let dir = std::env::temp_dir().join(format!(
+        "md_viewer-test-{}-{}",
+        std::process::id(),
+        crate::tab::next_id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    image::RgbaImage::from_pixel(300, 150, image::Rgba([40, 90, 160, 255]))
+        .save(dir.join("screenshot.png"))
+        .unwrap();
+    let path = dir.join("bug.md");
+    let markdown = format!(
+        "# Bug\n\nIt looks like this:\n\n![The bug]({})\n",
+        dir.join("screenshot.png").display()
+    );
+    fs::write(&path, markdown).unwrap();
+
+    let mut harness = harness();
+    egui_extras::install_image_loaders(&harness.ctx);
+    open(&mut harness, path);
+    // The file loader reads the image on a thread of its own
+    for _ in 0..100 {
+        harness.step();
+        thread::sleep(Duration::from_millis(10));
+    }
+    harness.run();
+    harness.get_by_role(egui::accesskit::Role::Image).click();
+    harness.run();
+    assert!(harness.query_by_label("Save Image As…").is_some());
+    assert!(harness.query_by_label("screenshot.png").is_some());
+
+    harness.get_by_label("Actual Pixels").click();
+    harness.run();
+    assert!(harness.query_by_label("100%").is_some());
+    harness.get_by_label("+").click();
+    harness.run();
+    assert!(harness.query_by_label("125%").is_some());
+
+    harness.key_press(egui::Key::Escape);
+    harness.run();
+    assert!(harness.query_by_label("Save Image As…").is_none());
+}
+
+#[test]
+fn svg_images_are_rasterized_at_the_size_shown() {
+    let dir = std::env::temp_dir().join(format!(
+        "md_viewer-test-{}-{}",
+        std::process::id(),
+        crate::tab::next_id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    // One egui_extras knows by its name, one only this app's loader does
+    fs::copy(fixture("diagram.svg"), dir.join("diagram.svg")).unwrap();
+    fs::copy(fixture("diagram.svg"), dir.join("DIAGRAM.SVG")).unwrap();
+    let path = dir.join("architecture.md");
+    let markdown = format!(
+        "# Architecture\n\n![]({})\n\n![]({})\n",
+        dir.join("diagram.svg").display(),
+        dir.join("DIAGRAM.SVG").display()
+    );
+    fs::write(&path, markdown).unwrap();
+
+    let mut harness = harness();
+    egui_extras::install_image_loaders(&harness.ctx);
+    crate::svg::SvgLoader::install(&harness.ctx);
+    open(&mut harness, path);
+    for _ in 0..100 {
+        harness.step();
+        thread::sleep(Duration::from_millis(10));
+    }
+    harness.run();
+
+    let svg_size = |name: &str, size_hint| {
+        let uri = format!("file://{}", dir.join(name).display());
+        match harness.ctx.try_load_image(&uri, size_hint) {
+            Ok(egui::load::ImagePoll::Ready { image }) => image.size,
+            Ok(egui::load::ImagePoll::Pending { .. }) => {
+                panic!("{name} is still loading")
+            }
+            Err(e) => panic!("{name} didn't load: {e}"),
+        }
+    };
+    let scale = |factor: f32| egui::load::SizeHint::Scale(factor.into());
+    for name in ["diagram.svg", "DIAGRAM.SVG"] {
+        assert_eq!(svg_size(name, scale(1.0)), [240, 120]);
+        // Zoomed in, drawn again rather than stretched
+        assert_eq!(svg_size(name, scale(2.0)), [480, 240]);
+    }
+    assert_eq!(
+        harness
+            .get_all_by_role(egui::accesskit::Role::Image)
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn images_shown_longest_ago_are_let_go_of_over_the_limit() {
+    let dir = std::env::temp_dir().join(format!(
+        "md_viewer-test-{}-{}",
+        std::process::id(),
+        crate::tab::next_id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    let mut pages = Vec::new();
+    for name in ["before", "after"] {
+        // 4 MB as a texture, and as much again decoded
+        let path = dir.join(format!("{name}.md"));
+        let image = dir.join(format!("{name}.png"));
+        image::RgbaImage::from_pixel(1000, 1000, image::Rgba([40, 90, 160, 255]))
+            .save(&image)
+            .unwrap();
+        fs::write(&path, format!("# Screenshot\n\n![]({})\n", image.display())).unwrap();
+        pages.push(path);
+    }
+
+    let mut harness = harness();
+    egui_extras::install_image_loaders(&harness.ctx);
+    let cache = crate::image_cache::ImageCache::install(&harness.ctx);
+    for path in pages {
+        open(&mut harness, path);
+        for _ in 0..50 {
+            harness.step();
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+    let limit = 12 * 1024 * 1024;
+    cache.enforce(&harness.ctx, 2 * limit);
+    let stats = crate::image_cache::stats(&harness.ctx);
+    assert_eq!(stats.images, 2);
+    assert!(stats.total() > limit);
+
+    // Only the image of the tab in the background goes
+    cache.enforce(&harness.ctx, limit);
+    let stats = crate::image_cache::stats(&harness.ctx);
+    assert_eq!((stats.images, stats.evicted), (1, 1));
+    assert!(stats.textures > 0 && stats.total() <= limit);
+    harness.run();
+    assert_eq!(
+        harness
+            .get_all_by_role(egui::accesskit::Role::Image)
+            .count(),
+        1
+    );
+
+    harness.get_by_label("Help").click();
+    harness.run();
+    harness.get_by_label("Cache Statistics").click();
+    harness.run();
+    assert!(
+        harness
+            .query_all_by_value("Let go of so far")
+            .next()
+            .is_some()
+    );
+}
+
+/// A document showing a GIF of two frames, and the GIF's URI.
+fn recording() -> (PathBuf, String) {
+    let dir = std::env::temp_dir().join(format!(
+        "md_viewer-test-{}-{}",
+        std::process::id(),
+        crate::tab::next_id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    let gif = dir.join("recording.gif");
+    let frames = [[200, 40, 40, 255], [40, 40, 200, 255]].map(|color| {
+        image::Frame::from_parts(
+            image::RgbaImage::from_pixel(120, 60, image::Rgba(color)),
+            0,
+            0,
+            image::Delay::from_numer_denom_ms(100, 1),
+        )
+    });
+    let mut encoder = image::codecs::gif::GifEncoder::new(fs::File::create(&gif).unwrap());
+    encoder
+        .set_repeat(image::codecs::gif::Repeat::Infinite)
+        .unwrap();
+    encoder.encode_frames(frames).unwrap();
+    drop(encoder);
+    let path = dir.join("tutorial.md");
+    fs::write(&path, format!("# Tutorial\n\n![Demo]({})\n", gif.display())).unwrap();
+    (path, format!("file://{}", gif.display()))
+}
+
+#[test]
+fn animated_images_play_and_pause_inline() {
+    let (path, uri) = recording();
+    let mut harness = harness();
+    egui_extras::install_image_loaders(&harness.ctx);
+    let animations = crate::animation::Animations::install(&harness.ctx, true);
+    // Stepped rather than run, as playing keeps asking for frames
+    harness.state_mut().window.open_any(path);
+    let playback = |harness: &Harness<'_, TestApp>| crate::animation::playback(&harness.ctx, &uri);
+    for _ in 0..200 {
+        harness.step();
+        if playback(&harness).is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let mut shown = Vec::new();
+    for _ in 0..30 {
+        harness.step();
+        shown.push(playback(&harness).unwrap().shown);
+    }
+    assert_eq!(playback(&harness).unwrap().frames, 2);
+    assert!(shown.contains(&0) && shown.contains(&1), "{shown:?}");
+
+    // Paused where it is, from the button over it, without opening it
+    harness.get_by_role(egui::accesskit::Role::Image).hover();
+    harness.step();
+    harness.get_by_label("⏸").click();
+    harness.step();
+    harness.step();
+    let paused = playback(&harness).unwrap();
+    assert!(paused.paused);
+    for _ in 0..30 {
+        harness.step();
+    }
+    assert_eq!(playback(&harness).unwrap().shown, paused.shown);
+    assert!(harness.query_by_label("Save Image As…").is_none());
+
+    // Only the first frame, with playing turned off
+    animations.set_playing(&harness.ctx, false);
+    harness.step();
+    harness.step();
+    assert_eq!(playback(&harness), None);
+}
+
+#[test]
+fn animations_are_decoded_past_the_first_frame_once_played() {
+    use egui::load::TextureLoader as _;
+
+    let (path, uri) = recording();
+    let mut harness = harness();
+    egui_extras::install_image_loaders(&harness.ctx);
+    let animations = crate::animation::Animations::install(&harness.ctx, false);
+    harness.state_mut().window.open_any(path);
+    // Decoded on another thread, so waited for
+    let frame = 120 * 60 * size_of::<egui::Color32>();
+    let wait_for = |harness: &mut Harness<'_, TestApp>, bytes| {
+        for _ in 0..200 {
+            harness.step();
+            if animations.byte_size() == bytes {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("{} bytes of frames, not {bytes}", animations.byte_size());
+    };
+    wait_for(&mut harness, frame);
+    assert_eq!(crate::animation::playback(&harness.ctx, &uri), None);
+
+    animations.set_playing(&harness.ctx, true);
+    wait_for(&mut harness, 2 * frame);
+    harness.step();
+    let playback = crate::animation::playback(&harness.ctx, &uri).unwrap();
+    assert_eq!(playback.frames, 2);
+}
+
+#[test]
+fn csv_fences_and_linked_csv_files_show_as_tables() {
+    let mut harness = harness();
+    open(&mut harness, fixture("csv.md"));
+    for cell in ["name", "Lovelace, Ada", "88", "alpha"] {
+        assert!(
+            harness.query_by_label(cell).is_some(),
+            "{cell} should be a cell"
+        );
+    }
+    assert!(harness.query_by_label_contains("name,score").is_none());
+
+    harness.get_by_label("a file").click();
+    for _ in 0..200 {
+        harness.step();
+        let window = &harness.state().window;
+        if window.tabs.len() == 2 && !window.tabs[1].doc.blocks.is_empty() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    harness.run();
+    let window = &harness.state().window;
+    assert_eq!(window.tabs.len(), 2);
+    assert_eq!(window.tabs[window.active].title, "data.csv");
+    for cell in ["population", "421,878", "Quote \"City\""] {
+        assert!(
+            harness.query_by_label(cell).is_some(),
+            "{cell} should be a cell"
+        );
+    }
+}
+
+#[test]
+fn shortcuts_are_drawn_as_keys() {
+    let mut harness = harness();
+    open(&mut harness, fixture("keyboard.md"));
+    for key in ["Ctrl", "C", "Shift", "P", "F5", "Alt", "F4", "Cmd", "Q"] {
+        assert!(
+            harness.query_all_by_label(key).next().is_some(),
+            "{key} should be a key"
+        );
+    }
+    assert!(harness.query_by_label_contains("<kbd>").is_none());
+    // Code that isn't a combination, and tables, render as before
+    assert!(harness.query_by_label("a+b").is_some());
+    assert!(harness.query_by_label("Ctrl+O").is_some());
+}
+
+#[test]
+fn readme_html_is_drawn_instead_of_shown_as_tags() {
+    let mut harness = harness();
+    open(&mut harness, fixture("html.md"));
+    for text in [
+        "A viewer for Markdown",
+        "2",
+        "Already open",
+        "Shown from the start.",
+    ] {
+        assert!(
+            harness.query_all_by_label(text).next().is_some(),
+            "{text} should be shown"
+        );
+    }
+    assert!(harness.query_by_label_contains("<").is_none());
+    assert!(harness.query_by_label_contains("never shown").is_none());
+    assert!(harness.query_by_label("Text after the script.").is_some());
+
+    let hidden = "Run the build script once the toolchain is installed.";
+    assert!(harness.query_by_label(hidden).is_none());
+    harness.get_by_label_contains("Build from source").click();
+    harness.run();
+    assert!(harness.query_by_label(hidden).is_some());
+}
+
+#[test]
+fn details_stay_open_or_closed_in_their_tab() {
+    let mut harness = harness();
+    open(&mut harness, fixture("html.md"));
+    let body = "Shown from the start.";
+    assert!(harness.query_by_label(body).is_some());
+    harness.get_by_label("Already open").click();
+    harness.run();
+    assert!(harness.query_by_label(body).is_none());
+
+    open(&mut harness, fixture("keyboard.md"));
+    harness.state_mut().window.active = 0;
+    harness.run();
+    harness.state_mut().window.tabs[0].reload().unwrap();
+    harness.run();
+    assert!(harness.query_by_label(body).is_none());
+    assert!(harness.state().window.tabs[1].toggled_details.is_empty());
+}
+
+#[test]
+fn details_with_the_same_summary_open_apart() {
+    let mut harness = harness();
+    open(&mut harness, fixture("details.md"));
+    assert!(harness.query_by_label("About the first step.").is_none());
+    harness.query_all_by_label("More").next().unwrap().click();
+    harness.run();
+    assert!(harness.query_by_label("About the first step.").is_some());
+    assert!(harness.query_by_label("About the second step.").is_none());
+}
+
+#[test]
+fn very_large_text_still_wraps_to_the_window() {
+    let mut harness = harness();
+    harness.state_mut().shared.settings.md_text_scale = 3.0;
+    harness.run();
+    harness.get_by_label("A+").click();
+    harness.run();
+    let scale = harness.state().shared.settings.md_text_scale;
+    assert!(scale > 3.0, "zoom stopped at {scale}");
+
+    harness.state_mut().shared.settings.md_text_scale = 8.0;
+    open(&mut harness, fixture("unicode.md"));
+    let line = harness.get_by_label_contains("Mixed scripts").rect();
+    assert!(
+        line.right() <= 1000.0,
+        "prose overflows to {}",
+        line.right()
+    );
+    // Several lines of it, each as tall as the text
+    assert!(line.height() > 2.0 * 14.0 * 8.0);
+}
+
+#[test]
+fn text_can_be_kept_to_a_readable_column() {
+    let mut harness = harness();
+    open(&mut harness, fixture("tables.md"));
+    let words = "Some words to wrap around. ".repeat(40);
+    harness.state_mut().window.tabs[0].set_content(words.trim().to_string());
+    harness.run();
+    let bounds =
+        |harness: &Harness<'_, TestApp>| harness.get_by_label_contains("Some words to wrap").rect();
+    let full = bounds(&harness);
+
+    let settings = &mut harness.state_mut().shared.settings;
+    settings.limit_text_width = true;
+    settings.text_width = 40;
+    harness.run();
+    let column = bounds(&harness);
+    assert!(
+        column.width() < full.width() / 2.0,
+        "{column:?} against {full:?}"
+    );
+    // Centered where the text was
+    assert!(
+        (column.center().x - full.center().x).abs() < 20.0,
+        "{column:?}"
+    );
+
+    // Margins narrow it further, on both sides
+    let settings = &mut harness.state_mut().shared.settings;
+    settings.limit_text_width = false;
+    settings.margin = 100.0;
+    harness.run();
+    let margins = bounds(&harness);
+    assert!(margins.left() >= full.left() + 99.0);
+    assert!(margins.width() <= full.width() - 180.0, "{margins:?}");
+}
+
+#[test]
+fn documents_can_be_read_on_sepia_paper() {
+    let mut harness = harness();
+    open(&mut harness, fixture("tables.md"));
+    let sepia = Paper::Sepia.colors(false).unwrap().background;
+    let painted =
+        |harness: &Harness<'_, TestApp>| {
+            harness.output().shapes.iter().any(
+                |clipped| matches!(&clipped.shape, egui::Shape::Rect(rect) if rect.fill == sepia),
+            )
+        };
+    assert!(!painted(&harness));
+
+    harness.get_by_label("View").click();
+    harness.run();
+    harness.get_by_label("Theme ⏵").hover();
+    harness.run();
+    harness.get_by_label("Sepia").click();
+    harness.run();
+    assert_eq!(harness.state().shared.settings.paper, Paper::Sepia);
+    assert!(painted(&harness));
+}
+
+#[test]
+fn frontmatter_hints_change_how_their_tab_looks() {
+    let mut harness = harness();
+    open(&mut harness, fixture("tables.md"));
+    let words = "Some words to wrap around. ".repeat(40);
+    harness.state_mut().window.tabs[0].set_content(words.trim().to_string());
+    harness.run();
+    let bounds =
+        |harness: &Harness<'_, TestApp>| harness.get_by_label_contains("Some words to wrap").rect();
+    // The tests run in dark mode
+    let light = egui::Visuals::light().panel_fill;
+    let painted_light =
+        |harness: &Harness<'_, TestApp>| {
+            harness.output().shapes.iter().any(
+                |clipped| matches!(&clipped.shape, egui::Shape::Rect(rect) if rect.fill == light),
+            )
+        };
+    let plain = bounds(&harness);
+    assert!(!painted_light(&harness));
+
+    let hinted = format!("---\ntheme: light\nwidth: 30ch\nfont_scale: 1.5\n---\n\n{words}");
+    harness.state_mut().window.tabs[0].set_content(hinted);
+    harness.run();
+    let column = bounds(&harness);
+    assert!(
+        column.width() < plain.width() / 2.0,
+        "{column:?} against {plain:?}"
+    );
+    // Fewer, larger words to a line
+    assert!(
+        column.height() > plain.height() * 3.0,
+        "{column:?} against {plain:?}"
+    );
+    assert!(painted_light(&harness));
+
+    harness.state_mut().shared.settings.document_hints = false;
+    harness.run();
+    assert_eq!(bounds(&harness).width(), plain.width());
+    assert!(!painted_light(&harness));
+}
+
+#[test]
+fn scheduled_themes_follow_the_hour() {
+    let mut settings = Settings {
+        theme: config::Theme::Scheduled,
+        light_from: 7,
+        dark_from: 19,
+        ..Settings::default()
+    };
+    let dark_at =
+        |settings: &Settings, hour| settings.theme_preference(hour) == egui::ThemePreference::Dark;
+    assert!(dark_at(&settings, 6));
+    assert!(!dark_at(&settings, 7));
+    assert!(!dark_at(&settings, 18));
+    assert!(dark_at(&settings, 19));
+
+    // Light overnight, for people working nights
+    settings.light_from = 20;
+    settings.dark_from = 4;
+    assert!(!dark_at(&settings, 23));
+    assert!(!dark_at(&settings, 2));
+    assert!(dark_at(&settings, 12));
+}
+
+#[test]
+fn callouts_are_drawn_as_titled_boxes() {
+    let mut harness = harness();
+    open(&mut harness, fixture("callouts.md"));
+    for title in ["Note", "Mind the gap", "Info", "Do not run this"] {
+        assert!(
+            harness.query_by_label(title).is_some(),
+            "{title} should be a title"
+        );
+    }
+    for marker in ["[!NOTE]", "[!WARNING]", ":::info", ":::danger"] {
+        assert!(
+            harness.query_by_label_contains(marker).is_none(),
+            "{marker} should be hidden"
+        );
+    }
+    // Blank lines don't end a container, and tables in one are tables
+    assert_eq!(harness.state().window.tabs[0].doc.blocks.len(), 7);
+    assert!(harness.query_by_label("fast").is_some());
+    assert!(harness.query_by_label_contains(":::unknown").is_some());
+}
+
+#[test]
+fn frontmatter_is_hidden_and_shown_as_a_badge() {
+    let mut harness = harness();
+    open(&mut harness, fixture("frontmatter.md"));
+    assert!(harness.query_by_label_contains("tags:").is_none());
+    // Both on the tab and above the document
+    assert_eq!(harness.query_all_by_label("Draft").count(), 2);
+}
+
+#[test]
+fn utf16_files_are_decoded() {
+    let mut harness = harness();
+    open(&mut harness, fixture("utf16.md"));
+    let tab = &harness.state().window.tabs[0];
+    assert_eq!(tab.encoding, "UTF-16LE");
+    assert!(tab.content.contains("café"));
+}
+
+#[test]
+fn too_deeply_nested_documents_fall_back_to_an_error_panel() {
+    let mut harness = harness();
+    open(&mut harness, fixture("too_deep.md"));
+    assert!(harness.state().window.tabs[0].render_error.is_some());
+    assert!(
+        harness
+            .query_by_label("This document couldn't be rendered")
+            .is_some()
+    );
+
+    harness.get_by_label("Render as Plain Text").click();
+    harness.run();
+    assert!(harness.state().window.tabs[0].plain_text);
+}
+
+#[test]
+fn opening_a_file_twice_focuses_its_tab() {
+    let mut harness = harness();
+    open(&mut harness, fixture("tables.md"));
+    open(&mut harness, fixture("unicode.md"));
+    open(&mut harness, fixture("tables.md"));
+    let window = &harness.state().window;
+    assert_eq!(window.tabs.len(), 2);
+    assert_eq!(window.tabs[window.active].title, "tables.md");
+}
+
+#[test]
+fn tabs_switch_by_number_neighbor_and_last_use() {
+    let mut harness = harness();
+    for name in ["tables.md", "unicode.md", "callouts.md"] {
+        open(&mut harness, fixture(name));
+    }
+    let active = |harness: &Harness<'_, TestApp>| {
+        let window = &harness.state().window;
+        window.tabs[window.active].title.clone()
+    };
+    let press = |harness: &mut Harness<'_, TestApp>, modifiers, key| {
+        harness.key_press_modifiers(modifiers, key);
+        harness.run();
+    };
+
+    press(&mut harness, egui::Modifiers::COMMAND, egui::Key::Num1);
+    assert_eq!(active(&harness), "tables.md");
+    press(&mut harness, egui::Modifiers::COMMAND, egui::Key::Tab);
+    assert_eq!(active(&harness), "callouts.md");
+    press(&mut harness, egui::Modifiers::COMMAND, egui::Key::PageDown);
+    assert_eq!(active(&harness), "tables.md");
+    press(&mut harness, egui::Modifiers::COMMAND, egui::Key::PageUp);
+    assert_eq!(active(&harness), "callouts.md");
+
+    // Held down, Ctrl+Tab goes further back and lists the tabs until let go
+    harness.input_mut().modifiers = egui::Modifiers::COMMAND;
+    for _ in 0..2 {
+        harness.input_mut().events.push(egui::Event::Key {
+            key: egui::Key::Tab,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers::COMMAND,
+        });
+        harness.run();
+    }
+    assert!(harness.query_by_label("Recent tabs").is_some());
+    assert_eq!(active(&harness), "callouts.md");
+    harness.input_mut().modifiers = egui::Modifiers::NONE;
+    harness.run();
+    assert!(harness.query_by_label("Recent tabs").is_none());
+    assert_eq!(active(&harness), "unicode.md");
+}
+
+#[test]
+fn the_window_is_titled_after_the_active_document() {
+    let mut harness = harness();
+    assert_eq!(harness.state().window.title(false), "Markdown Viewer");
+    open(&mut harness, fixture("tables.md"));
+    let window = &harness.state().window;
+    assert_eq!(window.title(false), "tables.md — Markdown Viewer");
+    assert_eq!(
+        window.title(true),
+        format!("{} — Markdown Viewer", fixture("tables.md").display())
+    );
+
+    // The tab names the folder the file is in
+    harness.get_by_label("tables.md").hover();
+    harness.run();
+    let dir = fixture("tables.md").parent().unwrap().display().to_string();
+    assert!(harness.query_by_label(&dir).is_some());
+}
+
+#[test]
+fn closed_tabs_reopen_where_they_were_left() {
+    let mut harness = harness();
+    let long = scratch_copy("tables.md");
+    let text: String = (1..=200).map(|n| format!("Paragraph {n}.\n\n")).collect();
+    fs::write(&long, text).unwrap();
+    open(&mut harness, long.clone());
+    open(&mut harness, fixture("unicode.md"));
+    harness.state_mut().window.active = 0;
+    harness.run();
+    harness.key_press(egui::Key::Space);
+    harness.run();
+    let read_to = harness.state().window.tabs[0].scroll_offset;
+    assert!(read_to > 300.0);
+
+    harness.get_all_by_label("×").next().unwrap().click();
+    harness.run();
+    assert_eq!(harness.state().window.tabs.len(), 1);
+
+    harness.key_press_modifiers(
+        egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+        egui::Key::T,
+    );
+    for _ in 0..50 {
+        harness.run();
+        if !harness
+            .state()
+            .window
+            .tabs
+            .iter()
+            .any(|tab| tab.is_loading())
+        {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    harness.run();
+    let window = &harness.state().window;
+    assert_eq!(window.tabs.len(), 2);
+    assert_eq!(window.active, 0);
+    assert_eq!(window.tabs[0].path, long);
+    let offset = window.tabs[0].scroll_offset;
+    assert!(
+        (offset - read_to).abs() < 10.0,
+        "{offset} against {read_to}"
+    );
+
+    // And from the menu, once closed again
+    harness.get_all_by_label("×").next().unwrap().click();
+    harness.run();
+    harness.get_by_label("File").click();
+    harness.run();
+    harness.get_by_label("Reopen Closed Tab ⏵").hover();
+    harness.run();
+    harness.get_by_label("tables.md").click();
+    harness.run();
+    assert_eq!(harness.state().window.tabs.len(), 2);
+}
+
+#[test]
+fn paths_with_a_heading_open_at_that_heading() {
+    let filler = "Filler to scroll past.\n\n".repeat(60);
+    let source = fs::read_to_string(fixture("anchors.md"))
+        .unwrap()
+        .replace("### Changes\n", &format!("### Changes\n\n{filler}"));
+    let path = scratch_copy("anchors.md");
+    fs::write(&path, source).unwrap();
+
+    let (file, heading) =
+        crate::split_heading(PathBuf::from(format!("{}#changes-1", path.display())));
+    assert_eq!((&file, heading.as_deref()), (&path, Some("changes-1")));
+    // Existing files are left as they are
+    assert_eq!(crate::split_heading(path.clone()), (path.clone(), None));
+
+    let mut harness = harness();
+    harness.state_mut().window.open_at(file, heading);
+    wait_for_loads(&mut harness);
+    let tab = &harness.state().window.tabs[0];
+    assert_eq!(tab.current_heading, tab.doc.find_fragment("changes-1"));
+    assert_ne!(tab.current_heading, tab.doc.find_fragment("changes"));
+
+    harness
+        .state_mut()
+        .window
+        .open_at(path, Some("no-such-heading".into()));
+    harness.run();
+    assert!(matches!(
+        harness.state().window.events.latest(),
+        Some(AppEvent::PlaceNotFound { .. })
+    ));
+}
+
+#[test]
+fn mdviewer_links_name_a_document_and_its_section() {
+    let link = "mdviewer://open?path=%2Fdocs%2FRelease%20Notes.md&anchor=changes-1";
+    assert!(uri_scheme::is_link(link));
+    assert!(!uri_scheme::is_link("mdviewer-notes.md"));
+    assert_eq!(
+        uri_scheme::parse(link),
+        Some(PathBuf::from("/docs/Release Notes.md#changes-1"))
+    );
+    assert_eq!(
+        uri_scheme::parse("mdviewer://open/?anchor=%23intro&path=notes+today.md"),
+        Some(PathBuf::from("notes today.md#intro"))
+    );
+    assert_eq!(
+        uri_scheme::parse("mdviewer://open?path=a.md"),
+        Some(PathBuf::from("a.md"))
+    );
+    assert_eq!(uri_scheme::parse("mdviewer://open?anchor=intro"), None);
+    assert_eq!(uri_scheme::parse("mdviewer://close?path=a.md"), None);
+
+    // Routed like a path with a heading on the command line
+    let path = scratch_copy("anchors.md");
+    let link = format!("mdviewer://open?path={}&anchor=changes", path.display());
+    let (file, heading) = crate::split_heading(uri_scheme::parse(&link).unwrap());
+    assert_eq!((file, heading.as_deref()), (path, Some("changes")));
+}
+
+#[test]
+fn urls_open_from_the_file_menu_as_raw_documents() {
+    assert_eq!(
+        remote_docs::raw_url("https://github.com/NRohner/md_viewer/blob/main/docs/README.md"),
+        "https://raw.githubusercontent.com/NRohner/md_viewer/main/docs/README.md"
+    );
+    assert_eq!(
+        remote_docs::raw_url("https://gitlab.com/group/project/-/blob/main/README.md"),
+        "https://gitlab.com/group/project/-/raw/main/README.md"
+    );
+    // Anything else is fetched as it is
+    let raw = "https://github.com/NRohner/md_viewer/raw/main/README.md";
+    assert_eq!(remote_docs::raw_url(raw), raw);
+    assert_eq!(remote_docs::source_url(&fixture("tables.md")), None);
+
+    let mut harness = harness();
+    harness.get_by_label("File").click();
+    harness.run();
+    harness.get_by_label("Open URL…").click();
+    harness.run();
+    harness
+        .get_by_role(egui::accesskit::Role::TextInput)
+        .type_text("example.com/notes.md");
+    harness.run();
+    assert!(harness.get_by_label("Open").accesskit_node().is_disabled());
+    harness.get_by_label("Cancel").click();
+    harness.run();
+    assert!(harness.query_by_label("Open URL").is_none());
+}
+
+#[test]
+fn private_documents_are_fetched_with_the_login_for_their_site() {
+    let login = |site: &str, kind, user: &str, secret: &str| SiteLogin {
+        site: site.into(),
+        kind,
+        user: user.into(),
+        secret: secret.into(),
+    };
+    let logins = [
+        login("wiki.internal", LoginKind::Basic, "ada", "pa55\n"),
+        login("github.com", LoginKind::Bearer, "", " ghp_1234"),
+    ];
+    assert_eq!(
+        remote_docs::authorization(&logins, "https://docs.wiki.internal/setup.md").as_deref(),
+        Some("Basic YWRhOnBhNTU=")
+    );
+    // Raw files of github.com are on another site
+    assert_eq!(
+        remote_docs::authorization(&logins, "https://github.com/o/r/blob/main/README.md")
+            .as_deref(),
+        Some("Bearer ghp_1234")
+    );
+    assert_eq!(
+        remote_docs::authorization(&logins, "https://example.com/notes.md"),
+        None
+    );
+    assert_eq!(
+        remote_docs::authorization(&logins, "http://wiki.internal/setup.md"),
+        None
+    );
+}
+
+#[test]
+fn missing_files_close_their_tab_and_report_an_error() {
+    let mut harness = harness();
+    open(&mut harness, fixture("does_not_exist.md"));
+    let window = &harness.state().window;
+    assert!(window.tabs.is_empty());
+    assert!(matches!(
+        window.events.latest(),
+        Some(AppEvent::OpenFailed { .. })
+    ));
+}
+
+#[test]
+fn closing_tabs() {
+    let mut harness = harness();
+    open(&mut harness, fixture("tables.md"));
+    open(&mut harness, fixture("unicode.md"));
+
+    harness.get_all_by_label("×").next().unwrap().click();
+    harness.run();
+    let window = &harness.state().window;
+    assert_eq!(window.tabs.len(), 1);
+    assert_eq!(window.tabs[0].title, "unicode.md");
+
+    harness.get_by_label("×").click();
+    harness.run();
+    assert!(harness.state().window.tabs.is_empty());
+    assert!(
+        harness
+            .query_by_label("Welcome to Markdown Viewer")
+            .is_some()
+    );
+}
+
+#[test]
+fn reload_picks_up_changes() {
+    let mut harness = harness();
+    let path = scratch_copy("frontmatter.md");
+    open(&mut harness, path.clone());
+    assert!(!headings(&harness).contains(&"Added later".to_string()));
+
+    let mut text = fs::read_to_string(&path).unwrap();
+    text.push_str("\n## Added later\n");
+    fs::write(&path, text).unwrap();
+
+    harness.get_by_label("File").click();
+    harness.run();
+    harness.get_by_label("Reload").click();
+    harness.run();
+
+    assert!(headings(&harness).contains(&"Added later".to_string()));
+    assert!(matches!(
+        harness.state().window.events.latest(),
+        Some(AppEvent::Reloaded { .. })
+    ));
+}
+
+#[test]
+fn the_source_view_marks_a_column() {
+    let mut harness = harness();
+    open(&mut harness, fixture("tables.md"));
+    harness.state_mut().window.tabs[0].plain_text = true;
+    harness.run();
+    harness.get_by_role(egui::accesskit::Role::ComboBox).click();
+    harness.run();
+    harness.get_by_label("100 columns").click();
+    harness.run();
+
+    assert_eq!(
+        harness.state().shared.settings.column_guide,
+        ColumnGuide::At100
+    );
+}
+
+#[test]
+fn outside_changes_are_offered_for_reloading() {
+    let mut harness = harness();
+    let path = scratch_copy("frontmatter.md");
+    open(&mut harness, path.clone());
+    let change = |line: &str| {
+        let mut text = fs::read_to_string(&path).unwrap();
+        text.push_str(line);
+        fs::write(&path, text).unwrap();
+        // Ahead of the read, however coarse the file times are
+        let later = std::time::SystemTime::now() + Duration::from_secs(5);
+        fs::File::options()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+    };
+
+    change("\n## Added later\n");
+    harness.state_mut().window.tabs[0].check_external_change();
+    harness.run();
+    harness.get_by_label("Reload").click();
+    harness.run();
+    assert!(headings(&harness).contains(&"Added later".to_string()));
+    assert!(
+        harness
+            .query_by_label("This file changed on disk.")
+            .is_none()
+    );
+
+    change("\n## Ignored\n");
+    harness.state_mut().window.tabs[0].check_external_change();
+    harness.run();
+    harness.get_by_label("Ignore").click();
+    harness.run();
+    harness.state_mut().window.tabs[0].check_external_change();
+    harness.run();
+    assert!(
+        harness
+            .query_by_label("This file changed on disk.")
+            .is_none()
+    );
+    assert!(!headings(&harness).contains(&"Ignored".to_string()));
+}
+
+#[test]
+fn changes_keeping_the_file_time_are_found_by_comparing() {
+    let mut harness = harness();
+    let path = scratch_copy("frontmatter.md");
+    open(&mut harness, path.clone());
+    let modified = fs::metadata(&path).unwrap().modified().unwrap();
+    let mut text = fs::read_to_string(&path).unwrap();
+    text.push_str("\n## Same time\n");
+    fs::write(&path, text).unwrap();
+    fs::File::options()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    // On another thread, and taken in once done
+    let compare = |harness: &mut Harness<'_, TestApp>| {
+        let tab = &mut harness.state_mut().window.tabs[0];
+        tab.check_hash();
+        (0..100).find_map(|_| {
+            std::thread::sleep(Duration::from_millis(10));
+            tab.changed_by_hash()
+        })
+    };
+    assert!(!harness.state().window.tabs[0].changed_on_disk());
+    let decoded = compare(&mut harness).unwrap();
+    assert!(decoded.text.contains("## Same time"));
+
+    // Not found again once ignored
+    harness.state_mut().window.tabs[0].changed_externally = true;
+    harness.run();
+    harness.get_by_label("Ignore").click();
+    harness.run();
+    assert!(compare(&mut harness).is_none());
+}
+
+#[test]
+fn every_tab_can_be_reloaded() {
+    let mut harness = harness();
+    let paths = [scratch_copy("frontmatter.md"), scratch_copy("tables.md")];
+    for path in &paths {
+        open(&mut harness, path.clone());
+    }
+    let append = |path: &PathBuf, line: &str| {
+        let mut text = fs::read_to_string(path).unwrap();
+        text.push_str(line);
+        fs::write(path, text).unwrap();
+    };
+    let shows = |harness: &Harness<'_, TestApp>, idx: usize, line: &str| {
+        harness.state().window.tabs[idx].content.contains(line)
+    };
+
+    for path in &paths {
+        append(path, "\nRewritten by the generator.\n");
+    }
+    harness.get_by_label("File").click();
+    harness.run();
+    harness.get_by_label("Reload All Tabs").click();
+    harness.run();
+    assert!(shows(&harness, 0, "Rewritten by the generator."));
+    assert!(shows(&harness, 1, "Rewritten by the generator."));
+
+    // The tab in the background, without switching to it
+    append(&paths[0], "\nOnce more.\n");
+    harness.get_all_by_label("🔄").next().unwrap().click();
+    harness.run();
+    assert!(shows(&harness, 0, "Once more."));
+    assert_eq!(harness.state().window.active, 1);
+}
+
+#[test]
+fn uncommitted_changes_are_shown_against_head() {
+    let path = scratch_copy("frontmatter.md");
+    let dir = path.parent().unwrap();
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    };
+    git(&["init", "-q", "-b", "notes"]);
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "Start"]);
+    // Which a cloned repository could have, to run whatever it likes
+    let ran = dir.join("fsmonitor-ran");
+    git(&[
+        "config",
+        "core.fsmonitor",
+        &format!("touch '{}'", ran.display()),
+    ]);
+    let mut text = fs::read_to_string(&path).unwrap();
+    text.push_str("\nNot committed yet.\n");
+    fs::write(&path, text).unwrap();
+
+    let mut harness = harness();
+    harness.state_mut().shared.settings.trusted_folders = vec![dir.to_path_buf()];
+    open(&mut harness, path);
+    for _ in 0..200 {
+        harness.step();
+        if harness.query_by_label("● modified").is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(harness.query_by_label("notes").is_some());
+    assert!(!ran.exists());
+    harness.get_by_label("● modified").click();
+    harness.run();
+
+    assert!(harness.query_by_label("Comparing").is_some());
+    assert!(harness.query_by_label("+ Not committed yet.").is_some());
+
+    harness.state_mut().shared.settings.show_git_status = false;
+    harness.run();
+    assert!(harness.query_by_label("● modified").is_none());
+    assert!(harness.query_by_label("notes").is_none());
+}
+
+#[test]
+fn changes_of_a_reload_can_be_shown() {
+    let mut harness = harness();
+    let path = scratch_copy("frontmatter.md");
+    open(&mut harness, path.clone());
+    assert!(harness.query_by_label("Show Changes").is_none());
+
+    let mut text = fs::read_to_string(&path).unwrap();
+    text.push_str("\nA line added later.\n");
+    fs::write(&path, text).unwrap();
+    harness.state_mut().window.tabs[0].reload().unwrap();
+    harness.run();
+    harness.get_by_label("Show Changes").click();
+    harness.run();
+
+    assert!(harness.query_by_label("Comparing").is_some());
+    assert!(harness.query_by_label("+ A line added later.").is_some());
+    assert!(harness.query_by_label("Show Changes").is_none());
+}
+
+#[test]
+fn changes_of_reloads_in_a_row_are_shown_together() {
+    let mut harness = harness();
+    let path = scratch_copy("frontmatter.md");
+    open(&mut harness, path.clone());
+
+    let mut text = fs::read_to_string(&path).unwrap();
+    for line in ["\nA first regeneration.\n", "\nA second one.\n"] {
+        text.push_str(line);
+        fs::write(&path, &text).unwrap();
+        harness.state_mut().window.tabs[0].reload().unwrap();
+        harness.run();
+    }
+    assert!(
+        harness
+            .query_by_label("This document was reloaded 2 times with changes.")
+            .is_some()
+    );
+    harness.get_by_label("Show Changes").click();
+    harness.run();
+    assert!(harness.query_by_label("+ A first regeneration.").is_some());
+    assert!(harness.query_by_label("+ A second one.").is_some());
+
+    // Once looked at, the next reload is compared with what was shown
+    text.push_str("\nA third one.\n");
+    fs::write(&path, &text).unwrap();
+    harness.state_mut().window.tabs[0].reload().unwrap();
+    harness.run();
+    let tab = &harness.state().window.tabs[0];
+    assert!(
+        tab.previous_content
+            .as_ref()
+            .unwrap()
+            .contains("A second one.")
+    );
+}
+
+#[test]
+fn link_schemes_go_to_their_handlers() {
+    let mut harness = harness();
+    harness.state_mut().shared.settings.link_handlers = vec![
+        LinkHandler {
+            scheme: "jira".into(),
+            action: LinkAction::Rewrite,
+            template: "https://jira.example.com/browse/{target}".into(),
+        },
+        LinkHandler {
+            scheme: "TEL:".into(),
+            action: LinkAction::RunCommand,
+            template: "md-viewer-no-such-dialer --call \"{target}\"".into(),
+        },
+    ];
+    open(&mut harness, fixture("schemes.md"));
+    let opened = |harness: &Harness<'_, TestApp>| {
+        harness
+            .output()
+            .platform_output
+            .commands
+            .iter()
+            .find_map(|command| match command {
+                egui::OutputCommand::OpenUrl(open) => Some(open.url.clone()),
+                _ => None,
+            })
+    };
+
+    harness.get_by_label("ABC-123").click();
+    harness.step();
+    assert_eq!(
+        opened(&harness).as_deref(),
+        Some("https://jira.example.com/browse/ABC-123")
+    );
+
+    // Commands are confirmed first
+    harness.get_by_label("the desk").click();
+    harness.step();
+    assert_eq!(opened(&harness), None);
+    harness.run();
+    assert!(!matches!(
+        harness.state().window.events.latest(),
+        Some(AppEvent::LinkHandlerFailed { .. })
+    ));
+    assert!(
+        harness
+            .query_by_label("md-viewer-no-such-dialer --call \"+15551234\"")
+            .is_some()
+    );
+    harness.get_by_label("Run").click();
+    harness.run();
+    assert!(matches!(
+        harness.state().window.events.latest(),
+        Some(AppEvent::LinkHandlerFailed { url, .. }) if url == "tel:+15551234"
+    ));
+
+    harness.get_by_label("an option").click();
+    harness.run();
+    harness.get_by_label("Run").click();
+    harness.run();
+    assert!(matches!(
+        harness.state().window.events.latest(),
+        Some(AppEvent::LinkHandlerFailed { url, error })
+            if url == "tel:--help" && error.contains("taken for an option")
+    ));
+}
+
+#[test]
+fn the_interface_speaks_the_chosen_language() {
+    let mut harness = harness();
+    harness.state_mut().shared.settings.language = Language::German;
+    open(&mut harness, fixture("tables.md"));
+    assert_eq!(
+        harness.state().window.events.latest().unwrap().to_string(),
+        "tables.md geöffnet"
+    );
+
+    harness.get_by_label("Datei").click();
+    harness.run();
+    harness.get_by_label("Einstellungen…").click();
+    harness.run();
+    assert!(harness.query_by_label("Sprache").is_some());
+    assert!(harness.query_by_label("Preferences").is_none());
+
+    // And back, without a restart
+    harness.state_mut().shared.settings.language = Language::English;
+    harness.run();
+    assert!(harness.query_by_label("File").is_some());
+    assert!(harness.query_by_label("Language").is_some());
+}
+
+#[test]
+fn web_links_are_confirmed_first() {
+    let mut harness = harness();
+    harness.state_mut().shared.settings.web_links = WebLinks::Ask;
+    open(&mut harness, fixture("web_links.md"));
+    let opened = |harness: &Harness<'_, TestApp>| {
+        harness
+            .output()
+            .platform_output
+            .commands
+            .iter()
+            .find_map(|command| match command {
+                egui::OutputCommand::OpenUrl(open) => Some(open.url.clone()),
+                _ => None,
+            })
+    };
+    let guide = "https://docs.example.com/guide?from=viewer#setup";
+
+    harness.get_by_label("the guide").click();
+    harness.step();
+    assert_eq!(opened(&harness), None);
+    harness.run();
+    assert!(harness.query_by_label(guide).is_some());
+    harness.get_by_label("Always Open docs.example.com").click();
+    harness.step();
+    assert_eq!(opened(&harness).as_deref(), Some(guide));
+    assert_eq!(
+        harness.state().shared.settings.trusted_sites,
+        ["docs.example.com"]
+    );
+
+    // Trusted now, and the other site too once asking is off
+    harness.run();
+    harness.get_by_label("the guide").click();
+    harness.step();
+    assert_eq!(opened(&harness).as_deref(), Some(guide));
+
+    // Other schemes are asked about too, with no site to trust
+    let share = "smb://server/share/notes.txt";
+    harness.run();
+    harness.get_by_label("the share").click();
+    harness.step();
+    assert_eq!(opened(&harness), None);
+    harness.run();
+    assert!(
+        harness
+            .query_by_label("Open this link in another app?")
+            .is_some()
+    );
+    assert!(harness.query_by_label_contains("Always Open").is_none());
+    harness.get_by_label("Open").click();
+    harness.step();
+    assert_eq!(opened(&harness).as_deref(), Some(share));
+
+    harness.state_mut().shared.settings.web_links = WebLinks::Never;
+    harness.run();
+    harness.get_by_label("somewhere else").click();
+    harness.step();
+    assert_eq!(opened(&harness), None);
+    assert!(matches!(
+        harness.state().window.events.latest(),
+        Some(AppEvent::WebLinkBlocked { url }) if url == "http://elsewhere.test/"
+    ));
+    harness.run();
+    harness.get_by_label("the share").click();
+    harness.step();
+    assert_eq!(opened(&harness), None);
+    assert!(matches!(
+        harness.state().window.events.latest(),
+        Some(AppEvent::WebLinkBlocked { url }) if url == share
+    ));
+}
+
+#[test]
+fn heading_links_and_the_contents_are_copied() {
+    let mut harness = harness();
+    // Its links have the headings' titles too
+    harness.state_mut().shared.settings.show_toc = false;
+    open(&mut harness, fixture("reference_links.md"));
+    let heading = harness.get_by_label("Later section").rect();
+    harness.get_by_label("Later section").hover();
+    harness.run();
+    let button = harness.get_by_label("🔗");
+    assert!(button.rect().left() > heading.right());
+    button.click();
+    harness.step();
+    assert_eq!(
+        copied_text(&harness).as_deref(),
+        Some("reference_links.md#later-section")
+    );
+
+    harness.get_by_label("File").click();
+    harness.run();
+    harness.get_by_label("Copy Table of Contents").click();
+    harness.step();
+    assert_eq!(
+        copied_text(&harness).as_deref(),
+        Some("- [References](#references)\n  - [Later section](#later-section)\n")
+    );
+}
+
+#[test]
+fn wikilinks_open_their_notes() {
+    let mut harness = harness();
+    open(&mut harness, fixture("notes/index.md"));
+    let blocks = &harness.state().window.tabs[0].doc.blocks;
+    let markdown = blocks[1].wiki_markdown.as_deref().unwrap();
+    assert!(markdown.contains("*[Missing Note](<wiki:Missing Note>)*"));
+    // Inside code, brackets are just text
+    assert!(blocks[2].wiki_markdown.is_none());
+
+    harness.get_by_label("Other Note").click();
+    harness.run();
+    let window = &harness.state().window;
+    assert_eq!(window.tabs.len(), 2);
+    assert_eq!(window.tabs[window.active].title, "Other Note.md");
+
+    harness.state_mut().window.active = 0;
+    harness.run();
+    harness.get_by_label("Missing Note").click();
+    harness.run();
+    assert!(matches!(
+        harness.state().window.events.latest(),
+        Some(AppEvent::LinkUnresolved { .. })
+    ));
+}
+
+#[test]
+fn linked_web_pages_open_as_a_text_preview() {
+    let mut harness = harness();
+    open(&mut harness, fixture("web_page.md"));
+    harness.get_by_label("the manual").click();
+    for _ in 0..200 {
+        harness.step();
+        let window = &harness.state().window;
+        if window.tabs.len() == 2 && !window.tabs[1].doc.blocks.is_empty() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    harness.run();
+
+    let tab = &harness.state().window.tabs[1];
+    assert_eq!(tab.path, fixture("manual.html"));
+    assert_eq!(headings(&harness), ["Manual", "Installation"]);
+    assert!(
+        tab.content
+            .contains("Everything about the *tool* & its `config_file`.")
+    );
+    assert!(
+        tab.content
+            .contains("1. Download the [release](https://example.com/get)")
+    );
+    assert!(tab.content.contains("```\ntool --init\ntool <name>\n```"));
+    assert!(tab.content.contains("| --quiet | Less output |"));
+    assert!(!tab.content.contains("never shown"));
+
+    // Not a link in a document, so however those are treated
+    harness.state_mut().shared.settings.web_links = WebLinks::Never;
+    harness.get_by_label("Open in Browser").click();
+    harness.step();
+    let opened =
+        harness
+            .output()
+            .platform_output
+            .commands
+            .iter()
+            .find_map(|command| match command {
+                egui::OutputCommand::OpenUrl(open) => Some(open.url.clone()),
+                _ => None,
+            });
+    let url = opened.expect("the page should open in the browser");
+    assert!(url.starts_with("file:///"), "{url}");
+    assert!(url.ends_with("/tests/fixtures/manual.html"), "{url}");
+}
+
+#[test]
+fn hovering_a_link_previews_its_target() {
+    let mut harness = harness();
+    open(&mut harness, fixture("notes/index.md"));
+    harness.get_by_label("the details").hover();
+    harness.run();
+    let tab = &harness.state().window.tabs[0];
+    assert_eq!(tab.hovered_link.as_deref(), Some("wiki:other note#Details"));
+    assert!(harness.query_by_label("Linked from the index.").is_some());
+}
+
+#[test]
+fn dashboard_summarizes_the_folder() {
+    let mut harness = harness();
+    harness.state_mut().shared.workspace = Some(Workspace::open(fixture("notes"), &[]).unwrap());
+    harness.run();
+    harness.get_by_label("View").click();
+    harness.run();
+    harness.get_by_label("Folder Dashboard").click();
+
+    for _ in 0..200 {
+        harness.step();
+        if harness.query_by_label("Broken links (1)").is_some() {
+            assert!(harness.query_by_label("Without frontmatter (1)").is_some());
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("dashboard didn't report the missing note");
+}
+
+#[test]
+fn books_are_read_in_summary_order() {
+    let mut harness = harness();
+    harness.state_mut().shared.workspace = Some(Workspace::open(fixture("book"), &[]).unwrap());
+    harness.run();
+    assert!(harness.query_by_label("📖 Field Guide").is_some());
+    assert!(harness.query_by_label("Troubleshooting").is_some());
+
+    harness.get_by_label("Introduction").click();
+    harness.run();
+    for _ in 0..200 {
+        harness.step();
+        if harness.query_by_label("Getting Started →").is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    harness.get_by_label("Getting Started →").click();
+    harness.run();
+    let window = &harness.state().window;
+    assert_eq!(
+        window.tabs[window.active].path,
+        fixture("book/src/guide/start.md")
+    );
+}
+
+#[test]
+fn tags_of_the_folder_open_their_documents() {
+    let mut harness = harness();
+    harness.state_mut().shared.workspace = Some(Workspace::open(fixture("notes"), &[]).unwrap());
+    harness.state_mut().shared.settings.show_tags = true;
+    // The panel takes a frame more to settle on its width
+    harness.run();
+    harness.run();
+    assert!(harness.query_by_label("#project 1").is_some());
+
+    harness.get_by_label("#reading 1").click();
+    harness.run();
+    assert!(harness.query_by_label("Tagged #reading").is_some());
+    harness
+        // Not the entry in the file tree
+        .get_by_role_and_label(egui::accesskit::Role::Label, "Other Note.md")
+        .click();
+    harness.run();
+    let window = &harness.state().window;
+    assert_eq!(window.tabs.len(), 1);
+    assert_eq!(window.tabs[0].path, fixture("notes/Other Note.md"));
+}
+
+#[test]
+fn backlinks_list_the_documents_linking_here() {
+    let mut harness = harness();
+    harness.state_mut().shared.workspace = Some(Workspace::open(fixture("notes"), &[]).unwrap());
+    harness.state_mut().shared.settings.show_backlinks = true;
+    open(&mut harness, fixture("notes/Other Note.md"));
+
+    for _ in 0..200 {
+        harness.step();
+        if harness.query_by_label("2 links").is_some() {
+            // Listed beside its entry in the file tree
+            assert_eq!(harness.query_all_by_label("index.md").count(), 2);
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("backlinks didn't list the index");
+}
+
+#[test]
+fn quick_open_finds_documents_opened_lately() {
+    let mut harness = harness();
+    open(&mut harness, fixture("tables.md"));
+    open(&mut harness, fixture("unicode.md"));
+    assert_eq!(
+        harness.state().shared.settings.recent_files,
+        [fixture("unicode.md"), fixture("tables.md")]
+    );
+    for _ in 0..2 {
+        harness.get_all_by_label("×").next().unwrap().click();
+        harness.run();
+    }
+
+    // No folder is open, and the letters needn't be next to each other
+    harness.key_press_modifiers(egui::Modifiers::COMMAND, egui::Key::P);
+    harness.run();
+    harness
+        .get_by_role(egui::accesskit::Role::TextInput)
+        .type_text("tbls");
+    harness.run();
+    let row = format!("🕘 {}", fixture("tables.md").display());
+    assert!(harness.query_by_label(&row).is_some());
+    let other = format!("🕘 {}", fixture("unicode.md").display());
+    assert!(harness.query_by_label(&other).is_none());
+    harness.key_press(egui::Key::Enter);
+    harness.run();
+    let window = &harness.state().window;
+    assert_eq!(window.tabs.len(), 1);
+    assert_eq!(window.tabs[0].path, fixture("tables.md"));
+}
+
+#[test]
+fn the_command_palette_runs_what_is_typed() {
+    let mut harness = harness();
+    open(&mut harness, fixture("tables.md"));
+    open(&mut harness, fixture("frontmatter.md"));
+    let open_palette = |harness: &mut Harness<'_, TestApp>| {
+        harness.input_mut().events.push(egui::Event::Key {
+            key: egui::Key::P,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+        });
+        harness.run();
+    };
+
+    open_palette(&mut harness);
+    harness
+        .get_by_role(egui::accesskit::Role::TextInput)
+        .type_text("go tables");
+    harness.run();
+    assert!(harness.query_by_label("Go to Tab: tables.md").is_some());
+    harness.key_press(egui::Key::Enter);
+    harness.run();
+    assert_eq!(harness.state().window.active, 0);
+
+    // Letters scattered through the name are enough
+    open_palette(&mut harness);
+    harness
+        .get_by_role(egui::accesskit::Role::TextInput)
+        .type_text("hdtoc");
+    harness.run();
+    harness.key_press(egui::Key::Enter);
+    harness.run();
+    assert!(!harness.state().shared.settings.show_toc);
+}
+
+#[test]
+fn keys_scroll_the_document() {
+    let mut harness = harness();
+    open(&mut harness, fixture("tables.md"));
+    let long: String = (1..=200).map(|n| format!("Paragraph {n}.\n\n")).collect();
+    harness.state_mut().window.tabs[0].set_content(long);
+    harness.run();
+    let offset = |harness: &Harness<'_, TestApp>| harness.state().window.tabs[0].scroll_offset;
+    let press = |harness: &mut Harness<'_, TestApp>, key, modifiers| {
+        harness.key_press_modifiers(modifiers, key);
+        harness.run();
+    };
+
+    press(&mut harness, egui::Key::Space, egui::Modifiers::NONE);
+    let page = offset(&harness);
+    assert!(page > 300.0, "a page down went {page}");
+    press(&mut harness, egui::Key::K, egui::Modifiers::NONE);
+    assert!(offset(&harness) < page);
+    press(&mut harness, egui::Key::G, egui::Modifiers::SHIFT);
+    let bottom = offset(&harness);
+    assert!(bottom > page * 5.0);
+    press(&mut harness, egui::Key::PageUp, egui::Modifiers::NONE);
+    assert!(offset(&harness) < bottom);
+    press(&mut harness, egui::Key::G, egui::Modifiers::NONE);
+    press(&mut harness, egui::Key::G, egui::Modifiers::NONE);
+    assert_eq!(offset(&harness), 0.0);
+
+    // Off, the letters leave the view alone
+    harness.state_mut().shared.settings.vim_keys = false;
+    press(&mut harness, egui::Key::J, egui::Modifiers::NONE);
+    assert_eq!(offset(&harness), 0.0);
+}
+
+#[test]
+fn search_all_finds_hits_in_tabs_and_the_folder() {
+    let mut harness = harness();
+    harness.state_mut().shared.workspace = Some(Workspace::open(fixture("notes"), &[]).unwrap());
+    open(&mut harness, fixture("notes/index.md"));
+    harness.input_mut().events.push(egui::Event::Key {
+        key: egui::Key::F,
+        physical_key: None,
+        pressed: true,
+        repeat: false,
+        modifiers: egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+    });
+    harness.run();
+    // Not the find bar of the document
+    assert!(harness.query_by_label("Find:").is_none());
+    harness
+        .get_by_role(egui::accesskit::Role::TextInput)
+        .type_text("details");
+
+    for _ in 0..200 {
+        harness.step();
+        if harness.query_by_label("3 matches in 2 documents").is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(harness.query_by_label("index.md (2)").is_some());
+    // The other note isn't open, so it was read from the folder
+    harness.get_by_label("Other Note.md (1)");
+    harness.get_by_label_contains("## Details").click();
+    open(&mut harness, fixture("notes/Other Note.md"));
+    let window = &harness.state().window;
+    assert_eq!(window.tabs.len(), 2);
+    assert_eq!(window.tabs[window.active].title, "Other Note.md");
+}
+
+#[test]
+fn newer_releases_are_listed_newest_first() {
+    let json = fs::read_to_string(fixture("releases.json")).unwrap();
+    let releases = updates::newer(&json, "0.1.0").unwrap();
+    let versions: Vec<_> = releases.iter().map(|r| r.version.as_str()).collect();
+    // Pre-releases and the running version are left out
+    assert_eq!(versions, ["0.2.1", "0.2"]);
+    assert_eq!(releases[0].title(), "Version 0.2.1");
+    assert_eq!(releases[1].title(), "Folders and search");
+    assert!(updates::newer(&json, "0.2.1").unwrap().is_empty());
+}
+
+#[test]
+fn exported_tabs_keep_their_links() {
+    let mut harness = harness();
+    open(&mut harness, fixture("notes/index.md"));
+    open(&mut harness, fixture("notes/Other Note.md"));
+    let out = std::env::temp_dir().join(format!(
+        "md_viewer-export-{}-{}",
+        std::process::id(),
+        crate::tab::next_id()
+    ));
+    let tabs = &harness.state().window.tabs;
+    let docs: Vec<_> = tabs
+        .iter()
+        .map(|tab| (tab.path.as_path(), tab.content.as_str()))
+        .collect();
+
+    export::write(&docs, Bundle::Folder, &out, None).unwrap();
+    let index = fs::read_to_string(out.join("index.html")).unwrap();
+    assert!(index.contains("<a href=\"Other%20Note.html\">Other Note</a>"));
+    assert!(index.contains("<a href=\"Other%20Note.html#details\">the details</a>"));
+    assert!(index.contains(" and Missing Note."));
+    let other = fs::read_to_string(out.join("Other Note.html")).unwrap();
+    assert!(other.contains("<h2 id=\"details\">Details</h2>"));
+    assert!(!other.contains("aliases"));
+
+    let page = out.join("all.html");
+    export::write(&docs, Bundle::Page, &page, None).unwrap();
+    let page = fs::read_to_string(page).unwrap();
+    assert!(page.contains("<a href=\"#doc-1\">Other Note</a>"));
+    assert!(page.contains("<a href=\"#doc-1-details\">the details</a>"));
+    assert!(page.contains("<h2 id=\"doc-1-details\">"));
+}
+
+#[cfg(unix)]
+#[test]
+fn diagram_fences_are_drawn_by_their_program() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = scratch_copy("code_blocks.md");
+    fs::write(
+        &path,
+        "# Design\n\n```dot\ndigraph { app -> db }\n```\n\n```plantuml\nAlice -> Bob\n```\n",
+    )
+    .unwrap();
+    // Stands in for Graphviz: answers -Tsvg with a fixed SVG
+    let program = path.with_file_name("dot");
+    fs::write(
+        &program,
+        format!(
+            "#!/bin/sh\n[ \"$1\" = -Tsvg ] || exit 2\ncat > /dev/null\ncat '{}'\n",
+            fixture("diagram.svg").display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+    // And for PlantUML, failing with the security profile it's run under
+    let plantuml = path.with_file_name("plantuml");
+    fs::write(
+        &plantuml,
+        "#!/bin/sh\necho \"profile $PLANTUML_SECURITY_PROFILE\" >&2\nexit 1\n",
+    )
+    .unwrap();
+    fs::set_permissions(&plantuml, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut harness = harness();
+    egui_extras::install_image_loaders(&harness.ctx);
+    let settings = &mut harness.state_mut().shared.settings;
+    settings.graphviz_command = program.display().to_string();
+    settings.plantuml_command = plantuml.display().to_string();
+    open(&mut harness, path.clone());
+    harness.run();
+    // Not until the folder is trusted
+    assert!(
+        harness
+            .query_all_by_value("digraph { app -> db }")
+            .next()
+            .is_some()
+    );
+    harness.get_by_label("Untrusted folder").click();
+    harness.run();
+    assert!(harness.query_by_label("Trust this folder?").is_some());
+    harness.get_by_label("Trust Folder").click();
+    harness.run();
+    assert_eq!(
+        harness.state().shared.settings.trusted_folders,
+        [path.parent().unwrap()]
+    );
+    for _ in 0..200 {
+        harness.step();
+        if harness
+            .query_by_role(egui::accesskit::Role::Image)
+            .is_some()
+        {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    harness.run();
+    assert!(
+        harness
+            .query_by_role(egui::accesskit::Role::Image)
+            .is_some()
+    );
+    assert!(
+        harness
+            .query_all_by_value("digraph { app -> db }")
+            .next()
+            .is_none()
+    );
+    // Failing, the source is shown under why; the preprocessor is sandboxed
+    assert!(
+        harness
+            .query_by_label_contains("Couldn't render the diagram:")
+            .is_some_and(|node| node.value().is_some_and(|v| v.contains("profile SANDBOX")))
+    );
+    assert!(harness.query_all_by_value("Alice -> Bob").next().is_some());
+}
+
+#[cfg(unix)]
+#[test]
+fn installed_pandoc_is_offered_for_other_formats() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = scratch_copy("frontmatter.md");
+    // Stands in for pandoc: copies its input to the output file
+    let program = path.with_file_name("pandoc");
+    fs::write(
+        &program,
+        "#!/bin/sh\n\
+         if [ \"$1\" = --version ]; then echo 'pandoc 3.1'; exit 0; fi\n\
+         while [ $# -gt 0 ]; do [ \"$1\" = --output ] && out=\"$2\"; shift; done\n\
+         cat > \"$out\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut harness = harness();
+    harness.state_mut().shared.settings.pandoc_path = program.display().to_string();
+    open(&mut harness, path.clone());
+    harness.get_by_label("File").click();
+    harness.run();
+    harness.get_by_label("Export ⏵").hover();
+    for _ in 0..200 {
+        harness.step();
+        if !harness
+            .get_by_label("Active Tab with Pandoc…")
+            .accesskit_node()
+            .is_disabled()
+        {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    harness.get_by_label("Active Tab with Pandoc…").click();
+    harness.run();
+    harness.get_by_label("Export").click();
+
+    let out = path.with_extension("docx");
+    for _ in 0..200 {
+        harness.step();
+        if matches!(
+            harness.state().window.events.latest(),
+            Some(AppEvent::Exported { .. })
+        ) {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(matches!(
+        harness.state().window.events.latest(),
+        Some(AppEvent::Exported { path }) if *path == out
+    ));
+    let written = fs::read_to_string(out).unwrap();
+    assert_eq!(written, harness.state().window.tabs[0].content);
+}
+
+#[test]
+fn exported_headings_keep_their_anchors() {
+    let mut harness = harness();
+    open(&mut harness, fixture("anchors.md"));
+    let out = std::env::temp_dir().join(format!(
+        "md_viewer-anchors-{}-{}",
+        std::process::id(),
+        crate::tab::next_id()
+    ));
+    let tab = &harness.state().window.tabs[0];
+    let docs = [(tab.path.as_path(), tab.content.as_str())];
+
+    export::write(&docs, Bundle::Folder, &out, None).unwrap();
+    let notes = fs::read_to_string(out.join("anchors.html")).unwrap();
+    assert!(notes.contains("<h3 id=\"changes\">Changes</h3>"));
+    assert!(notes.contains("<h3 id=\"changes-1\">Changes</h3>"));
+    assert!(notes.contains("<a href=\"#changes-1\">the second Changes</a>"));
+    assert!(notes.contains("<nav class=\"toc\">"));
+    assert!(notes.contains("<a href=\"#version-1\">Version 1</a>"));
+
+    let page = out.join("all.html");
+    export::write(&docs, Bundle::Page, &page, None).unwrap();
+    let page = fs::read_to_string(page).unwrap();
+    assert!(page.contains("<h3 id=\"doc-0-changes-1\">"));
+    assert!(page.contains("<a href=\"#doc-0-changes-1\">Changes</a>"));
+}
+
+#[test]
+fn footnotes_show_on_hover_and_link_back_to_their_reference() {
+    let mut harness = harness();
+    open(&mut harness, fixture("footnotes.md"));
+    let offset = |harness: &Harness<'_, TestApp>| harness.state().window.tabs[0].scroll_offset;
+    let reference = |node: &egui_kittest::kittest::AccessKitNode<'_>| {
+        node.role() == egui::accesskit::Role::Label && node.value().as_deref() == Some("¹")
+    };
+
+    harness.get_by(reference).hover();
+    harness.run();
+    assert!(
+        harness
+            .query_by_label_contains("Smith and Jones, Reading Habits, page 4.")
+            .is_some()
+    );
+
+    harness.get_by(reference).click();
+    harness.run();
+    assert!(offset(&harness) > 100.0);
+
+    harness.get_by_label("↩").click();
+    harness.run();
+    assert!(offset(&harness) < 100.0, "{}", offset(&harness));
+}
+
+#[test]
+fn tabs_can_be_read_in_another_markdown_dialect() {
+    let mut harness = harness();
+    open(&mut harness, fixture("dialects.md"));
+    assert!(harness.query_all_by_value("Ada").next().is_some());
+    assert!(harness.query_all_by_value("abandoned").next().is_some());
+
+    // Strict CommonMark has no tables or strikethrough
+    harness.get_by_label("Extended").click();
+    harness.run();
+    harness.get_by_label("CommonMark").click();
+    harness.run();
+    assert!(harness.query_all_by_value("Ada").next().is_none());
+    assert!(harness.query_all_by_value("| Ada  ").next().is_some());
+    assert!(harness.query_all_by_value("~abandoned").next().is_some());
+
+    harness.get_by_label("CommonMark").click();
+    harness.run();
+    harness.get_by_label("Smart punctuation").click();
+    harness.run();
+    assert!(harness.query_by_label("Custom").is_some());
+    assert!(
+        harness
+            .query_all_by_value("It’s done – mostly…")
+            .next()
+            .is_some()
+    );
+    assert!(
+        harness
+            .query_all_by_value("It's done -- mostly...")
+            .next()
+            .is_none()
+    );
+    // Other tabs go on with the one in Preferences
+    assert_eq!(harness.state().shared.settings.dialect, Dialect::default());
+}
+
+#[test]
+fn sections_with_the_same_title_collapse_one_at_a_time() {
+    let mut harness = harness();
+    open(&mut harness, fixture("anchors.md"));
+    let shown = |harness: &Harness<'_, TestApp>, text: &str| {
+        harness.query_all_by_value(text).next().is_some()
+    };
+    assert!(shown(&harness, "Faster startup."));
+
+    harness.state_mut().window.tabs[0]
+        .collapsed
+        .insert("changes".into());
+    harness.run();
+    assert!(!shown(&harness, "Faster startup."));
+    assert!(shown(&harness, "First release."));
+
+    harness.state_mut().window.tabs[0].set_all_collapsed(true);
+    harness.run();
+    assert!(!shown(&harness, "First release."));
+    assert!(
+        harness.state().window.tabs[0]
+            .collapsed
+            .contains("changes-1")
+    );
+}
+
+#[test]
+fn headings_can_be_numbered_in_the_document_and_contents() {
+    let mut harness = harness();
+    open(&mut harness, fixture("anchors.md"));
+    assert!(harness.query_by_label("1.1 Version 2").is_none());
+
+    harness.state_mut().shared.settings.number_headings = true;
+    harness.run();
+    for heading in [
+        "1. Release Notes",
+        "1.1 Version 2",
+        "1.1.1 Changes",
+        "1.2.1 Changes",
+    ] {
+        // In the contents, and in the document
+        let shown = harness
+            .query_all_by_value(heading)
+            .filter(|node| node.accesskit_node().role() == egui::accesskit::Role::Label)
+            .count();
+        assert_eq!(shown, 2, "{heading}");
+    }
+    // Only drawn: the source stays as written
+    assert!(
+        harness.state().window.tabs[0]
+            .content
+            .starts_with("# Release Notes")
+    );
+}
+
+#[test]
+fn large_files_load_in_chunks_and_end_up_whole() {
+    let dir = std::env::temp_dir().join(format!(
+        "md_viewer-test-{}-{}",
+        std::process::id(),
+        crate::tab::next_id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("report.md");
+    let mut markdown = String::from("See [the summary][summary] first.\n\n");
+    let log = "Everything went as planned. ".repeat(64);
+    for n in 1..=400 {
+        markdown.push_str(&format!("## Run {n}\n\n```\n{log}\n\n{log}\n```\n\n"));
+    }
+    markdown.push_str("[summary]: https://example.com/summary\n");
+    fs::write(&path, &markdown).unwrap();
+
+    // The cuts fall between blocks, never inside the code
+    let whole = crate::blocks::split(&markdown);
+    let cuts = crate::blocks::Cuts::new(256 * 1024).find(&markdown);
+    assert!(cuts.len() > 1);
+    for cut in cuts {
+        assert!(whole.blocks.iter().any(|block| block.span.start == cut));
+    }
+
+    let mut harness = harness();
+    harness.state_mut().window.chunked_above = 0;
+    open(&mut harness, path);
+    let tab = &harness.state().window.tabs[0];
+    assert_eq!(tab.content, markdown);
+    assert_eq!(tab.doc.blocks.len(), whole.blocks.len());
+    assert_eq!(tab.load_progress(), None);
+    // Split again as a whole, the link finds its definition at the end
+    assert!(
+        tab.doc.blocks[0]
+            .markdown
+            .contains("[summary]: https://example.com/summary")
+    );
+    assert!(harness.query_by_label("report.md").is_some());
+}
+
+#[test]
+fn chunks_split_on_their_own_join_up_as_the_whole() {
+    let markdown = "\
+Intro with a note[^a] and [a link][site].
+
+![chart](one.png)
+
+*The first chart*
+
+<!--
+
+Left out
+
+-->
+
+Then a second note[^b].
+
+![chart](two.png)
+
+*The second chart*
+
+[^a]: The first note.
+[^b]: The second note.
+
+[site]: https://example.com
+";
+    // Cut wherever it can be, the comment included
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for end in crate::blocks::Cuts::new(1)
+        .find(markdown)
+        .into_iter()
+        .chain([markdown.len()])
+    {
+        pieces.push((start, crate::blocks::parse(&markdown[start..end])));
+        start = end;
+    }
+    assert!(pieces.len() > 8);
+
+    let whole = crate::blocks::split(markdown);
+    let joined = crate::blocks::join(markdown, pieces);
+    let blocks = |doc: &crate::blocks::Document| -> Vec<(std::ops::Range<usize>, String)> {
+        doc.blocks
+            .iter()
+            .map(|block| (block.span.clone(), block.markdown.clone()))
+            .collect()
+    };
+    assert_eq!(blocks(&joined), blocks(&whole));
+    assert!(blocks(&joined).iter().any(|(_, markdown)| {
+        markdown.starts_with("<!--") && markdown.trim_end().ends_with("-->")
+    }));
+    assert!(joined.blocks[2].markdown.contains("**Figure 1:**"));
+    assert!(joined.blocks[6].markdown.contains("**Figure 2:**"));
+    let notes: Vec<(String, usize)> = joined
+        .footnotes
+        .iter()
+        .map(|note| (note.label.clone(), note.number))
+        .collect();
+    assert_eq!(notes, [("a".to_string(), 1), ("b".to_string(), 2)]);
+}
+
+#[test]
+fn idle_tabs_let_go_of_their_documents_until_shown_again() {
+    let path = scratch_copy("anchors.md");
+    let original = fs::read_to_string(&path).unwrap();
+    let mut harness = harness();
+    open(&mut harness, path.clone());
+    open(&mut harness, fixture("footnotes.md"));
+    let settings = &mut harness.state_mut().shared.settings;
+    settings.unload_idle_tabs = true;
+    settings.unload_tab_contents = true;
+    harness.run();
+    // Not idle for long enough yet
+    assert!(!harness.state().window.tabs[0].is_unloaded());
+
+    let tab = &mut harness.state_mut().window.tabs[0];
+    tab.last_viewed = Instant::now() - Duration::from_secs(20 * 60);
+    harness.run();
+    let tab = &harness.state().window.tabs[0];
+    assert!(tab.is_unloaded());
+    assert!(tab.doc.blocks.is_empty());
+    assert!(tab.content.is_empty());
+    // What exports and searches go by is read from the file
+    assert_eq!(tab.markdown().unwrap(), original);
+    assert!(!harness.state().window.tabs[1].is_unloaded());
+
+    // Not there to be read, it stays unloaded and can be tried again
+    let moved = path.with_extension("moved");
+    fs::rename(&path, &moved).unwrap();
+    harness.get_by_label("anchors.md").click();
+    harness.run();
+    let tab = &harness.state().window.tabs[0];
+    assert!(tab.is_unloaded());
+    assert!(tab.load_again_failed().is_some());
+    fs::rename(&moved, &path).unwrap();
+    harness.get_by_label("Try Again").click();
+    harness.run();
+    let tab = &harness.state().window.tabs[0];
+    assert!(!tab.is_unloaded());
+    assert_eq!(tab.content, original);
+    assert!(harness.query_all_by_value("Release Notes").next().is_some());
+}
+
+#[test]
+fn reading_progress_and_section_times_are_shown() {
+    let mut harness = harness();
+    open(&mut harness, fixture("footnotes.md"));
+    // Some 860 words
+    assert!(harness.query_by_label("4 min").is_some());
+    assert_eq!(harness.state().window.tabs[0].read_fraction, 0.0);
+
+    harness.key_press(egui::Key::End);
+    harness.run();
+    assert_eq!(harness.state().window.tabs[0].read_fraction, 1.0);
+
+    harness.state_mut().shared.settings.show_reading_progress = false;
+    harness.run();
+    assert!(harness.query_by_label("4 min").is_none());
+}
+
+#[test]
+fn recorded_navigation_goes_back_to_its_places() {
+    let mut harness = harness();
+    open(&mut harness, fixture("reference_links.md"));
+    harness.key_press(egui::Key::F8);
+    harness.run();
+    // As a click in the table of contents would
+    harness.state_mut().window.tabs[0].scroll_to_block = Some(2);
+    harness.run();
+    assert!(
+        harness
+            .query_by_label("● Recording navigation (1)")
+            .is_some()
+    );
+    harness.key_press(egui::Key::F8);
+    harness.run();
+
+    harness.key_press(egui::Key::F9);
+    harness.run();
+    assert!(!matches!(
+        harness.state().window.events.latest(),
+        Some(AppEvent::PlaceNotFound { .. })
+    ));
+
+    // Gone from the document, the place can't be revisited
+    let tab = &mut harness.state_mut().window.tabs[0];
+    tab.set_content("# References\n\nNothing later.\n".into());
+    harness.run();
+    harness.key_press(egui::Key::F9);
+    harness.run();
+    assert!(matches!(
+        harness.state().window.events.latest(),
+        Some(AppEvent::PlaceNotFound { .. })
+    ));
+}
+
+#[test]
+fn copied_text_keeps_its_formatting() {
+    let mut harness = harness();
+    open(&mut harness, fixture("reference_links.md"));
+    let from = harness
+        .get_by_label_contains("in the first block")
+        .rect()
+        .left_center();
+    let to = harness
+        .get_by_label_contains("far from its definition")
+        .rect()
+        .center();
+
+    // Drag across from one paragraph into another and copy
+    drag(&mut harness, from, to);
+    harness.input_mut().events.push(egui::Event::Copy);
+    harness.step();
+    let copied = copied_text(&harness).unwrap();
+
+    let tab = &harness.state().window.tabs[0];
+    let html = rich_copy::selection(&tab.content, &copied).unwrap().html;
+    assert!(html.starts_with(
+        "<p>in the first block, and a <a href=\"https://example.com/collapsed\">collapsed one</a> here.</p>"
+    ));
+    assert!(html.contains("<h2 id=\"later-section\">Later section</h2>"));
+    assert!(html.ends_with("<p>The same <a href=\"https://example.com/docs\" title=\"Documentation\">reference link</a> again, far from</p>\n"));
+}
+
+#[test]
+fn the_whole_document_can_be_selected_and_copied() {
+    let mut harness = harness();
+    open(&mut harness, fixture("code_blocks.md"));
+    press_shortcut(&mut harness, egui::Key::A);
+    harness.step();
+    assert!(harness.state().window.tabs[0].selected_all);
+
+    harness.input_mut().events.push(egui::Event::Copy);
+    harness.step();
+    let copied = copied_text(&harness).unwrap();
+    assert!(copied.starts_with("Code\n\nfn main() {\n"));
+    assert!(copied.contains("\n\nlet labeled = true;\n\n"));
+    assert!(copied.ends_with("still shown as code"));
+
+    // Code blocks are their own text fields, but a selection across one
+    // copies it too
+    let markdown = "Before the code.\n\n```\nlet x = 1;\n```\n\n- After the code\n";
+    let selected = rich_copy::selection(markdown, "code.\nAfter the").unwrap();
+    assert_eq!(selected.text, "code.\n\nlet x = 1;\n\n- After the");
+}
+
+#[test]
+fn bookmarks_and_highlights_are_kept_next_to_the_document() {
+    let path = scratch_copy("reference_links.md");
+    // Long enough to scroll down to the later section
+    let mut text = fs::read_to_string(&path).unwrap();
+    text.push_str(&"\nMore to read.\n".repeat(40));
+    fs::write(&path, text).unwrap();
+    let state_file = path.parent().unwrap().join(".mdviewer/state.toml");
+    let mut harness = harness();
+    open(&mut harness, path.clone());
+
+    // Reading the document alone leaves nothing behind
+    harness.state_mut().window.save_reading();
+    assert!(!state_file.exists());
+
+    let tab = &mut harness.state_mut().window.tabs[0];
+    tab.scroll_to_block = tab.heading_block("Later section");
+    harness.run();
+    press_shortcut(&mut harness, egui::Key::D);
+    harness.step();
+    let saved = fs::read_to_string(&state_file).unwrap();
+    assert!(saved.contains("[documents.\"reference_links.md\"]"));
+    assert!(saved.contains("bookmarks = [\"Later section\"]"));
+
+    let from = harness
+        .get_by_label_contains("again, far from")
+        .rect()
+        .left_center();
+    let to = harness
+        .get_by_label_contains("again, far from")
+        .rect()
+        .center();
+    drag(&mut harness, from, to);
+    press_shortcut(&mut harness, egui::Key::H);
+    harness.step();
+    // Highlighting doesn't touch the clipboard
+    assert_eq!(copied_text(&harness), None);
+    harness.step();
+    let highlights = &harness.state().window.tabs[0].reading.highlights;
+    assert_eq!(highlights.len(), 1);
+    assert!("again, far from its definition.".starts_with(&highlights[0].text));
+    assert!(
+        fs::read_to_string(&state_file)
+            .unwrap()
+            .contains("[[documents.\"reference_links.md\".highlights]]")
+    );
+
+    // Opened again, the document is back where reading stopped
+    harness.state_mut().window.save_reading();
+    let mut harness = self::harness();
+    open(&mut harness, path);
+    let tab = &harness.state().window.tabs[0];
+    assert_eq!(tab.reading.bookmarks, ["Later section"]);
+    assert_eq!(tab.reading.highlights.len(), 1);
+    assert_eq!(tab.current_section(), Some("Later section"));
+}
+
+#[test]
+fn find_counts_and_steps_through_matches() {
+    let mut harness = harness();
+    open(&mut harness, fixture("reference_links.md"));
+
+    harness.key_press_modifiers(egui::Modifiers::COMMAND, egui::Key::F);
+    harness.run();
+    harness
+        .get_by_role(egui::accesskit::Role::TextInput)
+        .type_text("reference link");
+    harness.run();
+    assert!(harness.query_by_label("1 / 2").is_some());
+
+    harness.get_by_label("▼").click();
+    harness.run();
+    assert!(harness.query_by_label("2 / 2").is_some());
+
+    let query = harness.get_by_role(egui::accesskit::Role::TextInput);
+    query.focus();
+    query.type_text("zzz");
+    harness.run();
+    assert!(harness.query_by_label("No matches").is_some());
+}
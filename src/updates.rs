@@ -0,0 +1,246 @@
+//! Asking GitHub whether a newer release is out, and what changed in it.
+//! Nothing is sent besides the request itself, and only when asked to or
+//! when checking at startup is turned on in Preferences.
+
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use anyhow::{Context, Result, bail};
+use eframe::egui;
+use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
+use serde::Deserialize;
+
+use crate::events::AppEvent;
+use crate::i18n::{tr, tr_args};
+
+const RELEASES_URL: &str = "https://api.github.com/repos/NRohner/md_viewer/releases";
+
+/// The version running.
+pub const CURRENT: &str = env!("CARGO_PKG_VERSION");
+
+/// A release as the GitHub API lists it.
+#[derive(Deserialize)]
+struct ApiRelease {
+    tag_name: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    body: Option<String>,
+    html_url: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+}
+
+/// A release newer than the one running.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Release {
+    pub version: String,
+    /// Its name, if it was given one.
+    pub name: Option<String>,
+    /// Its changelog, in Markdown.
+    pub notes: String,
+    /// The release page, with the downloads.
+    pub url: String,
+}
+
+impl Release {
+    /// Its name, or its version number for one without.
+    pub fn title(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => tr_args("Version {version}", &[("version", &self.version)]),
+        }
+    }
+}
+
+enum Status {
+    Unchecked,
+    Checking(Receiver<Result<Vec<Release>>>),
+    Checked(Vec<Release>),
+    Failed(String),
+}
+
+pub struct Updates {
+    pub open: bool,
+    status: Status,
+    cache: CommonMarkCache,
+}
+
+impl Default for Updates {
+    fn default() -> Self {
+        Self {
+            open: false,
+            status: Status::Unchecked,
+            cache: CommonMarkCache::default(),
+        }
+    }
+}
+
+impl Updates {
+    pub fn is_unchecked(&self) -> bool {
+        matches!(self.status, Status::Unchecked)
+    }
+
+    /// The newest release out, once a check found one.
+    pub fn available(&self) -> Option<&Release> {
+        match &self.status {
+            Status::Checked(releases) => releases.first(),
+            _ => None,
+        }
+    }
+
+    /// Starts asking for the releases in the background.
+    pub fn check(&mut self, ctx: &egui::Context) {
+        if matches!(self.status, Status::Checking(_)) {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        let mut request = ehttp::Request::get(RELEASES_URL);
+        // GitHub turns away requests without one
+        request
+            .headers
+            .insert("User-Agent", format!("md_viewer/{CURRENT}"));
+        request
+            .headers
+            .insert("Accept", "application/vnd.github+json");
+        ehttp::fetch(request, move |response| {
+            let releases = match response {
+                Ok(response) if response.ok => newer(response.text().unwrap_or_default(), CURRENT),
+                Ok(response) => Err(anyhow::anyhow!(
+                    "GitHub answered {} {}",
+                    response.status,
+                    response.status_text
+                )),
+                Err(error) => Err(anyhow::anyhow!(error)),
+            };
+            let _ = sender.send(releases);
+            ctx.request_repaint();
+        });
+        self.status = Status::Checking(receiver);
+    }
+
+    /// Picks up the outcome of a check, once there is one.
+    pub fn poll(&mut self) -> Option<AppEvent> {
+        let Status::Checking(receiver) = &self.status else {
+            return None;
+        };
+        let outcome = match receiver.try_recv() {
+            Ok(outcome) => outcome,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => Err(anyhow::anyhow!("the check stopped")),
+        };
+        match outcome {
+            Ok(releases) => {
+                let event = releases.first().map(|newest| AppEvent::UpdateAvailable {
+                    version: newest.version.clone(),
+                });
+                self.status = Status::Checked(releases);
+                event
+            }
+            Err(e) => {
+                let error = format!("{e:#}");
+                self.status = Status::Failed(error.clone());
+                Some(AppEvent::UpdateCheckFailed { error })
+            }
+        }
+    }
+
+    /// The changelogs of the newer releases, with links to them.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut retry = false;
+        egui::Window::new(tr("Updates"))
+            .id(egui::Id::new("updates"))
+            .open(&mut self.open)
+            .default_width(460.0)
+            .show(ctx, |ui| match &self.status {
+                Status::Unchecked | Status::Checking(_) => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.weak(tr("Checking for updates…"));
+                    });
+                }
+                Status::Failed(error) => {
+                    ui.label(tr_args(
+                        "Couldn't check for updates: {error}",
+                        &[("error", error)],
+                    ));
+                    if ui.button(tr("Try Again")).clicked() {
+                        retry = true;
+                    }
+                }
+                Status::Checked(releases) if releases.is_empty() => {
+                    ui.label(tr_args(
+                        "Markdown Viewer {version} is the latest version.",
+                        &[("version", &CURRENT)],
+                    ));
+                }
+                Status::Checked(releases) => {
+                    ui.label(tr_args(
+                        "You have version {version}. Changes since then:",
+                        &[("version", &CURRENT)],
+                    ));
+                    ui.separator();
+                    egui::ScrollArea::vertical()
+                        .max_height(360.0)
+                        .show(ui, |ui| {
+                            for release in releases {
+                                ui.horizontal(|ui| {
+                                    ui.heading(release.title());
+                                    ui.hyperlink_to(tr("Download"), &release.url);
+                                });
+                                CommonMarkViewer::new().show(ui, &mut self.cache, &release.notes);
+                                ui.add_space(8.0);
+                            }
+                        });
+                }
+            });
+        if retry {
+            self.check(ctx);
+        }
+    }
+}
+
+/// The published releases in the GitHub API's `json` newer than `current`,
+/// newest first.
+pub fn newer(json: &str, current: &str) -> Result<Vec<Release>> {
+    let Some(running) = version(current) else {
+        bail!("{current} isn't a version number");
+    };
+    let listed: Vec<ApiRelease> =
+        serde_json::from_str(json).context("reading the list of releases")?;
+    let mut releases: Vec<(Vec<u64>, Release)> = listed
+        .into_iter()
+        .filter(|release| !release.draft && !release.prerelease)
+        .filter_map(|release| {
+            let number = version(&release.tag_name)?;
+            let tag = release.tag_name.trim_start_matches(['v', 'V']).to_string();
+            let name = release.name.filter(|name| !name.trim().is_empty());
+            Some((
+                number,
+                Release {
+                    version: tag,
+                    name,
+                    notes: release.body.unwrap_or_default(),
+                    url: release.html_url,
+                },
+            ))
+        })
+        .filter(|(number, _)| *number > running)
+        .collect();
+    releases.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(releases.into_iter().map(|(_, release)| release).collect())
+}
+
+/// The numbers of a version like `v1.2.0`, padded to three.
+fn version(tag: &str) -> Option<Vec<u64>> {
+    let mut numbers = tag
+        .trim()
+        .trim_start_matches(['v', 'V'])
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    numbers.resize(numbers.len().max(3), 0);
+    Some(numbers)
+}
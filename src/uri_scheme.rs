@@ -0,0 +1,153 @@
+//! `mdviewer://open?path=…&anchor=…` links, for other apps to point at a
+//! document and a section of it in the running viewer, and registering the
+//! viewer as what opens them.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+#[cfg(any(all(unix, not(target_os = "macos")), windows))]
+use anyhow::{Context, ensure};
+
+use crate::percent;
+
+pub const SCHEME: &str = "mdviewer";
+
+/// Whether the command line argument `arg` is one of these links.
+pub fn is_link(arg: &str) -> bool {
+    arg.strip_prefix(SCHEME)
+        .is_some_and(|rest| rest.starts_with(':'))
+}
+
+/// The document `uri` opens, with the `#anchor` to go to in it as the
+/// command line takes it, or `None` if it isn't an open link.
+pub fn parse(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix(SCHEME)?.strip_prefix("://open")?;
+    let query = rest.strip_prefix('/').unwrap_or(rest).strip_prefix('?')?;
+    let mut path = None;
+    let mut anchor = None;
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match key {
+            "path" => path = Some(decode(value)),
+            "anchor" => anchor = Some(decode(value)),
+            _ => {}
+        }
+    }
+    let path = path.filter(|path| !path.is_empty())?;
+    Some(
+        match anchor
+            .as_deref()
+            .map(|anchor| anchor.trim_start_matches('#'))
+        {
+            Some(anchor) if !anchor.is_empty() => PathBuf::from(format!("{path}#{anchor}")),
+            _ => PathBuf::from(path),
+        },
+    )
+}
+
+/// A query value without its `%xx` escapes, and `+` as a space.
+fn decode(value: &str) -> String {
+    percent::decode(&value.replace('+', " "))
+}
+
+/// Makes this program what the desktop opens `mdviewer://` links with.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn register() -> Result<()> {
+    use std::{fs, process::Command};
+
+    const DESKTOP_FILE: &str = "md_viewer-links.desktop";
+    let exe = std::env::current_exe().context("finding the program")?;
+    let dir = dirs::data_dir()
+        .context("no data directory for applications")?
+        .join("applications");
+    fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    let entry = format!(
+        "[Desktop Entry]\nType=Application\nName=Markdown Viewer\nExec=\"{}\" %u\nNoDisplay=true\nMimeType=x-scheme-handler/{SCHEME};\n",
+        exe.display()
+    );
+    let path = dir.join(DESKTOP_FILE);
+    fs::write(&path, entry).with_context(|| format!("writing {}", path.display()))?;
+    let status = Command::new("xdg-mime")
+        .args(["default", DESKTOP_FILE])
+        .arg(format!("x-scheme-handler/{SCHEME}"))
+        .status()
+        .context("running xdg-mime")?;
+    ensure!(status.success(), "xdg-mime failed: {status}");
+    Ok(())
+}
+
+/// Makes this program what Windows opens `mdviewer://` links with, for the
+/// current user.
+#[cfg(windows)]
+pub fn register() -> Result<()> {
+    use std::process::Command;
+
+    let exe = std::env::current_exe().context("finding the program")?;
+    let key = format!(r"HKCU\Software\Classes\{SCHEME}");
+    let command = format!("\"{}\" \"%1\"", exe.display());
+    let entries = [
+        (key.clone(), None, "URL:Markdown Viewer".to_string()),
+        (key.clone(), Some("URL Protocol"), String::new()),
+        (format!(r"{key}\shell\open\command"), None, command),
+    ];
+    for (key, value, data) in entries {
+        let mut reg = Command::new("reg");
+        reg.args(["add", &key, "/f", "/d", &data]);
+        match value {
+            Some(value) => reg.args(["/v", value]),
+            None => reg.arg("/ve"),
+        };
+        let status = reg.status().context("running reg")?;
+        ensure!(status.success(), "reg failed: {status}");
+    }
+    Ok(())
+}
+
+/// macOS only takes schemes from the app bundle's `Info.plist`.
+#[cfg(not(any(all(unix, not(target_os = "macos")), windows)))]
+pub fn register() -> Result<()> {
+    anyhow::bail!("links are registered by the app bundle on this system")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_links_give_the_path_with_the_anchor() {
+        assert_eq!(
+            parse("mdviewer://open?path=/home/me/notes.md&anchor=set-up"),
+            Some(PathBuf::from("/home/me/notes.md#set-up"))
+        );
+        assert_eq!(
+            parse("mdviewer://open/?anchor=%23intro&path=notes.md"),
+            Some(PathBuf::from("notes.md#intro"))
+        );
+        assert_eq!(
+            parse("mdviewer://open?path=notes.md&anchor="),
+            Some(PathBuf::from("notes.md"))
+        );
+    }
+
+    #[test]
+    fn other_links_give_nothing() {
+        assert_eq!(parse("mdviewer://open?anchor=intro"), None);
+        assert_eq!(parse("mdviewer://open?path="), None);
+        assert_eq!(parse("mdviewer://close?path=notes.md"), None);
+        assert_eq!(parse("https://open?path=notes.md"), None);
+        assert!(is_link("mdviewer://open?path=notes.md"));
+        assert!(!is_link("mdviewer.md"));
+    }
+
+    #[test]
+    fn escapes_are_undone() {
+        assert_eq!(decode("My%20Notes+2.md"), "My Notes 2.md");
+        assert_eq!(decode("caf%C3%A9"), "café");
+        assert_eq!(decode("%2Fetc%2fhosts"), "/etc/hosts");
+        // Not escapes, so kept as written
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz%4"), "%zz%4");
+        assert_eq!(decode("%+1"), "% 1");
+        assert_eq!(decode("%FF"), "\u{FFFD}");
+    }
+}
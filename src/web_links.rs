@@ -0,0 +1,172 @@
+//! Links leaving the viewer, for the browser or whatever app their scheme
+//! goes to: opened right away, after asking with the whole URL in view, or
+//! not at all, as set in Preferences. Web sites trusted once are opened
+//! without asking again.
+
+use eframe::egui;
+
+use crate::config::WebLinks;
+use crate::i18n::{tr, tr_args};
+use crate::link_preview;
+
+/// What to do with a link clicked.
+#[derive(Debug, PartialEq)]
+pub enum Policy {
+    Open,
+    Ask,
+    Block,
+}
+
+/// Whether `url` may be opened under `setting` with the sites in
+/// `trusted`. Links into documents stay in the viewer; any other scheme
+/// is asked about like the web, as only `http(s)` sites can be trusted.
+pub fn policy(setting: WebLinks, trusted: &[String], url: &str) -> Policy {
+    if link_preview::is_internal(url) {
+        return Policy::Open;
+    }
+    match setting {
+        WebLinks::Open => Policy::Open,
+        WebLinks::Never => Policy::Block,
+        WebLinks::Ask if host(url).is_some_and(|host| covers(trusted, host)) => Policy::Open,
+        WebLinks::Ask => Policy::Ask,
+    }
+}
+
+/// The host of an `http(s)` URL as written, without a port or login.
+pub fn host(url: &str) -> Option<&str> {
+    let (scheme, rest) = url.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = match host.strip_prefix('[') {
+        // IPv6, with colons of its own
+        Some(v6) => v6.split(']').next()?,
+        None => host.split(':').next()?,
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+/// Whether `host` is one of `sites` or under one of them, so trusting
+/// `example.com` covers `docs.example.com`.
+pub fn covers(sites: &[String], host: &str) -> bool {
+    let host = host.trim_end_matches('.');
+    sites.iter().any(|site| {
+        let site = site.trim().trim_end_matches('.');
+        !site.is_empty()
+            && (host.eq_ignore_ascii_case(site)
+                || host.len() > site.len()
+                    && host.as_bytes()[host.len() - site.len() - 1] == b'.'
+                    && host[host.len() - site.len()..].eq_ignore_ascii_case(site))
+    })
+}
+
+pub enum Answer {
+    Open(egui::OpenUrl),
+    /// Open it, and links to its site from now on.
+    Trust(egui::OpenUrl, String),
+}
+
+/// The question asked before a link leaving the viewer is opened.
+#[derive(Default)]
+pub struct Confirm {
+    pending: Option<egui::OpenUrl>,
+}
+
+impl Confirm {
+    pub fn ask(&mut self, open: egui::OpenUrl) {
+        self.pending = Some(open);
+    }
+
+    /// Asks about the pending link, if any. Returns the answer once given,
+    /// nothing while waiting or if the link was not to be opened.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<Answer> {
+        let open = self.pending.as_ref()?;
+        let mut answer = None;
+        let mut cancel = false;
+        let modal = egui::Modal::new(egui::Id::new("confirm_web_link")).show(ctx, |ui| {
+            ui.set_max_width(480.0);
+            ui.heading(if host(&open.url).is_some() {
+                tr("Open this link in your browser?")
+            } else {
+                tr("Open this link in another app?")
+            });
+            ui.add_space(6.0);
+            ui.add(egui::Label::new(egui::RichText::new(&open.url).monospace()).wrap());
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if ui.button(tr("Open")).clicked() {
+                    answer = Some(Answer::Open(open.clone()));
+                }
+                if let Some(host) = host(&open.url)
+                    && ui
+                        .button(tr_args("Always Open {site}", &[("site", &host)]))
+                        .clicked()
+                {
+                    answer = Some(Answer::Trust(open.clone(), host.to_lowercase()));
+                }
+                if ui.button(tr("Cancel")).clicked() {
+                    cancel = true;
+                }
+            });
+        });
+        if answer.is_some() || cancel || modal.should_close() {
+            self.pending = None;
+        }
+        answer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sites(sites: &[&str]) -> Vec<String> {
+        sites.iter().map(|site| site.to_string()).collect()
+    }
+
+    #[test]
+    fn hosts_leave_out_the_login_port_and_path() {
+        assert_eq!(host("https://example.com/docs"), Some("example.com"));
+        assert_eq!(host("HTTP://Example.com:8080?q=1"), Some("Example.com"));
+        assert_eq!(host("https://me:p@ss@example.com#top"), Some("example.com"));
+        assert_eq!(host("http://[::1]:8080/"), Some("::1"));
+        assert_eq!(host("https://[2001:db8::2]/notes"), Some("2001:db8::2"));
+    }
+
+    #[test]
+    fn only_web_urls_have_hosts() {
+        assert_eq!(host("ftp://example.com/file"), None);
+        assert_eq!(host("mailto:me@example.com"), None);
+        assert_eq!(host("https:///path"), None);
+        assert_eq!(host("example.com"), None);
+    }
+
+    #[test]
+    fn sites_cover_their_subdomains_and_no_others() {
+        let trusted = sites(&["example.com", " Docs.Rs. "]);
+        assert!(covers(&trusted, "example.com"));
+        assert!(covers(&trusted, "docs.example.com"));
+        assert!(covers(&trusted, "EXAMPLE.COM."));
+        assert!(covers(&trusted, "docs.rs"));
+        assert!(!covers(&trusted, "evilexample.com"));
+        assert!(!covers(&trusted, "example.com.evil.net"));
+        assert!(!covers(&trusted, "com"));
+        assert!(!covers(&sites(&["", " "]), "example.com"));
+        assert!(covers(&sites(&["::1"]), "::1"));
+    }
+
+    #[test]
+    fn asking_opens_trusted_sites_right_away() {
+        let trusted = sites(&["example.com"]);
+        let ask = |url| policy(WebLinks::Ask, &trusted, url);
+        assert_eq!(ask("https://docs.example.com/"), Policy::Open);
+        assert_eq!(ask("https://evilexample.com/"), Policy::Ask);
+        assert_eq!(ask("smb://example.com/share"), Policy::Ask);
+        assert_eq!(
+            policy(WebLinks::Never, &trusted, "https://example.com/"),
+            Policy::Block
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Obsidian-style `[[Other Note]]` links between the notes of a folder,
+//! rewritten into regular links the renderer can draw.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag};
+
+use crate::blocks;
+use crate::formats::Format;
+use crate::workspace::Workspace;
+
+/// URL scheme of rewritten wikilinks; clicks on them are handled by the
+/// viewer instead of the browser.
+pub const SCHEME: &str = "wiki:";
+
+/// `markdown` with its wikilinks turned into `wiki:` links, emphasized when
+/// `resolves` says their note doesn't exist. `None` if it has none.
+pub fn rewrite(markdown: &str, resolves: impl Fn(&str) -> bool) -> Option<String> {
+    let parser = Parser::new_ext(
+        markdown,
+        blocks::parser_options() | Options::ENABLE_WIKILINKS,
+    );
+    let mut out = String::new();
+    let mut copied = 0;
+    for (event, span) in parser.into_offset_iter() {
+        let Event::Start(Tag::Link {
+            link_type: LinkType::WikiLink { .. },
+            dest_url,
+            ..
+        }) = event
+        else {
+            continue;
+        };
+        // Angle-bracket destinations can hold spaces but not these
+        if dest_url.contains(['<', '>', '\n']) {
+            continue;
+        }
+
+        // The event's span stops short of the closing brackets
+        let Some(len) = markdown[span.start..].find("]]") else {
+            continue;
+        };
+        let end = span.start + len + 2;
+        let inner = &markdown[span.start + 2..end - 2];
+        let label = inner.rsplit_once('|').map_or(inner, |(_, label)| label);
+        let label = label.replace('[', "\\[").replace(']', "\\]");
+        out.push_str(&markdown[copied..span.start]);
+        if resolves(note(&dest_url)) {
+            out.push_str(&format!("[{label}](<{SCHEME}{dest_url}>)"));
+        } else {
+            out.push_str(&format!("*[{label}](<{SCHEME}{dest_url}>)*"));
+        }
+        copied = end;
+    }
+    if copied == 0 {
+        return None;
+    }
+    out.push_str(&markdown[copied..]);
+    Some(out)
+}
+
+/// The note a link target names, without its `#heading`.
+pub fn note(target: &str) -> &str {
+    target
+        .split_once('#')
+        .map_or(target, |(note, _)| note)
+        .trim()
+}
+
+/// The `#heading` part of a link target.
+pub fn heading(target: &str) -> Option<&str> {
+    target.split_once('#').map(|(_, heading)| heading.trim())
+}
+
+/// The document `note` refers to: a file next to the linking document or
+/// at the workspace root, else any document of the workspace by name or alias.
+pub fn resolve(
+    note: &str,
+    doc_dir: Option<&Path>,
+    workspace: Option<&Workspace>,
+) -> Option<PathBuf> {
+    if note.is_empty() {
+        return None;
+    }
+    let file = if Format::from_path(Path::new(note)).is_some() {
+        note.to_string()
+    } else {
+        format!("{note}.md")
+    };
+    doc_dir
+        .into_iter()
+        .chain(workspace.map(|ws| ws.root.as_path()))
+        .find_map(|dir| find_file(&dir.join(&file)))
+        .or_else(|| workspace?.resolve(note).map(Path::to_path_buf))
+}
+
+/// `path`, or a file in the same folder whose name differs only in case.
+fn find_file(path: &Path) -> Option<PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    let name = path.file_name()?.to_str()?;
+    fs::read_dir(path.parent()?)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|candidate| {
+            candidate.is_file()
+                && candidate
+                    .file_name()
+                    .and_then(|found| found.to_str())
+                    .is_some_and(|found| found.eq_ignore_ascii_case(name))
+        })
+}
@@ -0,0 +1,2608 @@
+//! A native window with its own set of tabs.
+
+use eframe::egui;
+use egui_commonmark::CommonMarkCache;
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use egui::Button;
+use rfd::FileDialog;
+
+use crate::Shared;
+use crate::backlinks::Backlinks;
+use crate::blocks;
+use crate::closed_tabs::{self, ClosedTabs};
+use crate::code_view::{self, Layout};
+use crate::compare::{CompareAction, Comparison};
+use crate::config::{self, ColumnGuide, Dialect, LinkAction, Paper, Settings, Theme};
+use crate::dashboard::Dashboard;
+use crate::diagram;
+use crate::events::{AppEvent, EventLog};
+use crate::export::{self, Bundle};
+use crate::formats::{self, Format};
+use crate::git::{self, FileState, GitWatch};
+use crate::global_search::{self, GlobalSearch, Picked, Place};
+use crate::hints::Hints;
+use crate::i18n::{self, tr, tr_args};
+use crate::image_cache;
+use crate::lightbox::Lightbox;
+use crate::link_handlers;
+use crate::link_preview::{self, LinkPreview};
+use crate::nav_macro::{self, NavMacro, Step};
+use crate::palette::{self, Command, Palette};
+use crate::pandoc::Pandoc;
+use crate::percent;
+use crate::preferences;
+use crate::present::{self, Presentation};
+use crate::quick_open::QuickOpen;
+use crate::reading_state::Highlight;
+use crate::remote_docs::{self, Outcome, RemoteDocs};
+use crate::render;
+use crate::rich_copy;
+use crate::scroll_keys::{self, ScrollKeys};
+use crate::search::{Search, SearchAction};
+use crate::snippet::{Captured, Destination, SnippetCapture};
+use crate::tab::{DocTab, next_id};
+use crate::tab_order::TabOrder;
+use crate::tags::TagPanel;
+use crate::toc;
+use crate::trust;
+use crate::updates::Updates;
+use crate::web_links::{self, Answer, Confirm, Policy};
+use crate::wikilink;
+use crate::workspace::{TreeAction, Workspace};
+
+/// How often to check on files loading in the background.
+const LOADING_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const FIND_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::F);
+
+const NEW_SCRATCH_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::N);
+
+const QUICK_OPEN_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::P);
+
+const SELECT_ALL_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::A);
+
+const BOOKMARK_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::D);
+
+const HIGHLIGHT_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::H);
+
+pub struct DocWindow {
+    pub viewport_id: egui::ViewportId,
+    pub tabs: Vec<DocTab>,
+    pub active: usize,
+    pub events: EventLog,
+    search: Search,
+    global_search: GlobalSearch,
+    quick_open: QuickOpen,
+    palette: Palette,
+    scroll_keys: ScrollKeys,
+    tab_order: TabOrder,
+    closed_tabs: ClosedTabs,
+    presentation: Option<Presentation>,
+    /// Compare mode, shown instead of the active document.
+    compare: Option<Comparison>,
+    snippet: SnippetCapture,
+    lightbox: Lightbox,
+    /// Picked or dropped files in no known format, waiting on "Open as…".
+    unrecognized: Vec<PathBuf>,
+    /// Always-on-top state last sent to the native window.
+    applied_always_on_top: Option<bool>,
+    /// Title last sent to the native window.
+    applied_title: Option<String>,
+    preferences_open: bool,
+    /// Overview of the opened folder, once asked for.
+    dashboard: Option<Dashboard>,
+    link_preview: LinkPreview,
+    backlinks: Backlinks,
+    tags: TagPanel,
+    git: GitWatch,
+    updates: Updates,
+    pandoc: Pandoc,
+    remote_docs: RemoteDocs,
+    /// A web link waiting to be confirmed.
+    web_link: Confirm,
+    trust: trust::Confirm,
+    /// A link waiting for its command to be confirmed.
+    link_command: link_handlers::Confirm,
+    nav_macro: NavMacro,
+    /// Holds what was last copied as rich text.
+    clipboard: Option<arboard::Clipboard>,
+    /// Files longer than this many bytes load in chunks, as set in Preferences.
+    pub chunked_above: usize,
+    cache_stats_open: bool,
+}
+
+/// Requests from a window that affect the rest of the app.
+pub enum WindowAction {
+    /// Move this tab into a brand-new window.
+    Detach(DocTab),
+    /// Move this tab back into the main window.
+    MoveToMain(DocTab),
+}
+
+impl DocWindow {
+    pub fn main() -> Self {
+        Self::with_viewport(egui::ViewportId::ROOT)
+    }
+
+    /// An additional window, initially showing `tab`.
+    pub fn detached(tab: DocTab) -> Self {
+        let mut window = Self::with_viewport(egui::ViewportId::from_hash_of(("window", next_id())));
+        window.add_tab(tab);
+        window
+    }
+
+    fn with_viewport(viewport_id: egui::ViewportId) -> Self {
+        Self {
+            viewport_id,
+            tabs: Vec::new(),
+            active: 0,
+            events: EventLog::default(),
+            search: Search::default(),
+            global_search: GlobalSearch::default(),
+            quick_open: QuickOpen::default(),
+            palette: Palette::default(),
+            scroll_keys: ScrollKeys::default(),
+            tab_order: TabOrder::default(),
+            closed_tabs: ClosedTabs::default(),
+            presentation: None,
+            compare: None,
+            snippet: SnippetCapture::default(),
+            lightbox: Lightbox::default(),
+            unrecognized: Vec::new(),
+            applied_always_on_top: None,
+            applied_title: None,
+            preferences_open: false,
+            dashboard: None,
+            link_preview: LinkPreview::default(),
+            backlinks: Backlinks::default(),
+            tags: TagPanel::default(),
+            git: GitWatch::default(),
+            updates: Updates::default(),
+            pandoc: Pandoc::default(),
+            remote_docs: RemoteDocs::default(),
+            web_link: Confirm::default(),
+            trust: trust::Confirm::default(),
+            link_command: link_handlers::Confirm::default(),
+            nav_macro: NavMacro::default(),
+            clipboard: None,
+            chunked_above: Settings::default().chunked_above(),
+            cache_stats_open: false,
+        }
+    }
+
+    pub fn is_main(&self) -> bool {
+        self.viewport_id == egui::ViewportId::ROOT
+    }
+
+    /// Native window title, naming the active document by its whole path
+    /// with `full_path`.
+    pub fn title(&self, full_path: bool) -> String {
+        match self.tabs.get(self.active) {
+            Some(tab) if full_path && !tab.is_scratch() => tr_args(
+                "{document} — Markdown Viewer",
+                &[("document", &tab.path.display())],
+            ),
+            Some(tab) => tr_args("{document} — Markdown Viewer", &[("document", &tab.title)]),
+            None => "Markdown Viewer".into(),
+        }
+    }
+
+    pub fn add_tab(&mut self, tab: DocTab) {
+        self.tabs.push(tab);
+        self.active = self.tabs.len().saturating_sub(1);
+    }
+
+    fn open_files(&mut self) {
+        let mut dialog = FileDialog::new()
+            .add_filter("All documents", &formats::all_extensions())
+            .set_title(tr("Open document(s)"));
+        for (format, extensions) in Format::ALL {
+            dialog = dialog.add_filter(format.name(), extensions);
+        }
+        if let Some(files) = dialog.add_filter(tr("All files"), &["*"]).pick_files() {
+            for path in files {
+                self.open_any(path);
+            }
+        }
+    }
+
+    /// Opens documents in a known format right away; anything else waits for
+    /// the user to pick how to show it.
+    pub fn open_any(&mut self, path: PathBuf) {
+        if Format::from_path(&path).is_some() {
+            self.open_path(path);
+        } else {
+            self.unrecognized.push(path);
+        }
+    }
+
+    /// Like [`Self::open_any`], then goes to the heading whose `#`
+    /// fragment is `heading`, once the document has loaded.
+    pub fn open_at(&mut self, path: PathBuf, heading: Option<String>) {
+        self.open_any(path.clone());
+        let Some(heading) = heading else {
+            return;
+        };
+        let Some(tab) = self
+            .tabs
+            .get_mut(self.active)
+            .filter(|tab| tab.path == path)
+        else {
+            return;
+        };
+        if tab.is_loading() {
+            tab.heading_on_load = Some(heading);
+        } else if !tab.go_to_fragment(&heading) {
+            self.events.push(AppEvent::PlaceNotFound {
+                place: format!("#{heading}"),
+            });
+        }
+    }
+
+    /// Shows the document fetched from `url`, in the tab already showing it
+    /// if there is one.
+    fn fetched(&mut self, url: String, outcome: Outcome) {
+        let path = match outcome {
+            Outcome::Fetched(path) => path,
+            Outcome::Offline { path, error } => {
+                self.events.push(AppEvent::FetchedOffline { url, error });
+                path
+            }
+            Outcome::Failed { error } => {
+                self.events.push(AppEvent::FetchFailed { url, error });
+                return;
+            }
+        };
+        match self.tabs.iter().position(|tab| tab.path == path) {
+            Some(idx) if !self.tabs[idx].is_loading() => self.reload_from_disk(idx),
+            _ => self.open_path(path),
+        }
+    }
+
+    /// Switches to the tab showing `path`, opening it first if needed.
+    fn open_path(&mut self, path: PathBuf) {
+        self.open_as(path, false);
+    }
+
+    /// Like [`Self::open_path`], optionally showing the file as plain text.
+    fn open_as(&mut self, path: PathBuf, plain_text: bool) {
+        if let Some(idx) = self.tabs.iter().position(|tab| tab.path == path) {
+            self.active = idx;
+            return;
+        }
+        let mut tab = DocTab::open(path, self.chunked_above);
+        tab.plain_text = plain_text;
+        self.add_tab(tab);
+    }
+
+    /// Opens the note a wikilink in the active tab points to, and goes to
+    /// its heading if the note is already loaded.
+    fn follow_wikilink(&mut self, target: &str, workspace: Option<&Workspace>) {
+        let note = wikilink::note(target);
+        if !note.is_empty() {
+            let dir = self.tabs.get(self.active).and_then(DocTab::dir);
+            let Some(path) = wikilink::resolve(note, dir, workspace) else {
+                self.events.push(AppEvent::LinkUnresolved {
+                    note: note.to_string(),
+                });
+                return;
+            };
+            self.open_path(path);
+        }
+
+        if let Some(heading) = wikilink::heading(target)
+            && let Some(tab) = self.tabs.get_mut(self.active)
+        {
+            tab.scroll_to_block = tab.doc.find_anchor(&blocks::slug(heading), 0);
+        }
+    }
+
+    /// Switches to the document of a search hit and brings the hit into view.
+    fn reveal(&mut self, Picked { place, offset }: Picked) {
+        match place {
+            Place::Tab(id) => match self.tabs.iter().position(|tab| tab.id == id) {
+                Some(idx) => self.active = idx,
+                None => return,
+            },
+            Place::File(path) => self.open_path(path),
+        }
+        if let Some(tab) = self.tabs.get_mut(self.active) {
+            if tab.is_loading() {
+                tab.reveal_on_load = Some(offset);
+            } else {
+                tab.scroll_to_block = tab.block_at(offset);
+            }
+        }
+    }
+
+    /// Goes to the next place of the recorded navigation, ending the
+    /// recording if one is going on.
+    fn replay_place(&mut self) {
+        if self.nav_macro.is_recording() {
+            self.nav_macro.toggle_recording();
+        }
+        let (Some(step), Some(tab)) = (self.nav_macro.next_step(), self.tabs.get_mut(self.active))
+        else {
+            return;
+        };
+        if !nav_macro::replay(&step, tab) {
+            self.events.push(AppEvent::PlaceNotFound {
+                place: step.to_string(),
+            });
+        }
+    }
+
+    /// Opens a scratch tab, prefilled with the clipboard's text if `from_clipboard`.
+    fn new_scratch(&mut self, from_clipboard: bool) {
+        let content = if from_clipboard {
+            match clipboard_text() {
+                Some(text) => text,
+                None => {
+                    self.events.push(AppEvent::ClipboardEmpty);
+                    String::new()
+                }
+            }
+        } else {
+            String::new()
+        };
+        self.add_tab(DocTab::scratch(content));
+    }
+
+    /// Asks how to open the first file that doesn't look like markdown.
+    fn show_open_as_prompt(&mut self, ctx: &egui::Context) {
+        let Some(path) = self.unrecognized.first().cloned() else {
+            return;
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        let mut choice = None;
+        let modal = egui::Modal::new(egui::Id::new("open_as_prompt")).show(ctx, |ui| {
+            ui.set_max_width(360.0);
+            ui.heading(tr("Open as…"));
+            ui.label(tr_args(
+                "{name} isn't a Markdown file. Many READMEs, changelogs and notes are Markdown anyway.",
+                &[("name", &name)],
+            ));
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if ui.button(tr("Plain Text")).clicked() {
+                    choice = Some(Some(true));
+                }
+                if ui.button(tr("Markdown")).clicked() {
+                    choice = Some(Some(false));
+                }
+                if ui.button(tr("Skip")).clicked() {
+                    choice = Some(None);
+                }
+            });
+        });
+        if modal.should_close() && choice.is_none() {
+            choice = Some(None);
+        }
+
+        match choice {
+            Some(Some(plain_text)) => {
+                self.unrecognized.remove(0);
+                self.open_as(path, plain_text);
+            }
+            Some(None) => {
+                self.unrecognized.remove(0);
+                self.events.push(AppEvent::SkippedUnrecognized {
+                    name: name.to_string(),
+                });
+            }
+            None => {}
+        }
+    }
+
+    /// Lets go of the documents of the tabs that weren't looked at for the
+    /// time set in Preferences, noting the active one as looked at now.
+    fn unload_idle_tabs(&mut self, settings: &Settings, cm_cache: &mut CommonMarkCache) {
+        let idle = Duration::from_secs(u64::from(settings.unload_tabs_after_mins.max(1)) * 60);
+        for (idx, tab) in self.tabs.iter_mut().enumerate() {
+            if idx == self.active {
+                tab.last_viewed = Instant::now();
+            } else if settings.unload_idle_tabs
+                && tab.last_viewed.elapsed() >= idle
+                && !tab.is_unloaded()
+            {
+                tab.unload(settings.unload_tab_contents);
+                // With whatever the CommonMark cache keeps for its viewer
+                cm_cache.clear_scrollable_with_id(tab.id);
+            }
+        }
+    }
+
+    /// Splits the active tab's document again if it was let go of, unless
+    /// reading it failed the last time; [`Self::show`] offers to try again.
+    fn load_active_again(&mut self) {
+        let Some(tab) = self
+            .tabs
+            .get_mut(self.active)
+            .filter(|tab| tab.load_again_failed().is_none())
+        else {
+            return;
+        };
+        if let Err(e) = tab.load_again() {
+            self.events.push(AppEvent::ReloadFailed {
+                title: tab.title.clone(),
+                error: format!("{e:#}"),
+            });
+        }
+    }
+
+    /// Picks up files that finished loading in the background, dropping the
+    /// tabs of those that couldn't be read.
+    fn poll_loading(
+        &mut self,
+        ctx: &egui::Context,
+        settings: &mut Settings,
+        workspace: Option<&Workspace>,
+    ) {
+        let mut idx = 0;
+        while idx < self.tabs.len() {
+            match self.tabs[idx].poll_load() {
+                Some(Ok(())) => {
+                    self.events.push(AppEvent::Opened {
+                        title: self.tabs[idx].title.clone(),
+                    });
+                    settings.note_recent(&self.tabs[idx].path);
+                    if let Err(e) = self.tabs[idx].load_reading(workspace) {
+                        self.events.push(AppEvent::ReadingStateFailed {
+                            error: format!("{e:#}"),
+                        });
+                    }
+                    let tab = &mut self.tabs[idx];
+                    if let Some(position) = tab.return_on_load.take() {
+                        tab.return_to(&position);
+                    }
+                    if let Some(offset) = tab.reveal_on_load.take() {
+                        // Where the search hit is, over where reading stopped
+                        tab.scroll_to_block = tab.block_at(offset);
+                        tab.restore_anchor = None;
+                    }
+                    if let Some(fragment) = tab.heading_on_load.take()
+                        && !tab.go_to_fragment(&fragment)
+                    {
+                        self.events.push(AppEvent::PlaceNotFound {
+                            place: format!("#{fragment}"),
+                        });
+                    }
+                }
+                Some(Err(e)) => {
+                    self.events.push(AppEvent::OpenFailed {
+                        title: self.tabs[idx].title.clone(),
+                        error: e.to_string(),
+                    });
+                    self.take_tab(idx);
+                    continue;
+                }
+                None => {}
+            }
+            idx += 1;
+        }
+        if self.tabs.iter().any(DocTab::is_loading) {
+            ctx.request_repaint_after(LOADING_POLL_INTERVAL);
+        }
+    }
+
+    fn open_folder(&mut self, shared: &mut Shared) {
+        if let Some(root) = FileDialog::new()
+            .set_title(tr("Open folder of Markdown files"))
+            .pick_folder()
+        {
+            self.scan_folder(shared, root);
+        }
+    }
+
+    fn scan_folder(&mut self, shared: &mut Shared, root: PathBuf) {
+        match Workspace::open(root, &shared.settings.exclude_patterns) {
+            Ok(ws) => {
+                self.events.push(AppEvent::OpenedFolder {
+                    name: ws.name(),
+                    documents: ws.files.len(),
+                });
+                let settings = &shared.settings;
+                if !trust::is_trusted(&settings.trusted_folders, &ws.root)
+                    && !settings.untrusted_folders.contains(&ws.root)
+                {
+                    self.trust.ask(ws.root.clone());
+                }
+                shared.workspace = Some(ws);
+            }
+            Err(e) => {
+                self.events.push(AppEvent::FolderFailed {
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    fn close_tab(&mut self, idx: usize) {
+        let Some(tab) = self.tabs.get_mut(idx) else {
+            return;
+        };
+        if let Err(e) = tab.save_reading() {
+            self.events.push(AppEvent::ReadingStateFailed {
+                error: format!("{e:#}"),
+            });
+        }
+        self.closed_tabs.push(&self.tabs[idx], idx);
+        self.take_tab(idx);
+    }
+
+    /// Opens the `n`th closed tab again where it was, scrolled to where it
+    /// was left, or the last one closed without `n`.
+    fn reopen_closed(&mut self, n: Option<usize>) {
+        let closed = match n {
+            Some(n) => self.closed_tabs.take(n),
+            None => self.closed_tabs.take_last(),
+        };
+        let Some(closed) = closed else {
+            return;
+        };
+        if let Some(idx) = self.tabs.iter().position(|tab| tab.path == closed.path) {
+            self.active = idx;
+            return;
+        }
+        let mut tab = DocTab::open(closed.path, self.chunked_above);
+        tab.plain_text = closed.plain_text;
+        tab.return_on_load = closed.position;
+        let idx = closed.index.min(self.tabs.len());
+        self.tabs.insert(idx, tab);
+        self.active = idx;
+    }
+
+    /// Writes the bookmarks, highlights and reading positions that changed.
+    pub fn save_reading(&mut self) {
+        for tab in &mut self.tabs {
+            if let Err(e) = tab.save_reading() {
+                self.events.push(AppEvent::ReadingStateFailed {
+                    error: format!("{e:#}"),
+                });
+            }
+        }
+    }
+
+    /// Bookmarks the section being read in the active tab, or removes its bookmark.
+    fn toggle_bookmark(&mut self) {
+        let Some(tab) = self
+            .tabs
+            .get_mut(self.active)
+            .filter(|tab| tab.sidecar.is_some())
+        else {
+            return;
+        };
+        let Some(title) = tab.current_section().map(str::to_string) else {
+            self.events.push(AppEvent::NoSectionToBookmark);
+            return;
+        };
+        tab.reading.toggle_bookmark(&title);
+        self.save_active_reading();
+    }
+
+    /// Handles the bookmark and highlight shortcuts, and highlights text
+    /// selected for it on the previous frame.
+    fn mark_reading(&mut self, ctx: &egui::Context) {
+        if ctx.input_mut(|i| i.consume_shortcut(&BOOKMARK_SHORTCUT)) {
+            self.toggle_bookmark();
+        }
+        let bookmarkable = self
+            .tabs
+            .get(self.active)
+            .is_some_and(|tab| tab.sidecar.is_some());
+        if bookmarkable && ctx.input_mut(|i| i.consume_shortcut(&HIGHLIGHT_SHORTCUT)) {
+            rich_copy::capture(ctx);
+        }
+        if let Some(text) = rich_copy::take_captured(ctx)
+            && let Some(tab) = self.tabs.get_mut(self.active)
+        {
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !text.is_empty() && !tab.reading.highlights.iter().any(|h| h.text == text) {
+                tab.reading.highlights.push(Highlight { text });
+                self.save_active_reading();
+            }
+        }
+    }
+
+    fn save_active_reading(&mut self) {
+        if let Some(tab) = self.tabs.get_mut(self.active)
+            && let Err(e) = tab.save_reading()
+        {
+            self.events.push(AppEvent::ReadingStateFailed {
+                error: format!("{e:#}"),
+            });
+        }
+    }
+
+    fn take_tab(&mut self, idx: usize) -> Option<DocTab> {
+        if idx < self.tabs.len() {
+            let tab = self.tabs.remove(idx);
+            if self.active >= self.tabs.len() {
+                self.active = self.tabs.len().saturating_sub(1);
+            }
+            Some(tab)
+        } else {
+            None
+        }
+    }
+
+    fn start_presentation(&mut self, ctx: &egui::Context) {
+        if let Some(tab) = self.tabs.get(self.active) {
+            self.presentation = Some(Presentation::new(&tab.title, &tab.content));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(true));
+        }
+    }
+
+    /// Compares the active tab before and after its last reload.
+    fn show_reload_changes(&mut self) {
+        if let Some(tab) = self.tabs.get_mut(self.active)
+            && let Some(previous) = &tab.previous_content
+        {
+            tab.reload_notice = false;
+            self.compare = Some(
+                Comparison::new(
+                    &tr_args("{title} before reloading", &[("title", &tab.title)]),
+                    (previous, &blocks::split(previous)),
+                    &tr_args("{title} now", &[("title", &tab.title)]),
+                    (&tab.content, &tab.doc),
+                )
+                .text_first(),
+            );
+        }
+    }
+
+    /// Compares the active tab against its last committed version.
+    fn show_git_changes(&mut self) {
+        let Some(tab) = self.tabs.get(self.active) else {
+            return;
+        };
+        match git::committed(&tab.path) {
+            Ok(text) => {
+                let committed = tab.format.to_markdown(text);
+                self.compare = Some(
+                    Comparison::new(
+                        &tr_args("{title} at HEAD", &[("title", &tab.title)]),
+                        (&committed, &blocks::split(&committed)),
+                        &tr_args("{title} with uncommitted changes", &[("title", &tab.title)]),
+                        (&tab.content, &tab.doc),
+                    )
+                    .text_first(),
+                );
+            }
+            Err(e) => self.events.push(AppEvent::GitFailed {
+                error: format!("{e:#}"),
+            }),
+        }
+    }
+
+    /// Everything the command palette offers right now, with its name.
+    fn commands(
+        &mut self,
+        ctx: &egui::Context,
+        settings: &Settings,
+        has_folder: bool,
+    ) -> Vec<(String, Command)> {
+        if !self.palette.open {
+            return Vec::new();
+        }
+        let has_tab = !self.tabs.is_empty();
+        let has_file = self.tabs.iter().any(|tab| !tab.is_scratch());
+        let reloaded = self
+            .tabs
+            .get(self.active)
+            .is_some_and(|tab| tab.previous_content.is_some());
+        let uncommitted = self
+            .git
+            .last()
+            .is_some_and(|status| status.file != FileState::Clean);
+        let pandoc = self.pandoc.version(ctx, &settings.pandoc_path).is_some();
+        let shown = |on: bool, what: &'static str| match on {
+            true => tr_args("Hide {what}", &[("what", &tr(what))]),
+            false => tr_args("Show {what}", &[("what", &tr(what))]),
+        };
+
+        let mut commands = vec![
+            (tr("Open…").to_string(), Command::OpenFiles, true),
+            (tr("New Scratch Tab").into(), Command::NewScratch, true),
+            (
+                tr("New Scratch Tab from Clipboard").into(),
+                Command::NewScratchFromClipboard,
+                true,
+            ),
+            (tr("Open Folder…").into(), Command::OpenFolder, true),
+            (tr("Open URL…").into(), Command::OpenUrl, true),
+            (
+                tr("Quick Open…").into(),
+                Command::QuickOpen,
+                has_folder || !settings.recent_files.is_empty(),
+            ),
+            (tr("Reload").into(), Command::Reload, has_tab),
+            (
+                tr("Reload All Tabs").into(),
+                Command::ReloadAll,
+                self.tabs.len() > 1,
+            ),
+            (
+                tr("Show Changes Since Reload").into(),
+                Command::ShowReloadChanges,
+                reloaded,
+            ),
+            (
+                tr("Copy Table of Contents").into(),
+                Command::CopyToc,
+                has_tab,
+            ),
+            (
+                tr("Export All Open Tabs to One HTML Page…").into(),
+                Command::Export(Bundle::Page),
+                has_file,
+            ),
+            (
+                tr("Export All Open Tabs to an HTML Folder…").into(),
+                Command::Export(Bundle::Folder),
+                has_file,
+            ),
+            (
+                tr("Export Active Tab with Pandoc…").into(),
+                Command::ExportWithPandoc,
+                has_file && pandoc,
+            ),
+            (tr("Preferences…").into(), Command::Preferences, true),
+            (tr("Close Tab").into(), Command::CloseTab, has_tab),
+            (
+                tr("Reopen Closed Tab").into(),
+                Command::ReopenClosedTab,
+                !self.closed_tabs.is_empty(),
+            ),
+            (tr("Find…").into(), Command::Find, true),
+            (tr("Search All Tabs…").into(), Command::SearchAll, true),
+            (
+                shown(settings.show_toc, "Table of Contents"),
+                Command::ToggleToc,
+                true,
+            ),
+            (
+                shown(settings.show_backlinks, "Backlinks"),
+                Command::ToggleBacklinks,
+                true,
+            ),
+            (
+                shown(settings.show_figures, "List of Figures"),
+                Command::ToggleFigures,
+                true,
+            ),
+            (
+                shown(settings.show_tags, "Tags"),
+                Command::ToggleTags,
+                has_folder,
+            ),
+            (shown(self.events.open, "Log"), Command::ToggleLog, true),
+            (
+                match settings.auto_reload {
+                    true => tr("Turn Off Auto-reload").into(),
+                    false => tr("Turn On Auto-reload").into(),
+                },
+                Command::ToggleAutoReload,
+                true,
+            ),
+            (
+                tr("Collapse All Sections").into(),
+                Command::CollapseAll,
+                has_tab,
+            ),
+            (
+                tr("Expand All Sections").into(),
+                Command::ExpandAll,
+                has_tab,
+            ),
+            (tr("Diff vs HEAD").into(), Command::DiffHead, uncommitted),
+            (tr("Present").into(), Command::Present, has_tab),
+        ];
+        for theme in Theme::ALL
+            .into_iter()
+            .filter(|theme| *theme != settings.theme)
+        {
+            let name = tr_args("Theme: {theme}", &[("theme", &theme.label())]);
+            commands.push((name, Command::Theme(theme), true));
+        }
+        for paper in Paper::ALL
+            .into_iter()
+            .filter(|paper| *paper != settings.paper)
+        {
+            let name = tr_args("Paper: {paper}", &[("paper", &paper.label())]);
+            commands.push((name, Command::Paper(paper), true));
+        }
+        for (idx, tab) in self.tabs.iter().enumerate() {
+            if idx != self.active {
+                let go = tr_args("Go to Tab: {title}", &[("title", &tab.title)]);
+                commands.push((go, Command::GoToTab(idx), true));
+                let compare = tr_args("Compare With: {title}", &[("title", &tab.title)]);
+                commands.push((compare, Command::CompareWith(idx), true));
+            }
+        }
+        commands
+            .into_iter()
+            .filter(|(_, _, available)| *available)
+            .map(|(name, command, _)| (name, command))
+            .collect()
+    }
+
+    /// Does what a command picked in the palette stands for, apart from
+    /// those handled along with the menus.
+    fn run_command(&mut self, ctx: &egui::Context, settings: &mut Settings, command: Command) {
+        match command {
+            Command::OpenFiles => self.open_files(),
+            Command::NewScratch => self.new_scratch(false),
+            Command::NewScratchFromClipboard => self.new_scratch(true),
+            Command::QuickOpen => self.quick_open.open(),
+            Command::Reload => self.reload_active(),
+            Command::ReloadAll => self.reload_all(),
+            Command::ShowReloadChanges => self.show_reload_changes(),
+            Command::CopyToc => {
+                if let Some(tab) = self.tabs.get(self.active) {
+                    ctx.copy_text(toc::to_markdown(&tab.doc));
+                    self.events.push(AppEvent::TocCopied {
+                        title: tab.title.clone(),
+                    });
+                }
+            }
+            Command::Preferences => self.preferences_open = true,
+            Command::CloseTab => self.close_tab(self.active),
+            Command::ReopenClosedTab => self.reopen_closed(None),
+            Command::Find => self.search.open(),
+            Command::SearchAll => self.global_search.open(),
+            Command::Theme(theme) => settings.theme = theme,
+            Command::Paper(paper) => settings.paper = paper,
+            Command::ToggleToc => settings.show_toc ^= true,
+            Command::ToggleBacklinks => settings.show_backlinks ^= true,
+            Command::ToggleFigures => settings.show_figures ^= true,
+            Command::ToggleTags => settings.show_tags ^= true,
+            Command::ToggleLog => self.events.open ^= true,
+            Command::ToggleAutoReload => settings.auto_reload ^= true,
+            Command::CollapseAll | Command::ExpandAll => {
+                if let Some(tab) = self.tabs.get_mut(self.active) {
+                    tab.set_all_collapsed(command == Command::CollapseAll);
+                }
+            }
+            Command::Present => self.start_presentation(ctx),
+            Command::OpenUrl => self.remote_docs.ask(),
+            Command::GoToTab(idx) if idx < self.tabs.len() => self.active = idx,
+            Command::CompareWith(idx) => self.start_compare(idx),
+            Command::OpenFolder
+            | Command::Export(_)
+            | Command::ExportWithPandoc
+            | Command::DiffHead
+            | Command::GoToTab(_) => {}
+        }
+    }
+
+    /// Compares the tab at `old` against the active one.
+    fn start_compare(&mut self, old: usize) {
+        // For its document as well as its contents
+        if let Some(tab) = self.tabs.get_mut(old)
+            && let Err(e) = tab.load_again()
+        {
+            self.events.push(AppEvent::ReloadFailed {
+                title: tab.title.clone(),
+                error: format!("{e:#}"),
+            });
+            return;
+        }
+        if let (Some(old), Some(new)) = (self.tabs.get(old), self.tabs.get(self.active)) {
+            self.compare = Some(Comparison::new(
+                &old.title,
+                (&old.content, &old.doc),
+                &new.title,
+                (&new.content, &new.doc),
+            ));
+        }
+    }
+
+    fn reload_active(&mut self) {
+        self.reload_tab(self.active);
+    }
+
+    /// Reloads every tab that has a file, e.g. after a generator rewrote
+    /// the folder.
+    fn reload_all(&mut self) {
+        for idx in 0..self.tabs.len() {
+            if !self.tabs[idx].is_scratch() && !self.tabs[idx].is_loading() {
+                self.reload_tab(idx);
+            }
+        }
+    }
+
+    /// Reads the tab at `idx` again, or fetches it again if it was opened
+    /// from a URL.
+    fn reload_tab(&mut self, idx: usize) {
+        match self
+            .tabs
+            .get(idx)
+            .and_then(|tab| remote_docs::source_url(&tab.path))
+        {
+            Some(url) => self.remote_docs.fetch(&url),
+            None => self.reload_from_disk(idx),
+        }
+    }
+
+    fn reload_from_disk(&mut self, idx: usize) {
+        if let Some(tab) = self.tabs.get_mut(idx) {
+            let title = tab.title.clone();
+            self.events.push(match tab.reload() {
+                Ok(()) => AppEvent::Reloaded { title },
+                Err(e) => AppEvent::ReloadFailed {
+                    title,
+                    error: e.to_string(),
+                },
+            });
+        }
+    }
+
+    /// Handles selecting the whole document, and puts text copied out of it
+    /// on the clipboard as HTML too.
+    fn select_and_copy(&mut self, ctx: &egui::Context) {
+        rich_copy::install(ctx);
+        let Some(tab) = self.tabs.get_mut(self.active) else {
+            return;
+        };
+        // Text fields keep Ctrl+A and Ctrl+C to themselves
+        let in_document = ctx.memory(|m| m.focused().is_none());
+        if ctx.input(|i| i.pointer.any_pressed() || i.key_pressed(egui::Key::Escape)) {
+            tab.selected_all = false;
+        }
+        if in_document && ctx.input_mut(|i| i.consume_shortcut(&SELECT_ALL_SHORTCUT)) {
+            tab.selected_all = true;
+            let mut labels = egui::text_selection::LabelSelectionState::load(ctx);
+            labels.clear_selection();
+            labels.store(ctx);
+        }
+
+        let copied = if tab.selected_all
+            && in_document
+            && ctx.input(|i| i.events.contains(&egui::Event::Copy))
+        {
+            let copied = rich_copy::everything(&tab.content);
+            ctx.copy_text(copied.text.clone());
+            Some(copied)
+        } else {
+            rich_copy::take_copied(ctx)
+                .and_then(|copied| rich_copy::selection(&tab.content, &copied))
+        };
+        if let Some(copied) = copied
+            && let Err(e) =
+                rich_copy::set_clipboard(&mut self.clipboard, &copied.html, &copied.text)
+        {
+            self.events.push(AppEvent::RichCopyFailed {
+                error: e.to_string(),
+            });
+        }
+    }
+
+    /// Draws the whole window into `ctx`, which must belong to this window's viewport.
+    pub fn show(&mut self, ctx: &egui::Context, shared: &mut Shared) -> Option<WindowAction> {
+        i18n::set_language(shared.settings.language);
+        self.chunked_above = shared.settings.chunked_above();
+        self.poll_loading(ctx, &mut shared.settings, shared.workspace.as_ref());
+        self.unload_idle_tabs(&shared.settings, &mut shared.cm_cache);
+        self.load_active_again();
+
+        let dropped: Vec<PathBuf> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .collect()
+        });
+        for path in dropped {
+            self.open_any(path);
+        }
+        self.show_open_as_prompt(ctx);
+
+        let mut open_folder = false;
+        let mut tree_action = None;
+        let Shared {
+            cm_cache,
+            settings,
+            workspace,
+        } = shared;
+
+        if self.is_main() && settings.check_for_updates && self.updates.is_unchecked() {
+            self.updates.check(ctx);
+        }
+        if let Some(event) = self.updates.poll() {
+            self.events.push(event);
+        }
+        for (url, outcome) in self.remote_docs.poll(ctx, &settings.site_logins) {
+            self.fetched(url, outcome);
+        }
+
+        if self.applied_always_on_top != Some(settings.always_on_top) {
+            let level = if settings.always_on_top {
+                egui::WindowLevel::AlwaysOnTop
+            } else {
+                egui::WindowLevel::Normal
+            };
+            ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(level));
+            self.applied_always_on_top = Some(settings.always_on_top);
+        }
+        // Detached windows get theirs with their viewport
+        let title = self.title(settings.path_in_title);
+        if self.is_main() && self.applied_title.as_ref() != Some(&title) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.applied_title = Some(title);
+        }
+
+        // Presentation mode takes over the whole window
+        if let Some(presentation) = &mut self.presentation {
+            if presentation.show(ctx, cm_cache, settings.md_text_scale) == present::Outcome::Exit {
+                self.presentation = None;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(false));
+            }
+            return None;
+        }
+
+        // Before Ctrl+F, which would take Ctrl+Shift+F too
+        if ctx.input_mut(|i| i.consume_shortcut(&global_search::SHORTCUT)) {
+            self.global_search.open();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&FIND_SHORTCUT)) {
+            self.search.open();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&NEW_SCRATCH_SHORTCUT)) {
+            self.new_scratch(false);
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&closed_tabs::SHORTCUT)) {
+            self.reopen_closed(None);
+        }
+        // Before Ctrl+P, which would take Ctrl+Shift+P too
+        if ctx.input_mut(|i| i.consume_shortcut(&palette::SHORTCUT)) {
+            self.palette.open();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&QUICK_OPEN_SHORTCUT)) {
+            self.quick_open.open();
+        }
+        self.select_and_copy(ctx);
+        self.mark_reading(ctx);
+        if ctx.input_mut(|i| i.consume_shortcut(&nav_macro::RECORD_SHORTCUT)) {
+            self.nav_macro.toggle_recording();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&nav_macro::REPLAY_SHORTCUT)) {
+            self.replay_place();
+        }
+        self.tab_order.update(&self.tabs, self.active);
+        if let Some(idx) = self.tab_order.read(ctx, &self.tabs, self.active) {
+            self.active = idx;
+        }
+
+        // Ctrl+wheel and trackpad pinch size document text, like A–/A+
+        let zoom = ctx.input(|i| i.zoom_delta());
+        if zoom != 1.0 && !settings.lock_zoom {
+            scale_text(settings, zoom);
+        }
+
+        let mut action = None;
+        let mut export_tabs = None;
+        let mut export_pandoc = false;
+        let mut toggle_bookmark = false;
+        let mut forget_highlight = None;
+        let mut show_changes = false;
+        let mut reload_changed = false;
+        let mut turn_page = None;
+        let mut show_git_changes = false;
+        let mut open_in_browser = false;
+        let mut ask_trust = None;
+        // A search moved the view, rather than a jump to a section
+        let mut revealed_match = false;
+
+        // Picked in the palette, the commands go the way menu clicks do
+        let commands = self.commands(ctx, settings, workspace.is_some());
+        match self.palette.show(ctx, &commands) {
+            Some(Command::OpenFolder) => open_folder = true,
+            Some(Command::Export(bundle)) => export_tabs = Some(bundle),
+            Some(Command::ExportWithPandoc) => export_pandoc = true,
+            Some(Command::DiffHead) => show_git_changes = true,
+            Some(command) => self.run_command(ctx, settings, command),
+            None => {}
+        }
+
+        // Top menu
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::MenuBar::new().ui(ui, |ui| {
+                ui.menu_button(tr("File"), |ui| {
+                    if ui.button(tr("Open…")).clicked() {
+                        ui.close();
+                        self.open_files();
+                    }
+                    if ui
+                        .button(tr("New Scratch Tab  (Ctrl+N)"))
+                        .on_hover_text(tr("Preview Markdown pasted into a text area"))
+                        .clicked()
+                    {
+                        ui.close();
+                        self.new_scratch(false);
+                    }
+                    if ui.button(tr("New Scratch Tab from Clipboard")).clicked() {
+                        ui.close();
+                        self.new_scratch(true);
+                    }
+                    if ui.button(tr("Open Folder…")).clicked() {
+                        ui.close();
+                        open_folder = true;
+                    }
+                    if ui
+                        .button(tr("Open URL…"))
+                        .on_hover_text(tr("Read a Markdown document from the web"))
+                        .clicked()
+                    {
+                        ui.close();
+                        self.remote_docs.ask();
+                    }
+                    if ui
+                        .add_enabled(
+                            workspace.is_some() || !settings.recent_files.is_empty(),
+                            Button::new(tr("Quick Open…  (Ctrl+P)")),
+                        )
+                        .on_hover_text(tr(
+                            "Jump to a document of the folder, or one opened lately, by name",
+                        ))
+                        .clicked()
+                    {
+                        ui.close();
+                        self.quick_open.open();
+                    }
+                    if ui.button(tr("Reload")).clicked() {
+                        ui.close();
+                        self.reload_active();
+                    }
+                    if ui
+                        .add_enabled(self.tabs.len() > 1, Button::new(tr("Reload All Tabs")))
+                        .clicked()
+                    {
+                        ui.close();
+                        self.reload_all();
+                    }
+                    let reloaded = self
+                        .tabs
+                        .get(self.active)
+                        .is_some_and(|tab| tab.previous_content.is_some());
+                    if ui
+                        .add_enabled(reloaded, Button::new(tr("Show Changes Since Reload")))
+                        .on_hover_text(tr(
+                            "What reloads changed in this document since you last looked",
+                        ))
+                        .on_disabled_hover_text(tr("No reload has changed this document yet"))
+                        .clicked()
+                    {
+                        ui.close();
+                        self.show_reload_changes();
+                    }
+                    ui.separator();
+                    if ui
+                        .add_enabled(
+                            !self.tabs.is_empty(),
+                            Button::new(tr("Export Snippet as PNG…")),
+                        )
+                        .on_hover_text(tr("Drag out a region of the document to save as an image"))
+                        .clicked()
+                    {
+                        ui.close();
+                        self.snippet.start(Destination::File);
+                    }
+                    if ui
+                        .add_enabled(
+                            !self.tabs.is_empty(),
+                            Button::new(tr("Copy Snippet as Image")),
+                        )
+                        .clicked()
+                    {
+                        ui.close();
+                        self.snippet.start(Destination::Clipboard);
+                    }
+                    if ui
+                        .add_enabled(
+                            !self.tabs.is_empty(),
+                            Button::new(tr("Copy Table of Contents")),
+                        )
+                        .on_hover_text(tr("The headings as a Markdown list of links"))
+                        .clicked()
+                        && let Some(tab) = self.tabs.get(self.active)
+                    {
+                        ui.close();
+                        ctx.copy_text(toc::to_markdown(&tab.doc));
+                        self.events.push(AppEvent::TocCopied {
+                            title: tab.title.clone(),
+                        });
+                    }
+                    ui.add_enabled_ui(self.tabs.iter().any(|tab| !tab.is_scratch()), |ui| {
+                        ui.menu_button(tr("Export"), |ui| {
+                            for (label, bundle) in [
+                                (tr("All Open Tabs to One HTML Page…"), Bundle::Page),
+                                (tr("All Open Tabs to an HTML Folder…"), Bundle::Folder),
+                            ] {
+                                if ui.button(label).clicked() {
+                                    ui.close();
+                                    export_tabs = Some(bundle);
+                                }
+                            }
+                            ui.separator();
+                            let version = self.pandoc.version(ctx, &settings.pandoc_path);
+                            let hover = match version {
+                                Some(version) => tr_args(
+                                    "Word, OpenDocument or EPUB, with {version}",
+                                    &[("version", &version)],
+                                ),
+                                None => {
+                                    tr("Install pandoc, or set where it is in Preferences").into()
+                                }
+                            };
+                            if ui
+                                .add_enabled(
+                                    version.is_some(),
+                                    Button::new(tr("Active Tab with Pandoc…")),
+                                )
+                                .on_hover_text(hover)
+                                .on_disabled_hover_text(tr(
+                                    "Install pandoc, or set where it is in Preferences",
+                                ))
+                                .clicked()
+                            {
+                                ui.close();
+                                export_pandoc = true;
+                            }
+                        })
+                        .response
+                        .on_hover_text(tr(
+                            "Static HTML with links between the documents kept working",
+                        ));
+                    });
+                    ui.checkbox(&mut settings.monochrome_code, tr("Monochrome Code Blocks"))
+                        .on_hover_text(tr(
+                            "Black on white with bold and italics instead of colors, \
+                             for printing",
+                        ));
+                    ui.separator();
+                    if ui.button(tr("Preferences…")).clicked() {
+                        ui.close();
+                        self.preferences_open = true;
+                    }
+                    ui.separator();
+                    if ui.button(tr("Close Tab")).clicked() {
+                        ui.close();
+                        let idx = self.active;
+                        self.close_tab(idx);
+                    }
+                    ui.add_enabled_ui(!self.closed_tabs.is_empty(), |ui| {
+                        ui.menu_button(tr("Reopen Closed Tab"), |ui| {
+                            let mut reopen = None;
+                            for (n, title) in self.closed_tabs.titles() {
+                                if ui.button(title).clicked() {
+                                    ui.close();
+                                    reopen = Some(n);
+                                }
+                            }
+                            if reopen.is_some() {
+                                self.reopen_closed(reopen);
+                            }
+                        })
+                        .response
+                        .on_hover_text(tr("Ctrl+Shift+T reopens the last one closed"));
+                    });
+                    if !self.is_main() && ui.button(tr("Close Window")).clicked() {
+                        ui.close();
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                    if ui.button(tr("Quit")).clicked() {
+                        ui.close();
+                        ctx.send_viewport_cmd_to(
+                            egui::ViewportId::ROOT,
+                            egui::ViewportCommand::Close,
+                        );
+                    }
+                });
+
+                ui.separator();
+
+                // Text size controls
+                let unlocked = !settings.lock_zoom;
+                let locked_hint = tr("Zoom is locked in View → Lock Zoom");
+                if ui
+                    .add_enabled(unlocked, Button::new(tr("A–")))
+                    .on_disabled_hover_text(locked_hint)
+                    .clicked()
+                {
+                    scale_text(settings, 0.9);
+                }
+                if ui
+                    .add_enabled(unlocked, Button::new(tr("A+")))
+                    .on_disabled_hover_text(locked_hint)
+                    .clicked()
+                {
+                    scale_text(settings, 1.1);
+                }
+
+                ui.separator();
+
+                ui.menu_button(tr("View"), |ui| {
+                    if ui
+                        .button(tr("Command Palette…  (Ctrl+Shift+P)"))
+                        .on_hover_text(tr("Run any command, or switch tabs, by typing its name"))
+                        .clicked()
+                    {
+                        ui.close();
+                        self.palette.open();
+                    }
+                    if ui.button(tr("Find…  (Ctrl+F)")).clicked() {
+                        ui.close();
+                        self.search.open();
+                    }
+                    if ui
+                        .button(tr("Search All Tabs…  (Ctrl+Shift+F)"))
+                        .on_hover_text(tr("Look through every open tab and the opened folder"))
+                        .clicked()
+                    {
+                        ui.close();
+                        self.global_search.open();
+                    }
+                    ui.separator();
+                    ui.menu_button(tr("Theme"), |ui| {
+                        for theme in Theme::ALL {
+                            ui.radio_value(&mut settings.theme, theme, theme.label());
+                        }
+                        ui.separator();
+                        for paper in Paper::ALL {
+                            ui.radio_value(&mut settings.paper, paper, paper.label());
+                        }
+                    });
+                    ui.menu_button(tr("UI Scale"), |ui| {
+                        for scale in config::UI_SCALES {
+                            ui.radio_value(
+                                &mut settings.ui_scale,
+                                scale,
+                                config::ui_scale_label(scale),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(tr("Size of the whole interface; A–/A+ size document text"));
+                    ui.checkbox(&mut settings.lock_zoom, tr("Lock Zoom"))
+                        .on_hover_text(tr("Ignore Ctrl+wheel, Ctrl+Plus/Minus and A–/A+"));
+                    if ui
+                        .add_enabled(workspace.is_some(), Button::new(tr("Folder Dashboard")))
+                        .on_hover_text(tr(
+                            "Word counts, stale files and broken links of the folder",
+                        ))
+                        .clicked()
+                        && let Some(ws) = workspace.as_ref()
+                    {
+                        ui.close();
+                        // Always a fresh report, as files may have changed since
+                        self.dashboard = Some(Dashboard::new(ws));
+                    }
+                    ui.checkbox(&mut settings.show_toc, tr("Table of Contents"));
+                    ui.checkbox(&mut settings.show_reading_progress, tr("Reading Progress"))
+                        .on_hover_text(tr(
+                            "A bar under the tabs for how far you've read, and reading times in the contents",
+                        ));
+                    ui.checkbox(&mut settings.show_backlinks, tr("Backlinks"))
+                        .on_hover_text(tr("Documents that link to this one"));
+                    ui.checkbox(&mut settings.show_figures, tr("List of Figures"))
+                        .on_hover_text(tr("Images with a caption under them"));
+                    ui.checkbox(&mut settings.show_tags, tr("Tags"))
+                        .on_hover_text(tr(
+                            "Tags of the folder's documents, from their frontmatter",
+                        ));
+                    ui.checkbox(&mut settings.show_git_status, tr("Git Status"))
+                        .on_hover_text(tr(
+                            "The branch and whether the document has uncommitted changes, asked of git every few seconds",
+                        ));
+                    ui.checkbox(&mut settings.limit_text_width, tr("Readable Width"))
+                        .on_hover_text(tr_args(
+                            "Keep text to a centered column of {width} characters",
+                            &[("width", &settings.text_width)],
+                        ));
+                    ui.checkbox(&mut self.events.open, tr("Log"));
+                    ui.checkbox(&mut settings.always_on_top, tr("Always on Top"))
+                        .on_hover_text(tr("Keep viewer windows above other applications"));
+                    ui.checkbox(&mut settings.auto_reload, tr("Auto-reload"))
+                        .on_hover_text(tr("Reload documents when they change on disk"));
+                    ui.add_enabled(
+                        settings.auto_reload,
+                        egui::Checkbox::new(
+                            &mut settings.pause_reload_while_reading,
+                            tr("Pause updates while reading"),
+                        ),
+                    )
+                    .on_hover_text(tr(
+                        "Hold back reloads until you scroll to the top or apply them",
+                    ));
+                    ui.checkbox(
+                        &mut settings.hash_change_detection,
+                        tr("Compare file contents"),
+                    )
+                    .on_hover_text(tr(
+                        "Also detect changes by content, for network drives where modification times are unreliable",
+                    ));
+                    if settings.hash_change_detection {
+                        ui.horizontal(|ui| {
+                            ui.label(tr("Check every"));
+                            ui.add(
+                                egui::DragValue::new(&mut settings.hash_poll_secs)
+                                    .range(1..=600)
+                                    .suffix(" s"),
+                            );
+                        });
+                    }
+                    ui.checkbox(
+                        &mut settings.guess_code_languages,
+                        tr("Guess code block languages"),
+                    )
+                    .on_hover_text(tr(
+                        "Highlight unlabeled code fences using a detected language",
+                    ));
+                    ui.checkbox(&mut settings.number_headings, tr("Number Headings"))
+                        .on_hover_text(tr(
+                            "Put 1., 1.1, 1.1.1… in front of headings and in the contents",
+                        ));
+                    ui.checkbox(&mut settings.justify_text, tr("Justify Paragraphs"))
+                        .on_hover_text(tr("Align plain paragraphs to both margins"));
+                    ui.add_enabled(
+                        settings.justify_text,
+                        egui::Checkbox::new(&mut settings.hyphenate, tr("Hyphenate")),
+                    )
+                    .on_hover_text(tr("Break long words at line ends (English patterns)"));
+                    ui.separator();
+                    if ui
+                        .add_enabled(
+                            !self.tabs.is_empty(),
+                            Button::new(tr("Collapse All Sections")),
+                        )
+                        .clicked()
+                    {
+                        ui.close();
+                        if let Some(tab) = self.tabs.get_mut(self.active) {
+                            tab.set_all_collapsed(true);
+                        }
+                    }
+                    if ui
+                        .add_enabled(
+                            !self.tabs.is_empty(),
+                            Button::new(tr("Expand All Sections")),
+                        )
+                        .clicked()
+                    {
+                        ui.close();
+                        if let Some(tab) = self.tabs.get_mut(self.active) {
+                            tab.set_all_collapsed(false);
+                        }
+                    }
+                    ui.separator();
+                    let uncommitted = self
+                        .git
+                        .last()
+                        .is_some_and(|status| status.file != FileState::Clean);
+                    if ui
+                        .add_enabled(uncommitted, Button::new(tr("Diff vs HEAD")))
+                        .on_hover_text(tr("The uncommitted changes to this document"))
+                        .on_disabled_hover_text(tr("No uncommitted changes in a git repository"))
+                        .clicked()
+                    {
+                        ui.close();
+                        show_git_changes = true;
+                    }
+                    ui.add_enabled_ui(self.tabs.len() > 1, |ui| {
+                        ui.menu_button(tr("Compare With"), |ui| {
+                            let active = self.active;
+                            for idx in (0..self.tabs.len()).filter(|idx| *idx != active) {
+                                if ui.button(&self.tabs[idx].title).clicked() {
+                                    ui.close();
+                                    self.start_compare(idx);
+                                }
+                            }
+                        })
+                        .response
+                        .on_hover_text(tr("Outline and text diff of another tab against this one"));
+                    });
+                    if ui
+                        .add_enabled(!self.tabs.is_empty(), Button::new(tr("Present")))
+                        .on_hover_text(tr("Show the document as slides split on --- separators"))
+                        .clicked()
+                    {
+                        ui.close();
+                        self.start_presentation(ctx);
+                    }
+                    ui.separator();
+                    let record = if self.nav_macro.is_recording() {
+                        tr("Stop Recording Navigation  (F8)")
+                    } else {
+                        tr("Record Navigation  (F8)")
+                    };
+                    if ui
+                        .button(record)
+                        .on_hover_text(tr(
+                            "Remember the sections, searches and scroll stops visited, \
+                             to go through them again",
+                        ))
+                        .clicked()
+                    {
+                        ui.close();
+                        self.nav_macro.toggle_recording();
+                    }
+                    if ui
+                        .add_enabled(
+                            !self.nav_macro.is_empty(),
+                            Button::new(tr("Go to Next Recorded Place  (F9)")),
+                        )
+                        .clicked()
+                    {
+                        ui.close();
+                        self.replay_place();
+                    }
+                });
+
+                ui.menu_button(tr("Bookmarks"), |ui| {
+                    let Some(tab) = self
+                        .tabs
+                        .get_mut(self.active)
+                        .filter(|tab| tab.sidecar.is_some())
+                    else {
+                        ui.weak(tr("Only saved documents can be bookmarked"));
+                        return;
+                    };
+                    let section = tab.current_section();
+                    let bookmarked = section
+                        .is_some_and(|title| tab.reading.bookmarks.iter().any(|b| b == title));
+                    let label = if bookmarked {
+                        tr("Remove Bookmark  (Ctrl+D)")
+                    } else {
+                        tr("Bookmark This Section  (Ctrl+D)")
+                    };
+                    if ui
+                        .add_enabled(section.is_some(), egui::Button::new(label))
+                        .clicked()
+                    {
+                        ui.close();
+                        toggle_bookmark = true;
+                    }
+                    ui.weak(tr("Select text and press Ctrl+H to highlight it"));
+
+                    let mut jump = None;
+                    if !tab.reading.bookmarks.is_empty() {
+                        ui.separator();
+                        for title in &tab.reading.bookmarks {
+                            if ui.button(title).clicked() {
+                                ui.close();
+                                jump = tab.heading_block(title);
+                            }
+                        }
+                    }
+                    if !tab.reading.highlights.is_empty() {
+                        ui.separator();
+                        let found = tab.highlighted().to_vec();
+                        for (n, highlight) in tab.reading.highlights.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                let mut text: String = highlight.text.chars().take(40).collect();
+                                if text.len() < highlight.text.len() {
+                                    text.push('…');
+                                }
+                                let button = ui.add_enabled(
+                                    found[n].is_some(),
+                                    egui::Button::new(format!("“{text}”")),
+                                );
+                                if button
+                                    .on_disabled_hover_text(tr("No longer in the document"))
+                                    .clicked()
+                                {
+                                    ui.close();
+                                    jump = found[n]
+                                        .as_ref()
+                                        .and_then(|range| tab.block_at(range.start));
+                                }
+                                if ui
+                                    .small_button("✕")
+                                    .on_hover_text(tr("Remove highlight"))
+                                    .clicked()
+                                {
+                                    forget_highlight = Some(n);
+                                }
+                            });
+                        }
+                    }
+                    if jump.is_some() {
+                        tab.scroll_to_block = jump;
+                    }
+                    if let Some(sidecar) = &tab.sidecar {
+                        ui.separator();
+                        ui.weak(tr_args(
+                            "Kept in {file}",
+                            &[("file", &sidecar.file().display())],
+                        ));
+                    }
+                });
+
+                ui.menu_button(tr("Help"), |ui| {
+                    ui.label(tr("Markdown Viewer"));
+                    ui.label(tr("View-only .md files with tabs and code highlighting."));
+                    ui.separator();
+                    let label = match self.updates.available() {
+                        Some(release) => tr_args(
+                            "Update Available: {version}…",
+                            &[("version", &release.version)],
+                        ),
+                        None => tr("Check for Updates…").to_string(),
+                    };
+                    if ui
+                        .button(label)
+                        .on_hover_text(tr("Ask GitHub for newer releases and what changed"))
+                        .clicked()
+                    {
+                        ui.close();
+                        if self.updates.available().is_none() {
+                            self.updates.check(ctx);
+                        }
+                        self.updates.open = true;
+                    }
+                    if ui
+                        .button(tr("Cache Statistics"))
+                        .on_hover_text(tr("How much memory images take, and the limit on it"))
+                        .clicked()
+                    {
+                        ui.close();
+                        self.cache_stats_open = true;
+                    }
+                });
+            });
+        });
+
+        // Status bar
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                self.events.show_status(ui);
+                self.pandoc.show_progress(ui);
+                self.remote_docs.show_progress(ui);
+
+                let Some(tab) = self.tabs.get_mut(self.active) else {
+                    return;
+                };
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if self.nav_macro.is_recording() {
+                        ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            tr_args(
+                                "● Recording navigation ({steps})",
+                                &[("steps", &self.nav_macro.len())],
+                            ),
+                        )
+                        .on_hover_text(tr("F8 to stop"));
+                        ui.separator();
+                    }
+                    ui.weak(tab.encoding)
+                        .on_hover_text(tr("Encoding of the file on disk"));
+                    ui.separator();
+                    let dialect = tab.dialect.unwrap_or(settings.dialect);
+                    ui.menu_button(egui::RichText::new(dialect.label()).weak(), |ui| {
+                        dialect_menu(ui, &mut tab.dialect, settings.dialect);
+                    })
+                    .response
+                    .on_hover_text(tr("Markdown dialect the document is read as"));
+                    let trusted = trust::is_trusted(&settings.trusted_folders, &tab.path);
+                    if !trusted
+                        && !tab.is_scratch()
+                        && (settings.show_git_status || settings.render_diagrams)
+                    {
+                        ui.separator();
+                        if ui
+                            .link(tr("Untrusted folder"))
+                            .on_hover_text(tr(
+                                "Git status and diagrams are off for documents here until the folder is trusted",
+                            ))
+                            .clicked()
+                        {
+                            let root = workspace.as_ref().map(|ws| ws.root.as_path());
+                            ask_trust = trust::folder_of(&tab.path, root);
+                        }
+                    }
+                    if settings.show_git_status
+                        && trusted
+                        && let Some(status) = self.git.status(ui.ctx(), tab)
+                    {
+                        ui.separator();
+                        match status.file {
+                            FileState::Clean => {
+                                ui.weak(tr("committed"))
+                                    .on_hover_text(tr("No uncommitted changes to this document"));
+                            }
+                            FileState::Modified => {
+                                if ui
+                                    .link(tr("● modified"))
+                                    .on_hover_text(tr("Show the uncommitted changes"))
+                                    .clicked()
+                                {
+                                    show_git_changes = true;
+                                }
+                            }
+                            FileState::Untracked => {
+                                if ui
+                                    .link(tr("untracked"))
+                                    .on_hover_text(tr("Not added to the repository yet"))
+                                    .clicked()
+                                {
+                                    show_git_changes = true;
+                                }
+                            }
+                        }
+                        ui.weak(&status.branch).on_hover_text(tr("Git branch"));
+                    }
+                    if tab.format != Format::Markdown {
+                        ui.weak(tab.format.name())
+                            .on_hover_text(tr("Converted to Markdown for display"));
+                    }
+
+                    // Without the sidebar, show where we are in the document too
+                    if !settings.show_toc {
+                        ui.separator();
+                        let mut jump = None;
+                        // Right-to-left, so walk the path innermost first
+                        for (n, (idx, title)) in tab.breadcrumbs().into_iter().rev().enumerate() {
+                            if n > 0 {
+                                ui.weak("›");
+                            }
+                            let label = egui::RichText::new(compact(title)).small();
+                            if ui
+                                .link(label)
+                                .on_hover_text(tr("Jump to section"))
+                                .clicked()
+                            {
+                                jump = Some(idx);
+                            }
+                        }
+                        if jump.is_some() {
+                            tab.scroll_to_block = jump;
+                        }
+                    }
+                });
+            });
+        });
+
+        if let Some(event) = preferences::show(ctx, &mut self.preferences_open, settings) {
+            self.events.push(event);
+        }
+        self.updates.show(ctx);
+        egui::Window::new(tr("Cache Statistics"))
+            .id(egui::Id::new("cache_stats"))
+            .open(&mut self.cache_stats_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                image_cache::show_stats(ui, settings.image_cache_limit());
+            });
+        if let Some(event) = self.pandoc.show(ctx, &settings.pandoc_path) {
+            self.events.push(event);
+        }
+        if let Some(url) = self.remote_docs.show(ctx) {
+            self.remote_docs.fetch(&url);
+        }
+        if let Some(path) = self
+            .quick_open
+            .show(ctx, workspace.as_ref(), &settings.recent_files)
+        {
+            self.open_path(path);
+        }
+        if let Some(dashboard) = &mut self.dashboard {
+            match workspace.as_ref().filter(|ws| dashboard.is_for(ws)) {
+                Some(ws) if dashboard.open => {
+                    if let Some(path) = dashboard.show(ctx, ws) {
+                        self.open_path(path);
+                    }
+                }
+                _ => self.dashboard = None,
+            }
+        }
+
+        // Everything that happened in this window
+        if self.events.open {
+            egui::TopBottomPanel::bottom("log")
+                .resizable(true)
+                .default_height(140.0)
+                .show(ctx, |ui| self.events.show_panel(ui));
+        }
+
+        // Tabs header
+        egui::TopBottomPanel::top("tab_strip").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                let mut close = None;
+                let mut reload = None;
+                for idx in 0..self.tabs.len() {
+                    let selected = idx == self.active;
+                    if let Some(status) = &self.tabs[idx].doc.status {
+                        status.show(ui, true);
+                    }
+                    let tab = &self.tabs[idx];
+                    let title = match tab.load_progress() {
+                        Some(progress) => tr_args(
+                            "{title} ({percent}%)",
+                            &[
+                                ("title", &tab.title),
+                                ("percent", &((progress * 100.0) as u32)),
+                            ],
+                        ),
+                        None => tab.title.clone(),
+                    };
+                    let mut response = ui.add(Button::selectable(selected, title));
+                    if let Some(dir) = self.tabs[idx].dir() {
+                        response = response.on_hover_text(dir.display().to_string());
+                    }
+                    if response.clicked() {
+                        self.active = idx;
+                    }
+                    response.context_menu(|ui| {
+                        if ui.button(tr("Move to New Window")).clicked() {
+                            ui.close();
+                            action = self.take_tab(idx).map(WindowAction::Detach);
+                        }
+                        if !self.is_main() && ui.button(tr("Move to Main Window")).clicked() {
+                            ui.close();
+                            action = self.take_tab(idx).map(WindowAction::MoveToMain);
+                        }
+                    });
+                    if action.is_some() {
+                        // The tab list changed under us; draw the rest next frame.
+                        break;
+                    }
+                    ui.scope(|ui| {
+                        ui.spacing_mut().item_spacing.x = 4.0;
+                        let tab = &self.tabs[idx];
+                        if tab.is_loading() {
+                            ui.add(egui::Spinner::new().size(12.0));
+                        } else if !tab.is_scratch()
+                            && ui
+                                .small_button("🔄")
+                                .on_hover_text(tr_args("Reload {title}", &[("title", &tab.title)]))
+                                .clicked()
+                        {
+                            reload = Some(idx);
+                        }
+                        if ui.button("×").on_hover_text(tr("Close tab")).clicked() {
+                            close = Some(idx);
+                        }
+                    });
+                }
+                if let Some(idx) = reload {
+                    self.reload_tab(idx);
+                }
+                // Closed after the loop so the indices above stay valid
+                if let Some(idx) = close {
+                    self.close_tab(idx);
+                }
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button(tr("+ Open")).clicked() {
+                        self.open_files();
+                    }
+                });
+            });
+        });
+
+        // A tab picked above shows its document straight away
+        self.load_active_again();
+
+        if settings.show_reading_progress
+            && let Some(tab) = self.tabs.get(self.active)
+            && !tab.is_loading()
+        {
+            egui::TopBottomPanel::top("reading_progress")
+                .exact_height(3.0)
+                .show_separator_line(false)
+                .frame(egui::Frame::NONE)
+                .show(ctx, |ui| {
+                    let rect = ui.max_rect();
+                    let read = egui::Rect::from_min_size(
+                        rect.min,
+                        egui::vec2(rect.width() * tab.read_fraction, rect.height()),
+                    );
+                    ui.painter()
+                        .rect_filled(read, 0.0, ui.visuals().selection.bg_fill);
+                    let total = tab.doc.words();
+                    let left = (total as f32 * (1.0 - tab.read_fraction)) as usize;
+                    ui.allocate_rect(rect, egui::Sense::hover())
+                        .on_hover_text(tr_args(
+                            "{percent}% read, {time} left",
+                            &[
+                                ("percent", &((tab.read_fraction * 100.0).round() as u32)),
+                                ("time", &toc::reading_time(left)),
+                            ],
+                        ));
+                });
+        }
+
+        // Find bar
+        if self.search.open
+            && let Some(tab) = self.tabs.get_mut(self.active)
+        {
+            egui::TopBottomPanel::top("find_bar").show(ctx, |ui| {
+                if let SearchAction::Reveal(offset) =
+                    self.search.show(ui, &tab.content, &tab.doc, tab.revision)
+                {
+                    tab.scroll_to_block = tab.block_at(offset);
+                    revealed_match = true;
+                    self.nav_macro.record(Step::Find {
+                        query: self.search.query().to_string(),
+                        nth: self.search.current(),
+                    });
+                }
+            });
+        }
+
+        // Source of a scratch tab, re-rendered as it's edited
+        if let Some(tab) = self.tabs.get_mut(self.active)
+            && tab.is_scratch()
+            && tab.editing
+        {
+            egui::TopBottomPanel::top("scratch_editor")
+                .resizable(true)
+                .default_height(160.0)
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        let edit = egui::TextEdit::multiline(&mut tab.content)
+                            .code_editor()
+                            .hint_text(tr("Paste Markdown here"))
+                            .desired_width(f32::INFINITY);
+                        if ui.add(edit).changed() {
+                            tab.set_content(tab.content.clone());
+                        }
+                    });
+                });
+        }
+
+        // Files of the opened folder
+        if let Some(ws) = workspace {
+            egui::SidePanel::left("files")
+                .resizable(true)
+                .default_width(200.0)
+                .show(ctx, |ui| {
+                    let active = self.tabs.get(self.active).map(|tab| tab.path.as_path());
+                    tree_action = ws.show_tree(ui, active);
+                });
+        }
+
+        // Table of contents
+        if settings.show_toc
+            && let Some(tab) = self.tabs.get_mut(self.active)
+        {
+            egui::SidePanel::left("toc")
+                .resizable(true)
+                .default_width(220.0)
+                .show(ctx, |ui| {
+                    if let Some(idx) = toc::show(
+                        ui,
+                        &tab.doc,
+                        settings.number_headings,
+                        settings.show_reading_progress,
+                        &mut tab.toc_depth,
+                    ) {
+                        tab.scroll_to_block = Some(idx);
+                    }
+                });
+        }
+
+        // Hits in every document
+        if self.global_search.open {
+            let picked = egui::SidePanel::right("global_search")
+                .resizable(true)
+                .default_width(280.0)
+                .show(ctx, |ui| {
+                    self.global_search.show(ui, &self.tabs, workspace.as_ref())
+                })
+                .inner;
+            if let Some(picked) = picked {
+                self.reveal(picked);
+            }
+        }
+
+        // Documents linking here
+        if settings.show_backlinks {
+            let clicked = egui::SidePanel::right("backlinks")
+                .resizable(true)
+                .default_width(220.0)
+                .show(ctx, |ui| {
+                    self.backlinks
+                        .show(ui, &self.tabs, self.active, workspace.as_ref())
+                })
+                .inner;
+            if let Some(path) = clicked {
+                self.open_path(path);
+            }
+        }
+
+        // Captioned images
+        if settings.show_figures
+            && let Some(tab) = self.tabs.get_mut(self.active)
+        {
+            egui::SidePanel::right("figures")
+                .resizable(true)
+                .default_width(220.0)
+                .show(ctx, |ui| {
+                    if let Some(idx) = toc::show_figures(ui, &tab.doc.figures) {
+                        tab.scroll_to_block = Some(idx);
+                    }
+                });
+        }
+
+        // Tags of the folder
+        if settings.show_tags {
+            let clicked = egui::SidePanel::right("tags")
+                .resizable(true)
+                .default_width(220.0)
+                .show(ctx, |ui| self.tags.show(ui, workspace.as_ref()))
+                .inner;
+            if let Some(path) = clicked {
+                self.open_path(path);
+            }
+        }
+
+        // Main viewer
+        let mut retry_load = false;
+        let viewer = egui::CentralPanel::default().show(ctx, |ui| {
+            if self.tabs.is_empty() {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(40.0);
+                    ui.heading(tr("Welcome to Markdown Viewer"));
+                    ui.label(tr(
+                        "Use File → Open… or the + Open button to load one or more .md files.",
+                    ));
+                });
+                return;
+            }
+
+            if let Some(comparison) = &self.compare {
+                if comparison.show(ui) == CompareAction::Close {
+                    self.compare = None;
+                }
+                return;
+            }
+
+            let tab = &mut self.tabs[self.active];
+
+            // Files loading in chunks are shown as far as they are
+            if tab.is_loading() && tab.doc.blocks.is_empty() {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(40.0);
+                    ui.spinner();
+                    ui.weak(tr_args("Loading {title}…", &[("title", &tab.title)]));
+                });
+                return;
+            }
+
+            if let Some(error) = tab.load_again_failed() {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(40.0);
+                    ui.label(tr_args(
+                        "Couldn't read {title} again: {error}",
+                        &[("title", &tab.title), ("error", &error)],
+                    ));
+                    if ui.button(tr("Try Again")).clicked() {
+                        retry_load = true;
+                    }
+                });
+                return;
+            }
+
+            tab.use_dialect(tab.dialect.unwrap_or(settings.dialect));
+            tab.resolve_wikilinks(workspace.as_ref());
+
+            if tab.changed_externally {
+                ui.horizontal(|ui| {
+                    ui.label(tr("This file changed on disk."));
+                    if ui.button(tr("Reload")).clicked() {
+                        reload_changed = true;
+                    }
+                    if ui.button(tr("Ignore")).clicked() {
+                        tab.ignore_external_change();
+                    }
+                });
+                ui.separator();
+            }
+
+            if tab.reload_notice {
+                ui.horizontal(|ui| {
+                    ui.label(match tab.unseen_reloads {
+                        1 => tr("This document was reloaded with changes.").to_string(),
+                        n => tr_args(
+                            "This document was reloaded {n} times with changes.",
+                            &[("n", &n)],
+                        ),
+                    });
+                    if ui.button(tr("Show Changes")).clicked() {
+                        show_changes = true;
+                    }
+                    if ui.small_button("×").on_hover_text(tr("Dismiss")).clicked() {
+                        tab.reload_notice = false;
+                    }
+                });
+                ui.separator();
+            }
+
+            if tab.pending_content.is_some() {
+                // Queued updates land once the reader is back at the top
+                if tab.scroll_offset <= 0.0 {
+                    tab.apply_pending();
+                } else {
+                    ui.horizontal(|ui| {
+                        ui.label(tr(
+                            "This document changed on disk. Updates are paused while you read.",
+                        ));
+                        if ui.button(tr("Apply now")).clicked() {
+                            tab.apply_pending();
+                        }
+                    });
+                    ui.separator();
+                }
+            }
+
+            if let Some(error) = tab.render_error.clone()
+                && !tab.plain_text
+            {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(40.0);
+                    ui.heading(tr("This document couldn't be rendered"));
+                    ui.label(tr_args(
+                        "Rendering stopped because {error}.",
+                        &[("error", &error)],
+                    ));
+                    ui.add_space(8.0);
+                    if ui.button(tr("Render as Plain Text")).clicked() {
+                        tab.plain_text = true;
+                    }
+                    if ui.button(tr("Try Again")).clicked() {
+                        tab.render_error = None;
+                    }
+                });
+                return;
+            }
+
+            if tab.plain_text {
+                ui.horizontal(|ui| {
+                    ui.label(tr("Showing the plain text of this document."));
+                    if ui.button(tr("Render Markdown")).clicked() {
+                        tab.plain_text = false;
+                        tab.render_error = None;
+                    }
+                    egui::ComboBox::from_id_salt("column_guide")
+                        .selected_text(settings.column_guide.label())
+                        .show_ui(ui, |ui| {
+                            for guide in ColumnGuide::ALL {
+                                ui.selectable_value(
+                                    &mut settings.column_guide,
+                                    guide,
+                                    guide.label(),
+                                );
+                            }
+                        });
+                });
+                ui.separator();
+            }
+
+            if tab.format == Format::Html {
+                ui.horizontal(|ui| {
+                    ui.label(tr(
+                        "A text preview of this web page, without its styling or scripts.",
+                    ));
+                    if ui.button(tr("Open in Browser")).clicked() {
+                        open_in_browser = true;
+                    }
+                });
+                ui.separator();
+            }
+
+            if !revealed_match
+                && let Some(idx) = tab.scroll_to_block
+                && let Some(blocks::Block {
+                    kind: blocks::BlockKind::Heading { title, .. },
+                    ..
+                }) = tab.doc.blocks.get(idx)
+            {
+                self.nav_macro.record(Step::Heading(title.clone()));
+            }
+
+            let marked: Vec<_> = tab.highlighted().iter().flatten().cloned().collect();
+            let scroll = match self.lightbox.is_open() {
+                true => None,
+                false => self.scroll_keys.read(ctx, settings.vim_keys),
+            };
+            let hints = match settings.document_hints {
+                true => tab.doc.hints,
+                false => Hints::default(),
+            };
+            let text_scale = settings.md_text_scale * hints.font_scale.unwrap_or(1.0);
+            let line_height = ui.text_style_height(&egui::TextStyle::Body) * text_scale;
+            let theme_dark = ui.visuals().dark_mode;
+            let dark = hints.dark.unwrap_or(theme_dark);
+            let visuals = (dark != theme_dark).then(|| match dark {
+                true => egui::Visuals::dark(),
+                false => egui::Visuals::light(),
+            });
+            let paper = hints.paper.unwrap_or(settings.paper).colors(dark);
+            let background = match (&paper, &visuals) {
+                (Some(paper), _) => Some(paper.background),
+                (None, visuals) => visuals.as_ref().map(|visuals| visuals.panel_fill),
+            };
+            if let Some(background) = background {
+                // Out to the edges of the panel, under the document only
+                let top = ui.available_rect_before_wrap().top();
+                let mut surface = ui.clip_rect();
+                surface.min.y = top;
+                ui.painter().rect_filled(surface, 0.0, background);
+            }
+            let output = egui::ScrollArea::vertical()
+                .id_salt(tab.id)
+                .auto_shrink([false, false])
+                .show(ui, |ui| {
+                    scroll_keys::start(ui, scroll, line_height);
+                    ui.scope(|ui| {
+                        // Temporarily scale ONLY the markdown area's text styles
+                        let style = ui.style_mut();
+                        for (text_style, font_id) in style.text_styles.iter_mut() {
+                            font_id.size *= text_scale;
+                            if *text_style != egui::TextStyle::Monospace {
+                                font_id.family = settings.font.family();
+                            }
+                        }
+                        // Text wraps even in rows, so prose never needs
+                        // scrolling sideways however large it gets
+                        style.wrap_mode = Some(egui::TextWrapMode::Wrap);
+                        // Buttons and checkboxes grow with the text, never
+                        // smaller than they are at 100%
+                        let grow = text_scale.max(1.0);
+                        let spacing = &mut style.spacing;
+                        spacing.interact_size *= grow;
+                        spacing.icon_width *= grow;
+                        spacing.icon_width_inner *= grow;
+                        spacing.icon_spacing *= grow;
+                        if let Some(visuals) = visuals {
+                            style.visuals = visuals;
+                        }
+                        if let Some(paper) = &paper {
+                            paper.apply(&mut style.visuals);
+                        }
+
+                        let width = hints
+                            .width
+                            .or(settings.limit_text_width.then_some(settings.text_width));
+                        let column = text_column(ui, settings.margin, width);
+                        ui.scope_builder(egui::UiBuilder::new().max_rect(column), |ui| {
+                            let options = render::ViewOptions {
+                                guess_code_languages: settings.guess_code_languages,
+                                justify: settings.justify_text,
+                                hyphenate: settings.hyphenate,
+                                highlight: self.search.current_match(),
+                                code_theme_dark: &settings.code_theme_dark,
+                                code_theme_light: &settings.code_theme_light,
+                                monochrome_code: settings.monochrome_code,
+                                code_layout: Layout {
+                                    line_numbers: settings.code_line_numbers,
+                                    wrap: settings.wrap_code,
+                                },
+                                diff_side_by_side: settings.diff_side_by_side,
+                                number_headings: settings.number_headings,
+                                diagrams: (settings.render_diagrams
+                                    && trust::is_trusted(&settings.trusted_folders, &tab.path))
+                                .then_some(diagram::Commands {
+                                    plantuml: &settings.plantuml_command,
+                                    graphviz: &settings.graphviz_command,
+                                }),
+                                link_previews: settings.link_previews,
+                                selected_all: tab.selected_all,
+                                marked: &marked,
+                            };
+                            if tab.plain_text {
+                                render::show_plain_text(ui, tab, settings.column_guide.columns());
+                            } else if let Err(e) =
+                                render::show_document_guarded(ui, cm_cache, tab, &options)
+                            {
+                                self.events.push(AppEvent::RenderFailed {
+                                    title: tab.title.clone(),
+                                    error: e.to_string(),
+                                });
+                                tab.render_error = Some(e.to_string());
+                            } else if let Some(book) =
+                                workspace.as_ref().and_then(|ws| ws.book.as_ref())
+                                && book.contains(&tab.path)
+                            {
+                                turn_page = book.show_pager(ui, &tab.path);
+                            }
+                        });
+                    });
+                    scroll_keys::finish(ui, scroll);
+                });
+            tab.scroll_offset = output.state.offset.y;
+            let scrollable = output.content_size.y - output.inner_rect.height();
+            tab.read_fraction = match scrollable > 0.0 {
+                true => (tab.scroll_offset / scrollable).clamp(0.0, 1.0),
+                false => 1.0,
+            };
+            if let Some(uri) = tab.clicked_image.take() {
+                self.lightbox.open(uri);
+            }
+            if let Some(block) = tab.save_code.take().and_then(|idx| tab.doc.blocks.get(idx))
+                && let Some(code) = &block.code
+            {
+                let lang = render::code_lang(block, settings.guess_code_languages);
+                match code_view::save(&code.text, lang) {
+                    Ok(Some(path)) => self.events.push(AppEvent::CodeSaved { path }),
+                    Ok(None) => {}
+                    Err(e) => self.events.push(AppEvent::CodeSaveFailed {
+                        error: format!("{e:#}"),
+                    }),
+                }
+            }
+            self.nav_macro.watch_scrolling(ctx, tab);
+            self.link_preview.show(ctx, tab, workspace.as_ref());
+        });
+
+        // Wikilinks, and web pages and data files next to the document, open
+        // as tabs rather than in the browser. Schemes with a handler go to it
+        // first, and rewritten links may be any of these.
+        let (wikilinks, pages, handled, asked, blocked) = ctx.output_mut(|o| {
+            let mut targets = Vec::new();
+            let mut pages = Vec::new();
+            let mut handled = Vec::new();
+            let mut asked = Vec::new();
+            let mut blocked = Vec::new();
+            o.commands.retain_mut(|command| {
+                let egui::OutputCommand::OpenUrl(open) = command else {
+                    return true;
+                };
+                if let Some(handler) = link_handlers::find(&settings.link_handlers, &open.url) {
+                    match handler.action {
+                        LinkAction::OpenExternally => return true,
+                        LinkAction::Rewrite => {
+                            open.url = link_handlers::expand(&handler.template, &open.url);
+                        }
+                        LinkAction::RunCommand => {
+                            handled.push((handler.template.clone(), open.url.clone()));
+                            return false;
+                        }
+                    }
+                }
+                if let Some(target) = open.url.strip_prefix(wikilink::SCHEME) {
+                    targets.push(target.to_string());
+                    false
+                } else if link_preview::is_html_page(&open.url)
+                    || link_preview::is_csv_file(&open.url)
+                {
+                    pages.push(open.url.clone());
+                    false
+                } else {
+                    match web_links::policy(settings.web_links, &settings.trusted_sites, &open.url)
+                    {
+                        Policy::Open => true,
+                        Policy::Ask => {
+                            asked.push(open.clone());
+                            false
+                        }
+                        Policy::Block => {
+                            blocked.push(open.url.clone());
+                            false
+                        }
+                    }
+                }
+            });
+            (targets, pages, handled, asked, blocked)
+        });
+        for url in blocked {
+            self.events.push(AppEvent::WebLinkBlocked { url });
+        }
+        if let Some(open) = asked.into_iter().last() {
+            self.web_link.ask(open);
+        }
+        // Opened after the links above were looked at, so not asked about again
+        match self.web_link.show(ctx) {
+            Some(Answer::Open(open)) => ctx.open_url(open),
+            Some(Answer::Trust(open, site)) => {
+                if !settings.trusted_sites.contains(&site) {
+                    settings.trusted_sites.push(site);
+                }
+                ctx.open_url(open);
+            }
+            None => {}
+        }
+        // Nor is the page itself, which isn't a link in a document
+        if open_in_browser && let Some(tab) = self.tabs.get(self.active) {
+            ctx.open_url(egui::OpenUrl::new_tab(percent::file_url(&tab.path)));
+        }
+        if let Some(folder) = ask_trust {
+            self.trust.ask(folder);
+        }
+        match self.trust.show(ctx) {
+            Some(trust::Answer::Trust(folder)) => {
+                settings
+                    .untrusted_folders
+                    .retain(|untrusted| *untrusted != folder);
+                if !settings.trusted_folders.contains(&folder) {
+                    settings.trusted_folders.push(folder.clone());
+                }
+                self.events.push(AppEvent::FolderTrusted { path: folder });
+            }
+            Some(trust::Answer::Distrust(folder))
+                if !settings.untrusted_folders.contains(&folder) =>
+            {
+                settings.untrusted_folders.push(folder);
+            }
+            Some(trust::Answer::Distrust(_)) | None => {}
+        }
+        if let Some((template, url)) = handled.into_iter().last() {
+            self.link_command.ask(template, url);
+        }
+        if let Some((template, url)) = self.link_command.show(ctx)
+            && let Err(e) = link_handlers::run(&template, &url)
+        {
+            self.events.push(AppEvent::LinkHandlerFailed {
+                url,
+                error: format!("{e:#}"),
+            });
+        }
+        for target in wikilinks {
+            self.follow_wikilink(&target, workspace.as_ref());
+        }
+        for page in pages {
+            let file = page.split(['#', '?']).next().unwrap_or(&page);
+            if let Some(dir) = self.tabs.get(self.active).and_then(DocTab::dir) {
+                self.open_path(dir.join(percent::decode(file)));
+            }
+        }
+
+        self.events.show_toasts(ctx);
+        self.tab_order.show(ctx, &self.tabs);
+
+        if let Some(event) = self.lightbox.show(ctx) {
+            self.events.push(event);
+        }
+        if retry_load
+            && let Some(tab) = self.tabs.get_mut(self.active)
+            && let Err(e) = tab.load_again()
+        {
+            self.events.push(AppEvent::ReloadFailed {
+                title: tab.title.clone(),
+                error: format!("{e:#}"),
+            });
+        }
+        if let Some(result) = self.snippet.show(ctx, viewer.response.rect) {
+            self.events.push(match result {
+                Ok(Captured::Saved(path)) => AppEvent::SnippetSaved { path },
+                Ok(Captured::Copied) => AppEvent::SnippetCopied,
+                Ok(Captured::Cancelled) => AppEvent::SnippetCancelled,
+                Err(e) => AppEvent::SnippetFailed {
+                    error: e.to_string(),
+                },
+            });
+        }
+
+        if show_changes {
+            self.show_reload_changes();
+        }
+        if reload_changed {
+            self.reload_active();
+        }
+        if let Some(path) = turn_page {
+            self.open_path(path);
+        }
+        if show_git_changes {
+            self.show_git_changes();
+        }
+        if toggle_bookmark {
+            self.toggle_bookmark();
+        }
+        if let Some(n) = forget_highlight
+            && let Some(tab) = self.tabs.get_mut(self.active)
+        {
+            tab.reading.highlights.remove(n);
+            self.save_active_reading();
+        }
+        if export_pandoc
+            && let Some(tab) = self.tabs.get(self.active)
+            && let Err(e) = self.pandoc.start(tab)
+        {
+            self.events.push(AppEvent::ExportFailed {
+                error: format!("{e:#}"),
+            });
+        }
+        if let Some(bundle) = export_tabs {
+            match export::export(&self.tabs, bundle, shared.workspace.as_ref()) {
+                Ok(Some(path)) => self.events.push(AppEvent::Exported { path }),
+                Ok(None) => {}
+                Err(e) => self.events.push(AppEvent::ExportFailed {
+                    error: format!("{e:#}"),
+                }),
+            }
+        }
+
+        // Handled last, since these need the shared state as a whole
+        if open_folder {
+            self.open_folder(shared);
+        }
+        match tree_action {
+            Some(TreeAction::Open(path)) => self.open_path(path),
+            Some(TreeAction::SetExcludes(patterns)) => {
+                shared.settings.exclude_patterns = patterns;
+                if let Some(root) = shared.workspace.as_ref().map(|ws| ws.root.clone()) {
+                    self.scan_folder(shared, root);
+                }
+            }
+            Some(TreeAction::Close) => shared.workspace = None,
+            None => {}
+        }
+
+        action
+    }
+}
+
+/// The dialect a tab is read as: the one in Preferences, `default`, or one
+/// of its own.
+fn dialect_menu(ui: &mut egui::Ui, dialect: &mut Option<Dialect>, default: Dialect) {
+    let same = tr_args(
+        "As in Preferences ({dialect})",
+        &[("dialect", &default.label())],
+    );
+    if ui.radio(dialect.is_none(), same).clicked() {
+        *dialect = None;
+    }
+    for preset in Dialect::presets() {
+        if ui.radio(*dialect == Some(preset), preset.label()).clicked() {
+            *dialect = Some(preset);
+        }
+    }
+    ui.separator();
+    let mut own = dialect.unwrap_or(default);
+    if preferences::extension_choice(ui, &mut own) {
+        *dialect = Some(own);
+    }
+}
+
+/// Shortens long section titles so the breadcrumb trail stays on one line.
+fn compact(title: &str) -> String {
+    const MAX_CHARS: usize = 32;
+    if title.chars().count() <= MAX_CHARS {
+        title.to_string()
+    } else {
+        let mut short: String = title.chars().take(MAX_CHARS - 1).collect();
+        short.push('…');
+        short
+    }
+}
+
+/// The clipboard's text, read directly since egui only sees it on paste.
+fn clipboard_text() -> Option<String> {
+    arboard::Clipboard::new().ok()?.get_text().ok()
+}
+
+/// Where in `ui` the document goes: inside the margins, and centered in a
+/// column as wide as `width` characters of body text if limited.
+fn text_column(ui: &egui::Ui, margin: f32, width: Option<u32>) -> egui::Rect {
+    let mut rect = ui.available_rect_before_wrap();
+    let margin = margin.min(rect.width() / 4.0);
+    rect = rect.shrink2(egui::vec2(margin, 0.0));
+    if let Some(width) = width {
+        let font = egui::TextStyle::Body.resolve(ui.style());
+        let ch = ui.fonts(|f| f.glyph_width(&font, '0'));
+        let width = width as f32 * ch;
+        if width < rect.width() {
+            rect = egui::Rect::from_center_size(rect.center(), egui::vec2(width, rect.height()));
+        }
+    }
+    rect
+}
+
+/// Grows or shrinks document text by `factor`, within 50–800%.
+fn scale_text(settings: &mut config::Settings, factor: f32) {
+    settings.md_text_scale =
+        (settings.md_text_scale * factor).clamp(config::MIN_TEXT_SCALE, config::MAX_TEXT_SCALE);
+}
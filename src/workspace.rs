@@ -0,0 +1,273 @@
+//! An opened folder: the documents under it and the file tree sidebar.
+
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use eframe::egui;
+use ignore::{WalkBuilder, overrides::OverrideBuilder};
+
+use crate::formats::Format;
+use crate::frontmatter;
+use crate::i18n::{tr, tr_args};
+use crate::mdbook::Book;
+
+/// How much of each file is read looking for frontmatter aliases and tags.
+const FRONTMATTER_PEEK: u64 = 16 * 1024;
+
+#[derive(Clone)]
+pub struct Workspace {
+    pub root: PathBuf,
+    /// Absolute paths of every document found, sorted.
+    pub files: Vec<PathBuf>,
+    /// Frontmatter `aliases` of the documents, each with its document.
+    pub aliases: Vec<(String, PathBuf)>,
+    /// Frontmatter `tags` of the documents, each with its document.
+    pub tags: Vec<(String, PathBuf)>,
+    /// The mdBook the folder holds, if it is one.
+    pub book: Option<Book>,
+    tree: DirNode,
+    /// One glob per line, edited in the sidebar before being saved to the settings.
+    exclude_draft: String,
+}
+
+#[derive(Clone, Default)]
+struct DirNode {
+    name: String,
+    /// Path relative to the workspace root, also used as the header id.
+    path: PathBuf,
+    dirs: Vec<DirNode>,
+    files: Vec<PathBuf>,
+}
+
+/// What the file tree asks the window to do.
+pub enum TreeAction {
+    Open(PathBuf),
+    /// Exclusion patterns were edited; save them and rescan.
+    SetExcludes(Vec<String>),
+    Close,
+}
+
+impl Workspace {
+    /// Scans `root` for documents in any supported format, honoring `.gitignore` files and the
+    /// extra `excludes` globs (gitignore syntax, relative to `root`).
+    pub fn open(root: PathBuf, excludes: &[String]) -> Result<Self> {
+        let mut overrides = OverrideBuilder::new(&root);
+        for pattern in excludes.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            overrides.add(&format!("!{pattern}"))?;
+        }
+
+        let walker = WalkBuilder::new(&root)
+            // Honor .gitignore even in folders that aren't a git checkout
+            .require_git(false)
+            .overrides(overrides.build()?)
+            .build();
+
+        let mut files: Vec<PathBuf> = walker
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .map(|entry| entry.into_path())
+            .filter(|path| Format::from_path(path).is_some())
+            .collect();
+        files.sort();
+
+        let mut aliases = Vec::new();
+        let mut tags = Vec::new();
+        for path in &files {
+            if let Some(frontmatter) = read_frontmatter(path) {
+                aliases.extend(
+                    frontmatter::aliases(&frontmatter)
+                        .into_iter()
+                        .map(|alias| (alias, path.clone())),
+                );
+                tags.extend(
+                    frontmatter::tags(&frontmatter)
+                        .into_iter()
+                        .map(|tag| (tag, path.clone())),
+                );
+            }
+        }
+
+        let tree = build_tree(&root, &files);
+        Ok(Self {
+            exclude_draft: excludes.join("\n"),
+            book: Book::detect(&root),
+            root,
+            files,
+            aliases,
+            tags,
+            tree,
+        })
+    }
+
+    /// The document a note name like `Other Note` refers to: by file name
+    /// first, then by alias. Case doesn't matter, and `.md` may be included.
+    pub fn resolve(&self, name: &str) -> Option<&Path> {
+        let name = name.trim();
+        let name = name.strip_suffix(".md").unwrap_or(name);
+        let by_stem = self.files.iter().find(|path| {
+            path.file_stem()
+                .is_some_and(|stem| stem.to_string_lossy().eq_ignore_ascii_case(name))
+        });
+        by_stem
+            .or_else(|| {
+                self.aliases
+                    .iter()
+                    .find(|(alias, _)| alias.to_lowercase() == name.to_lowercase())
+                    .map(|(_, path)| path)
+            })
+            .map(PathBuf::as_path)
+    }
+
+    /// `path` relative to the root, for display.
+    pub fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+
+    pub fn name(&self) -> String {
+        self.root
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.root.display().to_string())
+    }
+
+    pub fn show_tree(&mut self, ui: &mut egui::Ui, active: Option<&Path>) -> Option<TreeAction> {
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            let title = match self.book.as_ref().and_then(|book| book.title.as_ref()) {
+                Some(title) => format!("📖 {title}"),
+                None => self.name(),
+            };
+            ui.strong(title)
+                .on_hover_text(self.root.display().to_string());
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui
+                    .small_button("×")
+                    .on_hover_text(tr("Close folder"))
+                    .clicked()
+                {
+                    action = Some(TreeAction::Close);
+                }
+            });
+        });
+        ui.weak(tr_args("{n} documents", &[("n", &self.files.len())]));
+        ui.separator();
+
+        egui::CollapsingHeader::new(tr("Exclude patterns"))
+            .id_salt("workspace_excludes")
+            .show(ui, |ui| {
+                ui.weak(tr(
+                    "One glob per line, e.g. node_modules/ or *.generated.md",
+                ));
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.exclude_draft)
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY)
+                        .code_editor(),
+                );
+                if ui.button(tr("Apply & Rescan")).clicked() {
+                    let patterns = self
+                        .exclude_draft
+                        .lines()
+                        .map(str::trim)
+                        .filter(|p| !p.is_empty())
+                        .map(String::from)
+                        .collect();
+                    action = Some(TreeAction::SetExcludes(patterns));
+                }
+            });
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .id_salt("workspace_tree")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                // A book's chapters come first, in reading order
+                let clicked = match &self.book {
+                    Some(book) => {
+                        let chapter = book.show_contents(ui, active);
+                        ui.add_space(8.0);
+                        let file = egui::CollapsingHeader::new(tr("All files"))
+                            .id_salt("workspace_all_files")
+                            .show(ui, |ui| show_dir(ui, &self.tree, active))
+                            .body_returned
+                            .flatten();
+                        chapter.or(file)
+                    }
+                    None => show_dir(ui, &self.tree, active),
+                };
+                if let Some(path) = clicked {
+                    action = Some(TreeAction::Open(path));
+                }
+            });
+
+        action
+    }
+}
+
+fn build_tree(root: &Path, files: &[PathBuf]) -> DirNode {
+    let mut tree = DirNode::default();
+    for file in files {
+        let Ok(relative) = file.strip_prefix(root) else {
+            continue;
+        };
+        let mut node = &mut tree;
+        if let Some(parent) = relative.parent() {
+            for component in parent.components() {
+                let name = component.as_os_str().to_string_lossy().to_string();
+                let idx = match node.dirs.iter().position(|d| d.name == name) {
+                    Some(idx) => idx,
+                    None => {
+                        let path = node.path.join(&name);
+                        node.dirs.push(DirNode {
+                            name,
+                            path,
+                            ..Default::default()
+                        });
+                        node.dirs.len() - 1
+                    }
+                };
+                node = &mut node.dirs[idx];
+            }
+        }
+        node.files.push(file.clone());
+    }
+    tree
+}
+
+fn show_dir(ui: &mut egui::Ui, node: &DirNode, active: Option<&Path>) -> Option<PathBuf> {
+    let mut clicked = None;
+    for dir in &node.dirs {
+        egui::CollapsingHeader::new(format!("📁 {}", dir.name))
+            .id_salt(&dir.path)
+            .show(ui, |ui| {
+                if let Some(path) = show_dir(ui, dir, active) {
+                    clicked = Some(path);
+                }
+            });
+    }
+    for file in &node.files {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let selected = active == Some(file.as_path());
+        if ui.selectable_label(selected, name).clicked() {
+            clicked = Some(file.clone());
+        }
+    }
+    clicked
+}
+
+/// The frontmatter of a Markdown file, reading only its start.
+fn read_frontmatter(path: &Path) -> Option<String> {
+    if Format::from_path(path) != Some(Format::Markdown) {
+        return None;
+    }
+    let mut head = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(FRONTMATTER_PEEK).read_to_end(&mut head))
+        .ok()?;
+    frontmatter::extract(&String::from_utf8_lossy(&head)).map(str::to_string)
+}